@@ -0,0 +1,89 @@
+use crate::models::{ChangeAction, Manifest, PlannedChange};
+use crate::services::GitOpsService;
+use crate::state::AppState;
+use clap::Subcommand;
+use std::path::PathBuf;
+
+#[derive(Subcommand)]
+pub enum Command {
+    /// Show the changes needed to converge the controller to a manifest
+    Plan {
+        /// YAML or JSON manifest, defaults to `gitops.manifest` from the config
+        manifest: Option<PathBuf>,
+    },
+    /// Converge the controller to a manifest
+    Apply {
+        /// YAML or JSON manifest, defaults to `gitops.manifest` from the config
+        manifest: Option<PathBuf>,
+    },
+}
+
+/// Actor recorded in the audit log for changes made from the command line
+const CLI_ACTOR: &str = "cli";
+
+async fn load_manifest(
+    app_state: &AppState,
+    path: Option<PathBuf>,
+) -> crate::error::Result<Manifest> {
+    match path {
+        Some(path) => GitOpsService::load_manifest(&path).await,
+        None => app_state.gitops.configured_manifest().await,
+    }
+}
+
+fn line(change: &PlannedChange) -> String {
+    let symbol = match change.action {
+        ChangeAction::Create => '+',
+        ChangeAction::Update => '~',
+        ChangeAction::Delete => '-',
+    };
+    format!("{} {}", symbol, change.describe())
+}
+
+/// Run a command instead of the server
+pub async fn run(command: Command, app_state: &AppState) -> Result<(), Box<dyn std::error::Error>> {
+    match command {
+        Command::Plan { manifest } => {
+            let manifest = load_manifest(app_state, manifest).await?;
+            let plan = app_state.gitops.plan(&manifest).await?;
+
+            for change in &plan.changes {
+                println!("{}", line(change));
+            }
+            for network_id in &plan.released {
+                println!(
+                    "  release network {}, it is kept on the controller",
+                    network_id
+                );
+            }
+            if plan.is_empty() {
+                println!("No changes, the controller matches the manifest.");
+            }
+            println!(
+                "Plan: {} to create, {} to update, {} to delete, {} unmanaged members left alone.",
+                plan.create, plan.update, plan.delete, plan.unmanaged
+            );
+        }
+        Command::Apply { manifest } => {
+            let manifest = load_manifest(app_state, manifest).await?;
+            let result = app_state.gitops.apply(CLI_ACTOR, &manifest).await?;
+
+            for applied in &result.changes {
+                match &applied.error {
+                    None => println!("{}", line(&applied.change)),
+                    Some(error) => println!("{} failed: {}", line(&applied.change), error),
+                }
+            }
+            println!(
+                "Applied {} changes, {} failed.",
+                result.succeeded, result.failed
+            );
+
+            if result.failed > 0 {
+                return Err(format!("{} changes failed", result.failed).into());
+            }
+        }
+    }
+
+    Ok(())
+}
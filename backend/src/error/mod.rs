@@ -3,14 +3,40 @@ use axum::{
     response::{IntoResponse, Response},
     Json,
 };
+use serde::Serialize;
 use serde_json::json;
 use thiserror::Error;
 
+/// A validation failure for a single field of a request body
+#[derive(Debug, Clone, Serialize)]
+pub struct FieldError {
+    pub field: String,
+    pub message: String,
+}
+
+impl FieldError {
+    pub fn new(field: impl Into<String>, message: impl Into<String>) -> Self {
+        Self {
+            field: field.into(),
+            message: message.into(),
+        }
+    }
+}
+
 #[derive(Error, Debug)]
 pub enum AppError {
     #[error("Authentication failed")]
     Unauthorized,
 
+    #[error("Not found: {0}")]
+    NotFound(String),
+
+    #[error("Validation failed")]
+    ValidationError(Vec<FieldError>),
+
+    #[error("Service unavailable: {0}")]
+    ServiceUnavailable(String),
+
     #[error("Too many requests: {0}")]
     TooManyRequests(String),
 
@@ -37,6 +63,15 @@ impl IntoResponse for AppError {
     fn into_response(self) -> Response {
         let (status, error_message) = match self {
             AppError::Unauthorized => (StatusCode::UNAUTHORIZED, "Unauthorized".to_string()),
+            AppError::NotFound(e) => (StatusCode::NOT_FOUND, e.to_string()),
+            AppError::ValidationError(fields) => {
+                let body = Json(json!({
+                    "error": "Validation failed",
+                    "fields": fields,
+                }));
+                return (StatusCode::UNPROCESSABLE_ENTITY, body).into_response();
+            }
+            AppError::ServiceUnavailable(e) => (StatusCode::SERVICE_UNAVAILABLE, e.to_string()),
             AppError::TooManyRequests(e) => (StatusCode::TOO_MANY_REQUESTS, e.to_string()),
             AppError::InternalServerError(e) => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()),
             AppError::ZeroTierError(e) => (StatusCode::BAD_GATEWAY, e.to_string()),
@@ -0,0 +1,41 @@
+use crate::error::Result;
+use crate::models::UpdateModeRequest;
+use crate::state::AppState;
+use axum::{extract::State, response::IntoResponse, Json};
+
+pub async fn get_mode(State(app_state): State<AppState>) -> Result<impl IntoResponse> {
+    Ok(Json(app_state.config.get_config().mode.clone()))
+}
+
+pub async fn update_mode(
+    State(app_state): State<AppState>,
+    Json(request): Json<UpdateModeRequest>,
+) -> Result<impl IntoResponse> {
+    let config = app_state
+        .config
+        .update(|config| {
+            let mode = &mut config.mode;
+            if let Some(read_only) = request.read_only {
+                mode.read_only = read_only;
+            }
+            if let Some(message) = request.read_only_message {
+                mode.read_only_message = message;
+            }
+            if let Some(maintenance) = request.maintenance {
+                mode.maintenance = maintenance;
+            }
+            if let Some(message) = request.maintenance_message {
+                mode.maintenance_message = message;
+            }
+            Ok(())
+        })
+        .await?;
+
+    tracing::info!(
+        "Mode updated: read_only={}, maintenance={}",
+        config.mode.read_only,
+        config.mode.maintenance
+    );
+
+    Ok(Json(config.mode.clone()))
+}
@@ -0,0 +1,113 @@
+use crate::error::Result;
+use crate::models::AuditQuery;
+use crate::state::AppState;
+use axum::{
+    extract::{Query, State},
+    response::IntoResponse,
+    Json,
+};
+
+/// List recent audit log entries, newest first
+pub async fn list_audit_entries(
+    State(app_state): State<AppState>,
+    Query(query): Query<AuditQuery>,
+) -> Result<impl IntoResponse> {
+    let entries = app_state
+        .audit
+        .recent(query.limit, |entry| {
+            query
+                .network_id
+                .as_ref()
+                .is_none_or(|id| entry.network_id.as_ref() == Some(id))
+                && query
+                    .member_id
+                    .as_ref()
+                    .is_none_or(|id| entry.member_id.as_ref() == Some(id))
+                && query
+                    .actor
+                    .as_ref()
+                    .is_none_or(|actor| entry.actor.starts_with(actor.as_str()))
+                && query
+                    .action
+                    .as_ref()
+                    .is_none_or(|action| entry.action.starts_with(action.as_str()))
+        })
+        .await;
+
+    Ok(Json(entries))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::services::audit::AuditEntry;
+    use crate::test_support::{json_body, send, user_token, TestEnv};
+    use axum::{
+        body::Body,
+        http::{header, Request, StatusCode},
+    };
+
+    #[tokio::test]
+    async fn filters_entries_by_target_and_prefix() {
+        let env = TestEnv::new().await;
+        let app_state = env.app_state();
+        let audit = &app_state.audit;
+        audit
+            .record(AuditEntry::new("admin", "network.create").network("abcdef0123000001"))
+            .await;
+        audit
+            .record(
+                AuditEntry::new("policy:lab", "member.authorize")
+                    .member("abcdef0123000001", "1111111111"),
+            )
+            .await;
+        audit
+            .record(
+                AuditEntry::new("policy:lab", "member.reject")
+                    .member("abcdef0123000002", "2222222222"),
+            )
+            .await;
+
+        let list = |query: &str| {
+            let request = Request::builder()
+                .uri(format!("/api/audit?{}", query))
+                .header(
+                    header::AUTHORIZATION,
+                    format!("Bearer {}", user_token(&app_state)),
+                )
+                .body(Body::empty())
+                .unwrap();
+            send(&app_state, request)
+        };
+        let actions = |body: serde_json::Value| -> Vec<String> {
+            body.as_array()
+                .unwrap()
+                .iter()
+                .map(|entry| entry["action"].as_str().unwrap().to_string())
+                .collect()
+        };
+
+        let (status, body) = json_body(list("network_id=abcdef0123000001").await).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(actions(body), ["member.authorize", "network.create"]);
+
+        let (_, body) = json_body(list("actor=policy:&action=member.").await).await;
+        assert_eq!(actions(body), ["member.reject", "member.authorize"]);
+
+        let (_, body) = json_body(list("member_id=2222222222").await).await;
+        assert_eq!(actions(body), ["member.reject"]);
+
+        let (_, body) = json_body(list("limit=1").await).await;
+        assert_eq!(actions(body), ["member.reject"]);
+    }
+
+    #[tokio::test]
+    async fn requires_a_session() {
+        let env = TestEnv::new().await;
+        let request = Request::builder()
+            .uri("/api/audit")
+            .body(Body::empty())
+            .unwrap();
+        let response = send(&env.app_state(), request).await;
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    }
+}
@@ -1,11 +1,12 @@
 use crate::error::{AppError, Result};
 use crate::models::{LoginRequest, UpdateProfileRequest};
 use crate::services::auth::Claims;
+use crate::services::events::EventKind;
 use crate::state::AppState;
 use crate::utils::is_private_ip;
 use axum::{
     extract::{ConnectInfo, State},
-    http::HeaderMap,
+    http::{header, HeaderMap},
     response::IntoResponse,
     Extension, Json,
 };
@@ -14,7 +15,7 @@ use std::net::{IpAddr, SocketAddr};
 
 /// Extract the real IP address from the request headers or connection information.
 /// Priority: If ConnectInfo IP is public, use it; otherwise check X-Real-IP and X-Forwarded-For headers.
-fn extract_real_ip(headers: &HeaderMap, fallback_addr: SocketAddr) -> IpAddr {
+pub(crate) fn extract_real_ip(headers: &HeaderMap, fallback_addr: SocketAddr) -> IpAddr {
     let connect_ip = fallback_addr.ip();
 
     // If the IP from ConnectInfo is not private, use it directly
@@ -78,16 +79,32 @@ pub async fn login(
         // Login successful, clear failure records
         app_state.ip_ban.record_success(&client_ip).await;
 
+        match app_state
+            .login_history
+            .record(&request.username, client_ip)
+            .await
+        {
+            Ok(true) => app_state.events.publish(EventKind::LoginFromNewIp {
+                username: request.username.clone(),
+                ip: client_ip,
+            }),
+            Ok(false) => {}
+            Err(e) => tracing::warn!("Failed to record login history: {}", e),
+        }
+
         // Create JWT token
         let (token, expires_at) = app_state.auth.create_token(&request.username)?;
 
         // Return structured response with token info
-        Ok(Json(json!({
-            "token": token,
-            "message": "Login successful",
-            "expires_at": expires_at,
-            "username": request.username
-        })))
+        Ok((
+            [(header::SET_COOKIE, app_state.auth.session_cookie(&token))],
+            Json(json!({
+                "token": token,
+                "message": "Login successful",
+                "expires_at": expires_at,
+                "username": request.username
+            })),
+        ))
     } else {
         // Login failed, record failure attempt
         app_state.ip_ban.record_failure(&client_ip).await;
@@ -101,13 +118,22 @@ pub async fn update_profile(
     Json(request): Json<UpdateProfileRequest>,
 ) -> Result<impl IntoResponse> {
     // Claims are already validated by the middleware
-    let _username = &claims.username;
+    let username = &claims.username;
 
     app_state
         .config
         .update_user_info(&request.username, &request.password)
         .await?;
 
+    tracing::info!(
+        "Admin credentials changed by {}, new username {}",
+        username,
+        request.username
+    );
+    app_state.events.publish(EventKind::PasswordChanged {
+        username: request.username.clone(),
+    });
+
     Ok(Json(json!({
         "message": "Profile updated successfully"
     })))
@@ -124,9 +150,24 @@ pub async fn refresh_token(
     // Create a new token
     let (new_token, expires_at) = app_state.auth.create_token(username)?;
 
-    Ok(Json(json!({
-        "token": new_token,
-        "expires_at": expires_at,
-        "message": "Token refreshed successfully"
-    })))
+    Ok((
+        [(
+            header::SET_COOKIE,
+            app_state.auth.session_cookie(&new_token),
+        )],
+        Json(json!({
+            "token": new_token,
+            "expires_at": expires_at,
+            "message": "Token refreshed successfully"
+        })),
+    ))
+}
+
+pub async fn logout(State(app_state): State<AppState>) -> impl IntoResponse {
+    (
+        [(header::SET_COOKIE, app_state.auth.clear_session_cookie())],
+        Json(json!({
+            "message": "Logout successful"
+        })),
+    )
 }
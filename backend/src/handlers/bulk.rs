@@ -0,0 +1,471 @@
+use crate::error::{AppError, FieldError, Result};
+use crate::models::{
+    BulkMemberRequest, BulkMemberResponse, BulkMemberResult, BulkOperation, BulkStatus,
+};
+use crate::services::audit::AuditEntry;
+use crate::services::auth::Claims;
+use crate::state::AppState;
+use crate::utils::is_node_id;
+use axum::{
+    extract::{Path, State},
+    response::IntoResponse,
+    Extension, Json,
+};
+use futures::{stream, StreamExt};
+use serde_json::{json, Map, Value};
+
+const MAX_BULK_MEMBERS: usize = 1000;
+
+/// Apply one operation to many members of a network, each member is
+/// handled independently so failures do not stop the others
+pub async fn bulk_member_operation(
+    State(app_state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Path(network_id): Path<String>,
+    Json(request): Json<BulkMemberRequest>,
+) -> Result<impl IntoResponse> {
+    let mut errors = Vec::new();
+    if request.member_ids.is_empty() || request.member_ids.len() > MAX_BULK_MEMBERS {
+        errors.push(FieldError::new(
+            "member_ids",
+            format!("must contain 1 to {} member IDs", MAX_BULK_MEMBERS),
+        ));
+    }
+    if let Some(invalid) = request.member_ids.iter().find(|id| !is_node_id(id)) {
+        errors.push(FieldError::new(
+            "member_ids",
+            format!("{} is not a valid member ID", invalid),
+        ));
+    }
+    if matches!(&request.operation, BulkOperation::Tag { tags } if tags.is_empty()) {
+        errors.push(FieldError::new("tags", "must not be empty"));
+    }
+    if !errors.is_empty() {
+        return Err(AppError::ValidationError(errors));
+    }
+
+    // Keep the first occurrence of every member
+    let mut member_ids: Vec<String> = Vec::with_capacity(request.member_ids.len());
+    for member_id in &request.member_ids {
+        let member_id = member_id.to_ascii_lowercase();
+        if !member_ids.contains(&member_id) {
+            member_ids.push(member_id);
+        }
+    }
+
+    let app_state = &app_state;
+    let network_id = &network_id;
+    let request = &request;
+    let actor = claims.username.as_str();
+
+    let results: Vec<BulkMemberResult> = stream::iter(member_ids.into_iter().enumerate())
+        .map(|(index, member_id)| async move {
+            match apply(app_state, actor, network_id, &member_id, index, request).await {
+                Ok((status, update)) => BulkMemberResult {
+                    member_id,
+                    status,
+                    update,
+                    error: None,
+                },
+                Err(e) => BulkMemberResult {
+                    member_id,
+                    status: BulkStatus::Failed,
+                    update: None,
+                    error: Some(e.to_string()),
+                },
+            }
+        })
+        .buffered(app_state.zerotier.max_concurrency())
+        .collect()
+        .await;
+
+    let failed = results
+        .iter()
+        .filter(|result| result.status == BulkStatus::Failed)
+        .count();
+
+    Ok(Json(BulkMemberResponse {
+        dry_run: request.dry_run,
+        total: results.len(),
+        succeeded: results.len() - failed,
+        failed,
+        results,
+    }))
+}
+
+/// Work out the update for one member and send it unless this is a dry run
+async fn apply(
+    app_state: &AppState,
+    actor: &str,
+    network_id: &str,
+    member_id: &str,
+    index: usize,
+    request: &BulkMemberRequest,
+) -> Result<(BulkStatus, Option<Value>)> {
+    let member: Map<String, Value> = app_state
+        .zerotier
+        .get_json(&format!(
+            "/controller/network/{}/member/{}",
+            network_id, member_id
+        ))
+        .await?;
+
+    let update = match &request.operation {
+        BulkOperation::Delete => {
+            if request.dry_run {
+                return Ok((BulkStatus::Planned, None));
+            }
+
+            app_state
+                .zerotier
+                .delete_member(network_id, member_id)
+                .await?;
+            if let Err(e) = app_state
+                .metadata
+                .remove_member(network_id, member_id)
+                .await
+            {
+                tracing::error!("Failed to remove metadata for {}: {}", member_id, e);
+            }
+            record(
+                app_state,
+                actor,
+                network_id,
+                member_id,
+                request,
+                Value::Null,
+            )
+            .await;
+            return Ok((BulkStatus::Applied, None));
+        }
+        BulkOperation::Authorize | BulkOperation::Deauthorize => {
+            let authorized = matches!(request.operation, BulkOperation::Authorize);
+            let current = member.get("authorized").and_then(Value::as_bool);
+            (current != Some(authorized)).then(|| json!({ "authorized": authorized }))
+        }
+        BulkOperation::Rename { name } => {
+            let name = name
+                .replace("{id}", member_id)
+                .replace("{index}", &(index + 1).to_string());
+            let current = member.get("name").and_then(Value::as_str);
+            (current != Some(name.as_str())).then(|| json!({ "name": name }))
+        }
+        BulkOperation::Tag { tags } => {
+            let current: Vec<[u32; 2]> = member
+                .get("tags")
+                .cloned()
+                .and_then(|tags| serde_json::from_value(tags).ok())
+                .unwrap_or_default();
+
+            let mut merged = current.clone();
+            for [id, value] in tags {
+                match merged.iter_mut().find(|tag| tag[0] == *id) {
+                    Some(tag) => tag[1] = *value,
+                    None => merged.push([*id, *value]),
+                }
+            }
+            (merged != current).then(|| json!({ "tags": merged }))
+        }
+    };
+
+    let Some(update) = update else {
+        return Ok((BulkStatus::Unchanged, None));
+    };
+    if request.dry_run {
+        return Ok((BulkStatus::Planned, Some(update)));
+    }
+
+    app_state
+        .zerotier
+        .update_member(network_id, member_id, &update)
+        .await?;
+    record(
+        app_state,
+        actor,
+        network_id,
+        member_id,
+        request,
+        update.clone(),
+    )
+    .await;
+
+    Ok((BulkStatus::Applied, Some(update)))
+}
+
+async fn record(
+    app_state: &AppState,
+    actor: &str,
+    network_id: &str,
+    member_id: &str,
+    request: &BulkMemberRequest,
+    update: Value,
+) {
+    app_state
+        .audit
+        .record(
+            AuditEntry::new(actor, request.operation.action())
+                .member(network_id, member_id)
+                .details(json!({ "bulk": true, "update": update })),
+        )
+        .await;
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test_support::{json_body, send, user_token, TestEnv};
+    use axum::{
+        body::Body,
+        http::{header, Method, Request, StatusCode},
+    };
+    use serde_json::{json, Value};
+
+    const NETWORK: &str = "abcdef0123000001";
+
+    async fn setup() -> TestEnv {
+        let env = TestEnv::new().await;
+        env.controller.add_network(NETWORK, json!({}));
+        env.controller
+            .add_member(NETWORK, "1111111111", json!({ "authorized": true }));
+        env.controller.add_member(
+            NETWORK,
+            "2222222222",
+            json!({ "name": "printer", "tags": [[1, 5], [2, 7]] }),
+        );
+        env
+    }
+
+    async fn bulk(env: &TestEnv, body: Value) -> (StatusCode, Value) {
+        let app_state = env.app_state();
+        let request = Request::builder()
+            .method(Method::POST)
+            .uri(format!("/api/networks/{}/members/bulk", NETWORK))
+            .header(header::CONTENT_TYPE, "application/json")
+            .header(
+                header::AUTHORIZATION,
+                format!("Bearer {}", user_token(&app_state)),
+            )
+            .body(Body::from(body.to_string()))
+            .unwrap();
+        json_body(send(&app_state, request).await).await
+    }
+
+    fn statuses(body: &Value) -> Vec<(String, String)> {
+        body["results"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|result| {
+                (
+                    result["member_id"].as_str().unwrap().to_string(),
+                    result["status"].as_str().unwrap().to_string(),
+                )
+            })
+            .collect()
+    }
+
+    fn pairs(items: &[(&str, &str)]) -> Vec<(String, String)> {
+        items
+            .iter()
+            .map(|(a, b)| (a.to_string(), b.to_string()))
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn validates_the_request() {
+        let env = setup().await;
+
+        let (status, body) = bulk(&env, json!({ "member_ids": [], "action": "authorize" })).await;
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+        assert_eq!(body["fields"][0]["field"], "member_ids");
+
+        let (status, _) = bulk(
+            &env,
+            json!({ "member_ids": ["not-a-node"], "action": "authorize" }),
+        )
+        .await;
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+
+        let (status, body) = bulk(
+            &env,
+            json!({ "member_ids": ["1111111111"], "action": "tag", "tags": [] }),
+        )
+        .await;
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+        assert_eq!(body["fields"][0]["field"], "tags");
+
+        let too_many: Vec<String> = (0..1001).map(|i| format!("{:010x}", i)).collect();
+        let (status, _) = bulk(
+            &env,
+            json!({ "member_ids": too_many, "action": "authorize" }),
+        )
+        .await;
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+    }
+
+    #[tokio::test]
+    async fn authorizes_each_member_independently() {
+        let env = setup().await;
+
+        // Duplicates collapse onto the first occurrence, unknown members fail alone
+        let (status, body) = bulk(
+            &env,
+            json!({
+                "member_ids": ["1111111111", "2222222222", "2222222222", "3333333333"],
+                "action": "authorize",
+            }),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(
+            statuses(&body),
+            pairs(&[
+                ("1111111111", "unchanged"),
+                ("2222222222", "applied"),
+                ("3333333333", "failed"),
+            ])
+        );
+        assert_eq!(body["total"], 3);
+        assert_eq!(body["succeeded"], 2);
+        assert_eq!(body["failed"], 1);
+        assert!(body["results"][2]["error"].is_string());
+        assert_eq!(
+            env.controller.member(NETWORK, "2222222222").unwrap()["authorized"],
+            true
+        );
+
+        let entries = env.app_state().audit.recent(10, |_| true).await;
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].action, "member.authorize");
+        assert_eq!(entries[0].details["bulk"], true);
+    }
+
+    #[tokio::test]
+    async fn dry_runs_change_nothing() {
+        let env = setup().await;
+
+        let (_, body) = bulk(
+            &env,
+            json!({
+                "member_ids": ["1111111111", "2222222222"],
+                "action": "deauthorize",
+                "dry_run": true,
+            }),
+        )
+        .await;
+        assert_eq!(body["dry_run"], true);
+        assert_eq!(
+            statuses(&body),
+            pairs(&[("1111111111", "planned"), ("2222222222", "unchanged")])
+        );
+        assert_eq!(body["results"][0]["update"], json!({ "authorized": false }));
+        assert_eq!(
+            env.controller.member(NETWORK, "1111111111").unwrap()["authorized"],
+            true
+        );
+
+        let (_, body) = bulk(
+            &env,
+            json!({ "member_ids": ["1111111111"], "action": "delete", "dry_run": true }),
+        )
+        .await;
+        assert_eq!(statuses(&body), pairs(&[("1111111111", "planned")]));
+        assert!(env.controller.member(NETWORK, "1111111111").is_some());
+        assert!(env.app_state().audit.recent(10, |_| true).await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn renames_with_placeholders() {
+        let env = setup().await;
+
+        let (_, body) = bulk(
+            &env,
+            json!({
+                "member_ids": ["1111111111", "2222222222"],
+                "action": "rename",
+                "name": "node-{index}-{id}",
+            }),
+        )
+        .await;
+        assert_eq!(body["succeeded"], 2);
+        assert_eq!(
+            env.controller.member(NETWORK, "1111111111").unwrap()["name"],
+            "node-1-1111111111"
+        );
+        assert_eq!(
+            env.controller.member(NETWORK, "2222222222").unwrap()["name"],
+            "node-2-2222222222"
+        );
+    }
+
+    #[tokio::test]
+    async fn merges_tags() {
+        let env = setup().await;
+
+        let (_, body) = bulk(
+            &env,
+            json!({
+                "member_ids": ["2222222222", "1111111111"],
+                "action": "tag",
+                "tags": [[2, 9], [3, 1]],
+            }),
+        )
+        .await;
+        assert_eq!(
+            statuses(&body),
+            pairs(&[("2222222222", "applied"), ("1111111111", "applied")])
+        );
+        assert_eq!(
+            env.controller.member(NETWORK, "2222222222").unwrap()["tags"],
+            json!([[1, 5], [2, 9], [3, 1]])
+        );
+        assert_eq!(
+            env.controller.member(NETWORK, "1111111111").unwrap()["tags"],
+            json!([[2, 9], [3, 1]])
+        );
+
+        // Setting the same tags again changes nothing
+        let (_, body) = bulk(
+            &env,
+            json!({ "member_ids": ["2222222222"], "action": "tag", "tags": [[2, 9]] }),
+        )
+        .await;
+        assert_eq!(statuses(&body), pairs(&[("2222222222", "unchanged")]));
+    }
+
+    #[tokio::test]
+    async fn deletes_members_and_their_metadata() {
+        let env = setup().await;
+        let app_state = env.app_state();
+        app_state
+            .metadata
+            .update(NETWORK, "1111111111", |metadata| {
+                metadata.info.owner = Some("alice".to_string());
+                Ok(())
+            })
+            .await
+            .unwrap();
+        env.controller
+            .state
+            .lock()
+            .unwrap()
+            .failures
+            .insert(format!(
+                "DELETE /controller/network/{}/member/2222222222",
+                NETWORK
+            ));
+
+        let (_, body) = bulk(
+            &env,
+            json!({ "member_ids": ["1111111111", "2222222222"], "action": "delete" }),
+        )
+        .await;
+        assert_eq!(
+            statuses(&body),
+            pairs(&[("1111111111", "applied"), ("2222222222", "failed")])
+        );
+        assert!(env.controller.member(NETWORK, "1111111111").is_none());
+        assert!(env.controller.member(NETWORK, "2222222222").is_some());
+        let metadata = env.app_state().metadata.network(NETWORK).await;
+        assert!(metadata.is_empty());
+    }
+}
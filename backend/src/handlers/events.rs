@@ -0,0 +1,116 @@
+use crate::services::events::Event;
+use crate::state::AppState;
+use axum::{
+    extract::{Query, State},
+    response::sse::{self, KeepAlive, Sse},
+};
+use futures::{stream, Stream};
+use serde::Deserialize;
+use std::convert::Infallible;
+use std::sync::Arc;
+use tokio::sync::broadcast::{error::RecvError, Receiver};
+
+#[derive(Debug, Deserialize)]
+pub struct EventStreamQuery {
+    /// Only stream events for this network
+    pub network_id: Option<String>,
+}
+
+/// Stream controller events to the client as Server-Sent Events
+pub async fn stream_events(
+    State(app_state): State<AppState>,
+    Query(query): Query<EventStreamQuery>,
+) -> Sse<impl Stream<Item = Result<sse::Event, Infallible>>> {
+    let receiver = app_state.events.subscribe();
+
+    let events = stream::unfold(receiver, move |mut receiver: Receiver<Arc<Event>>| {
+        let network_id = query.network_id.clone();
+        async move {
+            loop {
+                match receiver.recv().await {
+                    Ok(event) => {
+                        if network_id
+                            .as_deref()
+                            .is_some_and(|id| event.kind.network_id() != Some(id))
+                        {
+                            continue;
+                        }
+
+                        let message = sse::Event::default()
+                            .id(event.id.to_string())
+                            .event(event.kind.name())
+                            .json_data(event.as_ref())
+                            .unwrap_or_default();
+                        return Some((Ok(message), receiver));
+                    }
+                    Err(RecvError::Lagged(skipped)) => {
+                        tracing::warn!("Event stream lagged, skipped {} events", skipped);
+                    }
+                    Err(RecvError::Closed) => return None,
+                }
+            }
+        }
+    });
+
+    Sse::new(events).keep_alive(KeepAlive::default())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::services::auth::SESSION_COOKIE;
+    use crate::services::events::EventKind;
+    use crate::test_support::{send, user_token, TestEnv};
+    use axum::{
+        body::Body,
+        http::{header, Request, StatusCode},
+    };
+    use futures::StreamExt;
+
+    #[tokio::test]
+    async fn streams_events_for_the_requested_network() {
+        let env = TestEnv::new().await;
+        let app_state = env.app_state();
+
+        // Browsers authenticate event streams with the session cookie
+        let request = Request::builder()
+            .uri("/api/events?network_id=abcdef0123000001")
+            .header(
+                header::COOKIE,
+                format!("{}={}", SESSION_COOKIE, user_token(&app_state)),
+            )
+            .body(Body::empty())
+            .unwrap();
+        let response = send(&app_state, request).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(
+            response.headers()[header::CONTENT_TYPE],
+            "text/event-stream"
+        );
+
+        app_state.events.publish(EventKind::NetworkDeleted {
+            network_id: "abcdef0123000002".to_string(),
+        });
+        app_state.events.publish(EventKind::MemberDeleted {
+            network_id: "abcdef0123000001".to_string(),
+            member_id: "1111111111".to_string(),
+        });
+
+        let mut body = response.into_body().into_data_stream();
+        let frame = body.next().await.unwrap().unwrap();
+        let frame = String::from_utf8_lossy(&frame);
+        assert!(frame.contains("event: member_deleted\n"), "{}", frame);
+        assert!(frame.contains("id: 2\n"), "{}", frame);
+        assert!(frame.contains(r#""member_id":"1111111111""#), "{}", frame);
+    }
+
+    #[tokio::test]
+    async fn rejects_unauthenticated_streams() {
+        let env = TestEnv::new().await;
+        let request = Request::builder()
+            .uri("/api/events")
+            .body(Body::empty())
+            .unwrap();
+        let response = send(&env.app_state(), request).await;
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    }
+}
@@ -0,0 +1,36 @@
+use crate::error::Result;
+use crate::models::Manifest;
+use crate::services::auth::Claims;
+use crate::services::GitOpsService;
+use crate::state::AppState;
+use axum::{extract::State, response::IntoResponse, Extension, Json};
+
+/// Parse the YAML or JSON manifest in the body, or load the configured one if the body is empty
+async fn manifest(app_state: &AppState, body: &str) -> Result<Manifest> {
+    if body.trim().is_empty() {
+        app_state.gitops.configured_manifest().await
+    } else {
+        GitOpsService::parse_manifest(body)
+    }
+}
+
+/// Show the changes needed to converge the controller to a manifest
+pub async fn plan_manifest(
+    State(app_state): State<AppState>,
+    body: String,
+) -> Result<impl IntoResponse> {
+    let manifest = manifest(&app_state, &body).await?;
+    Ok(Json(app_state.gitops.plan(&manifest).await?))
+}
+
+/// Converge the controller to a manifest, reporting the outcome per change
+pub async fn apply_manifest(
+    State(app_state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    body: String,
+) -> Result<impl IntoResponse> {
+    let manifest = manifest(&app_state, &body).await?;
+    Ok(Json(
+        app_state.gitops.apply(&claims.username, &manifest).await?,
+    ))
+}
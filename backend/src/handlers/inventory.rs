@@ -0,0 +1,77 @@
+use crate::error::Result;
+use crate::models::{InventoryFormat, InventoryQuery};
+use crate::state::AppState;
+use axum::{
+    extract::{Path, Query, State},
+    http::header,
+    response::IntoResponse,
+};
+
+/// Render a network's authorized members as a hosts file, Ansible inventory,
+/// Prometheus `http_sd` targets or SSH config
+pub async fn export_inventory(
+    State(app_state): State<AppState>,
+    Path((network_id, format)): Path<(String, InventoryFormat)>,
+    Query(query): Query<InventoryQuery>,
+) -> Result<impl IntoResponse> {
+    let (content_type, body) = app_state
+        .inventory
+        .render(&network_id, format, &query)
+        .await?;
+
+    Ok(([(header::CONTENT_TYPE, content_type)], body))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test_support::{send, text_body, user_token, TestEnv};
+    use axum::{
+        body::Body,
+        http::{header, Request, StatusCode},
+    };
+    use serde_json::json;
+
+    #[tokio::test]
+    async fn serves_each_format_with_its_content_type() {
+        let env = TestEnv::new().await;
+        env.controller
+            .add_network("abcdef0123000001", json!({ "name": "lab" }));
+        env.controller.add_member(
+            "abcdef0123000001",
+            "1111111111",
+            json!({ "name": "web", "authorized": true, "ipAssignments": ["10.0.0.1"] }),
+        );
+        let app_state = env.app_state();
+        let get = |path: &str, authorized: bool| {
+            let mut request = Request::builder()
+                .uri(format!("/api/networks/abcdef0123000001/inventory/{}", path));
+            if authorized {
+                request = request.header(
+                    header::AUTHORIZATION,
+                    format!("Bearer {}", user_token(&app_state)),
+                );
+            }
+            send(&app_state, request.body(Body::empty()).unwrap())
+        };
+
+        for (format, content_type) in [
+            ("hosts", "text/plain; charset=utf-8"),
+            ("ansible-yaml", "application/yaml"),
+            ("ansible-ini", "text/plain; charset=utf-8"),
+            ("prometheus", "application/json"),
+            ("ssh-config", "text/plain; charset=utf-8"),
+        ] {
+            let response = get(&format!("{}?name_source=name", format), true).await;
+            assert_eq!(response.status(), StatusCode::OK, "{}", format);
+            assert_eq!(response.headers()[header::CONTENT_TYPE], content_type);
+            assert!(text_body(response).await.contains("10.0.0.1"), "{}", format);
+        }
+
+        assert_eq!(get("csv", true).await.status(), StatusCode::BAD_REQUEST);
+        assert_eq!(get("hosts", false).await.status(), StatusCode::UNAUTHORIZED);
+        assert_eq!(
+            get("hosts?tag=x", true).await.status(),
+            StatusCode::UNPROCESSABLE_ENTITY
+        );
+    }
+}
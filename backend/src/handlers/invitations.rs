@@ -0,0 +1,215 @@
+use crate::error::{AppError, Result};
+use crate::handlers::auth::extract_real_ip;
+use crate::models::{CreateInvitationRequest, InvitationListQuery, RedeemInvitationRequest};
+use crate::services::auth::Claims;
+use crate::services::StaticFileService;
+use crate::state::AppState;
+use axum::{
+    extract::{ConnectInfo, Path, Query, State},
+    http::{HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+    Extension, Json,
+};
+use serde_json::json;
+use std::net::SocketAddr;
+
+pub async fn create_invitation(
+    State(app_state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Json(request): Json<CreateInvitationRequest>,
+) -> Result<impl IntoResponse> {
+    let invitation = app_state
+        .invitations
+        .create(&claims.username, request)
+        .await?;
+    let join_path = format!("/join/{}", invitation.token);
+
+    Ok((
+        StatusCode::CREATED,
+        Json(json!({
+            "invitation": invitation,
+            "join_path": join_path,
+        })),
+    ))
+}
+
+pub async fn list_invitations(
+    State(app_state): State<AppState>,
+    Query(query): Query<InvitationListQuery>,
+) -> Result<impl IntoResponse> {
+    Ok(Json(
+        app_state
+            .invitations
+            .list(query.network_id.as_deref())
+            .await,
+    ))
+}
+
+pub async fn revoke_invitation(
+    State(app_state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Path(id): Path<String>,
+) -> Result<impl IntoResponse> {
+    Ok(Json(
+        app_state.invitations.revoke(&claims.username, &id).await?,
+    ))
+}
+
+/// The public page where the recipient of a join link enters their node ID
+pub async fn serve_join_page(State(app_state): State<AppState>) -> Response {
+    let mode = &app_state.config.get_config().mode;
+    if mode.maintenance {
+        return StaticFileService::serve_maintenance_page(&mode.maintenance_message);
+    }
+
+    StaticFileService::serve_join_page()
+}
+
+pub async fn get_join_info(
+    State(app_state): State<AppState>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
+    Path(token): Path<String>,
+) -> Result<impl IntoResponse> {
+    let client_ip = extract_real_ip(&headers, addr);
+    check_not_banned(&app_state, &client_ip).await?;
+
+    match app_state.invitations.info(&token).await {
+        Ok(info) => Ok(Json(info)),
+        Err(e) => {
+            // Probing for tokens counts the same as a failed login
+            app_state.ip_ban.record_failure(&client_ip).await;
+            Err(e)
+        }
+    }
+}
+
+pub async fn redeem_invitation(
+    State(app_state): State<AppState>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
+    Path(token): Path<String>,
+    Json(request): Json<RedeemInvitationRequest>,
+) -> Result<impl IntoResponse> {
+    let mode = &app_state.config.get_config().mode;
+    if mode.maintenance {
+        return Err(AppError::ServiceUnavailable(
+            mode.maintenance_message.clone(),
+        ));
+    }
+
+    let client_ip = extract_real_ip(&headers, addr);
+    check_not_banned(&app_state, &client_ip).await?;
+
+    match app_state
+        .invitations
+        .redeem(&token, request.node_id.trim(), client_ip)
+        .await
+    {
+        Ok(invitation) => Ok(Json(json!({
+            "message": "Your node has been authorized",
+            "network_id": invitation.network_id,
+        }))),
+        Err(e) => {
+            if matches!(e, AppError::NotFound(_)) {
+                app_state.ip_ban.record_failure(&client_ip).await;
+            }
+            Err(e)
+        }
+    }
+}
+
+async fn check_not_banned(app_state: &AppState, client_ip: &std::net::IpAddr) -> Result<()> {
+    if app_state.ip_ban.is_banned(client_ip).await {
+        let remaining_seconds = app_state
+            .ip_ban
+            .get_ban_remaining_seconds(client_ip)
+            .await
+            .unwrap_or_default();
+        return Err(AppError::TooManyRequests(format!(
+            "Too many failed attempts. Please try again in {} seconds.",
+            remaining_seconds
+        )));
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test_support::{json_body, send, user_token, TestEnv};
+    use axum::{
+        body::Body,
+        http::{header, Method, Request, StatusCode},
+    };
+    use serde_json::{json, Value};
+
+    const NETWORK: &str = "abcdef0123000001";
+
+    fn post(path: &str, token: Option<&str>, body: Value) -> Request<Body> {
+        let mut request = Request::builder()
+            .method(Method::POST)
+            .uri(path)
+            .header(header::CONTENT_TYPE, "application/json");
+        if let Some(token) = token {
+            request = request.header(header::AUTHORIZATION, format!("Bearer {}", token));
+        }
+        request.body(Body::from(body.to_string())).unwrap()
+    }
+
+    #[tokio::test]
+    async fn huge_lifetimes_are_a_validation_error() {
+        let env = TestEnv::new().await;
+        env.controller.add_network(NETWORK, json!({}));
+        let app_state = env.app_state();
+        let token = user_token(&app_state);
+
+        let request = post(
+            "/api/invitations",
+            Some(&token),
+            json!({ "network_id": NETWORK, "expires_in": i64::MAX }),
+        );
+        let (status, body) = json_body(send(&app_state, request).await).await;
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+        assert_eq!(body["fields"][0]["field"], "expires_in");
+    }
+
+    #[tokio::test]
+    async fn join_links_authorize_nodes() {
+        let env = TestEnv::new().await;
+        env.controller.add_network(NETWORK, json!({}));
+        let app_state = env.app_state();
+        let token = user_token(&app_state);
+
+        let request = post(
+            "/api/invitations",
+            Some(&token),
+            json!({ "network_id": NETWORK, "expires_in": 600 }),
+        );
+        let (status, body) = json_body(send(&app_state, request).await).await;
+        assert_eq!(status, StatusCode::CREATED);
+        let join_path = body["join_path"].as_str().unwrap();
+
+        let request = post(
+            &format!("/api{}", join_path),
+            None,
+            json!({ "node_id": " 1111111111 " }),
+        );
+        let (status, body) = json_body(send(&app_state, request).await).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["network_id"], NETWORK);
+        assert_eq!(
+            env.controller.member(NETWORK, "1111111111").unwrap()["authorized"],
+            true
+        );
+
+        // Reusing a one-time link counts as a failed attempt
+        let request = post(
+            &format!("/api{}", join_path),
+            None,
+            json!({ "node_id": "2222222222" }),
+        );
+        let response = send(&app_state, request).await;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+}
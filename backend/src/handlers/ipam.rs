@@ -0,0 +1,136 @@
+use crate::error::Result;
+use crate::models::{
+    AllocateIpRequest, AllocateSubnetRequest, NextIpQuery, NextSubnetQuery, ReserveIpRequest,
+};
+use crate::services::auth::Claims;
+use crate::state::AppState;
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::IntoResponse,
+    Extension, Json,
+};
+use serde_json::json;
+use std::net::IpAddr;
+
+/// Supernet usage, the next free subnet and overlapping routes or pools
+pub async fn get_ipam_overview(State(app_state): State<AppState>) -> Result<impl IntoResponse> {
+    Ok(Json(app_state.ipam.overview().await?))
+}
+
+pub async fn get_next_subnet(
+    State(app_state): State<AppState>,
+    Query(query): Query<NextSubnetQuery>,
+) -> Result<impl IntoResponse> {
+    let subnet = app_state.ipam.next_subnet(query.prefix_len).await?;
+    Ok(Json(json!({ "subnet": subnet })))
+}
+
+/// Add the next free subnet to a network's routes and assignment pools
+pub async fn allocate_network_subnet(
+    State(app_state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Path(network_id): Path<String>,
+    Json(request): Json<AllocateSubnetRequest>,
+) -> Result<impl IntoResponse> {
+    Ok(Json(
+        app_state
+            .ipam
+            .allocate_subnet(&claims.username, &network_id, request.prefix_len)
+            .await?,
+    ))
+}
+
+pub async fn get_next_ips(
+    State(app_state): State<AppState>,
+    Path(network_id): Path<String>,
+    Query(query): Query<NextIpQuery>,
+) -> Result<impl IntoResponse> {
+    let ips = app_state.ipam.next_ips(&network_id, query.count).await?;
+    Ok(Json(json!({ "ips": ips })))
+}
+
+pub async fn list_ip_reservations(
+    State(app_state): State<AppState>,
+    Path(network_id): Path<String>,
+) -> impl IntoResponse {
+    Json(app_state.ipam.reservations(&network_id).await)
+}
+
+pub async fn reserve_ip(
+    State(app_state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Path(network_id): Path<String>,
+    Json(request): Json<ReserveIpRequest>,
+) -> Result<impl IntoResponse> {
+    let reservation = app_state
+        .ipam
+        .reserve(&claims.username, &network_id, request)
+        .await?;
+    Ok((StatusCode::CREATED, Json(reservation)))
+}
+
+pub async fn release_ip(
+    State(app_state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Path((network_id, ip)): Path<(String, IpAddr)>,
+) -> Result<impl IntoResponse> {
+    app_state
+        .ipam
+        .release(&claims.username, &network_id, ip)
+        .await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Assign the member's reservation or the next free address to it
+pub async fn allocate_member_ip(
+    State(app_state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Path((network_id, member_id)): Path<(String, String)>,
+    Json(request): Json<AllocateIpRequest>,
+) -> Result<impl IntoResponse> {
+    Ok(Json(
+        app_state
+            .ipam
+            .allocate_ip(&claims.username, &network_id, &member_id, request)
+            .await?,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test_support::{json_body, send, user_token, TestEnv};
+    use axum::{
+        body::Body,
+        http::{header, Request, StatusCode},
+    };
+    use serde_json::json;
+
+    #[tokio::test]
+    async fn rejects_a_prefix_length_the_supernet_cannot_hold() {
+        let env = TestEnv::with_config(|config| {
+            config.ipam.supernet = Some("10.8.0.0/16".parse().unwrap());
+            config.ipam.subnet_prefix_len = 64;
+        })
+        .await;
+        let app_state = env.app_state();
+
+        for uri in [
+            "/api/ipam",
+            "/api/ipam/next-subnet",
+            "/api/ipam/next-subnet?prefix_len=8",
+        ] {
+            let request = Request::builder()
+                .uri(uri)
+                .header(
+                    header::AUTHORIZATION,
+                    format!("Bearer {}", user_token(&app_state)),
+                )
+                .body(Body::empty())
+                .unwrap();
+            let (status, body) = json_body(send(&app_state, request).await).await;
+            assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY, "{}", uri);
+            assert_eq!(body["error"], json!("Validation failed"));
+        }
+    }
+}
@@ -1,7 +1,37 @@
+pub mod admin;
+pub mod audit;
 pub mod auth;
+pub mod bulk;
+pub mod events;
+pub mod gitops;
+pub mod inventory;
+pub mod invitations;
+pub mod ipam;
+pub mod networks;
+pub mod rules;
+pub mod schedules;
+pub mod search;
 pub mod static_files;
+pub mod templates;
+pub mod transfer;
+pub mod world;
 pub mod zerotier;
 
+pub use admin::*;
+pub use audit::*;
 pub use auth::*;
+pub use bulk::*;
+pub use events::*;
+pub use gitops::*;
+pub use inventory::*;
+pub use invitations::*;
+pub use ipam::*;
+pub use networks::*;
+pub use rules::*;
+pub use schedules::*;
+pub use search::*;
 pub use static_files::*;
+pub use templates::*;
+pub use transfer::*;
+pub use world::*;
 pub use zerotier::*;
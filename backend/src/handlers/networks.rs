@@ -0,0 +1,511 @@
+use crate::error::{AppError, FieldError, Result};
+use crate::models::{
+    MemberExpiry, MemberInfo, MemberListQuery, MemberListResponse, MemberMetadata, MemberSortKey,
+    NetworkMember, SetExpiryRequest, SortOrder,
+};
+use crate::services::audit::AuditEntry;
+use crate::services::auth::Claims;
+use crate::state::AppState;
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::IntoResponse,
+    Extension, Json,
+};
+use chrono::{TimeDelta, Utc};
+use serde_json::{json, Map, Value};
+use std::cmp::Ordering;
+
+const MAX_PER_PAGE: usize = 500;
+
+fn compare_members(a: &NetworkMember, b: &NetworkMember, key: MemberSortKey) -> Ordering {
+    let latency = |member: &NetworkMember| {
+        member
+            .peer
+            .as_ref()
+            .map(|peer| peer.latency)
+            .filter(|latency| *latency >= 0)
+            .unwrap_or(i64::MAX)
+    };
+
+    match key {
+        MemberSortKey::Id => a.id().cmp(b.id()),
+        MemberSortKey::Name => a.name().to_lowercase().cmp(&b.name().to_lowercase()),
+        MemberSortKey::Authorized => a.authorized().cmp(&b.authorized()),
+        MemberSortKey::Online => a.online.cmp(&b.online),
+        MemberSortKey::Latency => latency(a).cmp(&latency(b)),
+        MemberSortKey::CreationTime => a
+            .int_field("creationTime")
+            .cmp(&b.int_field("creationTime")),
+        MemberSortKey::LastAuthorizedTime => a
+            .int_field("lastAuthorizedTime")
+            .cmp(&b.int_field("lastAuthorizedTime")),
+    }
+    // Keep the order stable between pages
+    .then_with(|| a.id().cmp(b.id()))
+}
+
+fn matches_query(member: &NetworkMember, query: &MemberListQuery) -> bool {
+    if query
+        .authorized
+        .is_some_and(|authorized| member.authorized() != authorized)
+    {
+        return false;
+    }
+
+    if query.online.is_some_and(|online| member.online != online) {
+        return false;
+    }
+
+    match query.q.as_deref().map(str::trim) {
+        Some(q) if !q.is_empty() => {
+            let q = q.to_lowercase();
+            member.id().contains(&q)
+                || member.name().to_lowercase().contains(&q)
+                || member
+                    .addresses()
+                    .iter()
+                    .any(|ip| ip.to_string().contains(&q))
+        }
+        _ => true,
+    }
+}
+
+/// Add the backend-side metadata to a member response
+fn merge_metadata(member: &mut NetworkMember, metadata: &MemberMetadata) {
+    member.expiry = metadata
+        .expiry
+        .as_ref()
+        .map(|expiry| expiry.status(Utc::now()));
+    member.metadata = metadata.info.clone();
+}
+
+/// List a network's members merged with peer data in a single request
+pub async fn list_network_members(
+    State(app_state): State<AppState>,
+    Path(network_id): Path<String>,
+    Query(query): Query<MemberListQuery>,
+) -> Result<impl IntoResponse> {
+    let mut members = app_state.zerotier.network_members(&network_id).await?;
+
+    let metadata = app_state.metadata.network(&network_id).await;
+    for member in &mut members {
+        if let Some(metadata) = metadata.get(member.id()) {
+            merge_metadata(member, metadata);
+        }
+    }
+
+    members.retain(|member| matches_query(member, &query));
+    members.sort_by(|a, b| {
+        let ordering = compare_members(a, b, query.sort);
+        match query.order {
+            SortOrder::Asc => ordering,
+            SortOrder::Desc => ordering.reverse(),
+        }
+    });
+
+    let total = members.len();
+    let page = query.page.max(1);
+    let per_page = query.per_page.clamp(1, MAX_PER_PAGE);
+    let items = members
+        .into_iter()
+        .skip((page - 1).saturating_mul(per_page))
+        .take(per_page)
+        .collect();
+
+    Ok(Json(MemberListResponse {
+        total,
+        page,
+        per_page,
+        items,
+    }))
+}
+
+/// Get a single member merged with its peer data and metadata
+pub async fn get_network_member(
+    State(app_state): State<AppState>,
+    Path((network_id, member_id)): Path<(String, String)>,
+) -> Result<impl IntoResponse> {
+    let network_endpoint = format!("/controller/network/{}", network_id);
+    let endpoint = format!("{}/member/{}", network_endpoint, member_id);
+    let (network, config, mut peers) = tokio::try_join!(
+        app_state
+            .zerotier
+            .get_json::<Map<String, Value>>(&network_endpoint),
+        app_state.zerotier.get_json::<Map<String, Value>>(&endpoint),
+        app_state.zerotier.list_peers()
+    )?;
+
+    let peer = config
+        .get("address")
+        .and_then(Value::as_str)
+        .and_then(|address| peers.remove(address));
+    let mut member = NetworkMember::new(config, peer);
+    member.compute_addresses(&network_id, &network);
+    if let Some(metadata) = app_state
+        .metadata
+        .network(&network_id)
+        .await
+        .get(&member_id)
+    {
+        merge_metadata(&mut member, metadata);
+    }
+
+    Ok(Json(member))
+}
+
+pub async fn get_member_metadata(
+    State(app_state): State<AppState>,
+    Path((network_id, member_id)): Path<(String, String)>,
+) -> Result<impl IntoResponse> {
+    let metadata = app_state.metadata.network(&network_id).await;
+
+    Ok(Json(
+        metadata
+            .get(&member_id)
+            .map(|metadata| metadata.info.clone())
+            .unwrap_or_default(),
+    ))
+}
+
+/// Replace a member's owner, asset tag, location, notes and labels
+pub async fn update_member_metadata(
+    State(app_state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Path((network_id, member_id)): Path<(String, String)>,
+    Json(info): Json<MemberInfo>,
+) -> Result<impl IntoResponse> {
+    if info.labels.keys().any(|key| key.trim().is_empty()) {
+        return Err(AppError::ValidationError(vec![FieldError::new(
+            "labels",
+            "keys must not be empty",
+        )]));
+    }
+
+    // Only track members the controller knows about
+    app_state
+        .zerotier
+        .get_json::<Value>(&format!(
+            "/controller/network/{}/member/{}",
+            network_id, member_id
+        ))
+        .await?;
+
+    // Empty strings clear a field
+    let clean = |value: Option<String>| value.filter(|value| !value.trim().is_empty());
+    let info = MemberInfo {
+        owner: clean(info.owner),
+        asset_tag: clean(info.asset_tag),
+        location: clean(info.location),
+        notes: clean(info.notes),
+        labels: info.labels,
+    };
+
+    app_state
+        .metadata
+        .update(&network_id, &member_id, |metadata| {
+            metadata.info = info.clone();
+            Ok(())
+        })
+        .await?;
+
+    app_state
+        .audit
+        .record(
+            AuditEntry::new(&claims.username, "member.update_metadata")
+                .member(&network_id, &member_id)
+                .details(serde_json::to_value(&info)?),
+        )
+        .await;
+
+    Ok(Json(info))
+}
+
+/// Set when a member loses access, replacing any earlier expiry
+pub async fn set_member_expiry(
+    State(app_state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Path((network_id, member_id)): Path<(String, String)>,
+    Json(request): Json<SetExpiryRequest>,
+) -> Result<impl IntoResponse> {
+    let now = Utc::now();
+    let expires_at = match (request.expires_at, request.expires_in) {
+        (Some(expires_at), None) => expires_at,
+        (None, Some(expires_in)) => {
+            if expires_in <= 0 {
+                return Err(AppError::ValidationError(vec![FieldError::new(
+                    "expires_in",
+                    "must be greater than 0",
+                )]));
+            }
+            TimeDelta::try_seconds(expires_in)
+                .and_then(|lifetime| now.checked_add_signed(lifetime))
+                .ok_or_else(|| {
+                    AppError::ValidationError(vec![FieldError::new("expires_in", "is too large")])
+                })?
+        }
+        _ => {
+            return Err(AppError::ValidationError(vec![FieldError::new(
+                "expires_at",
+                "set exactly one of expires_at and expires_in",
+            )]))
+        }
+    };
+    if expires_at <= now {
+        return Err(AppError::ValidationError(vec![FieldError::new(
+            "expires_at",
+            "must be in the future",
+        )]));
+    }
+
+    // Only track members the controller knows about
+    app_state
+        .zerotier
+        .get_json::<Value>(&format!(
+            "/controller/network/{}/member/{}",
+            network_id, member_id
+        ))
+        .await?;
+
+    let expiry = MemberExpiry {
+        expires_at,
+        delete: request.delete,
+        warned: false,
+    };
+    app_state
+        .metadata
+        .update(&network_id, &member_id, |metadata| {
+            metadata.expiry = Some(expiry.clone());
+            metadata.expired_at = None;
+            Ok(())
+        })
+        .await?;
+
+    app_state
+        .audit
+        .record(
+            AuditEntry::new(&claims.username, "member.set_expiry")
+                .member(&network_id, &member_id)
+                .details(json!({ "expires_at": expires_at, "delete": request.delete })),
+        )
+        .await;
+
+    Ok(Json(expiry.status(now)))
+}
+
+pub async fn clear_member_expiry(
+    State(app_state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Path((network_id, member_id)): Path<(String, String)>,
+) -> Result<impl IntoResponse> {
+    let cleared = app_state
+        .metadata
+        .update(&network_id, &member_id, |metadata| {
+            let expired = metadata.expired_at.take();
+            Ok(metadata.expiry.take().is_some() || expired.is_some())
+        })
+        .await?;
+    if !cleared {
+        return Err(AppError::NotFound(format!(
+            "Member {} has no expiry",
+            member_id
+        )));
+    }
+
+    app_state
+        .audit
+        .record(
+            AuditEntry::new(&claims.username, "member.clear_expiry")
+                .member(&network_id, &member_id),
+        )
+        .await;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{json_body, send, user_token, TestEnv};
+    use axum::{
+        body::Body,
+        http::{header, Method, Request},
+    };
+
+    const NETWORK: &str = "abcdef0123000001";
+
+    fn query(query: &str) -> Query<MemberListQuery> {
+        let uri = format!("/?{}", query).parse().unwrap();
+        Query::try_from_uri(&uri).unwrap()
+    }
+
+    async fn list(env: &TestEnv, params: &str) -> Value {
+        let response = list_network_members(
+            State(env.app_state()),
+            Path(NETWORK.to_string()),
+            query(params),
+        )
+        .await
+        .unwrap();
+        json_body(response).await.1
+    }
+
+    fn ids(response: &Value) -> Vec<&str> {
+        response["items"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|member| member["id"].as_str().unwrap())
+            .collect()
+    }
+
+    async fn env() -> TestEnv {
+        let env = TestEnv::new().await;
+        env.controller
+            .add_network(NETWORK, json!({ "name": "lab" }));
+        env.controller.add_member(
+            NETWORK,
+            "1111111111",
+            json!({ "name": "Alpha", "authorized": true, "ipAssignments": ["10.0.0.11"] }),
+        );
+        env.controller.add_member(
+            NETWORK,
+            "2222222222",
+            json!({ "name": "beta", "authorized": false }),
+        );
+        env.controller.add_member(
+            NETWORK,
+            "3333333333",
+            json!({ "name": "gamma", "authorized": true, "ipAssignments": ["10.0.0.33"] }),
+        );
+        env.controller.add_peer("1111111111", 40, true);
+        env.controller.add_peer("3333333333", 5, true);
+        env.controller.add_peer("2222222222", -1, false);
+        env
+    }
+
+    #[tokio::test]
+    async fn merges_peer_data_into_members() {
+        let env = env().await;
+        let response = list(&env, "").await;
+
+        assert_eq!(response["total"], 3);
+        assert_eq!(ids(&response), ["1111111111", "2222222222", "3333333333"]);
+        let items = response["items"].as_array().unwrap();
+        assert_eq!(items[0]["online"], true);
+        assert_eq!(items[0]["peer"]["latency"], 40);
+        assert_eq!(items[0]["peer"]["role"], "LEAF");
+        // A peer without an active path is offline
+        assert_eq!(items[1]["online"], false);
+        assert_eq!(items[1]["peer"]["latency"], -1);
+    }
+
+    #[tokio::test]
+    async fn filters_and_sorts_members() {
+        let env = env().await;
+
+        let response = list(&env, "authorized=true&sort=latency").await;
+        assert_eq!(ids(&response), ["3333333333", "1111111111"]);
+
+        // Unknown latency sorts last
+        let response = list(&env, "sort=latency&order=asc").await;
+        assert_eq!(ids(&response), ["3333333333", "1111111111", "2222222222"]);
+
+        let response = list(&env, "sort=name&order=desc").await;
+        assert_eq!(ids(&response), ["3333333333", "2222222222", "1111111111"]);
+
+        let response = list(&env, "q=ALPHA").await;
+        assert_eq!(ids(&response), ["1111111111"]);
+        let response = list(&env, "q=10.0.0.33").await;
+        assert_eq!(ids(&response), ["3333333333"]);
+        let response = list(&env, "online=false").await;
+        assert_eq!(ids(&response), ["2222222222"]);
+    }
+
+    #[tokio::test]
+    async fn paginates_members() {
+        let env = env().await;
+
+        let response = list(&env, "page=2&per_page=2").await;
+        assert_eq!(response["total"], 3);
+        assert_eq!(response["page"], 2);
+        assert_eq!(ids(&response), ["3333333333"]);
+
+        // Out of range values are clamped
+        let response = list(&env, "page=0&per_page=0").await;
+        assert_eq!(response["page"], 1);
+        assert_eq!(response["per_page"], 1);
+        assert_eq!(ids(&response), ["1111111111"]);
+    }
+
+    #[tokio::test]
+    async fn unknown_network_is_not_found() {
+        let env = env().await;
+        let result = list_network_members(
+            State(env.app_state()),
+            Path("abcdef0123999999".to_string()),
+            query(""),
+        )
+        .await;
+        assert!(matches!(result, Err(AppError::NotFound(_))));
+    }
+
+    async fn set_expiry(env: &TestEnv, body: Value) -> (StatusCode, Value) {
+        let app_state = env.app_state();
+        let request = Request::builder()
+            .method(Method::PUT)
+            .uri(format!(
+                "/api/networks/{}/members/1111111111/expiry",
+                NETWORK
+            ))
+            .header(header::CONTENT_TYPE, "application/json")
+            .header(
+                header::AUTHORIZATION,
+                format!("Bearer {}", user_token(&app_state)),
+            )
+            .body(Body::from(body.to_string()))
+            .unwrap();
+        json_body(send(&app_state, request).await).await
+    }
+
+    #[tokio::test]
+    async fn rejects_expiry_lifetimes_out_of_range() {
+        let env = TestEnv::new().await;
+        env.controller.add_network(NETWORK, json!({}));
+        env.controller.add_member(NETWORK, "1111111111", json!({}));
+
+        for expires_in in [0, -60, i64::MAX, i64::MIN] {
+            let (status, body) = set_expiry(&env, json!({ "expires_in": expires_in })).await;
+            assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY, "{}", expires_in);
+            assert_eq!(body["fields"][0]["field"], "expires_in");
+        }
+
+        let (status, body) = set_expiry(
+            &env,
+            json!({ "expires_in": 60, "expires_at": "2099-01-01T00:00:00Z" }),
+        )
+        .await;
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+        assert_eq!(body["fields"][0]["field"], "expires_at");
+
+        let (status, _) = set_expiry(&env, json!({ "expires_at": "2000-01-01T00:00:00Z" })).await;
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+        assert!(env.app_state().metadata.all().await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn stores_member_expiry() {
+        let env = TestEnv::new().await;
+        env.controller.add_network(NETWORK, json!({}));
+        env.controller.add_member(NETWORK, "1111111111", json!({}));
+
+        let (status, body) = set_expiry(&env, json!({ "expires_in": 3600, "delete": true })).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["delete"], true);
+        let remaining = body["remainingSecs"].as_i64().unwrap();
+        assert!((3590..=3600).contains(&remaining), "{}", remaining);
+
+        let stored = env.app_state().metadata.network(NETWORK).await;
+        assert!(stored["1111111111"].expiry.as_ref().unwrap().delete);
+    }
+}
@@ -0,0 +1,130 @@
+use crate::error::{AppError, FieldError, Result};
+use crate::models::{CompileRulesRequest, SimulateFlowRequest};
+use crate::rules::{self, CompiledRules, Endpoint, RuleSets};
+use crate::services::audit::AuditEntry;
+use crate::services::auth::Claims;
+use crate::state::AppState;
+use crate::utils::is_node_id;
+use axum::{
+    extract::{Path, State},
+    response::IntoResponse,
+    Extension, Json,
+};
+use serde_json::{json, Map, Value};
+use std::collections::HashMap;
+
+fn compile(source: &str) -> Result<CompiledRules> {
+    rules::compile(source).map_err(|error| {
+        AppError::ValidationError(vec![FieldError::new("source", error.to_string())])
+    })
+}
+
+/// Compile rules language source without changing any network
+pub async fn compile_rules(Json(request): Json<CompileRulesRequest>) -> Result<impl IntoResponse> {
+    Ok(Json(compile(&request.source)?))
+}
+
+/// Compile rules language source and replace a network's rules, capabilities and tags
+pub async fn update_network_rules(
+    State(app_state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Path(network_id): Path<String>,
+    Json(request): Json<CompileRulesRequest>,
+) -> Result<impl IntoResponse> {
+    let compiled = compile(&request.source)?;
+
+    let network = app_state
+        .zerotier
+        .post_json(
+            &format!("/controller/network/{}", network_id),
+            &serde_json::to_value(&compiled)?,
+        )
+        .await?;
+
+    app_state
+        .audit
+        .record(
+            AuditEntry::new(&claims.username, "network.update_rules")
+                .network(&network_id)
+                .details(json!({
+                    "rules": compiled.rules.len(),
+                    "capabilities": compiled.capabilities.len(),
+                    "tags": compiled.tags.len(),
+                })),
+        )
+        .await;
+
+    Ok(Json(network))
+}
+
+/// Evaluate a synthetic packet between two members against a network's rules, or
+/// against rules that have not been pushed yet
+pub async fn simulate_flow(
+    State(app_state): State<AppState>,
+    Path(network_id): Path<String>,
+    Json(request): Json<SimulateFlowRequest>,
+) -> Result<impl IntoResponse> {
+    let network: Map<String, Value> = app_state
+        .zerotier
+        .get_json(&format!("/controller/network/{}", network_id))
+        .await?;
+    let current = |field: &str| {
+        network
+            .get(field)
+            .and_then(Value::as_array)
+            .cloned()
+            .unwrap_or_default()
+    };
+
+    let (rules, capabilities, tags) = match &request.source {
+        Some(source) => {
+            let compiled = compile(source)?;
+            (compiled.rules, compiled.capabilities, compiled.tags)
+        }
+        None => (
+            request.rules.unwrap_or_else(|| current("rules")),
+            request
+                .capabilities
+                .unwrap_or_else(|| current("capabilities")),
+            request.tags.unwrap_or_else(|| current("tags")),
+        ),
+    };
+
+    let network_number = u64::from_str_radix(&network_id, 16)
+        .map_err(|_| AppError::NotFound(format!("Network {} not found", network_id)))?;
+    let tag_defaults = rules::tag_defaults(&tags);
+    let members: HashMap<u64, Endpoint> = app_state
+        .zerotier
+        .list_members(&network_id)
+        .await?
+        .iter()
+        .filter_map(|member| Endpoint::from_member(network_number, member, &tag_defaults))
+        .map(|endpoint| (endpoint.address, endpoint))
+        .collect();
+
+    let endpoint = |field: &str, member_id: &str| {
+        u64::from_str_radix(member_id, 16)
+            .ok()
+            .filter(|_| is_node_id(member_id))
+            .and_then(|address| members.get(&address))
+            .ok_or_else(|| {
+                AppError::ValidationError(vec![FieldError::new(
+                    format!("packet.{}", field),
+                    format!("{} is not a member of the network", member_id),
+                )])
+            })
+    };
+    let source = endpoint("source", &request.packet.source)?;
+    let destination = endpoint("destination", &request.packet.destination)?;
+
+    Ok(Json(rules::simulate(
+        &RuleSets {
+            rules: &rules,
+            capabilities: &capabilities,
+        },
+        &members,
+        source,
+        destination,
+        &request.packet,
+    )))
+}
@@ -0,0 +1,65 @@
+use crate::error::Result;
+use crate::models::{SetScheduleOverrideRequest, TransitionQuery};
+use crate::services::audit::AuditEntry;
+use crate::services::auth::Claims;
+use crate::state::AppState;
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::IntoResponse,
+    Extension, Json,
+};
+
+const MAX_TRANSITIONS: usize = 500;
+
+/// List upcoming authorization changes from the access schedules
+pub async fn list_schedule_transitions(
+    State(app_state): State<AppState>,
+    Query(query): Query<TransitionQuery>,
+) -> Result<impl IntoResponse> {
+    Ok(Json(app_state.access_schedules.upcoming(
+        query.network_id.as_deref(),
+        query.limit.clamp(1, MAX_TRANSITIONS),
+    )))
+}
+
+pub async fn set_schedule_override(
+    State(app_state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Path((network_id, member_id)): Path<(String, String)>,
+    Json(request): Json<SetScheduleOverrideRequest>,
+) -> Result<impl IntoResponse> {
+    let schedule_override = app_state
+        .access_schedules
+        .set_override(
+            &claims.username,
+            &network_id,
+            &member_id,
+            request.authorized,
+            request.until,
+        )
+        .await?;
+
+    Ok(Json(schedule_override))
+}
+
+pub async fn clear_schedule_override(
+    State(app_state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Path((network_id, member_id)): Path<(String, String)>,
+) -> Result<impl IntoResponse> {
+    app_state
+        .access_schedules
+        .clear_override(&network_id, &member_id)
+        .await?;
+
+    app_state
+        .audit
+        .record(
+            AuditEntry::new(&claims.username, "member.clear_schedule_override")
+                .member(&network_id, &member_id),
+        )
+        .await;
+
+    Ok(StatusCode::NO_CONTENT)
+}
@@ -0,0 +1,23 @@
+use crate::error::Result;
+use crate::models::SearchQuery;
+use crate::state::AppState;
+use axum::{
+    extract::{Query, State},
+    response::IntoResponse,
+    Json,
+};
+
+const MAX_RESULTS: usize = 500;
+
+/// Search networks and members across the whole controller
+pub async fn search(
+    State(app_state): State<AppState>,
+    Query(query): Query<SearchQuery>,
+) -> Result<impl IntoResponse> {
+    let results = app_state
+        .search
+        .search(&query.q, query.limit.clamp(1, MAX_RESULTS))
+        .await?;
+
+    Ok(Json(results))
+}
@@ -1,6 +1,28 @@
+use crate::middleware::auth::extract_session_cookie;
 use crate::services::StaticFileService;
-use axum::{http::Uri, response::IntoResponse};
+use crate::state::AppState;
+use axum::{
+    extract::State,
+    http::{HeaderMap, Uri},
+    response::IntoResponse,
+};
+
+pub async fn serve_static_files(
+    State(app_state): State<AppState>,
+    headers: HeaderMap,
+    path: Uri,
+) -> impl IntoResponse {
+    let mode = &app_state.config.get_config().mode;
+
+    // During maintenance only logged-in admins get the web UI
+    if mode.maintenance && !StaticFileService::is_asset(path.path()) {
+        let is_admin = extract_session_cookie(&headers)
+            .is_some_and(|token| app_state.auth.validate_token(&token).is_ok());
+
+        if !is_admin {
+            return StaticFileService::serve_maintenance_page(&mode.maintenance_message);
+        }
+    }
 
-pub async fn serve_static_files(path: Uri) -> impl IntoResponse {
     StaticFileService::serve_file(&path.to_string())
 }
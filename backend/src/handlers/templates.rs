@@ -0,0 +1,64 @@
+use crate::error::Result;
+use crate::models::{CreateFromTemplateRequest, SaveTemplateRequest};
+use crate::services::auth::Claims;
+use crate::state::AppState;
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::IntoResponse,
+    Extension, Json,
+};
+
+pub async fn list_templates(State(app_state): State<AppState>) -> impl IntoResponse {
+    Json(app_state.templates.list().await)
+}
+
+pub async fn get_template(
+    State(app_state): State<AppState>,
+    Path(name): Path<String>,
+) -> Result<impl IntoResponse> {
+    Ok(Json(app_state.templates.get(&name).await?))
+}
+
+/// Create or replace a template
+pub async fn save_template(
+    State(app_state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Path(name): Path<String>,
+    Json(request): Json<SaveTemplateRequest>,
+) -> Result<impl IntoResponse> {
+    Ok(Json(
+        app_state
+            .templates
+            .save(&claims.username, &name, request)
+            .await?,
+    ))
+}
+
+pub async fn delete_template(
+    State(app_state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Path(name): Path<String>,
+) -> Result<impl IntoResponse> {
+    app_state.templates.delete(&claims.username, &name).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Render a template and create the network, or only preview it on a dry run
+pub async fn create_network_from_template(
+    State(app_state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Json(request): Json<CreateFromTemplateRequest>,
+) -> Result<impl IntoResponse> {
+    let response = app_state
+        .templates
+        .create_network(&claims.username, &request)
+        .await?;
+    let status = if response.dry_run {
+        StatusCode::OK
+    } else {
+        StatusCode::CREATED
+    };
+
+    Ok((status, Json(response)))
+}
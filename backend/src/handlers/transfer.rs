@@ -0,0 +1,120 @@
+use crate::error::Result;
+use crate::models::{ExportFormat, ExportQuery, ImportRequest};
+use crate::services::auth::Claims;
+use crate::services::MemberTransferService;
+use crate::state::AppState;
+use axum::{
+    extract::{Path, Query, State},
+    http::header,
+    response::{IntoResponse, Response},
+    Extension, Json,
+};
+
+/// Download all members of a network as CSV or JSON
+pub async fn export_members(
+    State(app_state): State<AppState>,
+    Path(network_id): Path<String>,
+    Query(query): Query<ExportQuery>,
+) -> Result<Response> {
+    let records = app_state.transfer.export(&network_id).await?;
+
+    let (content_type, extension, body) = match query.format {
+        ExportFormat::Csv => (
+            "text/csv; charset=utf-8",
+            "csv",
+            MemberTransferService::to_csv(&records)?,
+        ),
+        ExportFormat::Json => (
+            "application/json",
+            "json",
+            serde_json::to_string_pretty(&records)?,
+        ),
+    };
+    let disposition = format!(
+        "attachment; filename=\"{}-members.{}\"",
+        network_id, extension
+    );
+
+    Ok((
+        [
+            (header::CONTENT_TYPE, content_type.to_string()),
+            (header::CONTENT_DISPOSITION, disposition),
+        ],
+        body,
+    )
+        .into_response())
+}
+
+/// Create or update members from CSV, reporting the outcome per row
+pub async fn import_members(
+    State(app_state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Path(network_id): Path<String>,
+    Json(request): Json<ImportRequest>,
+) -> Result<impl IntoResponse> {
+    Ok(Json(
+        app_state
+            .transfer
+            .import(&claims.username, &network_id, &request)
+            .await?,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test_support::{send, text_body, user_token, TestEnv};
+    use axum::{
+        body::Body,
+        http::{header, Request, StatusCode},
+    };
+    use serde_json::{json, Value};
+
+    const NETWORK: &str = "abcdef0123000001";
+
+    #[tokio::test]
+    async fn downloads_csv_and_json() {
+        let env = TestEnv::new().await;
+        env.controller.add_network(NETWORK, json!({}));
+        env.controller
+            .add_member(NETWORK, "1111111111", json!({ "name": "laptop" }));
+        let app_state = env.app_state();
+
+        let export = |format: &str| {
+            let request = Request::builder()
+                .uri(format!(
+                    "/api/networks/{}/members/export{}",
+                    NETWORK, format
+                ))
+                .header(
+                    header::AUTHORIZATION,
+                    format!("Bearer {}", user_token(&app_state)),
+                )
+                .body(Body::empty())
+                .unwrap();
+            send(&app_state, request)
+        };
+
+        let response = export("").await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(
+            response.headers()[header::CONTENT_TYPE],
+            "text/csv; charset=utf-8"
+        );
+        assert_eq!(
+            response.headers()[header::CONTENT_DISPOSITION],
+            format!("attachment; filename=\"{}-members.csv\"", NETWORK)
+        );
+        let csv = text_body(response).await;
+        assert!(csv
+            .lines()
+            .nth(1)
+            .unwrap()
+            .starts_with("1111111111,laptop,"));
+
+        let response = export("?format=json").await;
+        assert_eq!(response.headers()[header::CONTENT_TYPE], "application/json");
+        let records: Value = serde_json::from_str(&text_body(response).await).unwrap();
+        assert_eq!(records[0]["id"], "1111111111");
+        assert_eq!(records[0]["name"], "laptop");
+    }
+}
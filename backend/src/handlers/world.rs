@@ -0,0 +1,85 @@
+use crate::error::{AppError, FieldError, Result};
+use crate::models::{ParseWorldQuery, WorldDefinition, WorldKeyPair};
+use crate::services::audit::AuditEntry;
+use crate::services::auth::Claims;
+use crate::state::AppState;
+use crate::world::{self, KeyPair, World};
+use axum::{
+    body::Bytes,
+    extract::{Query, State},
+    http::header,
+    response::{IntoResponse, Response},
+    Extension, Json,
+};
+use chrono::Utc;
+use serde_json::json;
+
+/// Build and sign a planet or moon, returned as the file nodes load
+pub async fn generate_world(
+    State(app_state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Json(definition): Json<WorldDefinition>,
+) -> Result<Response> {
+    let world = World::generate(&definition, Utc::now().timestamp_millis() as u64)
+        .map_err(AppError::ValidationError)?;
+
+    app_state
+        .audit
+        .record(
+            AuditEntry::new(&claims.username, "world.generate").details(json!({
+                "type": world.world_type,
+                "id": format!("{:016x}", world.id),
+                "timestamp": world.timestamp,
+                "roots": world
+                    .roots
+                    .iter()
+                    .map(|root| format!("{:010x}", root.identity.address))
+                    .collect::<Vec<_>>(),
+            })),
+        )
+        .await;
+
+    let disposition = format!("attachment; filename=\"{}\"", world.file_name());
+    Ok((
+        [
+            (header::CONTENT_TYPE, "application/octet-stream".to_string()),
+            (header::CONTENT_DISPOSITION, disposition),
+        ],
+        world.serialize(),
+    )
+        .into_response())
+}
+
+/// Read a planet or moon file and report its roots, keys and signature
+pub async fn parse_world(
+    Query(query): Query<ParseWorldQuery>,
+    body: Bytes,
+) -> Result<impl IntoResponse> {
+    let signed_by = match query.signed_by.as_deref() {
+        Some(key) => Some(world::parse_public_key(key).ok_or_else(|| {
+            AppError::ValidationError(vec![FieldError::new(
+                "signed_by",
+                "must be a 64-byte key in hex",
+            )])
+        })?),
+        None => None,
+    };
+
+    let world = World::parse(&body).map_err(|error| {
+        AppError::ValidationError(vec![FieldError::new("world", error.to_string())])
+    })?;
+
+    Ok(Json(world.describe(signed_by.as_ref())))
+}
+
+/// Generate a key pair for signing planets
+pub async fn generate_world_keys() -> Result<impl IntoResponse> {
+    let key = KeyPair::generate().map_err(|e| {
+        AppError::InternalServerError(format!("Failed to generate key pair: {}", e))
+    })?;
+
+    Ok(Json(WorldKeyPair {
+        signing_key: hex::encode(key.public),
+        signing_key_secret: hex::encode(key.secret()),
+    }))
+}
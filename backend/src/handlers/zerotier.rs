@@ -1,34 +1,310 @@
-use crate::error::{AppError, Result};
+use crate::error::Result;
+use crate::services::UpstreamResponse;
 use crate::state::AppState;
 use axum::{
     body::{Body, Bytes},
     extract::State,
-    http::{Method, StatusCode, Uri},
+    http::{header, HeaderMap, HeaderValue, Method, StatusCode, Uri},
     response::{IntoResponse, Response},
 };
+use chrono::Utc;
+use serde_json::{json, Map, Value};
 
 pub async fn forward_to_zerotier(
     State(app_state): State<AppState>,
     method: Method,
     uri: Uri,
+    headers: HeaderMap,
     body: Bytes,
 ) -> Result<impl IntoResponse> {
-    // Forward request and get response
-    let zt_response = app_state
-        .zerotier
-        .forward_request(uri.path(), method, body)
-        .await?;
-
-    // Convert response directly
-    let status = zt_response.status();
-    let response_body = zt_response
-        .bytes()
-        .await
-        .map_err(|e| AppError::ZeroTierError(format!("Failed to read response: {}", e)))?;
+    let is_get = method == Method::GET;
+    let is_delete = method == Method::DELETE;
+
+    if method == Method::POST {
+        app_state
+            .zerotier
+            .validate_update(uri.path(), &body)
+            .await?;
+    }
+
+    // Forward request and get the buffered response
+    let mut zt_response = app_state.zerotier.request(uri.path(), method, body).await?;
+
+    if is_get && zt_response.is_success() {
+        zt_response = merge_metadata(&app_state, uri.path(), zt_response).await;
+    }
+
+    if is_delete && zt_response.is_success() {
+        remove_metadata(&app_state, uri.path()).await;
+    }
 
-    let mut response = Response::new(Body::from(response_body));
+    let etag = HeaderValue::from_str(&zt_response.etag).ok();
+
+    // Let clients revalidate GET responses with If-None-Match
+    if is_get && zt_response.is_success() {
+        let not_modified = headers
+            .get(header::IF_NONE_MATCH)
+            .and_then(|value| value.to_str().ok())
+            .is_some_and(|value| zt_response.matches_etag(value));
+
+        if not_modified {
+            let mut response = StatusCode::NOT_MODIFIED.into_response();
+            if let Some(etag) = etag {
+                response.headers_mut().insert(header::ETAG, etag);
+            }
+            return Ok(response);
+        }
+    }
+
+    let mut response = Response::new(Body::from(zt_response.body));
     *response.status_mut() =
-        StatusCode::from_u16(status.as_u16()).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR);
+        StatusCode::from_u16(zt_response.status).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR);
+
+    if let Some(content_type) = zt_response
+        .content_type
+        .and_then(|value| HeaderValue::from_str(&value).ok())
+    {
+        response
+            .headers_mut()
+            .insert(header::CONTENT_TYPE, content_type);
+    }
+
+    if is_get {
+        if let Some(etag) = etag {
+            response.headers_mut().insert(header::ETAG, etag);
+        }
+    }
 
     Ok(response)
 }
+
+/// Add the backend-side metadata to a member read through the proxy, the ETag
+/// is recomputed so clients see metadata changes
+async fn merge_metadata(
+    app_state: &AppState,
+    path: &str,
+    response: UpstreamResponse,
+) -> UpstreamResponse {
+    let segments: Vec<&str> = path.trim_matches('/').split('/').collect();
+    let ["controller", "network", network_id, "member", member_id] = segments.as_slice() else {
+        return response;
+    };
+    let Some(metadata) = app_state
+        .metadata
+        .network(network_id)
+        .await
+        .remove(*member_id)
+    else {
+        return response;
+    };
+    let Ok(mut member) = serde_json::from_slice::<Map<String, Value>>(&response.body) else {
+        return response;
+    };
+
+    if !metadata.info.is_empty() {
+        member.insert("metadata".to_string(), json!(metadata.info));
+    }
+    if let Some(expiry) = &metadata.expiry {
+        member.insert("expiry".to_string(), json!(expiry.status(Utc::now())));
+    }
+
+    match serde_json::to_vec(&member) {
+        Ok(body) => UpstreamResponse::new(response.status, response.content_type, body.into()),
+        Err(_) => response,
+    }
+}
+
+/// Drop the backend-side metadata of a member or network deleted through the proxy
+async fn remove_metadata(app_state: &AppState, path: &str) {
+    let segments: Vec<&str> = path.trim_matches('/').split('/').collect();
+    let result = match segments.as_slice() {
+        ["controller", "network", network_id] => {
+            app_state.metadata.remove_network(network_id).await
+        }
+        ["controller", "network", network_id, "member", member_id] => {
+            app_state
+                .metadata
+                .remove_member(network_id, member_id)
+                .await
+        }
+        _ => Ok(()),
+    };
+
+    if let Err(e) = result {
+        tracing::error!("Failed to remove metadata for {}: {}", path, e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TestEnv;
+    use serde_json::json;
+
+    const NETWORK: &str = "abcdef0123000001";
+
+    async fn forward(
+        app_state: &AppState,
+        method: Method,
+        path: &str,
+        headers: HeaderMap,
+        body: &'static str,
+    ) -> Response {
+        forward_to_zerotier(
+            State(app_state.clone()),
+            method,
+            path.parse().unwrap(),
+            headers,
+            Bytes::from_static(body.as_bytes()),
+        )
+        .await
+        .unwrap()
+        .into_response()
+    }
+
+    async fn env() -> TestEnv {
+        let env = TestEnv::with_config(|config| config.zerotier.cache_ttl_secs = 60).await;
+        env.controller
+            .add_network(NETWORK, json!({ "name": "lab" }));
+        env
+    }
+
+    fn network_reads(env: &TestEnv) -> usize {
+        let request = format!("GET /controller/network/{}", NETWORK);
+        env.controller
+            .requests()
+            .iter()
+            .filter(|r| **r == request)
+            .count()
+    }
+
+    #[tokio::test]
+    async fn serves_repeated_reads_from_cache() {
+        let env = env().await;
+        let app_state = env.app_state();
+        let path = format!("/controller/network/{}", NETWORK);
+
+        let first = forward(&app_state, Method::GET, &path, HeaderMap::new(), "").await;
+        let second = forward(&app_state, Method::GET, &path, HeaderMap::new(), "").await;
+        assert_eq!(first.status(), StatusCode::OK);
+        assert_eq!(
+            first.headers().get(header::ETAG),
+            second.headers().get(header::ETAG)
+        );
+        assert_eq!(network_reads(&env), 1);
+
+        // A write invalidates the network so the next read is fresh
+        let update = forward(
+            &app_state,
+            Method::POST,
+            &path,
+            HeaderMap::new(),
+            r#"{"name":"renamed"}"#,
+        )
+        .await;
+        assert_eq!(update.status(), StatusCode::OK);
+        let third = forward(&app_state, Method::GET, &path, HeaderMap::new(), "").await;
+        assert_eq!(network_reads(&env), 2);
+        assert_ne!(
+            first.headers().get(header::ETAG),
+            third.headers().get(header::ETAG)
+        );
+    }
+
+    #[tokio::test]
+    async fn answers_matching_if_none_match_with_304() {
+        let env = env().await;
+        let app_state = env.app_state();
+        let path = format!("/controller/network/{}", NETWORK);
+
+        let first = forward(&app_state, Method::GET, &path, HeaderMap::new(), "").await;
+        let etag = first.headers().get(header::ETAG).unwrap().clone();
+
+        let mut headers = HeaderMap::new();
+        headers.insert(header::IF_NONE_MATCH, etag.clone());
+        let revalidated = forward(&app_state, Method::GET, &path, headers, "").await;
+        assert_eq!(revalidated.status(), StatusCode::NOT_MODIFIED);
+        assert_eq!(revalidated.headers().get(header::ETAG), Some(&etag));
+
+        let mut headers = HeaderMap::new();
+        headers.insert(header::IF_NONE_MATCH, HeaderValue::from_static("\"stale\""));
+        let changed = forward(&app_state, Method::GET, &path, headers, "").await;
+        assert_eq!(changed.status(), StatusCode::OK);
+    }
+
+    async fn read_json(response: Response) -> Value {
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        serde_json::from_slice(&body).unwrap()
+    }
+
+    #[tokio::test]
+    async fn merges_metadata_into_member_reads() {
+        let env = env().await;
+        env.controller.add_member(NETWORK, "1111111111", json!({}));
+        env.controller.add_member(NETWORK, "2222222222", json!({}));
+        let app_state = env.app_state();
+        let path = format!("/controller/network/{}/member/1111111111", NETWORK);
+
+        let plain = forward(&app_state, Method::GET, &path, HeaderMap::new(), "").await;
+        let plain_etag = plain.headers().get(header::ETAG).unwrap().clone();
+        let member = read_json(plain).await;
+        assert!(member.get("metadata").is_none());
+        assert!(member.get("expiry").is_none());
+
+        app_state
+            .metadata
+            .update(NETWORK, "1111111111", |metadata| {
+                metadata.info.owner = Some("alice".to_string());
+                metadata.expiry = Some(crate::models::MemberExpiry {
+                    expires_at: Utc::now() + chrono::TimeDelta::hours(1),
+                    delete: false,
+                    warned: false,
+                });
+                Ok(())
+            })
+            .await
+            .unwrap();
+
+        // The cached controller response is stale for clients once metadata changes
+        let mut headers = HeaderMap::new();
+        headers.insert(header::IF_NONE_MATCH, plain_etag);
+        let merged = forward(&app_state, Method::GET, &path, headers, "").await;
+        assert_eq!(merged.status(), StatusCode::OK);
+        let member = read_json(merged).await;
+        assert_eq!(member["id"], "1111111111");
+        assert_eq!(member["metadata"]["owner"], "alice");
+        assert_eq!(member["expiry"]["delete"], false);
+
+        // Other members and the member list are passed through
+        let other = format!("/controller/network/{}/member/2222222222", NETWORK);
+        let member =
+            read_json(forward(&app_state, Method::GET, &other, HeaderMap::new(), "").await).await;
+        assert!(member.get("metadata").is_none());
+        let list = format!("/controller/network/{}/member", NETWORK);
+        let members =
+            read_json(forward(&app_state, Method::GET, &list, HeaderMap::new(), "").await).await;
+        assert_eq!(members["1111111111"], 1);
+    }
+
+    #[tokio::test]
+    async fn deleting_a_member_removes_its_metadata() {
+        let env = env().await;
+        env.controller.add_member(NETWORK, "1111111111", json!({}));
+        let app_state = env.app_state();
+        app_state
+            .metadata
+            .update(NETWORK, "1111111111", |metadata| {
+                metadata.info.owner = Some("alice".to_string());
+                Ok(())
+            })
+            .await
+            .unwrap();
+
+        let path = format!("/controller/network/{}/member/1111111111", NETWORK);
+        let deleted = forward(&app_state, Method::DELETE, &path, HeaderMap::new(), "").await;
+        assert_eq!(deleted.status(), StatusCode::OK);
+        assert!(app_state.metadata.network(NETWORK).await.is_empty());
+    }
+}
@@ -1,15 +1,21 @@
 use clap::Parser;
 use tokio::net::TcpListener;
 
+mod cli;
 mod error;
 mod handlers;
 mod logger;
 mod middleware;
 mod models;
 mod routes;
+mod rules;
 mod services;
 mod state;
+#[cfg(test)]
+mod test_support;
 mod utils;
+mod validation;
+mod world;
 
 use services::ConfigService;
 use state::AppState;
@@ -25,6 +31,10 @@ struct Args {
 
     #[arg(short, long, default_value = "info")]
     log_level: String,
+
+    /// Run a command instead of starting the server
+    #[command(subcommand)]
+    command: Option<cli::Command>,
 }
 
 #[tokio::main]
@@ -36,7 +46,26 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     // Initialize services
     let config_service = ConfigService::new(args.config)?;
-    let app_state = AppState::new(config_service.clone());
+    let app_state = AppState::new(config_service.clone())?;
+
+    if let Some(command) = args.command {
+        return cli::run(command, &app_state).await;
+    }
+
+    // Start background tasks
+    app_state.webhooks.spawn();
+    app_state.email.spawn();
+    app_state.member_policy.spawn();
+    app_state.access_schedules.spawn();
+    app_state.search.spawn();
+    app_state.dns.spawn();
+    app_state.gitops.spawn();
+    app_state
+        .monitor
+        .spawn(config_service.get_config().monitor.poll_interval_secs);
+    app_state
+        .expiry
+        .spawn(config_service.get_config().expiry.check_interval_secs);
 
     // Build the application router
     let app = app_routes(app_state.clone());
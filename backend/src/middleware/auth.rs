@@ -1,4 +1,5 @@
-use crate::services::auth::Claims;
+use crate::models::Principal;
+use crate::services::auth::{Claims, SESSION_COOKIE};
 use crate::state::AppState;
 use crate::utils::is_private_ip;
 use axum::{
@@ -59,6 +60,18 @@ fn extract_token(headers: &HeaderMap) -> Option<String> {
     None
 }
 
+// Helper function to extract the session token mirrored into a cookie
+pub fn extract_session_cookie(headers: &HeaderMap) -> Option<String> {
+    headers
+        .get_all("Cookie")
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|cookies| cookies.split(';'))
+        .filter_map(|cookie| cookie.trim().split_once('='))
+        .find(|(name, _)| *name == SESSION_COOKIE)
+        .map(|(_, token)| token.to_string())
+}
+
 fn extract_api_key(headers: &HeaderMap) -> Option<String> {
     if let Some(api_key_header) = headers.get("X-API-Key") {
         if let Ok(api_key) = api_key_header.to_str() {
@@ -105,23 +118,29 @@ pub async fn auth_middleware(
 
     // Add claims to request extensions for use in handlers
     request.extensions_mut().insert(claims);
+    request.extensions_mut().insert(Principal::User);
 
     Ok(next.run(request).await)
 }
 
-// Authentication middleware for ZeroTier API proxy requests.
-// It accepts browser JWTs and configured API keys for automation clients.
-pub async fn auth_or_api_key_middleware(
-    State(app_state): State<AppState>,
-    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+// Shared by the middlewares that accept API keys: rejects banned clients, then
+// accepts a JWT from the Authorization header, or from the session cookie when
+// `accept_cookie` is set, and finally a configured API key.
+async fn authenticate_user_or_api_key(
+    app_state: &AppState,
+    addr: SocketAddr,
     mut request: Request,
     next: Next,
+    accept_cookie: bool,
 ) -> Result<Response, StatusCode> {
     let headers = request.headers();
 
     // Extract client IP address
     let client_ip = extract_real_ip(headers, addr);
-    let token = extract_token(headers);
+    let mut token = extract_token(headers);
+    if accept_cookie {
+        token = token.or_else(|| extract_session_cookie(headers));
+    }
     let api_key = extract_api_key(headers);
 
     // Check if the IP is banned
@@ -133,15 +152,127 @@ pub async fn auth_or_api_key_middleware(
     if let Some(token) = token {
         if let Ok(claims) = app_state.auth.validate_token(&token) {
             request.extensions_mut().insert(claims);
+            request.extensions_mut().insert(Principal::User);
             return Ok(next.run(request).await);
         }
     }
 
     if let Some(api_key) = api_key {
         if app_state.config.verify_api_key(&api_key) {
+            request.extensions_mut().insert(Principal::ApiKey);
             return Ok(next.run(request).await);
         }
     }
 
     Err(StatusCode::UNAUTHORIZED)
 }
+
+// Authentication middleware for ZeroTier API proxy requests.
+// It accepts browser JWTs and configured API keys for automation clients.
+pub async fn auth_or_api_key_middleware(
+    State(app_state): State<AppState>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    request: Request,
+    next: Next,
+) -> Result<Response, StatusCode> {
+    authenticate_user_or_api_key(&app_state, addr, request, next, false).await
+}
+
+// Authentication middleware for long-lived event streams.
+// Browsers cannot set headers on EventSource requests, so the session
+// cookie is accepted in addition to JWTs and API keys. Only use it on
+// routes that do not change state.
+pub async fn stream_auth_middleware(
+    State(app_state): State<AppState>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    request: Request,
+    next: Next,
+) -> Result<Response, StatusCode> {
+    authenticate_user_or_api_key(&app_state, addr, request, next, true).await
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::services::auth::SESSION_COOKIE;
+    use crate::state::AppState;
+    use crate::test_support::{send, user_token, TestEnv};
+    use axum::{
+        body::Body,
+        http::{header, Request, StatusCode},
+    };
+    use serde_json::json;
+    use std::net::{IpAddr, Ipv4Addr};
+
+    const NETWORK_PATH: &str = "/ztapi/controller/network/abcdef0123000001";
+
+    async fn env() -> TestEnv {
+        let env = TestEnv::with_config(|config| {
+            config.api_keys = vec!["automation".to_string()];
+        })
+        .await;
+        env.controller
+            .add_network("abcdef0123000001", json!({ "name": "lab" }));
+        env
+    }
+
+    async fn status(app_state: &AppState, path: &str, header: (&str, String)) -> StatusCode {
+        let request = Request::builder()
+            .uri(path)
+            .header(header.0, header.1)
+            .body(Body::empty())
+            .unwrap();
+        send(app_state, request).await.status()
+    }
+
+    #[tokio::test]
+    async fn accepts_the_session_cookie_only_for_streams() {
+        let env = env().await;
+        let app_state = env.app_state();
+        let cookie = (
+            header::COOKIE.as_str(),
+            format!("{}={}", SESSION_COOKIE, user_token(&app_state)),
+        );
+
+        assert_eq!(
+            status(&app_state, "/api/events", cookie.clone()).await,
+            StatusCode::OK
+        );
+        assert_eq!(
+            status(&app_state, NETWORK_PATH, cookie).await,
+            StatusCode::UNAUTHORIZED
+        );
+
+        for path in ["/api/events", NETWORK_PATH] {
+            let bearer = (
+                header::AUTHORIZATION.as_str(),
+                format!("Bearer {}", user_token(&app_state)),
+            );
+            assert_eq!(status(&app_state, path, bearer).await, StatusCode::OK);
+            let api_key = ("X-API-Key", "automation".to_string());
+            assert_eq!(status(&app_state, path, api_key).await, StatusCode::OK);
+            let wrong_key = (header::AUTHORIZATION.as_str(), "ApiKey wrong".to_string());
+            assert_eq!(
+                status(&app_state, path, wrong_key).await,
+                StatusCode::UNAUTHORIZED
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn blocks_banned_clients() {
+        let env = env().await;
+        let app_state = env.app_state();
+        let local = IpAddr::V4(Ipv4Addr::LOCALHOST);
+        for _ in 0..5 {
+            app_state.ip_ban.record_failure(&local).await;
+        }
+
+        for path in ["/api/events", NETWORK_PATH] {
+            let api_key = ("X-API-Key", "automation".to_string());
+            assert_eq!(
+                status(&app_state, path, api_key).await,
+                StatusCode::TOO_MANY_REQUESTS
+            );
+        }
+    }
+}
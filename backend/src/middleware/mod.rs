@@ -1,4 +1,10 @@
 pub mod auth;
+pub mod mode;
+pub mod proxy_policy;
 
 pub use auth::auth_middleware;
 pub use auth::auth_or_api_key_middleware;
+pub use auth::stream_auth_middleware;
+pub use mode::maintenance_middleware;
+pub use mode::read_only_middleware;
+pub use proxy_policy::proxy_policy_middleware;
@@ -0,0 +1,189 @@
+use crate::error::AppError;
+use crate::models::Principal;
+use crate::state::AppState;
+use axum::{
+    extract::{Request, State},
+    http::Method,
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+
+// Endpoints that keep working in read-only mode so admins can still
+// manage their session, switch the mode back off and preview changes.
+const READ_ONLY_EXEMPT: &[&str] = &[
+    "/api/login",
+    "/api/logout",
+    "/api/refresh",
+    "/api/admin/mode",
+    "/api/gitops/plan",
+    "/api/rules/compile",
+    "/api/worlds/generate",
+    "/api/worlds/keys",
+    "/api/worlds/parse",
+];
+
+// Per-network endpoints that only preview changes, matched by path suffix
+const READ_ONLY_EXEMPT_SUFFIXES: &[&str] = &["/rules/simulate"];
+
+// Reject changes through `/api` and `/ztapi` while read-only mode is enabled
+pub async fn read_only_middleware(
+    State(app_state): State<AppState>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let mode = &app_state.config.get_config().mode;
+    let path = request.uri().path();
+
+    let is_api = path.starts_with("/api/") || path.starts_with("/ztapi/");
+    let is_safe = matches!(
+        *request.method(),
+        Method::GET | Method::HEAD | Method::OPTIONS
+    );
+
+    let is_exempt = READ_ONLY_EXEMPT.contains(&path)
+        || READ_ONLY_EXEMPT_SUFFIXES
+            .iter()
+            .any(|suffix| path.starts_with("/api/") && path.ends_with(suffix));
+
+    if mode.read_only && is_api && !is_safe && !is_exempt {
+        tracing::info!("Rejected {} {} in read-only mode", request.method(), path);
+        return AppError::ServiceUnavailable(mode.read_only_message.clone()).into_response();
+    }
+
+    next.run(request).await
+}
+
+// Keep API key clients out of the ZeroTier proxy during maintenance.
+// Must run after one of the authentication middlewares.
+pub async fn maintenance_middleware(
+    State(app_state): State<AppState>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let mode = &app_state.config.get_config().mode;
+
+    if mode.maintenance && request.extensions().get::<Principal>() != Some(&Principal::User) {
+        return AppError::ServiceUnavailable(mode.maintenance_message.clone()).into_response();
+    }
+
+    next.run(request).await
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::services::auth::SESSION_COOKIE;
+    use crate::state::AppState;
+    use crate::test_support::{send, text_body, user_token, TestEnv};
+    use axum::{
+        body::Body,
+        http::{header, Request, StatusCode},
+    };
+    use serde_json::json;
+
+    const NETWORK_PATH: &str = "/ztapi/controller/network/abcdef0123000001";
+
+    async fn env() -> TestEnv {
+        let env = TestEnv::with_config(|config| {
+            config.api_keys = vec!["automation".to_string()];
+            config.mode.read_only_message = "Migrating".to_string();
+            config.mode.maintenance_message = "Back <soon>".to_string();
+        })
+        .await;
+        env.controller
+            .add_network("abcdef0123000001", json!({ "name": "lab" }));
+        env
+    }
+
+    fn request(app_state: &AppState, method: &str, path: &str, body: &str) -> Request<Body> {
+        Request::builder()
+            .method(method)
+            .uri(path)
+            .header(
+                header::AUTHORIZATION,
+                format!("Bearer {}", user_token(app_state)),
+            )
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(Body::from(body.to_string()))
+            .unwrap()
+    }
+
+    async fn set_mode(app_state: &AppState, mode: &str) -> StatusCode {
+        send(
+            app_state,
+            request(app_state, "PUT", "/api/admin/mode", mode),
+        )
+        .await
+        .status()
+    }
+
+    #[tokio::test]
+    async fn read_only_rejects_changes() {
+        let env = env().await;
+        let app_state = env.app_state();
+        assert_eq!(
+            set_mode(&app_state, r#"{"read_only":true}"#).await,
+            StatusCode::OK
+        );
+
+        let response = send(
+            &app_state,
+            request(&app_state, "POST", NETWORK_PATH, r#"{"name":"x"}"#),
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+        assert!(text_body(response).await.contains("Migrating"));
+
+        let response = send(&app_state, request(&app_state, "GET", NETWORK_PATH, "")).await;
+        assert_eq!(response.status(), StatusCode::OK);
+
+        // The mode endpoint stays writable so it can be switched back off
+        assert_eq!(
+            set_mode(&app_state, r#"{"read_only":false}"#).await,
+            StatusCode::OK
+        );
+        let response = send(
+            &app_state,
+            request(&app_state, "POST", NETWORK_PATH, r#"{"name":"x"}"#),
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn maintenance_only_lets_admins_in() {
+        let env = env().await;
+        let app_state = env.app_state();
+        assert_eq!(
+            set_mode(&app_state, r#"{"maintenance":true}"#).await,
+            StatusCode::OK
+        );
+        assert!(env.config.get_config().mode.maintenance);
+
+        let api_key_request = Request::builder()
+            .uri(NETWORK_PATH)
+            .header("X-API-Key", "automation")
+            .body(Body::empty())
+            .unwrap();
+        let response = send(&app_state, api_key_request).await;
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+
+        let response = send(&app_state, request(&app_state, "GET", NETWORK_PATH, "")).await;
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let page = Request::builder().uri("/").body(Body::empty()).unwrap();
+        let response = send(&app_state, page).await;
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+        assert!(text_body(response).await.contains("Back &lt;soon&gt;"));
+
+        let page = Request::builder()
+            .uri("/")
+            .header(
+                header::COOKIE,
+                format!("theme=dark; {}={}", SESSION_COOKIE, user_token(&app_state)),
+            )
+            .body(Body::empty())
+            .unwrap();
+        let response = send(&app_state, page).await;
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+}
@@ -0,0 +1,41 @@
+use crate::models::Principal;
+use crate::state::AppState;
+use axum::{
+    extract::{Request, State},
+    http::StatusCode,
+    middleware::Next,
+    response::Response,
+};
+
+// Restrict which ZeroTier endpoints and methods each principal may reach.
+// Must run after one of the authentication middlewares.
+pub async fn proxy_policy_middleware(
+    State(app_state): State<AppState>,
+    request: Request,
+    next: Next,
+) -> Result<Response, StatusCode> {
+    let principal = request
+        .extensions()
+        .get::<Principal>()
+        .copied()
+        .ok_or(StatusCode::UNAUTHORIZED)?;
+    let method = request.method().as_str();
+    let path = request.uri().path();
+
+    if !app_state
+        .config
+        .get_config()
+        .proxy_policy
+        .allows(principal, method, path)
+    {
+        tracing::warn!(
+            "Proxy policy rejected {} {} for principal {}",
+            method,
+            path,
+            principal
+        );
+        return Err(StatusCode::FORBIDDEN);
+    }
+
+    Ok(next.run(request).await)
+}
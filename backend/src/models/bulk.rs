@@ -0,0 +1,72 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// The change applied to every member of a bulk request
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum BulkOperation {
+    Authorize,
+    Deauthorize,
+    /// `{id}` and `{index}` (1-based) in the name are replaced per member
+    Rename {
+        name: String,
+    },
+    /// Set tags as `[id, value]` pairs, keeping the member's other tags
+    Tag {
+        tags: Vec<[u32; 2]>,
+    },
+    Delete,
+}
+
+impl BulkOperation {
+    /// Audit log action name
+    pub fn action(&self) -> &'static str {
+        match self {
+            BulkOperation::Authorize => "member.authorize",
+            BulkOperation::Deauthorize => "member.deauthorize",
+            BulkOperation::Rename { .. } => "member.rename",
+            BulkOperation::Tag { .. } => "member.tag",
+            BulkOperation::Delete => "member.delete",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct BulkMemberRequest {
+    pub member_ids: Vec<String>,
+    #[serde(flatten)]
+    pub operation: BulkOperation,
+    /// Only report what would change
+    #[serde(default)]
+    pub dry_run: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BulkStatus {
+    Applied,
+    /// Would be applied, only returned for dry runs
+    Planned,
+    Unchanged,
+    Failed,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BulkMemberResult {
+    pub member_id: String,
+    pub status: BulkStatus,
+    /// The update sent to the controller, `null` for deletions
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub update: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BulkMemberResponse {
+    pub dry_run: bool,
+    pub total: usize,
+    pub succeeded: usize,
+    pub failed: usize,
+    pub results: Vec<BulkMemberResult>,
+}
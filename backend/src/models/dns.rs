@@ -0,0 +1,30 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DnsConfig {
+    /// Address the DNS server answers on over UDP and TCP, e.g. `10.147.17.1:53`.
+    /// The server is disabled when unset.
+    #[serde(default)]
+    pub listen: Option<String>,
+    /// Parent domain for networks without their own `dns.domain`, which are served
+    /// as `<network>.<domain>`
+    #[serde(default)]
+    pub domain: Option<String>,
+    /// TTL of the records, in seconds
+    #[serde(default = "default_dns_ttl")]
+    pub ttl: u32,
+}
+
+fn default_dns_ttl() -> u32 {
+    60
+}
+
+impl Default for DnsConfig {
+    fn default() -> Self {
+        DnsConfig {
+            listen: None,
+            domain: None,
+            ttl: default_dns_ttl(),
+        }
+    }
+}
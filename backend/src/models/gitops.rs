@@ -0,0 +1,138 @@
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct GitOpsConfig {
+    /// Manifest used for drift detection and when plan or apply is called without one
+    #[serde(default)]
+    pub manifest: Option<PathBuf>,
+    /// Seconds between drift checks against `manifest`, 0 disables drift detection
+    #[serde(default)]
+    pub drift_check_interval_secs: u64,
+}
+
+/// Desired controller state, written in YAML or JSON
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct Manifest {
+    #[serde(default)]
+    pub networks: Vec<ManifestNetwork>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ManifestNetwork {
+    /// Networks without an ID are matched by name and created if missing
+    #[serde(default)]
+    pub id: Option<String>,
+    pub name: String,
+    #[serde(default)]
+    pub members: Vec<ManifestMember>,
+    /// Controller network fields such as `private`, `routes` or `ipAssignmentPools`
+    #[serde(flatten)]
+    pub config: Map<String, Value>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ManifestMember {
+    pub id: String,
+    /// Controller member fields such as `name`, `authorized` or `ipAssignments`
+    #[serde(flatten)]
+    pub config: Map<String, Value>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ChangeAction {
+    Create,
+    Update,
+    Delete,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ChangeTarget {
+    Network,
+    Member,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FieldChange {
+    pub from: Option<Value>,
+    pub to: Value,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PlannedChange {
+    pub action: ChangeAction,
+    pub target: ChangeTarget,
+    /// Unset for networks that do not exist yet
+    pub network_id: Option<String>,
+    pub network_name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub member_id: Option<String>,
+    /// Fields that are set by a create or update
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub fields: BTreeMap<String, FieldChange>,
+}
+
+impl PlannedChange {
+    /// One-line summary, e.g. `update member 1111111111 on office: name, authorized`
+    pub fn describe(&self) -> String {
+        let action = match self.action {
+            ChangeAction::Create => "create",
+            ChangeAction::Update => "update",
+            ChangeAction::Delete => "delete",
+        };
+        let network = match &self.network_id {
+            Some(network_id) => format!("{} ({})", self.network_name, network_id),
+            None => self.network_name.clone(),
+        };
+        let mut line = match &self.member_id {
+            Some(member_id) => format!("{} member {} on {}", action, member_id, network),
+            None => format!("{} network {}", action, network),
+        };
+
+        if self.action == ChangeAction::Update {
+            let fields: Vec<&str> = self.fields.keys().map(String::as_str).collect();
+            line.push_str(": ");
+            line.push_str(&fields.join(", "));
+        }
+
+        line
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct Plan {
+    pub create: usize,
+    pub update: usize,
+    pub delete: usize,
+    /// Members that are neither in the manifest nor managed by it, left alone
+    pub unmanaged: usize,
+    /// Adopted networks that left the manifest, they are no longer managed
+    /// but stay on the controller
+    pub released: Vec<String>,
+    pub changes: Vec<PlannedChange>,
+}
+
+impl Plan {
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct AppliedChange {
+    #[serde(flatten)]
+    pub change: PlannedChange,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ApplyResponse {
+    pub succeeded: usize,
+    pub failed: usize,
+    pub changes: Vec<AppliedChange>,
+}
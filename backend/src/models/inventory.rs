@@ -0,0 +1,54 @@
+use serde::Deserialize;
+
+/// Formats a network's members can be rendered in for other tools
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum InventoryFormat {
+    /// `/etc/hosts` lines
+    Hosts,
+    AnsibleYaml,
+    AnsibleIni,
+    /// Prometheus `http_sd` target groups
+    Prometheus,
+    /// `~/.ssh/config` host entries
+    SshConfig,
+}
+
+/// Where a member's host name comes from, the member ID is used when it is empty
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum NameSource {
+    /// The `hostname` metadata label, falling back to the member name
+    #[default]
+    Hostname,
+    Name,
+    Id,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum IpFamily {
+    Ipv4,
+    Ipv6,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct InventoryQuery {
+    #[serde(default)]
+    pub name_source: NameSource,
+    /// Appended to host names, e.g. `zt.example.com`
+    pub domain: Option<String>,
+    /// Only members with the tag, as `<id>` or `<id>:<value>`
+    pub tag: Option<String>,
+    /// Only members with the metadata label, as `<key>` or `<key>:<value>`
+    pub label: Option<String>,
+    /// Only members whose host name matches the glob
+    pub name: Option<String>,
+    pub online: Option<bool>,
+    /// Only addresses of this family
+    pub family: Option<IpFamily>,
+    /// Scrape port of Prometheus targets (default 9100) or SSH port
+    pub port: Option<u16>,
+    /// SSH user or Ansible `ansible_user`
+    pub user: Option<String>,
+}
@@ -0,0 +1,75 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::net::IpAddr;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Redemption {
+    pub member_id: String,
+    pub ip: IpAddr,
+    pub redeemed_at: DateTime<Utc>,
+}
+
+/// A join link that lets its holder authorize their own node on a network
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Invitation {
+    pub id: String,
+    /// Secret part of the join URL
+    pub token: String,
+    pub network_id: String,
+    pub created_by: String,
+    pub created_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+    pub max_uses: u32,
+    #[serde(default)]
+    pub uses: u32,
+    #[serde(default)]
+    pub default_name: Option<String>,
+    /// Tags to set as `[id, value]` pairs
+    #[serde(default)]
+    pub tags: Vec<[u32; 2]>,
+    #[serde(default)]
+    pub revoked: bool,
+    #[serde(default)]
+    pub redemptions: Vec<Redemption>,
+}
+
+impl Invitation {
+    pub fn is_usable(&self, now: DateTime<Utc>) -> bool {
+        !self.revoked && now < self.expires_at && self.uses < self.max_uses
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct CreateInvitationRequest {
+    pub network_id: String,
+    /// Lifetime of the invitation in seconds
+    pub expires_in: i64,
+    #[serde(default = "default_max_uses")]
+    pub max_uses: u32,
+    #[serde(default)]
+    pub default_name: Option<String>,
+    #[serde(default)]
+    pub tags: Vec<[u32; 2]>,
+}
+
+fn default_max_uses() -> u32 {
+    1
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct InvitationListQuery {
+    pub network_id: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct RedeemInvitationRequest {
+    pub node_id: String,
+}
+
+/// What the public join page may learn about an invitation
+#[derive(Debug, Clone, Serialize)]
+pub struct InvitationInfo {
+    pub network_id: String,
+    pub network_name: String,
+    pub expires_at: DateTime<Utc>,
+}
@@ -0,0 +1,109 @@
+use chrono::{DateTime, Utc};
+use ipnet::IpNet;
+use serde::{Deserialize, Serialize};
+use std::net::IpAddr;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IpamConfig {
+    /// Address space network subnets are allocated from
+    #[serde(default)]
+    pub supernet: Option<IpNet>,
+    /// Prefix length of allocated subnets unless a request sets one
+    #[serde(default = "default_subnet_prefix_len")]
+    pub subnet_prefix_len: u8,
+}
+
+fn default_subnet_prefix_len() -> u8 {
+    24
+}
+
+impl Default for IpamConfig {
+    fn default() -> Self {
+        IpamConfig {
+            supernet: None,
+            subnet_prefix_len: default_subnet_prefix_len(),
+        }
+    }
+}
+
+/// An address held back from automatic allocation, optionally for one member
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IpReservation {
+    pub network_id: String,
+    pub ip: IpAddr,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub member_id: Option<String>,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub note: String,
+    pub created_by: String,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ReserveIpRequest {
+    /// Reserve the next free address when unset
+    pub ip: Option<IpAddr>,
+    pub member_id: Option<String>,
+    #[serde(default)]
+    pub note: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct AllocateSubnetRequest {
+    pub prefix_len: Option<u8>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct AllocateIpRequest {
+    /// Assign this address instead of the member's reservation or the next free one
+    pub ip: Option<IpAddr>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct NextSubnetQuery {
+    pub prefix_len: Option<u8>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct NextIpQuery {
+    #[serde(default = "default_next_ip_count")]
+    pub count: usize,
+}
+
+fn default_next_ip_count() -> usize {
+    1
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum IpamUsageKind {
+    Route,
+    Pool,
+}
+
+/// A route or assignment pool of a network
+#[derive(Debug, Clone, Serialize)]
+pub struct IpamUsage {
+    pub kind: IpamUsageKind,
+    pub network_id: String,
+    pub network_name: String,
+    /// The route target, or the pool as `start-end`
+    pub range: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct IpamConflict {
+    pub first: IpamUsage,
+    pub second: IpamUsage,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct IpamOverview {
+    pub supernet: Option<IpNet>,
+    pub subnet_prefix_len: u8,
+    /// Routes and pools inside the supernet
+    pub allocated: Vec<IpamUsage>,
+    pub next_subnet: Option<IpNet>,
+    /// Routes or pools of different networks that overlap
+    pub conflicts: Vec<IpamConflict>,
+}
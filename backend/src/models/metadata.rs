@@ -0,0 +1,99 @@
+use super::ScheduleOverride;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// When a temporary member loses access
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MemberExpiry {
+    pub expires_at: DateTime<Utc>,
+    /// Delete the member instead of only deauthorizing it
+    #[serde(default)]
+    pub delete: bool,
+    /// Whether the expiry warning has been sent
+    #[serde(default)]
+    pub warned: bool,
+}
+
+impl MemberExpiry {
+    pub fn status(&self, now: DateTime<Utc>) -> ExpiryStatus {
+        ExpiryStatus {
+            expires_at: self.expires_at,
+            remaining_secs: (self.expires_at - now).num_seconds().max(0),
+            delete: self.delete,
+        }
+    }
+}
+
+/// Descriptive fields kept for a member in addition to its name and description
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct MemberInfo {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub owner: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub asset_tag: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub location: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub notes: Option<String>,
+    /// Arbitrary key/value labels
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub labels: BTreeMap<String, String>,
+}
+
+impl MemberInfo {
+    pub fn is_empty(&self) -> bool {
+        self.owner.is_none()
+            && self.asset_tag.is_none()
+            && self.location.is_none()
+            && self.notes.is_none()
+            && self.labels.is_empty()
+    }
+
+    /// The `hostname` label, which names the member in DNS and inventories
+    pub fn hostname(&self) -> Option<&str> {
+        self.labels.get("hostname").map(String::as_str)
+    }
+}
+
+/// Backend-side data about a member that the controller has no fields for
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct MemberMetadata {
+    #[serde(flatten)]
+    pub info: MemberInfo,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expiry: Option<MemberExpiry>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub schedule_override: Option<ScheduleOverride>,
+    /// When the member's expiry deauthorized it. Access schedules leave it
+    /// deauthorized until a new expiry is set or the expiry is cleared.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expired_at: Option<DateTime<Utc>>,
+}
+
+impl MemberMetadata {
+    pub fn is_empty(&self) -> bool {
+        self.info.is_empty()
+            && self.expiry.is_none()
+            && self.schedule_override.is_none()
+            && self.expired_at.is_none()
+    }
+}
+
+/// Expiry as shown in member responses
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExpiryStatus {
+    pub expires_at: DateTime<Utc>,
+    pub remaining_secs: i64,
+    pub delete: bool,
+}
+
+/// Either an absolute `expires_at` or `expires_in` seconds from now
+#[derive(Debug, Clone, Deserialize)]
+pub struct SetExpiryRequest {
+    pub expires_at: Option<DateTime<Utc>>,
+    pub expires_in: Option<i64>,
+    #[serde(default)]
+    pub delete: bool,
+}
@@ -1,4 +1,39 @@
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+pub mod bulk;
+pub mod dns;
+pub mod gitops;
+pub mod inventory;
+pub mod invitation;
+pub mod ipam;
+pub mod metadata;
+pub mod network;
+pub mod policy;
+pub mod proxy;
+pub mod rules;
+pub mod schedule;
+pub mod search;
+pub mod template;
+pub mod transfer;
+pub mod world;
+
+pub use bulk::*;
+pub use dns::*;
+pub use gitops::*;
+pub use inventory::*;
+pub use invitation::*;
+pub use ipam::*;
+pub use metadata::*;
+pub use network::*;
+pub use policy::*;
+pub use proxy::*;
+pub use rules::*;
+pub use schedule::*;
+pub use search::*;
+pub use template::*;
+pub use transfer::*;
+pub use world::*;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct LoginRequest {
@@ -22,6 +57,183 @@ pub struct UserInfo {
 pub struct ZeroTierConfig {
     pub auth_token: String,
     pub address: String,
+    /// Maximum number of concurrent requests when fanning out to the controller
+    #[serde(default = "default_max_concurrency")]
+    pub max_concurrency: usize,
+    /// How long GET responses from the controller are cached, 0 disables caching
+    #[serde(default = "default_cache_ttl_secs")]
+    pub cache_ttl_secs: u64,
+}
+
+fn default_max_concurrency() -> usize {
+    16
+}
+
+fn default_cache_ttl_secs() -> u64 {
+    2
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MonitorConfig {
+    /// Seconds between controller polls for live events, 0 disables polling
+    #[serde(default = "default_poll_interval_secs")]
+    pub poll_interval_secs: u64,
+}
+
+fn default_poll_interval_secs() -> u64 {
+    10
+}
+
+impl Default for MonitorConfig {
+    fn default() -> Self {
+        MonitorConfig {
+            poll_interval_secs: default_poll_interval_secs(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExpiryConfig {
+    /// Seconds between checks for expired members
+    #[serde(default = "default_expiry_check_interval_secs")]
+    pub check_interval_secs: u64,
+    /// How long before expiry the warning event is sent
+    #[serde(default = "default_expiry_warning_secs")]
+    pub warning_secs: i64,
+}
+
+fn default_expiry_check_interval_secs() -> u64 {
+    30
+}
+
+fn default_expiry_warning_secs() -> i64 {
+    24 * 60 * 60
+}
+
+impl Default for ExpiryConfig {
+    fn default() -> Self {
+        ExpiryConfig {
+            check_interval_secs: default_expiry_check_interval_secs(),
+            warning_secs: default_expiry_warning_secs(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WebhookConfig {
+    pub url: String,
+    /// Key for the `X-Ztvrui-Signature` HMAC-SHA256 header
+    #[serde(default)]
+    pub secret: Option<String>,
+    /// Event types to deliver, empty means all
+    #[serde(default)]
+    pub events: Vec<String>,
+    /// Networks to deliver events for, empty means all
+    #[serde(default)]
+    pub networks: Vec<String>,
+    #[serde(default = "default_webhook_max_attempts")]
+    pub max_attempts: u32,
+}
+
+fn default_webhook_max_attempts() -> u32 {
+    5
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SmtpSecurity {
+    /// Upgrade a plain connection with STARTTLS, usually on port 587
+    #[default]
+    Starttls,
+    /// Implicit TLS, usually on port 465
+    Tls,
+    /// Unencrypted, only for local relays
+    None,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EmailTemplate {
+    pub subject: String,
+    pub body: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SmtpConfig {
+    pub host: String,
+    #[serde(default)]
+    pub port: Option<u16>,
+    #[serde(default)]
+    pub security: SmtpSecurity,
+    #[serde(default)]
+    pub username: Option<String>,
+    #[serde(default)]
+    pub password: Option<String>,
+    pub from: String,
+    pub to: Vec<String>,
+    /// Event types to send mail for, empty means the built-in defaults
+    #[serde(default)]
+    pub events: Vec<String>,
+    /// Per-event overrides of the built-in templates
+    #[serde(default)]
+    pub templates: HashMap<String, EmailTemplate>,
+}
+
+/// Runtime switches that restrict access during controller migrations
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ModeConfig {
+    /// Reject every non-GET request except session management
+    #[serde(default)]
+    pub read_only: bool,
+    #[serde(default = "default_read_only_message")]
+    pub read_only_message: String,
+    /// Serve the maintenance page to everyone but logged-in admins
+    #[serde(default)]
+    pub maintenance: bool,
+    #[serde(default = "default_maintenance_message")]
+    pub maintenance_message: String,
+}
+
+fn default_read_only_message() -> String {
+    "The server is in read-only mode, changes are temporarily disabled.".to_string()
+}
+
+fn default_maintenance_message() -> String {
+    "ZTVRUI is undergoing maintenance. Please try again later.".to_string()
+}
+
+impl Default for ModeConfig {
+    fn default() -> Self {
+        ModeConfig {
+            read_only: false,
+            read_only_message: default_read_only_message(),
+            maintenance: false,
+            maintenance_message: default_maintenance_message(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UpdateModeRequest {
+    pub read_only: Option<bool>,
+    pub read_only_message: Option<String>,
+    pub maintenance: Option<bool>,
+    pub maintenance_message: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct AuditQuery {
+    pub network_id: Option<String>,
+    pub member_id: Option<String>,
+    /// Match actors starting with this prefix, e.g. `policy:`
+    pub actor: Option<String>,
+    /// Match actions starting with this prefix, e.g. `member.`
+    pub action: Option<String>,
+    #[serde(default = "default_audit_limit")]
+    pub limit: usize,
+}
+
+fn default_audit_limit() -> usize {
+    100
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -31,6 +243,28 @@ pub struct AppConfig {
     #[serde(default)]
     pub api_keys: Vec<String>,
     pub zerotier: ZeroTierConfig,
+    #[serde(default)]
+    pub proxy_policy: ProxyPolicy,
+    #[serde(default)]
+    pub mode: ModeConfig,
+    #[serde(default)]
+    pub monitor: MonitorConfig,
+    #[serde(default)]
+    pub webhooks: Vec<WebhookConfig>,
+    #[serde(default)]
+    pub smtp: Option<SmtpConfig>,
+    #[serde(default)]
+    pub member_policies: Vec<MemberPolicy>,
+    #[serde(default)]
+    pub expiry: ExpiryConfig,
+    #[serde(default)]
+    pub access_schedules: Vec<AccessSchedule>,
+    #[serde(default)]
+    pub gitops: GitOpsConfig,
+    #[serde(default)]
+    pub ipam: IpamConfig,
+    #[serde(default)]
+    pub dns: DnsConfig,
 }
 
 impl Default for AppConfig {
@@ -45,7 +279,20 @@ impl Default for AppConfig {
             zerotier: ZeroTierConfig {
                 auth_token: String::new(),
                 address: String::new(),
+                max_concurrency: default_max_concurrency(),
+                cache_ttl_secs: default_cache_ttl_secs(),
             },
+            proxy_policy: ProxyPolicy::default(),
+            mode: ModeConfig::default(),
+            monitor: MonitorConfig::default(),
+            webhooks: Vec::new(),
+            smtp: None,
+            member_policies: Vec::new(),
+            expiry: ExpiryConfig::default(),
+            access_schedules: Vec::new(),
+            gitops: GitOpsConfig::default(),
+            ipam: IpamConfig::default(),
+            dns: DnsConfig::default(),
         }
     }
 }
@@ -0,0 +1,244 @@
+use super::{ExpiryStatus, MemberInfo};
+use crate::utils::{rfc4193_address, sixplane_address, sixplane_prefix};
+use chrono::{DateTime, Utc};
+use ipnet::Ipv6Net;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::net::{IpAddr, Ipv6Addr};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SortOrder {
+    #[default]
+    Asc,
+    Desc,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum MemberSortKey {
+    #[default]
+    Id,
+    Name,
+    Authorized,
+    Online,
+    Latency,
+    CreationTime,
+    LastAuthorizedTime,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct MemberListQuery {
+    #[serde(default = "default_page")]
+    pub page: usize,
+    #[serde(default = "default_per_page")]
+    pub per_page: usize,
+    #[serde(default)]
+    pub sort: MemberSortKey,
+    #[serde(default)]
+    pub order: SortOrder,
+    pub authorized: Option<bool>,
+    pub online: Option<bool>,
+    /// Case-insensitive match against member ID, name and IPs, computed ones included
+    pub q: Option<String>,
+}
+
+fn default_page() -> usize {
+    1
+}
+
+fn default_per_page() -> usize {
+    50
+}
+
+/// Live peer data reported by the local node for a member
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PeerSummary {
+    pub latency: i64,
+    pub role: String,
+    pub version: String,
+    pub paths: Vec<Value>,
+}
+
+/// IPv6 addresses a network derives from its ID and the member's when
+/// `v6AssignMode` enables them, the controller doesn't report these
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct ComputedAddresses {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rfc4193: Option<Ipv6Addr>,
+    #[serde(rename = "6plane", skip_serializing_if = "Option::is_none")]
+    pub sixplane: Option<Ipv6Addr>,
+    /// The /80 routed to the member, e.g. for containers behind it
+    #[serde(rename = "6planePrefix", skip_serializing_if = "Option::is_none")]
+    pub sixplane_prefix: Option<Ipv6Net>,
+}
+
+impl ComputedAddresses {
+    pub fn new(network_id: &str, network: &Map<String, Value>, member_id: &str) -> Self {
+        let (Ok(network_id), Ok(node_id)) = (
+            u64::from_str_radix(network_id, 16),
+            u64::from_str_radix(member_id, 16),
+        ) else {
+            return Self::default();
+        };
+        let enabled = |mode: &str| {
+            network
+                .get("v6AssignMode")
+                .and_then(|modes| modes.get(mode))
+                .and_then(Value::as_bool)
+                .unwrap_or(false)
+        };
+
+        let mut addresses = Self::default();
+        if enabled("rfc4193") {
+            addresses.rfc4193 = Some(rfc4193_address(network_id, node_id));
+        }
+        if enabled("6plane") {
+            addresses.sixplane = Some(sixplane_address(network_id, node_id));
+            addresses.sixplane_prefix = Ipv6Net::new(sixplane_prefix(network_id, node_id), 80).ok();
+        }
+        addresses
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rfc4193.is_none() && self.sixplane.is_none()
+    }
+
+    pub fn addresses(&self) -> Vec<IpAddr> {
+        self.rfc4193
+            .into_iter()
+            .chain(self.sixplane)
+            .map(IpAddr::V6)
+            .collect()
+    }
+}
+
+/// Member configuration from the controller merged with its peer state
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NetworkMember {
+    #[serde(flatten)]
+    pub config: Map<String, Value>,
+    pub online: bool,
+    pub peer: Option<PeerSummary>,
+    #[serde(skip_serializing_if = "ComputedAddresses::is_empty")]
+    pub computed_addresses: ComputedAddresses,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub expiry: Option<ExpiryStatus>,
+    #[serde(skip_serializing_if = "MemberInfo::is_empty")]
+    pub metadata: MemberInfo,
+}
+
+impl NetworkMember {
+    pub fn new(config: Map<String, Value>, peer: Option<PeerSummary>) -> Self {
+        // A member is online while the local node has a live path to it
+        let online = peer.as_ref().is_some_and(|peer| {
+            peer.paths.iter().any(|path| {
+                path.get("active").and_then(Value::as_bool).unwrap_or(false)
+                    && !path
+                        .get("expired")
+                        .and_then(Value::as_bool)
+                        .unwrap_or(false)
+            })
+        });
+
+        Self {
+            config,
+            online,
+            peer,
+            computed_addresses: ComputedAddresses::default(),
+            expiry: None,
+            metadata: MemberInfo::default(),
+        }
+    }
+
+    pub fn id(&self) -> &str {
+        self.str_field("id")
+    }
+
+    pub fn name(&self) -> &str {
+        self.str_field("name")
+    }
+
+    pub fn description(&self) -> &str {
+        self.str_field("description")
+    }
+
+    pub fn authorized(&self) -> bool {
+        self.config
+            .get("authorized")
+            .and_then(Value::as_bool)
+            .unwrap_or(false)
+    }
+
+    pub fn ip_assignments(&self) -> Vec<&str> {
+        self.config
+            .get("ipAssignments")
+            .and_then(Value::as_array)
+            .map(|ips| ips.iter().filter_map(Value::as_str).collect())
+            .unwrap_or_default()
+    }
+
+    /// Assigned IPs followed by the computed RFC4193 and 6PLANE addresses
+    pub fn addresses(&self) -> Vec<IpAddr> {
+        self.ip_assignments()
+            .iter()
+            .filter_map(|ip| ip.parse().ok())
+            .chain(self.computed_addresses.addresses())
+            .collect()
+    }
+
+    /// Fill in the addresses the network's `v6AssignMode` derives for the member
+    pub fn compute_addresses(&mut self, network_id: &str, network: &Map<String, Value>) {
+        self.computed_addresses = ComputedAddresses::new(network_id, network, self.id());
+    }
+
+    pub fn revision(&self) -> u64 {
+        self.config
+            .get("revision")
+            .and_then(Value::as_u64)
+            .unwrap_or(0)
+    }
+
+    /// When the local node last received a packet from the member
+    pub fn last_seen(&self) -> Option<DateTime<Utc>> {
+        self.peer
+            .iter()
+            .flat_map(|peer| peer.paths.iter())
+            .filter_map(|path| path.get("lastReceive").and_then(Value::as_i64))
+            .max()
+            .and_then(DateTime::from_timestamp_millis)
+    }
+
+    /// Physical addresses of the member's active paths as seen by the local node
+    pub fn physical_addresses(&self) -> Vec<IpAddr> {
+        self.peer
+            .iter()
+            .flat_map(|peer| peer.paths.iter())
+            .filter(|path| path.get("active").and_then(Value::as_bool).unwrap_or(false))
+            .filter_map(|path| {
+                let address = path.get("address")?.as_str()?;
+                // Paths are reported as `ip/port`
+                let (ip, _port) = address.rsplit_once('/')?;
+                ip.parse().ok()
+            })
+            .collect()
+    }
+
+    pub fn int_field(&self, key: &str) -> i64 {
+        self.config.get(key).and_then(Value::as_i64).unwrap_or(0)
+    }
+
+    fn str_field(&self, key: &str) -> &str {
+        self.config.get(key).and_then(Value::as_str).unwrap_or("")
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct MemberListResponse {
+    pub total: usize,
+    pub page: usize,
+    pub per_page: usize,
+    pub items: Vec<NetworkMember>,
+}
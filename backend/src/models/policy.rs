@@ -0,0 +1,50 @@
+use ipnet::IpNet;
+use serde::{Deserialize, Serialize};
+
+/// Conditions a new member must meet for a policy to apply.
+/// Empty conditions match every member.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PolicyConditions {
+    /// Node IDs allowed to match, empty means any
+    #[serde(default)]
+    pub node_ids: Vec<String>,
+    /// The member's physical address must be inside one of these subnets
+    #[serde(default)]
+    pub physical_cidrs: Vec<IpNet>,
+    /// Glob pattern for the member name, `*` and `?` are supported
+    #[serde(default)]
+    pub name_pattern: Option<String>,
+    /// Only match while the network has fewer authorized members than this
+    #[serde(default)]
+    pub max_members: Option<usize>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PolicyAction {
+    /// Authorize the member
+    Authorize,
+    /// Leave the member unauthorized and stop evaluating further policies
+    Reject,
+    /// Only apply the configured tags and IPs
+    Assign,
+}
+
+/// An automatic authorization rule for new members of a network.
+/// Policies are evaluated in order and the first match wins.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MemberPolicy {
+    pub name: String,
+    pub network_id: String,
+    #[serde(default)]
+    pub conditions: PolicyConditions,
+    pub action: PolicyAction,
+    /// Tags to set as `[id, value]` pairs
+    #[serde(default)]
+    pub tags: Vec<[u32; 2]>,
+    #[serde(default)]
+    pub ip_assignments: Vec<String>,
+    /// Delete rejected members from the controller
+    #[serde(default)]
+    pub delete_rejected: bool,
+}
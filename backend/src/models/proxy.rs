@@ -0,0 +1,178 @@
+use serde::{Deserialize, Serialize};
+
+/// The kind of credential a request was authenticated with
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Principal {
+    /// A browser session authenticated with a JWT
+    User,
+    /// An automation client authenticated with a configured API key
+    ApiKey,
+}
+
+impl std::fmt::Display for Principal {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Principal::User => write!(f, "user"),
+            Principal::ApiKey => write!(f, "api_key"),
+        }
+    }
+}
+
+/// A single allow rule for the ZeroTier API proxy.
+/// `path` is matched segment by segment: `*` matches one segment and a
+/// trailing `**` matches zero or more remaining segments.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProxyRule {
+    /// Principals the rule applies to, empty means all
+    #[serde(default)]
+    pub principals: Vec<Principal>,
+    /// Allowed HTTP methods, empty or `*` means all
+    #[serde(default)]
+    pub methods: Vec<String>,
+    pub path: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProxyPolicy {
+    pub rules: Vec<ProxyRule>,
+}
+
+impl Default for ProxyPolicy {
+    /// Only the controller API is reachable by default
+    fn default() -> Self {
+        ProxyPolicy {
+            rules: vec![ProxyRule {
+                principals: Vec::new(),
+                methods: Vec::new(),
+                path: "/controller/**".to_string(),
+            }],
+        }
+    }
+}
+
+impl ProxyRule {
+    fn matches(&self, principal: Principal, method: &str, segments: &[&str]) -> bool {
+        let principal_allowed = self.principals.is_empty() || self.principals.contains(&principal);
+        let method_allowed = self.methods.is_empty()
+            || self
+                .methods
+                .iter()
+                .any(|allowed| allowed == "*" || allowed.eq_ignore_ascii_case(method));
+
+        principal_allowed && method_allowed && path_matches(&self.path, segments)
+    }
+}
+
+impl ProxyPolicy {
+    pub fn allows(&self, principal: Principal, method: &str, path: &str) -> bool {
+        let segments: Vec<&str> = path.trim_matches('/').split('/').collect();
+
+        // Dot segments are resolved by the HTTP client and could escape a pattern
+        let has_dot_segment = segments.iter().any(|segment| {
+            let decoded = segment.to_ascii_lowercase().replace("%2e", ".");
+            !decoded.is_empty() && decoded.chars().all(|c| c == '.')
+        });
+        if has_dot_segment {
+            return false;
+        }
+
+        self.rules
+            .iter()
+            .any(|rule| rule.matches(principal, method, &segments))
+    }
+}
+
+fn path_matches(pattern: &str, segments: &[&str]) -> bool {
+    let pattern: Vec<&str> = pattern.trim_matches('/').split('/').collect();
+
+    for (i, part) in pattern.iter().enumerate() {
+        if *part == "**" && i == pattern.len() - 1 {
+            return true;
+        }
+
+        match segments.get(i) {
+            Some(segment) if *part == "*" || part == segment => {}
+            _ => return false,
+        }
+    }
+
+    pattern.len() == segments.len()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rule(principals: &[Principal], methods: &[&str], path: &str) -> ProxyRule {
+        ProxyRule {
+            principals: principals.to_vec(),
+            methods: methods.iter().map(|method| method.to_string()).collect(),
+            path: path.to_string(),
+        }
+    }
+
+    #[test]
+    fn default_policy_only_allows_the_controller_api() {
+        let policy = ProxyPolicy::default();
+
+        assert!(policy.allows(Principal::User, "GET", "/controller"));
+        assert!(policy.allows(Principal::ApiKey, "POST", "/controller/network/abc"));
+        assert!(!policy.allows(Principal::User, "GET", "/status"));
+        assert!(!policy.allows(Principal::User, "GET", "/peer"));
+        assert!(!policy.allows(Principal::User, "GET", "/controllers"));
+    }
+
+    #[test]
+    fn matches_wildcard_segments() {
+        assert!(path_matches(
+            "/controller/network/*",
+            &["controller", "network", "abc"]
+        ));
+        assert!(!path_matches(
+            "/controller/network/*",
+            &["controller", "network"]
+        ));
+        assert!(!path_matches(
+            "/controller/network/*",
+            &["controller", "network", "abc", "member"]
+        ));
+        assert!(path_matches("/controller/**", &["controller"]));
+        assert!(path_matches(
+            "/controller/**",
+            &["controller", "a", "b", "c"]
+        ));
+        // `**` only has its special meaning at the end
+        assert!(!path_matches("/**/member", &["controller", "member"]));
+    }
+
+    #[test]
+    fn applies_principals_and_methods() {
+        let policy = ProxyPolicy {
+            rules: vec![
+                rule(&[], &["get"], "/controller/**"),
+                rule(&[Principal::User], &["*"], "/controller/network/*/member/*"),
+                rule(&[Principal::ApiKey], &[], "/status"),
+            ],
+        };
+        let member = "/controller/network/abc/member/def";
+
+        assert!(policy.allows(Principal::ApiKey, "GET", member));
+        assert!(!policy.allows(Principal::ApiKey, "POST", member));
+        assert!(policy.allows(Principal::User, "POST", member));
+        assert!(policy.allows(Principal::User, "DELETE", member));
+        assert!(!policy.allows(Principal::User, "POST", "/controller/network/abc"));
+        assert!(policy.allows(Principal::ApiKey, "GET", "/status"));
+        assert!(!policy.allows(Principal::User, "GET", "/status"));
+    }
+
+    #[test]
+    fn rejects_dot_segments() {
+        let policy = ProxyPolicy::default();
+
+        assert!(!policy.allows(Principal::User, "GET", "/controller/../status"));
+        assert!(!policy.allows(Principal::User, "GET", "/controller/%2e%2E/peer"));
+        assert!(!policy.allows(Principal::User, "GET", "/controller/./network"));
+        assert!(policy.allows(Principal::User, "GET", "/controller/network/a.b"));
+    }
+}
@@ -0,0 +1,76 @@
+use serde::Deserialize;
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::net::IpAddr;
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct CompileRulesRequest {
+    /// Rules language source, e.g. `accept ethertype ipv4;`
+    pub source: String,
+}
+
+/// Rules to simulate a flow against and the flow's packet
+#[derive(Debug, Clone, Deserialize)]
+pub struct SimulateFlowRequest {
+    /// Rules language source to test instead of the network's current rules
+    #[serde(default)]
+    pub source: Option<String>,
+    /// Compiled rules to test instead of the network's current rules
+    #[serde(default)]
+    pub rules: Option<Vec<Value>>,
+    #[serde(default)]
+    pub capabilities: Option<Vec<Value>>,
+    #[serde(default)]
+    pub tags: Option<Vec<Value>>,
+    pub packet: SimulatedPacket,
+}
+
+/// A synthetic packet between two members of a network
+#[derive(Debug, Clone, Deserialize)]
+pub struct SimulatedPacket {
+    /// Member ID of the sender
+    pub source: String,
+    /// Member ID of the receiver
+    pub destination: String,
+    #[serde(default = "default_ether_type")]
+    pub ether_type: u16,
+    #[serde(default)]
+    pub ip_protocol: Option<u8>,
+    /// The sender's first assigned address of the packet's family when unset
+    #[serde(default)]
+    pub source_ip: Option<IpAddr>,
+    /// The receiver's first assigned address of the packet's family when unset
+    #[serde(default)]
+    pub destination_ip: Option<IpAddr>,
+    #[serde(default)]
+    pub source_port: Option<u16>,
+    #[serde(default)]
+    pub destination_port: Option<u16>,
+    #[serde(default)]
+    pub icmp_type: Option<u8>,
+    #[serde(default)]
+    pub icmp_code: Option<u8>,
+    #[serde(default)]
+    pub ip_tos: u8,
+    /// TCP flags as matched by `chr`, e.g. 2 for SYN
+    #[serde(default)]
+    pub tcp_flags: u16,
+    #[serde(default)]
+    pub vlan_id: u16,
+    /// Length of the frame's payload, the size of the IP and transport headers when unset
+    #[serde(default)]
+    pub frame_size: Option<u16>,
+    /// Tag values of the sender by tag ID, overriding its assigned tags
+    #[serde(default)]
+    pub sender_tags: BTreeMap<u32, u32>,
+    /// Capability IDs of the sender, replacing its assigned capabilities
+    #[serde(default)]
+    pub sender_capabilities: Option<Vec<u32>>,
+    /// Value compared with the probability of `random` matches, 0 (always matching) when unset
+    #[serde(default)]
+    pub random: u32,
+}
+
+fn default_ether_type() -> u16 {
+    0x0800
+}
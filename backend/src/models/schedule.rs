@@ -0,0 +1,61 @@
+use chrono::{DateTime, Utc};
+use chrono_tz::Tz;
+use serde::{Deserialize, Serialize};
+
+/// Authorizes and deauthorizes members of a network at fixed times.
+/// A member is covered when its ID is listed or it carries one of the tags.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AccessSchedule {
+    pub name: String,
+    pub network_id: String,
+    #[serde(default)]
+    pub members: Vec<String>,
+    /// Tags as `[id, value]` pairs
+    #[serde(default)]
+    pub tags: Vec<[u32; 2]>,
+    /// Cron expression for when access starts, e.g. `0 9 * * MON-FRI`
+    pub authorize: String,
+    /// Cron expression for when access ends
+    pub deauthorize: String,
+    #[serde(default = "default_timezone")]
+    pub timezone: Tz,
+}
+
+fn default_timezone() -> Tz {
+    Tz::UTC
+}
+
+/// A scheduled change of a member's authorization
+#[derive(Debug, Clone, Serialize)]
+pub struct ScheduleTransition {
+    pub schedule: String,
+    pub network_id: String,
+    pub at: DateTime<Utc>,
+    pub authorized: bool,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct TransitionQuery {
+    pub network_id: Option<String>,
+    #[serde(default = "default_transition_limit")]
+    pub limit: usize,
+}
+
+fn default_transition_limit() -> usize {
+    20
+}
+
+/// Keeps a member in a fixed state regardless of its schedules
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScheduleOverride {
+    pub authorized: bool,
+    pub until: DateTime<Utc>,
+    pub set_by: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct SetScheduleOverrideRequest {
+    pub authorized: bool,
+    /// Defaults to the member's next scheduled transition
+    pub until: Option<DateTime<Utc>>,
+}
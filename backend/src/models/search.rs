@@ -0,0 +1,38 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct SearchQuery {
+    /// Whitespace separated terms, every term has to match
+    pub q: String,
+    #[serde(default = "default_search_limit")]
+    pub limit: usize,
+}
+
+fn default_search_limit() -> usize {
+    50
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SearchResultKind {
+    Network,
+    Member,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SearchResult {
+    pub kind: SearchResultKind,
+    pub network_id: String,
+    pub network_name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub member_id: Option<String>,
+    pub name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub authorized: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub online: Option<bool>,
+    /// Higher is a better match
+    pub score: u32,
+    /// Fields the terms matched in
+    pub matched: Vec<&'static str>,
+}
@@ -0,0 +1,54 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Reusable network configuration with `{{placeholders}}` in its string values
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NetworkTemplate {
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    /// Values for placeholders the creation request does not provide
+    #[serde(default)]
+    pub defaults: BTreeMap<String, String>,
+    /// Variables referenced by the config's placeholders
+    #[serde(default)]
+    pub variables: BTreeSet<String>,
+    /// Controller network fields, e.g. `routes`, `ipAssignmentPools`, `dns`, `mtu`,
+    /// `multicastLimit` and `rules`
+    pub config: Map<String, Value>,
+    pub updated_by: String,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct SaveTemplateRequest {
+    #[serde(default)]
+    pub description: String,
+    #[serde(default)]
+    pub defaults: BTreeMap<String, String>,
+    pub config: Map<String, Value>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct CreateFromTemplateRequest {
+    pub template: String,
+    #[serde(default)]
+    pub variables: BTreeMap<String, String>,
+    /// Fill this variable with the next free subnet from IPAM
+    #[serde(default)]
+    pub allocate_subnet: Option<String>,
+    /// Render and validate the config without creating the network
+    #[serde(default)]
+    pub dry_run: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CreateFromTemplateResponse {
+    pub dry_run: bool,
+    /// Unset for dry runs
+    pub network_id: Option<String>,
+    /// The rendered config, or the created network as reported by the controller
+    pub config: Map<String, Value>,
+}
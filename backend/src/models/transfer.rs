@@ -0,0 +1,108 @@
+use chrono::{DateTime, Utc};
+use ipnet::Ipv6Net;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::net::Ipv6Addr;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    #[default]
+    Csv,
+    Json,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ExportQuery {
+    #[serde(default)]
+    pub format: ExportFormat,
+}
+
+/// One exported member, the CSV columns use the same names
+#[derive(Debug, Clone, Serialize)]
+pub struct MemberRecord {
+    pub id: String,
+    pub name: String,
+    pub authorized: bool,
+    pub ip_assignments: Vec<String>,
+    pub rfc4193: Option<Ipv6Addr>,
+    #[serde(rename = "6plane")]
+    pub sixplane: Option<Ipv6Addr>,
+    #[serde(rename = "6plane_prefix")]
+    pub sixplane_prefix: Option<Ipv6Net>,
+    pub tags: Vec<[u32; 2]>,
+    pub online: bool,
+    pub last_seen: Option<DateTime<Utc>>,
+    pub owner: Option<String>,
+    pub asset_tag: Option<String>,
+    pub location: Option<String>,
+    pub notes: Option<String>,
+    pub labels: BTreeMap<String, String>,
+}
+
+/// Member fields an import column can be mapped to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ImportField {
+    Id,
+    Name,
+    Authorized,
+    IpAssignments,
+    Tags,
+    Owner,
+    AssetTag,
+    Location,
+    Notes,
+    Labels,
+    /// Skip the column
+    Ignore,
+}
+
+impl ImportField {
+    /// The field a column of the export format maps to by default
+    pub fn from_column(column: &str) -> Option<Self> {
+        serde_json::from_value(serde_json::Value::String(column.trim().to_lowercase())).ok()
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ImportRequest {
+    /// CSV with a header row
+    pub csv: String,
+    /// Header name to field, columns named like the export map themselves
+    #[serde(default)]
+    pub mapping: HashMap<String, ImportField>,
+    #[serde(default)]
+    pub dry_run: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ImportStatus {
+    Created,
+    Updated,
+    Unchanged,
+    Failed,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ImportRowResult {
+    /// Line number in the CSV, the header is line 1
+    pub line: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub member_id: Option<String>,
+    pub status: ImportStatus,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub errors: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ImportResponse {
+    pub dry_run: bool,
+    pub total: usize,
+    pub created: usize,
+    pub updated: usize,
+    pub unchanged: usize,
+    pub failed: usize,
+    pub rows: Vec<ImportRowResult>,
+}
@@ -0,0 +1,74 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum WorldType {
+    /// Replaces the default roots of every node that loads it
+    Planet,
+    /// Additional roots nodes orbit by ID
+    Moon,
+}
+
+/// A root server of a world
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WorldRoot {
+    /// Public identity, `<address>:0:<public key>`
+    pub identity: String,
+    /// Addresses the root is reachable at, as `<ip>/<port>`
+    #[serde(default)]
+    pub stable_endpoints: Vec<String>,
+}
+
+/// A world to generate, in the JSON format written by `zerotier-idtool initmoon`
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WorldDefinition {
+    pub world_type: WorldType,
+    /// 64-bit world ID in hex, for moons the address of the root that owns it
+    pub id: String,
+    /// Milliseconds since the epoch, nodes only replace a world with a newer one.
+    /// Defaults to now.
+    pub timestamp: Option<u64>,
+    pub roots: Vec<WorldRoot>,
+    /// Public key later updates have to be signed with, defaults to the signing key
+    pub updates_must_be_signed_by: Option<String>,
+    /// Public key of the signing key pair, checked against the secret when given
+    pub signing_key: Option<String>,
+    /// Secret of the key pair the world is signed with, for moons the owning
+    /// root's identity secret and for planets the previous update key
+    #[serde(rename = "signingKey_SECRET")]
+    pub signing_key_secret: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ParseWorldQuery {
+    /// Public key to check the signature against, e.g. the previous planet's update key
+    pub signed_by: Option<String>,
+}
+
+/// A world read from its binary form
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ParsedWorld {
+    pub world_type: WorldType,
+    pub id: String,
+    pub timestamp: u64,
+    pub roots: Vec<WorldRoot>,
+    pub updates_must_be_signed_by: String,
+    pub signature: String,
+    /// Whether the world is signed with its own update key, as moons are
+    pub self_signed: bool,
+    /// Whether the signature matches the `signed_by` key, when one was given
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub verified: Option<bool>,
+}
+
+/// A new C25519 key pair for signing worlds
+#[derive(Debug, Clone, Serialize)]
+pub struct WorldKeyPair {
+    #[serde(rename = "signingKey")]
+    pub signing_key: String,
+    #[serde(rename = "signingKey_SECRET")]
+    pub signing_key_secret: String,
+}
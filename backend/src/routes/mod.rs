@@ -2,13 +2,16 @@ use crate::handlers::{self, *};
 use crate::state::AppState;
 use axum::{
     middleware::from_fn_with_state,
-    routing::{any, post},
+    routing::{any, delete, get, post, put},
     Router,
 };
 
 // Public API routes (no authentication required)
 pub fn public_api_routes() -> Router<AppState> {
-    Router::new().route("/login", post(login))
+    Router::new()
+        .route("/login", post(login))
+        .route("/logout", post(logout))
+        .route("/join/{token}", get(get_join_info).post(redeem_invitation))
 }
 
 // Protected API routes (authentication required)
@@ -16,6 +19,86 @@ pub fn protected_api_routes() -> Router<AppState> {
     Router::new()
         .route("/editprofile", post(update_profile))
         .route("/refresh", post(refresh_token))
+        .route("/admin/mode", get(get_mode).put(update_mode))
+        .route("/audit", get(list_audit_entries))
+        .route("/gitops/plan", post(plan_manifest))
+        .route("/gitops/apply", post(apply_manifest))
+        .route(
+            "/invitations",
+            get(list_invitations).post(create_invitation),
+        )
+        .route("/invitations/{id}", delete(revoke_invitation))
+        .route("/ipam", get(get_ipam_overview))
+        .route("/ipam/next-subnet", get(get_next_subnet))
+        .route(
+            "/networks/from-template",
+            post(create_network_from_template),
+        )
+        .route(
+            "/networks/{network_id}/inventory/{format}",
+            get(export_inventory),
+        )
+        .route("/networks/{network_id}/ipam/next-ip", get(get_next_ips))
+        .route(
+            "/networks/{network_id}/ipam/reservations",
+            get(list_ip_reservations).post(reserve_ip),
+        )
+        .route(
+            "/networks/{network_id}/ipam/reservations/{ip}",
+            delete(release_ip),
+        )
+        .route(
+            "/networks/{network_id}/ipam/subnet",
+            post(allocate_network_subnet),
+        )
+        .route("/networks/{network_id}/members", get(list_network_members))
+        .route(
+            "/networks/{network_id}/members/bulk",
+            post(bulk_member_operation),
+        )
+        .route("/networks/{network_id}/members/export", get(export_members))
+        .route(
+            "/networks/{network_id}/members/import",
+            post(import_members),
+        )
+        .route(
+            "/networks/{network_id}/members/{member_id}",
+            get(get_network_member),
+        )
+        .route(
+            "/networks/{network_id}/members/{member_id}/ipam/allocate",
+            post(allocate_member_ip),
+        )
+        .route(
+            "/networks/{network_id}/members/{member_id}/metadata",
+            get(get_member_metadata).put(update_member_metadata),
+        )
+        .route(
+            "/networks/{network_id}/members/{member_id}/expiry",
+            put(set_member_expiry).delete(clear_member_expiry),
+        )
+        .route(
+            "/networks/{network_id}/members/{member_id}/schedule-override",
+            put(set_schedule_override).delete(clear_schedule_override),
+        )
+        .route("/networks/{network_id}/rules", put(update_network_rules))
+        .route("/networks/{network_id}/rules/simulate", post(simulate_flow))
+        .route("/rules/compile", post(compile_rules))
+        .route("/schedules/transitions", get(list_schedule_transitions))
+        .route("/search", get(search))
+        .route("/templates", get(list_templates))
+        .route(
+            "/templates/{name}",
+            get(get_template).put(save_template).delete(delete_template),
+        )
+        .route("/worlds/generate", post(generate_world))
+        .route("/worlds/keys", post(generate_world_keys))
+        .route("/worlds/parse", post(parse_world))
+}
+
+// Event stream routes (authentication required, session cookie accepted)
+pub fn event_routes() -> Router<AppState> {
+    Router::new().route("/events", get(stream_events))
 }
 
 // ZeroTier routes (authentication required)
@@ -35,15 +118,38 @@ pub fn app_routes(app_state: AppState) -> Router {
                 crate::middleware::auth_middleware,
             )),
         )
-        // ZeroTier routes with authentication middleware
+        // Event stream routes with authentication middleware
         .nest(
-            "/ztapi",
-            zerotier_routes().layer(from_fn_with_state(
+            "/api",
+            event_routes().layer(from_fn_with_state(
                 app_state.clone(),
-                crate::middleware::auth_or_api_key_middleware,
+                crate::middleware::stream_auth_middleware,
             )),
         )
+        // ZeroTier routes with authentication middleware
+        .nest(
+            "/ztapi",
+            zerotier_routes()
+                .layer(from_fn_with_state(
+                    app_state.clone(),
+                    crate::middleware::proxy_policy_middleware,
+                ))
+                .layer(from_fn_with_state(
+                    app_state.clone(),
+                    crate::middleware::maintenance_middleware,
+                ))
+                .layer(from_fn_with_state(
+                    app_state.clone(),
+                    crate::middleware::auth_or_api_key_middleware,
+                )),
+        )
+        // Public join page for invitation links
+        .route("/join/{token}", get(serve_join_page))
         .fallback(handlers::serve_static_files)
+        .layer(from_fn_with_state(
+            app_state.clone(),
+            crate::middleware::read_only_middleware,
+        ))
         .layer(
             tower_http::trace::TraceLayer::new_for_http()
                 .make_span_with(
@@ -0,0 +1,1352 @@
+//! Compiler for the ZeroTier rules language, a port of the frontend's `RulesCompile.ts`
+
+use ipnet::IpNet;
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::fmt;
+
+/// Names for bits in characteristics, 0 is the LSB and 63 the MSB
+const CHARACTERISTIC_BITS: &[(&str, u32)] = &[
+    ("inbound", 63),
+    ("multicast", 62),
+    ("broadcast", 61),
+    ("ipauth", 60),
+    ("macauth", 59),
+    ("tcp_fin", 0),
+    ("tcp_syn", 1),
+    ("tcp_rst", 2),
+    ("tcp_psh", 3),
+    ("tcp_ack", 4),
+    ("tcp_urg", 5),
+    ("tcp_ece", 6),
+    ("tcp_cwr", 7),
+    ("tcp_ns", 8),
+    ("tcp_rs2", 9),
+    ("tcp_rs1", 10),
+    ("tcp_rs0", 11),
+];
+
+/// Shorthand names for common ethernet types
+const ETHERTYPES: &[(&str, i64)] = &[
+    ("ipv4", 0x0800),
+    ("arp", 0x0806),
+    ("wol", 0x0842),
+    ("rarp", 0x8035),
+    ("ipv6", 0x86dd),
+    ("atalk", 0x809b),
+    ("aarp", 0x80f3),
+    ("ipx_a", 0x8137),
+    ("ipx_b", 0x8138),
+];
+
+/// Shorthand names for common IP protocols
+const IP_PROTOCOLS: &[(&str, i64)] = &[
+    ("icmp", 0x01),
+    ("icmp4", 0x01),
+    ("icmpv4", 0x01),
+    ("igmp", 0x02),
+    ("ipip", 0x04),
+    ("tcp", 0x06),
+    ("egp", 0x08),
+    ("igp", 0x09),
+    ("udp", 0x11),
+    ("rdp", 0x1b),
+    ("esp", 0x32),
+    ("ah", 0x33),
+    ("icmp6", 0x3a),
+    ("icmpv6", 0x3a),
+    ("l2tp", 0x73),
+    ("sctp", 0x84),
+    ("udplite", 0x88),
+];
+
+/// Keywords that open a block terminated by a semicolon
+const OPEN_BLOCK_KEYWORDS: &[&str] = &[
+    "macro", "tag", "cap", "drop", "accept", "tee", "watch", "redirect", "break",
+];
+
+/// Words that can't be used as tag, capability or macro names, or as match arguments
+const RESERVED_WORDS: &[&str] = &[
+    "macro",
+    "tag",
+    "cap",
+    "default",
+    "drop",
+    "accept",
+    "tee",
+    "watch",
+    "redirect",
+    "break",
+    "ztsrc",
+    "ztdest",
+    "vlan",
+    "vlanpcp",
+    "vlandei",
+    "ethertype",
+    "macsrc",
+    "macdest",
+    "ipsrc",
+    "ipdest",
+    "iptos",
+    "ipprotocol",
+    "icmp",
+    "sport",
+    "dport",
+    "chr",
+    "framesize",
+    "random",
+    "tand",
+    "tor",
+    "txor",
+    "tdiff",
+    "teq",
+    "tseq",
+    "treq",
+    "type",
+    "enum",
+    "class",
+    "define",
+    "import",
+    "include",
+    "log",
+    "not",
+    "xor",
+    "or",
+    "and",
+    "set",
+    "var",
+    "let",
+];
+
+/// Maximum nesting of `include` directives
+const MAX_INCLUDE_DEPTH: usize = 32;
+
+fn lookup<T: Copy>(table: &[(&str, T)], name: &str) -> Option<T> {
+    table
+        .iter()
+        .find(|(key, _)| *key == name)
+        .map(|(_, value)| *value)
+}
+
+/// The rule type a keyword compiles to
+fn api_type(keyword: &str) -> &'static str {
+    match keyword {
+        "drop" => "ACTION_DROP",
+        "accept" => "ACTION_ACCEPT",
+        "tee" => "ACTION_TEE",
+        "watch" => "ACTION_WATCH",
+        "redirect" => "ACTION_REDIRECT",
+        "break" => "ACTION_BREAK",
+        "ztsrc" => "MATCH_SOURCE_ZEROTIER_ADDRESS",
+        "ztdest" => "MATCH_DEST_ZEROTIER_ADDRESS",
+        "vlan" => "MATCH_VLAN_ID",
+        "vlanpcp" => "MATCH_VLAN_PCP",
+        "vlandei" => "MATCH_VLAN_DEI",
+        "ethertype" => "MATCH_ETHERTYPE",
+        "macsrc" => "MATCH_MAC_SOURCE",
+        "macdest" => "MATCH_MAC_DEST",
+        "iptos" => "MATCH_IP_TOS",
+        "ipprotocol" => "MATCH_IP_PROTOCOL",
+        "icmp" => "MATCH_ICMP",
+        "sport" => "MATCH_IP_SOURCE_PORT_RANGE",
+        "dport" => "MATCH_IP_DEST_PORT_RANGE",
+        "chr" => "MATCH_CHARACTERISTICS",
+        "framesize" => "MATCH_FRAME_SIZE_RANGE",
+        "random" => "MATCH_RANDOM",
+        "tand" => "MATCH_TAGS_BITWISE_AND",
+        "tor" => "MATCH_TAGS_BITWISE_OR",
+        "txor" => "MATCH_TAGS_BITWISE_XOR",
+        "tdiff" => "MATCH_TAGS_DIFFERENCE",
+        "teq" => "MATCH_TAGS_EQUAL",
+        "tseq" => "MATCH_TAG_SENDER",
+        "treq" => "MATCH_TAG_RECEIVER",
+        _ => "",
+    }
+}
+
+/// Number of arguments each match takes
+fn match_arg_count(keyword: &str) -> Option<usize> {
+    match keyword {
+        "ztsrc" | "ztdest" | "vlan" | "vlanpcp" | "vlandei" | "ethertype" | "macsrc"
+        | "macdest" | "ipsrc" | "ipdest" | "ipprotocol" | "sport" | "dport" | "chr"
+        | "framesize" | "random" => Some(1),
+        "iptos" | "icmp" | "tand" | "tor" | "txor" | "tdiff" | "teq" | "tseq" | "treq" => Some(2),
+        _ => None,
+    }
+}
+
+/// A compile error at a 1-based line and column of the source
+#[derive(Debug, Clone, Serialize)]
+pub struct RuleError {
+    pub line: usize,
+    pub column: usize,
+    pub message: String,
+}
+
+impl fmt::Display for RuleError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "line {}, column {}: {}",
+            self.line, self.column, self.message
+        )
+    }
+}
+
+type CompileResult<T> = std::result::Result<T, RuleError>;
+
+/// Rules, capabilities and tags in the controller's network JSON format
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct CompiledRules {
+    pub rules: Vec<Value>,
+    pub capabilities: Vec<Value>,
+    pub tags: Vec<Value>,
+}
+
+#[derive(Debug, Clone)]
+struct Token {
+    text: String,
+    line: usize,
+    column: usize,
+}
+
+impl Token {
+    fn error(&self, message: &str) -> RuleError {
+        RuleError {
+            line: self.line,
+            column: self.column,
+            message: message.to_string(),
+        }
+    }
+}
+
+/// A word of the source or a block opened by a keyword and closed by `;`
+#[derive(Debug, Clone)]
+enum Node {
+    Token(Token),
+    Block(Vec<Node>),
+}
+
+impl Node {
+    fn token(&self) -> Option<&Token> {
+        match self {
+            Node::Token(token) => Some(token),
+            Node::Block(_) => None,
+        }
+    }
+
+    fn block(&self) -> Option<&[Node]> {
+        match self {
+            Node::Token(_) => None,
+            Node::Block(nodes) => Some(nodes),
+        }
+    }
+
+    /// Lowercased text of a token, empty for blocks
+    fn keyword(&self) -> String {
+        self.token()
+            .map(|token| token.text.to_lowercase())
+            .unwrap_or_default()
+    }
+
+    fn error(&self, message: &str) -> RuleError {
+        match self {
+            Node::Token(token) => token.error(message),
+            // Blocks are never empty
+            Node::Block(nodes) => nodes[0].error(message),
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Macro {
+    /// Parameter names such as `$port` and their positions
+    params: HashMap<String, usize>,
+    rules: Vec<Node>,
+}
+
+#[derive(Debug)]
+struct Tag {
+    id: i64,
+    default: Option<i64>,
+    flags: HashMap<String, i64>,
+    enums: HashMap<String, i64>,
+}
+
+#[derive(Debug)]
+struct Capability {
+    id: i64,
+    default: bool,
+    rules: Vec<Node>,
+}
+
+/// Definitions collected in the second pass, in source order
+#[derive(Default)]
+struct Definitions {
+    macros: HashMap<String, Macro>,
+    tags: Vec<(String, Tag)>,
+    capabilities: Vec<(String, Capability)>,
+}
+
+impl Definitions {
+    fn tag(&self, name: &str) -> Option<&Tag> {
+        self.tags
+            .iter()
+            .find(|(tag_name, _)| tag_name == name)
+            .map(|(_, tag)| tag)
+    }
+}
+
+/// Whether something is a valid capability, tag, macro, flag or enum name
+fn is_valid_name(name: &str) -> bool {
+    !name.is_empty()
+        && !name.starts_with(|c: char| c.is_ascii_digit())
+        && name.chars().all(|c| c == '_' || c.is_alphanumeric())
+}
+
+fn is_reserved(word: &str) -> bool {
+    RESERVED_WORDS.contains(&word)
+}
+
+/// Parse a decimal or `0x` hexadecimal number, -1 if there is none.
+/// Like `parseInt`, trailing garbage after the digits is ignored.
+fn parse_num(text: &str) -> i64 {
+    let text = text.trim().to_lowercase();
+    let (digits, radix) = match text.strip_prefix("0x") {
+        Some(hex) if !hex.is_empty() => (hex, 16),
+        _ => (text.as_str(), 10),
+    };
+    let (negative, digits) = match digits.strip_prefix('-') {
+        Some(digits) => (true, digits),
+        None => (false, digits.strip_prefix('+').unwrap_or(digits)),
+    };
+
+    let end = digits
+        .find(|c: char| !c.is_digit(radix))
+        .unwrap_or(digits.len());
+    if end == 0 {
+        return -1;
+    }
+    let value = u64::from_str_radix(&digits[..end], radix)
+        .ok()
+        .and_then(|value| i64::try_from(value).ok())
+        .unwrap_or(i64::MAX);
+
+    if negative {
+        -value
+    } else {
+        value
+    }
+}
+
+/// Parse `from-to` or a single number as a range
+fn parse_range(text: &str) -> Option<(i64, i64)> {
+    match text.find('-') {
+        Some(index) if index > 0 => {
+            let (from, to) = text.split_once('-')?;
+            if to.contains('-') {
+                return None;
+            }
+            Some((parse_num(from), parse_num(to)))
+        }
+        _ => {
+            let value = parse_num(text);
+            Some((value, value))
+        }
+    }
+}
+
+fn is_zerotier_address(text: &str) -> bool {
+    text.len() == 10 && text.chars().all(|c| c.is_ascii_hexdigit())
+}
+
+/// Lowercase hex digits of a MAC address, separated by colons
+fn clean_mac(text: &str) -> String {
+    let mut mac = String::new();
+    for c in text.to_lowercase().chars().filter(char::is_ascii_hexdigit) {
+        if mac.len() >= 17 {
+            break;
+        }
+        mac.push(c);
+        if mac.len() != 17 && mac.len() % 3 == 2 {
+            mac.push(':');
+        }
+    }
+    mac
+}
+
+/// Split `name(a,b)` into the lowercased name and its parameters
+fn split_call(text: &str) -> (String, Vec<String>) {
+    match text.find('(') {
+        Some(index) if index > 0 => (
+            text[..index].to_lowercase(),
+            text[index + 1..]
+                .split([',', ')'])
+                .filter(|param| !param.is_empty())
+                .map(str::to_string)
+                .collect(),
+        ),
+        _ => (text.to_lowercase(), Vec::new()),
+    }
+}
+
+/// End the current word, opening or closing a block as needed
+fn finish_token(current: &mut Option<Token>, stack: &mut Vec<Vec<Node>>) {
+    let Some(mut token) = current.take() else {
+        return;
+    };
+    let end_of_block = token.text.ends_with(';');
+    if end_of_block {
+        token.text.pop();
+    }
+    let opens_block = OPEN_BLOCK_KEYWORDS.contains(&token.text.as_str());
+    if !token.text.is_empty() {
+        stack.last_mut().unwrap().push(Node::Token(token));
+    }
+
+    if end_of_block {
+        if stack.len() > 1 && !stack.last().unwrap().is_empty() {
+            let block = stack.pop().unwrap();
+            stack.last_mut().unwrap().push(Node::Block(block));
+        }
+    } else if opens_block {
+        stack.push(Vec::new());
+    }
+}
+
+/// Pass 1: split the source into words and nested blocks
+fn parse(source: &str) -> Vec<Node> {
+    let mut stack: Vec<Vec<Node>> = vec![Vec::new()];
+    let mut current: Option<Token> = None;
+    let mut skip_rest_of_line = false;
+    let mut line = 1;
+    let mut column = 0;
+
+    for ch in source.chars() {
+        column += 1;
+        if skip_rest_of_line {
+            if ch == '\n' {
+                skip_rest_of_line = false;
+                line += 1;
+                column = 0;
+            }
+            continue;
+        }
+
+        match ch {
+            '\n' | '\r' | '\t' | ' ' => {
+                finish_token(&mut current, &mut stack);
+                if ch == '\n' {
+                    line += 1;
+                    column = 0;
+                }
+            }
+            '#' if current.is_none() => skip_rest_of_line = true,
+            _ => current
+                .get_or_insert_with(|| Token {
+                    text: String::new(),
+                    line,
+                    column,
+                })
+                .text
+                .push(ch),
+        }
+    }
+
+    // Close blocks left open at the end of the source
+    finish_token(&mut current, &mut stack);
+    while stack.len() > 1 {
+        let block = stack.pop().unwrap();
+        if !block.is_empty() {
+            stack.last_mut().unwrap().push(Node::Block(block));
+        }
+    }
+    stack.pop().unwrap()
+}
+
+/// The first token of the block following a `macro`, `tag` or `cap` keyword
+fn definition<'a>(
+    keyword: &Node,
+    next: Option<&'a Node>,
+    kind: &str,
+) -> CompileResult<(&'a Token, &'a [Node])> {
+    next.and_then(Node::block)
+        .and_then(|block| Some((block.first()?.token()?, &block[1..])))
+        .ok_or_else(|| keyword.error(&format!("{} definition is missing name.", kind)))
+}
+
+fn check_name(token: &Token, name: &str, kind: &str) -> CompileResult<()> {
+    if !is_valid_name(name) {
+        return Err(token.error(&format!("Invalid {} name.", kind.to_lowercase())));
+    }
+    if is_reserved(name) {
+        return Err(token.error(&format!("{} name is a reserved word.", kind)));
+    }
+    Ok(())
+}
+
+fn parse_macro(name: &Token, body: &[Node], definitions: &mut Definitions) -> CompileResult<()> {
+    let (macro_name, params) = split_call(&name.text);
+    check_name(name, &macro_name, "Macro")?;
+    if definitions.macros.contains_key(&macro_name) {
+        return Err(name.error("Multiple definition of macro name."));
+    }
+
+    let params = params
+        .into_iter()
+        .enumerate()
+        .map(|(index, param)| (param.to_lowercase(), index))
+        .collect();
+    definitions.macros.insert(
+        macro_name,
+        Macro {
+            params,
+            rules: body.to_vec(),
+        },
+    );
+    Ok(())
+}
+
+fn parse_tag(name: &Token, body: &[Node], definitions: &mut Definitions) -> CompileResult<()> {
+    let tag_name = name.text.to_lowercase();
+    check_name(name, &tag_name, "Tag")?;
+    if definitions.tag(&tag_name).is_some() {
+        return Err(name.error("Multiple definition of tag name."));
+    }
+
+    let mut id = -1;
+    let mut default: Option<&Token> = None;
+    let mut flags = HashMap::new();
+    let mut enums = HashMap::new();
+
+    let mut nodes = body.iter();
+    while let Some(node) = nodes.next() {
+        let Some(token) = node.token() else {
+            return Err(node.error("Unrecognized keyword in tag definition."));
+        };
+        let mut argument = |message: &str| {
+            nodes
+                .next()
+                .and_then(Node::token)
+                .ok_or_else(|| token.error(message))
+        };
+
+        match token.text.to_lowercase().as_str() {
+            "id" => {
+                if id >= 0 {
+                    return Err(token.error("Duplicate tag id definition."));
+                }
+                let value = argument("Missing numeric value for ID.")?;
+                id = parse_num(&value.text);
+                if !(0..=0xffffffff).contains(&id) {
+                    return Err(value.error("Invalid or out of range tag ID."));
+                }
+            }
+            "default" => {
+                if default.is_some() {
+                    return Err(token.error("Duplicate tag default directive."));
+                }
+                default = Some(argument("Missing value for default.")?);
+            }
+            "flag" => {
+                let bits = argument("Missing tag flag name or bit index.")?;
+                let flag = argument("Missing tag flag name or bit index.")?;
+                let mut mask = 0;
+                for bit in bits
+                    .text
+                    .to_lowercase()
+                    .split(',')
+                    .filter(|b| !b.is_empty())
+                {
+                    if let Some(flag_mask) = flags.get(bit) {
+                        mask |= flag_mask;
+                        continue;
+                    }
+                    let bit = parse_num(bit);
+                    if !(0..=31).contains(&bit) {
+                        return Err(bits.error(
+                            "Bit index invalid, out of range, or references an undefined flag name.",
+                        ));
+                    }
+                    mask |= 1 << bit;
+                }
+
+                let flag_name = flag.text.to_lowercase();
+                if !is_valid_name(&flag_name) {
+                    return Err(flag.error("Invalid or reserved flag name."));
+                }
+                if flags.contains_key(&flag_name) {
+                    return Err(flag.error("Duplicate flag name in tag definition."));
+                }
+                flags.insert(flag_name, mask);
+            }
+            "enum" => {
+                let value = argument("Missing tag enum name or value.")?;
+                let name = argument("Missing tag enum name or value.")?;
+                let number = parse_num(&value.text);
+                if !(0..=0xffffffff).contains(&number) {
+                    return Err(value.error("Tag enum value invalid or out of range."));
+                }
+
+                let enum_name = name.text.to_lowercase();
+                if !is_valid_name(&enum_name) {
+                    return Err(name.error("Invalid or reserved tag enum name."));
+                }
+                if enums.contains_key(&enum_name) {
+                    return Err(name.error("Duplicate enum name in tag definition."));
+                }
+                enums.insert(enum_name, number);
+            }
+            _ => return Err(token.error("Unrecognized keyword in tag definition.")),
+        }
+    }
+    if id < 0 {
+        return Err(name.error("Tag definition is missing a numeric ID."));
+    }
+
+    // The default may name an enum or flag of the tag
+    let default = default.map(|token| {
+        let text = token.text.to_lowercase();
+        enums
+            .get(&text)
+            .or_else(|| flags.get(&text))
+            .copied()
+            .unwrap_or_else(|| parse_num(&text).max(0) & 0xffffffff)
+    });
+
+    definitions.tags.push((
+        tag_name,
+        Tag {
+            id,
+            default,
+            flags,
+            enums,
+        },
+    ));
+    Ok(())
+}
+
+fn parse_capability(
+    name: &Token,
+    body: &[Node],
+    definitions: &mut Definitions,
+) -> CompileResult<()> {
+    let capability_name = name.text.to_lowercase();
+    check_name(name, &capability_name, "Capability")?;
+    if definitions
+        .capabilities
+        .iter()
+        .any(|(existing, _)| *existing == capability_name)
+    {
+        return Err(name.error("Multiple definition of capability name."));
+    }
+
+    let mut id = -1;
+    let mut default = false;
+    let mut rules = Vec::new();
+
+    let mut nodes = body.iter();
+    while let Some(node) = nodes.next() {
+        match node.keyword().as_str() {
+            "id" => {
+                if id >= 0 {
+                    return Err(node.error("Duplicate id directive in capability definition."));
+                }
+                let value = nodes
+                    .next()
+                    .and_then(Node::token)
+                    .ok_or_else(|| node.error("Missing value for ID."))?;
+                id = parse_num(&value.text);
+                if !(0..=0xffffffff).contains(&id) {
+                    return Err(node.error("Invalid or out of range capability ID."));
+                }
+                if definitions
+                    .capabilities
+                    .iter()
+                    .any(|(_, capability)| capability.id == id)
+                {
+                    return Err(node.error("Duplicate capability ID."));
+                }
+            }
+            "default" => default = true,
+            _ => rules.push(node.clone()),
+        }
+    }
+    if id < 0 {
+        return Err(name.error("Capability definition is missing a numeric ID."));
+    }
+
+    definitions
+        .capabilities
+        .push((capability_name, Capability { id, default, rules }));
+    Ok(())
+}
+
+/// Pass 3: render actions and matches into low-level rules
+impl Definitions {
+    fn render_matches(
+        &self,
+        nodes: &[Node],
+        params: &HashMap<String, String>,
+        rules: &mut Vec<Value>,
+    ) -> CompileResult<()> {
+        let mut not = false;
+        let mut or = false;
+
+        let mut index = 0;
+        while index < nodes.len() {
+            let node = &nodes[index];
+            index += 1;
+
+            let keyword = node.keyword();
+            match keyword.as_str() {
+                "and" => continue,
+                "not" => {
+                    not = true;
+                    continue;
+                }
+                "or" => {
+                    or = true;
+                    continue;
+                }
+                _ => {}
+            }
+
+            let count = match_arg_count(&keyword)
+                .ok_or_else(|| node.error(&format!("Unrecognized match type \"{}\".", keyword)))?;
+            let mut args = Vec::with_capacity(count);
+            for _ in 0..count {
+                let Some(arg) = nodes.get(index).and_then(Node::token) else {
+                    return Err(nodes[index - 1].error("Missing argument(s) to match."));
+                };
+                index += 1;
+                if arg.text.is_empty() || is_reserved(&arg.text) {
+                    return Err(nodes[index - 2].error(
+                        "Missing argument(s) to match (invalid argument or argument is reserved word).",
+                    ));
+                }
+
+                if arg.text.starts_with('$') {
+                    let value = params
+                        .get(&arg.text.to_lowercase())
+                        .ok_or_else(|| arg.error("Undefined variable name."))?;
+                    args.push(Token {
+                        text: value.clone(),
+                        ..arg.clone()
+                    });
+                } else {
+                    args.push(arg.clone());
+                }
+            }
+
+            let mut rule = self.render_match(&keyword, &args)?;
+            rule["not"] = json!(not);
+            rule["or"] = json!(or);
+            rules.push(rule);
+
+            not = false;
+            or = false;
+        }
+
+        Ok(())
+    }
+
+    fn render_match(&self, keyword: &str, args: &[Token]) -> CompileResult<Value> {
+        let arg = &args[0];
+        let rule = match keyword {
+            "ztsrc" | "ztdest" => {
+                let zt: String = arg
+                    .text
+                    .to_lowercase()
+                    .chars()
+                    .filter(char::is_ascii_hexdigit)
+                    .collect();
+                if zt.len() != 10 {
+                    return Err(arg.error("Invalid ZeroTier address."));
+                }
+                json!({ "type": api_type(keyword), "zt": zt })
+            }
+            "vlan" | "vlanpcp" | "vlandei" | "ethertype" | "ipprotocol" => {
+                let named = match keyword {
+                    "ethertype" => lookup(ETHERTYPES, &arg.text),
+                    "ipprotocol" => lookup(IP_PROTOCOLS, &arg.text),
+                    _ => None,
+                };
+                let number = named.unwrap_or_else(|| parse_num(&arg.text));
+                if !(0..=0xffffffff).contains(&number) {
+                    return Err(arg.error("Invalid numeric value."));
+                }
+                let field = match keyword {
+                    "vlan" => "vlanId",
+                    "vlanpcp" => "vlanPcp",
+                    "vlandei" => "vlanDei",
+                    "ethertype" => "etherType",
+                    _ => "ipProtocol",
+                };
+                json!({ "type": api_type(keyword), field: number })
+            }
+            "random" => {
+                let probability = arg.text.parse::<f64>().unwrap_or(0.0);
+                let probability = if probability.is_nan() {
+                    0.0
+                } else {
+                    probability.clamp(0.0, 1.0)
+                };
+                json!({
+                    "type": api_type(keyword),
+                    "probability": (4294967295.0 * probability).floor() as u64,
+                })
+            }
+            "macsrc" | "macdest" => {
+                let mac = clean_mac(&arg.text);
+                if mac.len() != 17 {
+                    return Err(arg.error("Invalid MAC address."));
+                }
+                json!({ "type": api_type(keyword), "mac": mac })
+            }
+            "ipsrc" | "ipdest" => {
+                if arg.text.find('/').unwrap_or(0) == 0 {
+                    return Err(arg.error("Missing /bits netmask length designation in IP."));
+                }
+                let network: IpNet = arg
+                    .text
+                    .parse()
+                    .map_err(|_| arg.error("Invalid IP address (not valid IPv4 or IPv6)."))?;
+                let rule_type = match (network, keyword) {
+                    (IpNet::V4(_), "ipsrc") => "MATCH_IPV4_SOURCE",
+                    (IpNet::V4(_), _) => "MATCH_IPV4_DEST",
+                    (IpNet::V6(_), "ipsrc") => "MATCH_IPV6_SOURCE",
+                    (IpNet::V6(_), _) => "MATCH_IPV6_DEST",
+                };
+                json!({ "type": rule_type, "ip": arg.text })
+            }
+            "icmp" => {
+                let icmp_type = parse_num(&arg.text);
+                if !(0..=0xff).contains(&icmp_type) {
+                    return Err(arg.error("Missing or invalid ICMP type."));
+                }
+                // -1 matches any code
+                let icmp_code = parse_num(&args[1].text);
+                if icmp_code > 0xff {
+                    return Err(args[1].error("Invalid ICMP code (use -1 for none)."));
+                }
+                json!({
+                    "type": api_type(keyword),
+                    "icmpType": icmp_type,
+                    "icmpCode": (icmp_code >= 0).then_some(icmp_code),
+                })
+            }
+            "sport" | "dport" | "framesize" => {
+                let (start, end) = parse_range(&arg.text)
+                    .filter(|(start, end)| {
+                        (0..=0xffff).contains(start) && (0..=0xffff).contains(end) && start <= end
+                    })
+                    .ok_or_else(|| arg.error("Invalid numeric range."))?;
+                json!({ "type": api_type(keyword), "start": start, "end": end })
+            }
+            "iptos" => {
+                let mask = parse_num(&arg.text);
+                if !(0..=0xff).contains(&mask) {
+                    return Err(arg.error("Invalid mask."));
+                }
+                let (start, end) = parse_range(&args[1].text)
+                    .filter(|(start, end)| {
+                        (0..=0xff).contains(start) && (0..=0xff).contains(end) && start <= end
+                    })
+                    .ok_or_else(|| args[1].error("Invalid value range."))?;
+                json!({
+                    "type": api_type(keyword),
+                    "mask": mask,
+                    "start": start,
+                    "end": end,
+                })
+            }
+            "chr" => {
+                let mut mask: u64 = 0;
+                for name in arg.text.split(',').filter(|name| !name.is_empty()) {
+                    let bit = lookup(CHARACTERISTIC_BITS, name)
+                        .map(i64::from)
+                        .unwrap_or_else(|| parse_num(name));
+                    if !(0..=63).contains(&bit) {
+                        return Err(
+                            arg.error("Invalid bit index (range 0-63) or unrecognized name.")
+                        );
+                    }
+                    mask |= 1 << bit;
+                }
+                json!({ "type": api_type(keyword), "mask": format!("{:016x}", mask) })
+            }
+            // Tag matches
+            _ => {
+                let value = &args[1];
+                let (id, number) = match self.tag(&arg.text.to_lowercase()) {
+                    Some(tag) => {
+                        let name = value.text.to_lowercase();
+                        let number = tag
+                            .flags
+                            .get(&name)
+                            .or_else(|| tag.enums.get(&name))
+                            .copied()
+                            .unwrap_or_else(|| parse_num(&value.text));
+                        (tag.id, number)
+                    }
+                    None => (parse_num(&arg.text), parse_num(&value.text)),
+                };
+                if !(0..=0xffffffff).contains(&id) {
+                    return Err(arg.error("Undefined tag name and invalid tag value."));
+                }
+                if !(0..=0xffffffff).contains(&number) {
+                    return Err(value.error("Invalid tag value or unrecognized flag/enum name."));
+                }
+                json!({ "type": api_type(keyword), "id": id, "value": number })
+            }
+        };
+
+        Ok(rule)
+    }
+
+    fn render_actions(
+        &self,
+        nodes: &[Node],
+        params: &HashMap<String, String>,
+        depth: usize,
+        rules: &mut Vec<Value>,
+    ) -> CompileResult<()> {
+        let mut index = 0;
+        while index < nodes.len() {
+            let node = &nodes[index];
+            index += 1;
+            // The block of arguments and matches following an action
+            let block = nodes.get(index).and_then(Node::block);
+
+            let action = node.keyword();
+            match action.as_str() {
+                "include" => {
+                    let name = nodes
+                        .get(index)
+                        .and_then(Node::token)
+                        .ok_or_else(|| node.error("Include directive is missing a macro name."))?;
+                    index += 1;
+
+                    let (macro_name, args) = split_call(&name.text);
+                    let Some(definition) = self.macros.get(&macro_name) else {
+                        return Err(name.error("Macro name not found."));
+                    };
+                    if depth >= MAX_INCLUDE_DEPTH {
+                        return Err(name.error("Macro includes are nested too deeply."));
+                    }
+
+                    let mut macro_params = HashMap::new();
+                    for (param, position) in &definition.params {
+                        let value = args.get(*position).ok_or_else(|| {
+                            name.error("Missing one or more required macro parameter.")
+                        })?;
+                        macro_params.insert(param.clone(), value.clone());
+                    }
+
+                    self.render_actions(&definition.rules, &macro_params, depth + 1, rules)?;
+                }
+                "drop" | "accept" | "break" => {
+                    if let Some(block) = block {
+                        index += 1;
+                        self.render_matches(block, params, rules)?;
+                    }
+                    rules.push(json!({ "type": api_type(&action) }));
+                }
+                "tee" | "watch" => {
+                    let Some(block) = block.filter(|block| block.len() >= 2) else {
+                        return Err(node.error(
+                            "The tee and watch actions require two parameters (max length or 0 for all, target).",
+                        ));
+                    };
+                    index += 1;
+
+                    let length = block[0].token().map_or(i64::MIN, |t| parse_num(&t.text));
+                    if !(-1..=0xffff).contains(&length) {
+                        return Err(block[0].error(
+                            "Tee/watch max packet length to forward invalid or out of range.",
+                        ));
+                    }
+                    let target = block[1]
+                        .token()
+                        .filter(|target| is_zerotier_address(&target.text))
+                        .ok_or_else(|| {
+                            block[1]
+                                .error("Missing or invalid ZeroTier address target for tee/watch.")
+                        })?;
+
+                    self.render_matches(&block[2..], params, rules)?;
+                    rules.push(json!({
+                        "type": api_type(&action),
+                        "address": target.text,
+                        "length": length,
+                    }));
+                }
+                "redirect" => {
+                    let Some(block) = block else {
+                        return Err(node.error("The redirect action requires a target parameter."));
+                    };
+                    index += 1;
+
+                    let target = block[0]
+                        .token()
+                        .filter(|target| is_zerotier_address(&target.text))
+                        .ok_or_else(|| {
+                            block[0]
+                                .error("Missing or invalid ZeroTier address target for redirect.")
+                        })?;
+
+                    self.render_matches(&block[1..], params, rules)?;
+                    rules.push(json!({ "type": api_type(&action), "address": target.text }));
+                }
+                _ => return Err(node.error("Unrecognized action or directive in rule set.")),
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Compile rules language source into the rules, capabilities and tags of a network
+pub fn compile(source: &str) -> CompileResult<CompiledRules> {
+    let parsed = parse(source);
+
+    // Pass 2: collect macros, tags and capabilities, leaving the base rules
+    let mut definitions = Definitions::default();
+    let mut base_rules = Vec::new();
+    let mut nodes = parsed.iter();
+    while let Some(node) = nodes.next() {
+        let keyword = node.keyword();
+        match keyword.as_str() {
+            "macro" => {
+                let (name, body) = definition(node, nodes.next(), "Macro")?;
+                parse_macro(name, body, &mut definitions)?;
+            }
+            "tag" => {
+                let (name, body) = definition(node, nodes.next(), "Tag")?;
+                parse_tag(name, body, &mut definitions)?;
+            }
+            "cap" => {
+                let (name, body) = definition(node, nodes.next(), "Capability")?;
+                parse_capability(name, body, &mut definitions)?;
+            }
+            _ => base_rules.push(node.clone()),
+        }
+    }
+
+    // Pass 3: render the rules of capabilities and the network
+    let no_params = HashMap::new();
+    let mut compiled = CompiledRules::default();
+
+    for (_, capability) in &definitions.capabilities {
+        let mut rules = Vec::new();
+        definitions.render_actions(&capability.rules, &no_params, 0, &mut rules)?;
+        compiled.capabilities.push(json!({
+            "id": capability.id,
+            "default": capability.default,
+            "rules": rules,
+        }));
+    }
+    for (_, tag) in &definitions.tags {
+        compiled
+            .tags
+            .push(json!({ "id": tag.id, "default": tag.default }));
+    }
+    definitions.render_actions(&base_rules, &no_params, 0, &mut compiled.rules)?;
+
+    Ok(compiled)
+}
+
+#[cfg(test)]
+mod tests {
+    // Expected output comes from the frontend's RulesCompile.ts for the same source
+    use super::*;
+
+    fn compiled(source: &str) -> Value {
+        serde_json::to_value(compile(source).unwrap()).unwrap()
+    }
+
+    fn error(source: &str) -> (usize, usize, String) {
+        let error = compile(source).unwrap_err();
+        (error.line, error.column, error.message)
+    }
+
+    #[test]
+    fn compiles_actions() {
+        let source = "\
+drop ethertype arp;
+tee -1 deadbeef01 ethertype ipv4;
+watch 128 DEADBEEF02 ethertype ipv6;
+redirect 0123456789 ipprotocol icmpv4;
+break ipprotocol udp;
+accept;
+";
+        assert_eq!(
+            compiled(source),
+            json!({
+                "rules": [
+                    {"type":"MATCH_ETHERTYPE","not":false,"or":false,"etherType":2054},
+                    {"type":"ACTION_DROP"},
+                    {"type":"MATCH_ETHERTYPE","not":false,"or":false,"etherType":2048},
+                    {"type":"ACTION_TEE","address":"deadbeef01","length":-1},
+                    {"type":"MATCH_ETHERTYPE","not":false,"or":false,"etherType":34525},
+                    {"type":"ACTION_WATCH","address":"DEADBEEF02","length":128},
+                    {"type":"MATCH_IP_PROTOCOL","not":false,"or":false,"ipProtocol":1},
+                    {"type":"ACTION_REDIRECT","address":"0123456789"},
+                    {"type":"MATCH_IP_PROTOCOL","not":false,"or":false,"ipProtocol":17},
+                    {"type":"ACTION_BREAK"},
+                    {"type":"ACTION_ACCEPT"}
+                ],
+                "capabilities": [],
+                "tags": []
+            })
+        );
+    }
+
+    #[test]
+    fn compiles_every_match_type() {
+        let source = "\
+accept
+  ztsrc 1122334455 and ztdest 0123456789
+  vlan 5 vlanpcp 3 vlandei 1
+  ethertype ipv4 ethertype 0x86dd
+  macsrc 00:11:22:33:44:55 macdest 0a-BB-cc-dd-ee-ff
+  ipsrc 10.1.0.0/16 ipdest fd00::1/128
+  iptos 0xfc 10-20 ipprotocol tcp ipprotocol 47
+  icmp 8 -1 icmp 0 3
+  sport 80 dport 1000-2000
+  chr tcp_syn,inbound framesize 64-1500 random 0.5
+;
+";
+        assert_eq!(
+            compiled(source)["rules"],
+            json!([
+                {"type":"MATCH_SOURCE_ZEROTIER_ADDRESS","not":false,"or":false,"zt":"1122334455"},
+                {"type":"MATCH_DEST_ZEROTIER_ADDRESS","not":false,"or":false,"zt":"0123456789"},
+                {"type":"MATCH_VLAN_ID","not":false,"or":false,"vlanId":5},
+                {"type":"MATCH_VLAN_PCP","not":false,"or":false,"vlanPcp":3},
+                {"type":"MATCH_VLAN_DEI","not":false,"or":false,"vlanDei":1},
+                {"type":"MATCH_ETHERTYPE","not":false,"or":false,"etherType":2048},
+                {"type":"MATCH_ETHERTYPE","not":false,"or":false,"etherType":34525},
+                // RulesCompile.ts misplaces the colons of MAC addresses
+                {"type":"MATCH_MAC_SOURCE","not":false,"or":false,"mac":"00:11:22:33:44:55"},
+                {"type":"MATCH_MAC_DEST","not":false,"or":false,"mac":"0a:bb:cc:dd:ee:ff"},
+                {"type":"MATCH_IPV4_SOURCE","not":false,"or":false,"ip":"10.1.0.0/16"},
+                {"type":"MATCH_IPV6_DEST","not":false,"or":false,"ip":"fd00::1/128"},
+                {"type":"MATCH_IP_TOS","not":false,"or":false,"mask":252,"start":10,"end":20},
+                {"type":"MATCH_IP_PROTOCOL","not":false,"or":false,"ipProtocol":6},
+                {"type":"MATCH_IP_PROTOCOL","not":false,"or":false,"ipProtocol":47},
+                {"type":"MATCH_ICMP","not":false,"or":false,"icmpType":8,"icmpCode":null},
+                {"type":"MATCH_ICMP","not":false,"or":false,"icmpType":0,"icmpCode":3},
+                {"type":"MATCH_IP_SOURCE_PORT_RANGE","not":false,"or":false,"start":80,"end":80},
+                {"type":"MATCH_IP_DEST_PORT_RANGE","not":false,"or":false,"start":1000,"end":2000},
+                {"type":"MATCH_CHARACTERISTICS","not":false,"or":false,"mask":"8000000000000002"},
+                {"type":"MATCH_FRAME_SIZE_RANGE","not":false,"or":false,"start":64,"end":1500},
+                {"type":"MATCH_RANDOM","not":false,"or":false,"probability":2147483647},
+                {"type":"ACTION_ACCEPT"}
+            ])
+        );
+    }
+
+    #[test]
+    fn chains_not_and_or() {
+        let source = "\
+drop not ethertype ipv4 and not ethertype arp or ipprotocol udp;
+accept or dport 22 not or sport 22;
+";
+        assert_eq!(
+            compiled(source)["rules"],
+            json!([
+                {"type":"MATCH_ETHERTYPE","not":true,"or":false,"etherType":2048},
+                {"type":"MATCH_ETHERTYPE","not":true,"or":false,"etherType":2054},
+                {"type":"MATCH_IP_PROTOCOL","not":false,"or":true,"ipProtocol":17},
+                {"type":"ACTION_DROP"},
+                {"type":"MATCH_IP_DEST_PORT_RANGE","not":false,"or":true,"start":22,"end":22},
+                {"type":"MATCH_IP_SOURCE_PORT_RANGE","not":true,"or":true,"start":22,"end":22},
+                {"type":"ACTION_ACCEPT"}
+            ])
+        );
+    }
+
+    #[test]
+    fn compiles_tags_and_capabilities() {
+        let source = "\
+# Departments
+tag department
+  id 1000
+  enum 100 engineering
+  enum 200 sales
+  flag 0 remote
+  flag 4 contractor
+  default engineering
+;
+tag level id 5 default 3;
+tag plain id 7;
+cap superuser
+  id 2000
+  default
+  accept;
+cap web
+  id 10
+  accept dport 80;
+  drop;
+;
+accept teq department sales and tand department remote and tor level 1 txor level 2 \
+tdiff level 3 tseq department engineering treq department contractor;
+drop;
+";
+        assert_eq!(
+            compiled(source),
+            json!({
+                "rules": [
+                    {"type":"MATCH_TAGS_EQUAL","not":false,"or":false,"id":1000,"value":200},
+                    {"type":"MATCH_TAGS_BITWISE_AND","not":false,"or":false,"id":1000,"value":1},
+                    {"type":"MATCH_TAGS_BITWISE_OR","not":false,"or":false,"id":5,"value":1},
+                    {"type":"MATCH_TAGS_BITWISE_XOR","not":false,"or":false,"id":5,"value":2},
+                    {"type":"MATCH_TAGS_DIFFERENCE","not":false,"or":false,"id":5,"value":3},
+                    {"type":"MATCH_TAG_SENDER","not":false,"or":false,"id":1000,"value":100},
+                    {"type":"MATCH_TAG_RECEIVER","not":false,"or":false,"id":1000,"value":16},
+                    {"type":"ACTION_ACCEPT"},
+                    {"type":"ACTION_DROP"}
+                ],
+                "capabilities": [
+                    {"id":2000,"default":true,"rules":[{"type":"ACTION_ACCEPT"}]},
+                    {"id":10,"default":false,"rules":[
+                        {"type":"MATCH_IP_DEST_PORT_RANGE","not":false,"or":false,"start":80,"end":80},
+                        {"type":"ACTION_ACCEPT"},
+                        {"type":"ACTION_DROP"}
+                    ]}
+                ],
+                "tags": [
+                    {"id":1000,"default":100},
+                    {"id":5,"default":3},
+                    {"id":7,"default":null}
+                ]
+            })
+        );
+    }
+
+    #[test]
+    fn expands_macros_and_includes() {
+        let source = "\
+macro allow($proto,$port)
+  accept ipprotocol $proto and dport $port;
+;
+macro base
+  include allow(tcp,22)
+  include allow(udp,53)
+;
+include base
+include allow(tcp,443-444)
+drop;
+";
+        assert_eq!(
+            compiled(source)["rules"],
+            json!([
+                {"type":"MATCH_IP_PROTOCOL","not":false,"or":false,"ipProtocol":6},
+                {"type":"MATCH_IP_DEST_PORT_RANGE","not":false,"or":false,"start":22,"end":22},
+                {"type":"ACTION_ACCEPT"},
+                {"type":"MATCH_IP_PROTOCOL","not":false,"or":false,"ipProtocol":17},
+                {"type":"MATCH_IP_DEST_PORT_RANGE","not":false,"or":false,"start":53,"end":53},
+                {"type":"ACTION_ACCEPT"},
+                {"type":"MATCH_IP_PROTOCOL","not":false,"or":false,"ipProtocol":6},
+                {"type":"MATCH_IP_DEST_PORT_RANGE","not":false,"or":false,"start":443,"end":444},
+                {"type":"ACTION_ACCEPT"},
+                {"type":"ACTION_DROP"}
+            ])
+        );
+
+        // RulesCompile.ts overflows its stack here
+        assert_eq!(
+            error("macro loop include loop ; include loop").2,
+            "Macro includes are nested too deeply."
+        );
+    }
+
+    #[test]
+    fn reports_errors_at_their_line_and_column() {
+        // RulesCompile.ts counts the columns of the first line from 0, later lines from 1
+        let cases: &[(&str, (usize, usize, &str))] = &[
+            ("accept foo 1;", (1, 8, "Unrecognized match type \"foo\".")),
+            (
+                "drop ipsrc 10.0.0.1;",
+                (1, 12, "Missing /bits netmask length designation in IP."),
+            ),
+            (
+                "tag x\n  enum 1 a\n;",
+                (1, 5, "Tag definition is missing a numeric ID."),
+            ),
+            ("cap 1bad id 1 accept;", (1, 5, "Invalid capability name.")),
+            (
+                "tee 5 nothex;",
+                (
+                    1,
+                    7,
+                    "Missing or invalid ZeroTier address target for tee/watch.",
+                ),
+            ),
+            ("\n   include missing", (2, 12, "Macro name not found.")),
+            ("accept dport 70000;", (1, 14, "Invalid numeric range.")),
+            ("accept ztsrc $x;", (1, 14, "Undefined variable name.")),
+            (
+                "macro m($a) accept dport $a; ;\ninclude m",
+                (2, 9, "Missing one or more required macro parameter."),
+            ),
+            ("accept dport;", (1, 8, "Missing argument(s) to match.")),
+            ("accept ztsrc 12345;", (1, 14, "Invalid ZeroTier address.")),
+            (
+                "accept teq nope 1;",
+                (1, 12, "Undefined tag name and invalid tag value."),
+            ),
+            (
+                "tag t id 1 enum 1 a;\naccept teq t b;",
+                (2, 14, "Invalid tag value or unrecognized flag/enum name."),
+            ),
+            (
+                "tag t id 1;\ntag t id 2;",
+                (2, 5, "Multiple definition of tag name."),
+            ),
+            (
+                "cap c id 1 accept;\ncap d id 1 accept;",
+                (2, 7, "Duplicate capability ID."),
+            ),
+            (
+                "accept chr bogus;",
+                (
+                    1,
+                    12,
+                    "Invalid bit index (range 0-63) or unrecognized name.",
+                ),
+            ),
+            ("macro\n", (1, 1, "Macro definition is missing name.")),
+            ("tag drop id 1;", (1, 5, "Tag name is a reserved word.")),
+            (
+                "nonsense;",
+                (1, 1, "Unrecognized action or directive in rule set."),
+            ),
+            (
+                "redirect;",
+                (1, 1, "The redirect action requires a target parameter."),
+            ),
+            (
+                "accept iptos 0xfc;",
+                (1, 14, "Missing argument(s) to match."),
+            ),
+            ("accept framesize 10-5;", (1, 18, "Invalid numeric range.")),
+        ];
+
+        for (source, (line, column, message)) in cases {
+            assert_eq!(
+                error(source),
+                (*line, *column, message.to_string()),
+                "{:?}",
+                source
+            );
+        }
+        assert_eq!(
+            compile("accept foo;").unwrap_err().to_string(),
+            "line 1, column 8: Unrecognized match type \"foo\"."
+        );
+    }
+}
@@ -0,0 +1,5 @@
+pub mod compiler;
+pub mod simulator;
+
+pub use compiler::*;
+pub use simulator::*;
@@ -0,0 +1,1140 @@
+//! Evaluates a packet against compiled rules the way ZeroTier's `Network::_doZtFilter` does
+
+use crate::models::SimulatedPacket;
+use crate::utils::zerotier_mac;
+use ipnet::IpNet;
+use serde::Serialize;
+use serde_json::{Map, Value};
+use std::collections::{BTreeMap, HashMap};
+use std::net::IpAddr;
+
+const ETHERTYPE_IPV4: u16 = 0x0800;
+const ETHERTYPE_IPV6: u16 = 0x86dd;
+
+/// Bits of `MATCH_CHARACTERISTICS` that are not TCP flags
+const CHARACTERISTIC_INBOUND: u64 = 1 << 63;
+const CHARACTERISTIC_MULTICAST: u64 = 1 << 62;
+const CHARACTERISTIC_BROADCAST: u64 = 1 << 61;
+const CHARACTERISTIC_IP_AUTHENTICATED: u64 = 1 << 60;
+
+/// Parse a ZeroTier address or MAC address, ignoring separators
+fn parse_hex(value: Option<&Value>) -> Option<u64> {
+    let text: String = value?
+        .as_str()?
+        .chars()
+        .filter(|c| c.is_ascii_hexdigit())
+        .collect();
+    u64::from_str_radix(&text, 16).ok()
+}
+
+fn number(rule: &Value, field: &str) -> Option<u64> {
+    rule.get(field).and_then(Value::as_u64)
+}
+
+fn flag(rule: &Value, field: &str) -> bool {
+    rule.get(field).and_then(Value::as_bool).unwrap_or(false)
+}
+
+fn in_range(rule: &Value, value: u64) -> bool {
+    matches!(
+        (number(rule, "start"), number(rule, "end")),
+        (Some(start), Some(end)) if (start..=end).contains(&value)
+    )
+}
+
+/// A network member as seen by the rules engine
+#[derive(Debug, Clone, Default)]
+pub struct Endpoint {
+    pub address: u64,
+    pub mac: u64,
+    pub ips: Vec<IpAddr>,
+    pub tags: BTreeMap<u32, u32>,
+    pub capabilities: Vec<u32>,
+}
+
+impl Endpoint {
+    /// Read a member's addresses, tags and capabilities, applying the network's tag defaults
+    pub fn from_member(
+        network_id: u64,
+        member: &Map<String, Value>,
+        tag_defaults: &BTreeMap<u32, u32>,
+    ) -> Option<Self> {
+        let address = parse_hex(member.get("address").or_else(|| member.get("id")))?;
+        let ips = member
+            .get("ipAssignments")
+            .and_then(Value::as_array)
+            .map(|ips| {
+                ips.iter()
+                    .filter_map(|ip| ip.as_str()?.parse().ok())
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        let mut tags = tag_defaults.clone();
+        let assigned = member.get("tags").and_then(Value::as_array);
+        for tag in assigned.into_iter().flatten() {
+            if let Some([id, value]) = tag.as_array().map(Vec::as_slice) {
+                if let (Some(id), Some(value)) = (id.as_u64(), value.as_u64()) {
+                    tags.insert(id as u32, value as u32);
+                }
+            }
+        }
+
+        let capabilities = member
+            .get("capabilities")
+            .and_then(Value::as_array)
+            .map(|ids| {
+                ids.iter()
+                    .filter_map(|id| Some(id.as_u64()? as u32))
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        Some(Self {
+            address,
+            mac: zerotier_mac(network_id, address),
+            ips,
+            tags,
+            capabilities,
+        })
+    }
+}
+
+/// Default values of a network's tags by tag ID
+pub fn tag_defaults(tags: &[Value]) -> BTreeMap<u32, u32> {
+    tags.iter()
+        .filter_map(|tag| {
+            let id = number(tag, "id")?;
+            let default = number(tag, "default")?;
+            Some((id as u32, default as u32))
+        })
+        .collect()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Direction {
+    /// Evaluated by the sender before the packet leaves
+    Outbound,
+    /// Evaluated by the receiver when the packet arrives
+    Inbound,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FilterResult {
+    /// The rule set ended or hit `break` without a decision
+    NoMatch,
+    Drop,
+    Accept,
+    /// Accepted as the target of a `tee`, `watch` or `redirect`
+    SuperAccept,
+    Redirect,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StepResult {
+    Matched,
+    NotMatched,
+    /// An AND match skipped because its set can no longer match
+    Skipped,
+    /// An action whose matches held
+    Taken,
+    NotTaken,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TraceStep {
+    pub index: usize,
+    pub rule: Value,
+    pub result: StepResult,
+    /// Whether the current set of matches still holds after this rule
+    pub set_matches: bool,
+}
+
+/// Evaluation of one rule set by one node
+#[derive(Debug, Clone, Serialize)]
+pub struct RuleSetTrace {
+    pub direction: Direction,
+    pub node: String,
+    /// Unset for the network's base rules
+    pub capability: Option<u32>,
+    pub result: FilterResult,
+    pub steps: Vec<TraceStep>,
+}
+
+/// A copy of the packet sent by `tee` or `watch`
+#[derive(Debug, Clone, Serialize)]
+pub struct FlowCopy {
+    pub direction: Direction,
+    pub address: String,
+    pub length: i64,
+    pub watch: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Verdict {
+    Accept,
+    Drop,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FlowSimulation {
+    pub verdict: Verdict,
+    /// The node that dropped the packet
+    pub dropped_by: Option<Direction>,
+    /// Capability that let the packet through when the base rules did not decide
+    pub capability: Option<u32>,
+    pub redirected_to: Option<String>,
+    pub copies: Vec<FlowCopy>,
+    pub trace: Vec<RuleSetTrace>,
+}
+
+/// Compiled rules and capabilities of a network
+pub struct RuleSets<'a> {
+    pub rules: &'a [Value],
+    pub capabilities: &'a [Value],
+}
+
+impl RuleSets<'_> {
+    /// Rules of the given capabilities, ordered by ID like the engine's credentials
+    fn capabilities(&self, ids: &[u32]) -> Vec<(u32, &[Value])> {
+        let mut capabilities: Vec<(u32, &[Value])> = self
+            .capabilities
+            .iter()
+            .filter_map(|capability| {
+                let id = number(capability, "id")? as u32;
+                let rules = capability.get("rules")?.as_array()?;
+                ids.contains(&id).then_some((id, rules.as_slice()))
+            })
+            .collect();
+        capabilities.sort_by_key(|(id, _)| *id);
+        capabilities
+    }
+}
+
+/// The packet's addresses and headers, with defaults filled in
+struct Packet {
+    ether_type: u16,
+    ip_protocol: Option<u8>,
+    source_ip: Option<IpAddr>,
+    destination_ip: Option<IpAddr>,
+    source_port: Option<u16>,
+    destination_port: Option<u16>,
+    icmp_type: Option<u8>,
+    icmp_code: Option<u8>,
+    ip_tos: u8,
+    tcp_flags: u16,
+    vlan_id: u16,
+    frame_size: u64,
+    random: u32,
+    /// Whether the sender owns the source IP, as certified by the controller
+    ip_authenticated: bool,
+}
+
+impl Packet {
+    fn new(packet: &SimulatedPacket, source: &Endpoint, destination: &Endpoint) -> Self {
+        let is_ip = matches!(packet.ether_type, ETHERTYPE_IPV4 | ETHERTYPE_IPV6);
+        let family_ip = |endpoint: &Endpoint| {
+            endpoint
+                .ips
+                .iter()
+                .copied()
+                .find(|ip| ip.is_ipv4() == (packet.ether_type == ETHERTYPE_IPV4))
+        };
+        let source_ip = packet
+            .source_ip
+            .or_else(|| family_ip(source))
+            .filter(|_| is_ip);
+        let destination_ip = packet
+            .destination_ip
+            .or_else(|| family_ip(destination))
+            .filter(|_| is_ip);
+
+        let frame_size = packet.frame_size.map(u64::from).unwrap_or_else(|| {
+            let ip_header = match packet.ether_type {
+                ETHERTYPE_IPV4 => 20,
+                ETHERTYPE_IPV6 => 40,
+                _ => return 0,
+            };
+            let transport_header = match packet.ip_protocol {
+                Some(0x06) => 20,
+                Some(0x84) => 12,
+                Some(0x01 | 0x11 | 0x3a | 0x88) => 8,
+                _ => 0,
+            };
+            ip_header + transport_header
+        });
+
+        Self {
+            ether_type: packet.ether_type,
+            ip_protocol: packet.ip_protocol.filter(|_| is_ip),
+            source_ip,
+            destination_ip,
+            source_port: packet.source_port,
+            destination_port: packet.destination_port,
+            icmp_type: packet.icmp_type,
+            icmp_code: packet.icmp_code,
+            ip_tos: packet.ip_tos,
+            tcp_flags: packet.tcp_flags,
+            vlan_id: packet.vlan_id,
+            frame_size,
+            random: packet.random,
+            ip_authenticated: source_ip.is_some_and(|ip| source.ips.contains(&ip)),
+        }
+    }
+
+    /// Whether the frame carries a complete IP header of the given family
+    fn is_ip(&self, ether_type: u16) -> bool {
+        let header = if ether_type == ETHERTYPE_IPV4 { 20 } else { 40 };
+        self.ether_type == ether_type && self.frame_size >= header
+    }
+
+    fn has_ports(&self) -> bool {
+        matches!(self.ip_protocol, Some(0x06 | 0x11 | 0x84 | 0x88))
+    }
+}
+
+/// One node evaluating the packet
+struct Filter<'a> {
+    direction: Direction,
+    packet: &'a Packet,
+    source: &'a Endpoint,
+    destination: &'a Endpoint,
+    /// The node doing the evaluation
+    node: u64,
+    /// The node's own tags and those of the other side
+    local_tags: &'a BTreeMap<u32, u32>,
+    remote_tags: &'a BTreeMap<u32, u32>,
+}
+
+/// Outcome of evaluating one rule set
+struct FilterOutcome {
+    result: FilterResult,
+    redirect: Option<u64>,
+    /// A rule set keeps one copy target, a later `tee` or `watch` replaces it
+    copy: Option<FlowCopy>,
+    steps: Vec<TraceStep>,
+}
+
+impl Filter<'_> {
+    fn inbound(&self) -> bool {
+        self.direction == Direction::Inbound
+    }
+
+    fn evaluate(&self, rules: &[Value]) -> FilterOutcome {
+        let mut outcome = FilterOutcome {
+            result: FilterResult::NoMatch,
+            redirect: None,
+            copy: None,
+            steps: Vec::new(),
+        };
+        // Set when this node is the target of a tee, watch or redirect
+        let mut super_accept = false;
+        // An action without matches before it is always taken
+        let mut set_matches = true;
+
+        for (index, rule) in rules.iter().enumerate() {
+            let rule_type = rule.get("type").and_then(Value::as_str).unwrap_or("");
+            let mut step = |result, set_matches| {
+                outcome.steps.push(TraceStep {
+                    index,
+                    rule: rule.clone(),
+                    result,
+                    set_matches,
+                })
+            };
+
+            if rule_type.starts_with("ACTION_") {
+                let forward = parse_hex(rule.get("address"));
+                if !set_matches {
+                    // Receivers that are a forwarding target accept if they accept at all
+                    if self.inbound()
+                        && matches!(rule_type, "ACTION_TEE" | "ACTION_WATCH" | "ACTION_REDIRECT")
+                        && forward == Some(self.node)
+                    {
+                        super_accept = true;
+                    }
+                    step(StepResult::NotTaken, false);
+                    set_matches = true;
+                    continue;
+                }
+                step(StepResult::Taken, true);
+
+                match rule_type {
+                    "ACTION_DROP" => {
+                        outcome.result = FilterResult::Drop;
+                        return outcome;
+                    }
+                    "ACTION_ACCEPT" => {
+                        outcome.result = if super_accept {
+                            FilterResult::SuperAccept
+                        } else {
+                            FilterResult::Accept
+                        };
+                        return outcome;
+                    }
+                    "ACTION_TEE" | "ACTION_WATCH" | "ACTION_REDIRECT" => {
+                        let Some(forward) = forward else {
+                            continue;
+                        };
+                        // Forwarding to the sender or receiver is a no-op
+                        if forward == self.source.address {
+                        } else if forward == self.node {
+                            if self.inbound() {
+                                outcome.result = FilterResult::SuperAccept;
+                                return outcome;
+                            }
+                        } else if forward == self.destination.address {
+                        } else if rule_type == "ACTION_REDIRECT" {
+                            outcome.result = FilterResult::Redirect;
+                            outcome.redirect = Some(forward);
+                            return outcome;
+                        } else {
+                            outcome.copy = Some(FlowCopy {
+                                direction: self.direction,
+                                address: format!("{:010x}", forward),
+                                length: rule.get("length").and_then(Value::as_i64).unwrap_or(0),
+                                watch: rule_type == "ACTION_WATCH",
+                            });
+                        }
+                    }
+                    "ACTION_BREAK" => return outcome,
+                    // Unrecognized actions are no-ops
+                    _ => {}
+                }
+                continue;
+            }
+
+            let or = flag(rule, "or");
+            if !set_matches && !or {
+                step(StepResult::Skipped, false);
+                continue;
+            }
+
+            let matched = self.matches(rule_type, rule, super_accept);
+            if or {
+                set_matches |= matched != flag(rule, "not");
+            } else {
+                set_matches &= matched != flag(rule, "not");
+            }
+            let result = if matched {
+                StepResult::Matched
+            } else {
+                StepResult::NotMatched
+            };
+            step(result, set_matches);
+        }
+
+        outcome
+    }
+
+    fn matches(&self, rule_type: &str, rule: &Value, super_accept: bool) -> bool {
+        let packet = self.packet;
+        let ip_in = |ip: Option<IpAddr>, ether_type| {
+            let network: Option<IpNet> = rule
+                .get("ip")
+                .and_then(Value::as_str)
+                .and_then(|ip| ip.parse().ok());
+            packet.is_ip(ether_type)
+                && matches!((network, ip), (Some(network), Some(ip)) if network.contains(&ip))
+        };
+        let is_ip = packet.is_ip(ETHERTYPE_IPV4) || packet.is_ip(ETHERTYPE_IPV6);
+        let tag_id = number(rule, "id").map(|id| id as u32);
+        let tag_value = number(rule, "value");
+
+        match rule_type {
+            "MATCH_SOURCE_ZEROTIER_ADDRESS" => {
+                parse_hex(rule.get("zt")) == Some(self.source.address)
+            }
+            "MATCH_DEST_ZEROTIER_ADDRESS" => {
+                parse_hex(rule.get("zt")) == Some(self.destination.address)
+            }
+            "MATCH_VLAN_ID" => number(rule, "vlanId") == Some(u64::from(packet.vlan_id)),
+            // Not supported by the engine, which compares against 0
+            "MATCH_VLAN_PCP" => number(rule, "vlanPcp") == Some(0),
+            "MATCH_VLAN_DEI" => number(rule, "vlanDei") == Some(0),
+            "MATCH_MAC_SOURCE" => parse_hex(rule.get("mac")) == Some(self.source.mac),
+            "MATCH_MAC_DEST" => parse_hex(rule.get("mac")) == Some(self.destination.mac),
+            "MATCH_IPV4_SOURCE" => ip_in(packet.source_ip, ETHERTYPE_IPV4),
+            "MATCH_IPV4_DEST" => ip_in(packet.destination_ip, ETHERTYPE_IPV4),
+            "MATCH_IPV6_SOURCE" => ip_in(packet.source_ip, ETHERTYPE_IPV6),
+            "MATCH_IPV6_DEST" => ip_in(packet.destination_ip, ETHERTYPE_IPV6),
+            "MATCH_IP_TOS" => {
+                is_ip
+                    && in_range(
+                        rule,
+                        u64::from(packet.ip_tos) & number(rule, "mask").unwrap_or(0),
+                    )
+            }
+            "MATCH_IP_PROTOCOL" => {
+                is_ip && packet.ip_protocol.map(u64::from) == number(rule, "ipProtocol")
+            }
+            "MATCH_ETHERTYPE" => number(rule, "etherType") == Some(u64::from(packet.ether_type)),
+            "MATCH_ICMP" => {
+                let icmp = if packet.is_ip(ETHERTYPE_IPV4) {
+                    0x01
+                } else {
+                    0x3a
+                };
+                is_ip
+                    && packet.ip_protocol == Some(icmp)
+                    && packet.icmp_type.map(u64::from) == number(rule, "icmpType")
+                    && number(rule, "icmpCode")
+                        .is_none_or(|code| packet.icmp_code.map(u64::from) == Some(code))
+            }
+            "MATCH_IP_SOURCE_PORT_RANGE" => {
+                is_ip
+                    && packet.has_ports()
+                    && packet
+                        .source_port
+                        .is_some_and(|port| in_range(rule, u64::from(port)))
+            }
+            "MATCH_IP_DEST_PORT_RANGE" => {
+                is_ip
+                    && packet.has_ports()
+                    && packet
+                        .destination_port
+                        .is_some_and(|port| in_range(rule, u64::from(port)))
+            }
+            "MATCH_CHARACTERISTICS" => {
+                let mut characteristics = 0;
+                if self.inbound() {
+                    characteristics |= CHARACTERISTIC_INBOUND;
+                }
+                if self.destination.mac & (1 << 40) != 0 {
+                    characteristics |= CHARACTERISTIC_MULTICAST;
+                }
+                if self.destination.mac == 0xffff_ffff_ffff {
+                    characteristics |= CHARACTERISTIC_BROADCAST;
+                }
+                // The controller only certifies IP ownership, never MAC ownership
+                if packet.ip_authenticated {
+                    characteristics |= CHARACTERISTIC_IP_AUTHENTICATED;
+                }
+                if is_ip && packet.ip_protocol == Some(0x06) {
+                    characteristics |= u64::from(packet.tcp_flags & 0x0fff);
+                }
+                parse_hex(rule.get("mask")).is_some_and(|mask| characteristics & mask != 0)
+            }
+            "MATCH_FRAME_SIZE_RANGE" => in_range(rule, packet.frame_size),
+            "MATCH_RANDOM" => {
+                number(rule, "probability").is_some_and(|p| u64::from(packet.random) <= p)
+            }
+            "MATCH_TAGS_DIFFERENCE"
+            | "MATCH_TAGS_BITWISE_AND"
+            | "MATCH_TAGS_BITWISE_OR"
+            | "MATCH_TAGS_BITWISE_XOR"
+            | "MATCH_TAGS_EQUAL" => {
+                let (Some(id), Some(value)) = (tag_id, tag_value) else {
+                    return false;
+                };
+                let Some(&local) = self.local_tags.get(&id) else {
+                    return false;
+                };
+                let Some(&remote) = self.remote_tags.get(&id) else {
+                    // Senders can't know the receiver's tag, so leave the decision to it,
+                    // and forwarding targets likely don't know the sender's either
+                    return !self.inbound() || super_accept;
+                };
+                let (local, remote) = (u64::from(local), u64::from(remote));
+                match rule_type {
+                    "MATCH_TAGS_DIFFERENCE" => local.abs_diff(remote) <= value,
+                    "MATCH_TAGS_BITWISE_AND" => local & remote == value,
+                    "MATCH_TAGS_BITWISE_OR" => local | remote == value,
+                    "MATCH_TAGS_BITWISE_XOR" => local ^ remote == value,
+                    _ => local == value && remote == value,
+                }
+            }
+            "MATCH_TAG_SENDER" | "MATCH_TAG_RECEIVER" => {
+                let (Some(id), Some(value)) = (tag_id, tag_value) else {
+                    return false;
+                };
+                let sender = rule_type == "MATCH_TAG_SENDER";
+                if super_accept {
+                    true
+                } else if sender == self.inbound() {
+                    // The tag belongs to the other side
+                    match self.remote_tags.get(&id) {
+                        Some(&remote) => u64::from(remote) == value,
+                        None => !sender,
+                    }
+                } else {
+                    self.local_tags
+                        .get(&id)
+                        .is_some_and(|&local| u64::from(local) == value)
+                }
+            }
+            _ => false,
+        }
+    }
+}
+
+/// Result of one node's base rules and, if they don't decide, the sender's capabilities
+struct NodeOutcome {
+    result: FilterResult,
+    capability: Option<u32>,
+    redirect: Option<u64>,
+    copies: Vec<FlowCopy>,
+}
+
+fn run_node(
+    filter: &Filter,
+    rule_sets: &RuleSets,
+    capabilities: &[u32],
+    trace: &mut Vec<RuleSetTrace>,
+) -> NodeOutcome {
+    let node = format!("{:010x}", filter.node);
+    let mut record = |capability, outcome: &mut FilterOutcome| {
+        trace.push(RuleSetTrace {
+            direction: filter.direction,
+            node: node.clone(),
+            capability,
+            result: outcome.result,
+            steps: std::mem::take(&mut outcome.steps),
+        })
+    };
+
+    let mut outcome = filter.evaluate(rule_sets.rules);
+    record(None, &mut outcome);
+    if outcome.result != FilterResult::NoMatch {
+        return NodeOutcome {
+            result: outcome.result,
+            capability: None,
+            redirect: outcome.redirect,
+            copies: outcome.copy.into_iter().collect(),
+        };
+    }
+
+    // The base rules' copy is sent if a capability accepts, along with that
+    // capability's own copy
+    let mut copies: Vec<FlowCopy> = outcome.copy.into_iter().collect();
+    for (id, rules) in rule_sets.capabilities(capabilities) {
+        let mut outcome = filter.evaluate(rules);
+        record(Some(id), &mut outcome);
+        // A drop in a capability only ends that capability
+        if matches!(outcome.result, FilterResult::NoMatch | FilterResult::Drop) {
+            continue;
+        }
+        copies.extend(outcome.copy);
+        return NodeOutcome {
+            result: outcome.result,
+            capability: Some(id),
+            redirect: outcome.redirect,
+            copies,
+        };
+    }
+
+    NodeOutcome {
+        result: FilterResult::NoMatch,
+        capability: None,
+        redirect: None,
+        copies,
+    }
+}
+
+/// Send a packet from `source` to `destination`, evaluating the sender's outbound
+/// and the receiver's inbound rules
+pub fn simulate(
+    rule_sets: &RuleSets,
+    members: &HashMap<u64, Endpoint>,
+    source: &Endpoint,
+    destination: &Endpoint,
+    packet: &SimulatedPacket,
+) -> FlowSimulation {
+    let mut source = source.clone();
+    source.tags.extend(&packet.sender_tags);
+    if let Some(capabilities) = &packet.sender_capabilities {
+        source.capabilities = capabilities.clone();
+    }
+    let source = &source;
+
+    let flow = Packet::new(packet, source, destination);
+    let mut simulation = FlowSimulation {
+        verdict: Verdict::Drop,
+        dropped_by: None,
+        capability: None,
+        redirected_to: None,
+        copies: Vec::new(),
+        trace: Vec::new(),
+    };
+
+    let outbound = run_node(
+        &Filter {
+            direction: Direction::Outbound,
+            packet: &flow,
+            source,
+            destination,
+            node: source.address,
+            local_tags: &source.tags,
+            remote_tags: &destination.tags,
+        },
+        rule_sets,
+        &source.capabilities,
+        &mut simulation.trace,
+    );
+    if matches!(outbound.result, FilterResult::NoMatch | FilterResult::Drop) {
+        simulation.dropped_by = Some(Direction::Outbound);
+        return simulation;
+    }
+    simulation.capability = outbound.capability;
+    simulation.copies = outbound.copies;
+
+    // A redirect sends the packet to another node, which evaluates it as the receiver
+    let receiver = match outbound.redirect {
+        Some(address) => {
+            simulation.redirected_to = Some(format!("{:010x}", address));
+            members.get(&address).cloned().unwrap_or(Endpoint {
+                address,
+                ..Endpoint::default()
+            })
+        }
+        None => destination.clone(),
+    };
+    let receiver = &receiver;
+
+    let inbound = run_node(
+        &Filter {
+            direction: Direction::Inbound,
+            packet: &flow,
+            source,
+            destination: receiver,
+            node: receiver.address,
+            local_tags: &receiver.tags,
+            remote_tags: &source.tags,
+        },
+        rule_sets,
+        &source.capabilities,
+        &mut simulation.trace,
+    );
+    if matches!(inbound.result, FilterResult::NoMatch | FilterResult::Drop) {
+        simulation.dropped_by = Some(Direction::Inbound);
+        return simulation;
+    }
+    if let Some(address) = inbound.redirect {
+        simulation.redirected_to = Some(format!("{:010x}", address));
+    }
+    simulation.capability = simulation.capability.or(inbound.capability);
+    simulation.copies.extend(inbound.copies);
+    simulation.verdict = Verdict::Accept;
+
+    simulation
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rules::compile;
+    use serde_json::json;
+
+    const NETWORK: u64 = 0x8056_c2e2_1c00_0001;
+    const SENDER: u64 = 0x11_1111_1111;
+    const RECEIVER: u64 = 0x22_2222_2222;
+    const OTHER: u64 = 0x33_3333_3333;
+    const WATCHER: u64 = 0x44_4444_4444;
+
+    fn endpoint(address: u64, ip: &str, tags: &[(u32, u32)]) -> Endpoint {
+        let member = json!({
+            "address": format!("{:010x}", address),
+            "ipAssignments": [ip],
+            "tags": tags.iter().map(|(id, value)| json!([id, value])).collect::<Vec<_>>(),
+        });
+        Endpoint::from_member(NETWORK, member.as_object().unwrap(), &BTreeMap::new()).unwrap()
+    }
+
+    fn packet(fields: Value) -> SimulatedPacket {
+        let mut packet = json!({
+            "source": format!("{:010x}", SENDER),
+            "destination": format!("{:010x}", RECEIVER),
+            "ip_protocol": 6,
+            "destination_port": 22,
+        });
+        packet
+            .as_object_mut()
+            .unwrap()
+            .extend(fields.as_object().unwrap().clone());
+        serde_json::from_value(packet).unwrap()
+    }
+
+    /// Simulate a packet from the sender to the receiver under compiled `source`
+    fn run(source: &str, sender: &Endpoint, receiver: &Endpoint, fields: Value) -> FlowSimulation {
+        let compiled = compile(source).unwrap();
+        let members = [sender, receiver, &endpoint(OTHER, "10.0.0.3", &[])]
+            .into_iter()
+            .map(|endpoint| (endpoint.address, endpoint.clone()))
+            .collect();
+        simulate(
+            &RuleSets {
+                rules: &compiled.rules,
+                capabilities: &compiled.capabilities,
+            },
+            &members,
+            sender,
+            receiver,
+            &packet(fields),
+        )
+    }
+
+    fn pair() -> (Endpoint, Endpoint) {
+        (
+            endpoint(SENDER, "10.0.0.1", &[]),
+            endpoint(RECEIVER, "10.0.0.2", &[]),
+        )
+    }
+
+    fn results(simulation: &FlowSimulation) -> Vec<(Direction, Option<u32>, FilterResult)> {
+        simulation
+            .trace
+            .iter()
+            .map(|trace| (trace.direction, trace.capability, trace.result))
+            .collect()
+    }
+
+    #[test]
+    fn reads_members_with_tag_defaults() {
+        let member = json!({
+            "id": "89e92ceee5",
+            "ipAssignments": ["10.0.0.1", "fd00::1", "bogus"],
+            "tags": [[1, 7], [2]],
+            "capabilities": [3, 4],
+        });
+        let defaults = tag_defaults(&[
+            json!({ "id": 1, "default": 5 }),
+            json!({ "id": 9, "default": 2 }),
+            json!({ "id": 10, "default": null }),
+        ]);
+        let endpoint =
+            Endpoint::from_member(NETWORK, member.as_object().unwrap(), &defaults).unwrap();
+
+        assert_eq!(endpoint.address, 0x89_e92c_eee5);
+        assert_eq!(endpoint.mac, zerotier_mac(NETWORK, 0x89_e92c_eee5));
+        assert_eq!(endpoint.ips.len(), 2);
+        assert_eq!(endpoint.tags, BTreeMap::from([(1, 7), (9, 2)]));
+        assert_eq!(endpoint.capabilities, [3, 4]);
+    }
+
+    #[test]
+    fn chains_or_and_not() {
+        let (sender, receiver) = pair();
+        let source = "\
+drop not ethertype ipv4 and not ethertype arp;
+accept ipprotocol tcp and dport 22 or dport 80 and not sport 1;
+drop;
+";
+
+        let accepted = run(source, &sender, &receiver, json!({}));
+        assert_eq!(accepted.verdict, Verdict::Accept);
+        let steps: Vec<(StepResult, bool)> = accepted.trace[0]
+            .steps
+            .iter()
+            .map(|step| (step.result, step.set_matches))
+            .collect();
+        assert_eq!(
+            steps,
+            [
+                // not ethertype ipv4 fails, so the AND is skipped
+                (StepResult::Matched, false),
+                (StepResult::Skipped, false),
+                (StepResult::NotTaken, false),
+                (StepResult::Matched, true),
+                (StepResult::Matched, true),
+                (StepResult::NotMatched, true),
+                (StepResult::NotMatched, true),
+                (StepResult::Taken, true),
+            ]
+        );
+
+        // An OR match revives the set after an earlier match failed
+        let port_80 = json!({ "ip_protocol": 17, "destination_port": 80 });
+        assert_eq!(
+            run(source, &sender, &receiver, port_80).verdict,
+            Verdict::Accept
+        );
+        let negated = json!({ "ip_protocol": 17, "destination_port": 80, "source_port": 1 });
+        let dropped = run(source, &sender, &receiver, negated);
+        assert_eq!(dropped.verdict, Verdict::Drop);
+        assert_eq!(dropped.dropped_by, Some(Direction::Outbound));
+
+        let ipv6 = json!({ "ether_type": 0x86dd });
+        let dropped = run(source, &sender, &receiver, ipv6);
+        assert_eq!(dropped.trace[0].steps.len(), 3);
+        assert_eq!(dropped.trace[0].result, FilterResult::Drop);
+    }
+
+    #[test]
+    fn falls_back_to_the_senders_capabilities() {
+        let (mut sender, receiver) = pair();
+        let source = "\
+cap ssh
+  id 10
+  drop sport 1;
+  accept dport 22;
+;
+cap web id 20 accept dport 80;
+accept dport 443;
+";
+
+        let dropped = run(source, &sender, &receiver, json!({}));
+        assert_eq!(dropped.dropped_by, Some(Direction::Outbound));
+        assert_eq!(dropped.capability, None);
+
+        sender.capabilities = vec![20, 10];
+        let accepted = run(source, &sender, &receiver, json!({}));
+        assert_eq!(accepted.verdict, Verdict::Accept);
+        assert_eq!(accepted.capability, Some(10));
+        assert_eq!(
+            results(&accepted),
+            [
+                (Direction::Outbound, None, FilterResult::NoMatch),
+                (Direction::Outbound, Some(10), FilterResult::Accept),
+                (Direction::Inbound, None, FilterResult::NoMatch),
+                (Direction::Inbound, Some(10), FilterResult::Accept),
+            ]
+        );
+
+        // A drop in a capability only ends that capability
+        let dropped = run(source, &sender, &receiver, json!({ "source_port": 1 }));
+        assert_eq!(
+            results(&dropped),
+            [
+                (Direction::Outbound, None, FilterResult::NoMatch),
+                (Direction::Outbound, Some(10), FilterResult::Drop),
+                (Direction::Outbound, Some(20), FilterResult::NoMatch),
+            ]
+        );
+
+        // The packet's capabilities replace the assigned ones
+        let web = json!({ "destination_port": 80, "sender_capabilities": [20] });
+        assert_eq!(run(source, &sender, &receiver, web).capability, Some(20));
+        let none = json!({ "sender_capabilities": [] });
+        assert_eq!(run(source, &sender, &receiver, none).verdict, Verdict::Drop);
+    }
+
+    #[test]
+    fn matches_tags_on_both_sides() {
+        let source = "\
+tag role id 1 enum 5 server;
+accept teq role server;
+drop;
+";
+        let sender = endpoint(SENDER, "10.0.0.1", &[(1, 5)]);
+        let receiver = endpoint(RECEIVER, "10.0.0.2", &[(1, 5)]);
+        assert_eq!(
+            run(source, &sender, &receiver, json!({})).verdict,
+            Verdict::Accept
+        );
+
+        let other = json!({ "sender_tags": { "1": 6 } });
+        let dropped = run(source, &sender, &receiver, other);
+        assert_eq!(dropped.dropped_by, Some(Direction::Outbound));
+
+        // The sender accepts without knowing the receiver's tag, the receiver decides
+        let untagged = endpoint(RECEIVER, "10.0.0.2", &[]);
+        let dropped = run(source, &sender, &untagged, json!({}));
+        assert_eq!(dropped.dropped_by, Some(Direction::Inbound));
+        assert_eq!(
+            results(&dropped),
+            [
+                (Direction::Outbound, None, FilterResult::Accept),
+                (Direction::Inbound, None, FilterResult::Drop),
+            ]
+        );
+    }
+
+    #[test]
+    fn treats_missing_remote_tags_by_direction() {
+        let (sender, receiver) = pair();
+        let flow = Packet::new(&packet(json!({})), &sender, &receiver);
+        let local = BTreeMap::from([(1, 5)]);
+        let remote = BTreeMap::new();
+        let filter = |direction| Filter {
+            direction,
+            packet: &flow,
+            source: &sender,
+            destination: &receiver,
+            node: if direction == Direction::Inbound {
+                RECEIVER
+            } else {
+                SENDER
+            },
+            local_tags: &local,
+            remote_tags: &remote,
+        };
+
+        for rule_type in [
+            "MATCH_TAGS_DIFFERENCE",
+            "MATCH_TAGS_BITWISE_AND",
+            "MATCH_TAGS_BITWISE_OR",
+            "MATCH_TAGS_BITWISE_XOR",
+            "MATCH_TAGS_EQUAL",
+        ] {
+            let rule = json!({ "type": rule_type, "id": 1, "value": 5 });
+            let matches =
+                |direction, super_accept| filter(direction).matches(rule_type, &rule, super_accept);
+            assert!(matches(Direction::Outbound, false), "{}", rule_type);
+            assert!(!matches(Direction::Inbound, false), "{}", rule_type);
+            assert!(matches(Direction::Inbound, true), "{}", rule_type);
+
+            // Without a local tag nothing matches
+            let missing = json!({ "type": rule_type, "id": 2, "value": 5 });
+            assert!(!filter(Direction::Outbound).matches(rule_type, &missing, true));
+        }
+
+        let sender_tag = json!({ "type": "MATCH_TAG_SENDER", "id": 1, "value": 5 });
+        let receiver_tag = json!({ "type": "MATCH_TAG_RECEIVER", "id": 1, "value": 5 });
+        let inbound = filter(Direction::Inbound);
+        let outbound = filter(Direction::Outbound);
+        assert!(!inbound.matches("MATCH_TAG_SENDER", &sender_tag, false));
+        assert!(inbound.matches("MATCH_TAG_SENDER", &sender_tag, true));
+        assert!(inbound.matches("MATCH_TAG_RECEIVER", &receiver_tag, false));
+        assert!(outbound.matches("MATCH_TAG_SENDER", &sender_tag, false));
+        assert!(outbound.matches("MATCH_TAG_RECEIVER", &receiver_tag, false));
+    }
+
+    #[test]
+    fn super_accepts_as_a_forwarding_target() {
+        let sender = endpoint(SENDER, "10.0.0.1", &[]);
+        let receiver = endpoint(RECEIVER, "10.0.0.2", &[(1, 5)]);
+        // Outbound always accepts, inbound needs the untagged sender's role
+        let rules = "\
+tag role id 1;
+accept chr inbound and teq role 5 or not chr inbound;
+drop;
+";
+        let dropped = run(rules, &sender, &receiver, json!({}));
+        assert_eq!(dropped.dropped_by, Some(Direction::Inbound));
+
+        // As the target of a tee whose matches did not hold the receiver is lenient
+        let tee = format!("tee -1 {:010x} ipprotocol 47;\n{}", RECEIVER, rules);
+        let simulation = run(&tee, &sender, &receiver, json!({}));
+        assert_eq!(simulation.verdict, Verdict::Accept);
+        assert_eq!(
+            results(&simulation),
+            [
+                (Direction::Outbound, None, FilterResult::Accept),
+                (Direction::Inbound, None, FilterResult::SuperAccept),
+            ]
+        );
+        assert_eq!(simulation.trace[1].steps[1].result, StepResult::NotTaken);
+        assert!(simulation.copies.is_empty());
+    }
+
+    #[test]
+    fn sends_copies_only_from_accepting_rule_sets() {
+        let (mut sender, receiver) = pair();
+        sender.capabilities = vec![10, 20];
+        let source = format!(
+            "\
+tee 64 {other:010x};
+tee 128 {watcher:010x};
+cap spy
+  id 10
+  tee -1 {other:010x};
+  drop;
+;
+cap ssh id 20 accept dport 22;
+",
+            watcher = WATCHER,
+            other = OTHER
+        );
+
+        let simulation = run(&source, &sender, &receiver, json!({}));
+        assert_eq!(simulation.verdict, Verdict::Accept);
+        assert_eq!(simulation.capability, Some(20));
+        assert_eq!(
+            results(&simulation)[..3],
+            [
+                (Direction::Outbound, None, FilterResult::NoMatch),
+                (Direction::Outbound, Some(10), FilterResult::Drop),
+                (Direction::Outbound, Some(20), FilterResult::Accept),
+            ]
+        );
+        // The base rules keep their last tee, the dropping capability sends nothing
+        let copies: Vec<(Direction, &str, i64)> = simulation
+            .copies
+            .iter()
+            .map(|copy| (copy.direction, copy.address.as_str(), copy.length))
+            .collect();
+        assert_eq!(
+            copies,
+            [
+                (Direction::Outbound, "4444444444", 128),
+                (Direction::Inbound, "4444444444", 128),
+            ]
+        );
+
+        // Nothing is copied when no capability accepts
+        let dropped = run(
+            &source,
+            &sender,
+            &receiver,
+            json!({ "destination_port": 80 }),
+        );
+        assert_eq!(dropped.dropped_by, Some(Direction::Outbound));
+        assert!(dropped.copies.is_empty());
+    }
+
+    #[test]
+    fn traces_tees_and_redirects() {
+        let (sender, receiver) = pair();
+        let source = format!(
+            "\
+tee 128 {watcher:010x};
+watch -1 {sender:010x};
+redirect {other:010x} ipprotocol udp;
+accept;
+",
+            watcher = WATCHER,
+            sender = SENDER,
+            other = OTHER
+        );
+
+        let copied = run(&source, &sender, &receiver, json!({}));
+        assert_eq!(copied.verdict, Verdict::Accept);
+        assert_eq!(copied.redirected_to, None);
+        let copies: Vec<(Direction, &str, i64, bool)> = copied
+            .copies
+            .iter()
+            .map(|copy| {
+                (
+                    copy.direction,
+                    copy.address.as_str(),
+                    copy.length,
+                    copy.watch,
+                )
+            })
+            .collect();
+        // Watching the sender itself is a no-op
+        assert_eq!(
+            copies,
+            [
+                (Direction::Outbound, "4444444444", 128, false),
+                (Direction::Inbound, "4444444444", 128, false),
+            ]
+        );
+
+        let redirected = run(&source, &sender, &receiver, json!({ "ip_protocol": 17 }));
+        assert_eq!(redirected.verdict, Verdict::Accept);
+        assert_eq!(redirected.redirected_to.as_deref(), Some("3333333333"));
+        assert_eq!(
+            results(&redirected),
+            [
+                (Direction::Outbound, None, FilterResult::Redirect),
+                (Direction::Inbound, None, FilterResult::SuperAccept),
+            ]
+        );
+        assert_eq!(redirected.trace[1].node, "3333333333");
+
+        let stopped = run(
+            "break ethertype ipv4;\naccept;",
+            &sender,
+            &receiver,
+            json!({}),
+        );
+        assert_eq!(stopped.dropped_by, Some(Direction::Outbound));
+        assert_eq!(stopped.trace[0].result, FilterResult::NoMatch);
+        assert_eq!(stopped.trace[0].steps.len(), 2);
+    }
+}
@@ -0,0 +1,813 @@
+use crate::error::{AppError, FieldError, Result};
+use crate::models::{AccessSchedule, ScheduleOverride, ScheduleTransition};
+use crate::services::audit::{AuditEntry, AuditService};
+use crate::services::{ConfigService, MetadataService, ZeroTierService};
+use chrono::{DateTime, Utc};
+use cron::Schedule;
+use serde_json::{json, Map, Value};
+use std::collections::HashMap;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::Mutex;
+
+const CHECK_INTERVAL: Duration = Duration::from_secs(15);
+
+/// Parse a cron expression. Standard five-field expressions run at second 0 and
+/// number weekdays from Sunday as 0 (or 7), longer ones use the `cron` crate's
+/// format, which numbers them from Sunday as 1.
+fn parse_cron(expression: &str) -> Result<Schedule> {
+    let invalid = |reason: String| {
+        AppError::ConfigError(format!(
+            "Invalid cron expression {}: {}",
+            expression, reason
+        ))
+    };
+
+    let fields: Vec<&str> = expression.split_whitespace().collect();
+    let expression = if let [minute, hour, day, month, weekday] = fields[..] {
+        let weekday = standard_weekdays(weekday).map_err(invalid)?;
+        format!("0 {} {} {} {} {}", minute, hour, day, month, weekday)
+    } else {
+        expression.to_string()
+    };
+
+    Schedule::from_str(&expression).map_err(|e| invalid(e.to_string()))
+}
+
+/// Renumber a standard day-of-week field for the `cron` crate. Numeric items are
+/// expanded to the days they match, named days are passed through.
+fn standard_weekdays(field: &str) -> std::result::Result<String, String> {
+    let invalid = || format!("invalid day of week {}", field);
+    let number = |day: &str| day.parse::<usize>().ok().filter(|day| *day <= 7);
+
+    let mut items = Vec::new();
+    for item in field.split(',') {
+        if !item.bytes().any(|byte| byte.is_ascii_digit()) {
+            items.push(item.to_string());
+            continue;
+        }
+
+        let (range, step) = match item.split_once('/') {
+            Some((range, step)) => (
+                range,
+                Some(
+                    step.parse::<usize>()
+                        .ok()
+                        .filter(|step| *step > 0)
+                        .ok_or_else(invalid)?,
+                ),
+            ),
+            None => (item, None),
+        };
+        let (first, last) = match (range, range.split_once('-')) {
+            ("*", _) => (0, 6),
+            (_, Some((first, last))) => (
+                number(first).ok_or_else(invalid)?,
+                number(last).ok_or_else(invalid)?,
+            ),
+            // `n/step` runs from n to the end of the week
+            (first, None) => {
+                let first = number(first).ok_or_else(invalid)?;
+                (first, if step.is_some() { 6 } else { first })
+            }
+        };
+        if first > last {
+            return Err(invalid());
+        }
+
+        items.extend(
+            (first..=last)
+                .step_by(step.unwrap_or(1))
+                .map(|day| (day % 7 + 1).to_string()),
+        );
+    }
+    Ok(items.join(","))
+}
+
+/// An access schedule with its cron expressions parsed
+struct ParsedSchedule<'a> {
+    schedule: &'a AccessSchedule,
+    authorize: Schedule,
+    deauthorize: Schedule,
+}
+
+impl<'a> ParsedSchedule<'a> {
+    fn parse(schedule: &'a AccessSchedule) -> Result<Self> {
+        Ok(Self {
+            schedule,
+            authorize: parse_cron(&schedule.authorize)?,
+            deauthorize: parse_cron(&schedule.deauthorize)?,
+        })
+    }
+
+    /// The most recent transition before `now` and the state it set
+    fn last_transition(&self, now: DateTime<Utc>) -> Option<(DateTime<Utc>, bool)> {
+        let now = now.with_timezone(&self.schedule.timezone);
+        let authorize = self.authorize.after(&now).next_back();
+        let deauthorize = self.deauthorize.after(&now).next_back();
+
+        match (authorize, deauthorize) {
+            (Some(a), Some(d)) if a > d => Some((a.with_timezone(&Utc), true)),
+            (_, Some(d)) => Some((d.with_timezone(&Utc), false)),
+            (Some(a), None) => Some((a.with_timezone(&Utc), true)),
+            (None, None) => None,
+        }
+    }
+
+    /// The next `limit` transitions after `now`, in order
+    fn upcoming(&self, now: DateTime<Utc>, limit: usize) -> Vec<ScheduleTransition> {
+        let now = now.with_timezone(&self.schedule.timezone);
+        let transition = |at: DateTime<chrono_tz::Tz>, authorized| ScheduleTransition {
+            schedule: self.schedule.name.clone(),
+            network_id: self.schedule.network_id.clone(),
+            at: at.with_timezone(&Utc),
+            authorized,
+        };
+
+        let mut transitions: Vec<ScheduleTransition> = self
+            .authorize
+            .after(&now)
+            .take(limit)
+            .map(|at| transition(at, true))
+            .chain(
+                self.deauthorize
+                    .after(&now)
+                    .take(limit)
+                    .map(|at| transition(at, false)),
+            )
+            .collect();
+        transitions.sort_by_key(|transition| transition.at);
+        transitions.truncate(limit);
+        transitions
+    }
+
+    fn covers(&self, member: &Map<String, Value>) -> bool {
+        let id = member.get("id").and_then(Value::as_str).unwrap_or("");
+        if self
+            .schedule
+            .members
+            .iter()
+            .any(|member_id| member_id.eq_ignore_ascii_case(id))
+        {
+            return true;
+        }
+
+        let tags: Vec<[u32; 2]> = member
+            .get("tags")
+            .cloned()
+            .and_then(|tags| serde_json::from_value(tags).ok())
+            .unwrap_or_default();
+        self.schedule.tags.iter().any(|tag| tags.contains(tag))
+    }
+}
+
+/// Flips member authorization according to the configured access schedules
+#[derive(Clone)]
+pub struct AccessScheduleService {
+    config: ConfigService,
+    metadata: MetadataService,
+    zerotier: ZeroTierService,
+    audit: AuditService,
+    /// Time of the last transition fully applied, by schedule name
+    applied: Arc<Mutex<HashMap<String, DateTime<Utc>>>>,
+}
+
+impl AccessScheduleService {
+    pub fn new(
+        config: ConfigService,
+        metadata: MetadataService,
+        zerotier: ZeroTierService,
+        audit: AuditService,
+    ) -> Self {
+        Self {
+            config,
+            metadata,
+            zerotier,
+            audit,
+            applied: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// Start applying schedules in the background
+    pub fn spawn(&self) {
+        let config = self.config.get_config();
+        if config.access_schedules.is_empty() {
+            return;
+        }
+        for schedule in &config.access_schedules {
+            if let Err(e) = ParsedSchedule::parse(schedule) {
+                tracing::warn!("Access schedule {} is ignored: {}", schedule.name, e);
+            }
+        }
+
+        let service = self.clone();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(CHECK_INTERVAL);
+            interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+
+            loop {
+                interval.tick().await;
+                service.check().await;
+            }
+        });
+    }
+
+    async fn check(&self) {
+        let now = Utc::now();
+        let config = self.config.get_config();
+        // Leave the controller alone while it is frozen, transitions are applied afterwards
+        if config.mode.read_only {
+            return;
+        }
+
+        // Overrides that ended hand the member back to its schedules
+        for (network_id, member_id, metadata) in self.metadata.all().await {
+            if metadata
+                .schedule_override
+                .is_some_and(|schedule_override| schedule_override.until <= now)
+            {
+                if let Err(e) = self.clear_override(&network_id, &member_id).await {
+                    tracing::warn!("Failed to end schedule override for {}: {}", member_id, e);
+                }
+            }
+        }
+
+        // Apply the latest transition of each schedule, or the current state on startup.
+        // A transition that failed for any member is retried on the next check.
+        let mut applied = self.applied.lock().await;
+        for schedule in &config.access_schedules {
+            let Ok(parsed) = ParsedSchedule::parse(schedule) else {
+                continue;
+            };
+            let Some((at, authorized)) = parsed.last_transition(now) else {
+                continue;
+            };
+            if applied
+                .get(&schedule.name)
+                .is_some_and(|applied_at| *applied_at >= at)
+            {
+                continue;
+            }
+
+            match self.apply_schedule(&parsed, authorized).await {
+                Ok(failures) if failures.is_empty() => {
+                    applied.insert(schedule.name.clone(), at);
+                }
+                Ok(_) => {}
+                Err(e) => {
+                    tracing::warn!("Failed to apply access schedule {}: {}", schedule.name, e);
+                }
+            }
+        }
+    }
+
+    /// Set the authorization of every member the schedule covers.
+    /// Returns the members that could not be updated with their errors.
+    async fn apply_schedule(
+        &self,
+        parsed: &ParsedSchedule<'_>,
+        authorized: bool,
+    ) -> Result<Vec<(String, AppError)>> {
+        let network_id = &parsed.schedule.network_id;
+        let members = self.zerotier.list_members(network_id).await?;
+        let metadata = self.metadata.network(network_id).await;
+
+        let mut failures = Vec::new();
+        for member in members.iter().filter(|member| parsed.covers(member)) {
+            let member_id = member.get("id").and_then(Value::as_str).unwrap_or("");
+            // Overridden members keep their state, expired ones stay deauthorized
+            let held = metadata.get(member_id).is_some_and(|metadata| {
+                metadata.schedule_override.is_some() || metadata.expired_at.is_some()
+            });
+            if held {
+                continue;
+            }
+
+            if let Err(e) = self
+                .set_authorized(
+                    &format!("schedule:{}", parsed.schedule.name),
+                    network_id,
+                    member,
+                    authorized,
+                )
+                .await
+            {
+                tracing::warn!(
+                    "Failed to apply access schedule {} to {}: {}",
+                    parsed.schedule.name,
+                    member_id,
+                    e
+                );
+                failures.push((member_id.to_string(), e));
+            }
+        }
+
+        Ok(failures)
+    }
+
+    async fn set_authorized(
+        &self,
+        actor: &str,
+        network_id: &str,
+        member: &Map<String, Value>,
+        authorized: bool,
+    ) -> Result<()> {
+        let current = member
+            .get("authorized")
+            .and_then(Value::as_bool)
+            .unwrap_or(false);
+        if current == authorized {
+            return Ok(());
+        }
+
+        let member_id = member.get("id").and_then(Value::as_str).unwrap_or("");
+        self.zerotier
+            .update_member(network_id, member_id, &json!({ "authorized": authorized }))
+            .await?;
+
+        self.audit
+            .record(
+                AuditEntry::new(
+                    actor,
+                    if authorized {
+                        "member.authorize"
+                    } else {
+                        "member.deauthorize"
+                    },
+                )
+                .member(network_id, member_id),
+            )
+            .await;
+
+        Ok(())
+    }
+
+    async fn fetch_member(&self, network_id: &str, member_id: &str) -> Result<Map<String, Value>> {
+        self.zerotier
+            .get_json(&format!(
+                "/controller/network/{}/member/{}",
+                network_id, member_id
+            ))
+            .await
+    }
+
+    /// Upcoming transitions of all schedules, soonest first
+    pub fn upcoming(&self, network_id: Option<&str>, limit: usize) -> Vec<ScheduleTransition> {
+        let now = Utc::now();
+        let config = self.config.get_config();
+
+        let mut transitions: Vec<ScheduleTransition> = config
+            .access_schedules
+            .iter()
+            .filter(|schedule| network_id.is_none_or(|id| schedule.network_id == id))
+            .filter_map(|schedule| ParsedSchedule::parse(schedule).ok())
+            .flat_map(|parsed| parsed.upcoming(now, limit))
+            .collect();
+        transitions.sort_by_key(|transition| transition.at);
+        transitions.truncate(limit);
+        transitions
+    }
+
+    /// Keep a member authorized or deauthorized until `until`, by default
+    /// until the next transition of a schedule covering it
+    pub async fn set_override(
+        &self,
+        actor: &str,
+        network_id: &str,
+        member_id: &str,
+        authorized: bool,
+        until: Option<DateTime<Utc>>,
+    ) -> Result<ScheduleOverride> {
+        let now = Utc::now();
+        let member = self.fetch_member(network_id, member_id).await?;
+
+        let until = match until {
+            Some(until) => until,
+            None => {
+                let config = self.config.get_config();
+                config
+                    .access_schedules
+                    .iter()
+                    .filter(|schedule| schedule.network_id == network_id)
+                    .filter_map(|schedule| ParsedSchedule::parse(schedule).ok())
+                    .filter(|parsed| parsed.covers(&member))
+                    .filter_map(|parsed| parsed.upcoming(now, 1).first().map(|t| t.at))
+                    .min()
+                    .ok_or_else(|| {
+                        AppError::ValidationError(vec![FieldError::new(
+                            "until",
+                            "required because no schedule covers this member",
+                        )])
+                    })?
+            }
+        };
+        if until <= now {
+            return Err(AppError::ValidationError(vec![FieldError::new(
+                "until",
+                "must be in the future",
+            )]));
+        }
+
+        let schedule_override = ScheduleOverride {
+            authorized,
+            until,
+            set_by: actor.to_string(),
+        };
+        self.metadata
+            .update(network_id, member_id, |metadata| {
+                metadata.schedule_override = Some(schedule_override.clone());
+                Ok(())
+            })
+            .await?;
+
+        self.audit
+            .record(
+                AuditEntry::new(actor, "member.schedule_override")
+                    .member(network_id, member_id)
+                    .details(json!({ "authorized": authorized, "until": until })),
+            )
+            .await;
+        self.set_authorized(actor, network_id, &member, authorized)
+            .await?;
+
+        Ok(schedule_override)
+    }
+
+    /// Remove a member's override and apply the state its schedules
+    /// currently call for
+    pub async fn clear_override(&self, network_id: &str, member_id: &str) -> Result<()> {
+        let (previous, expired) = self
+            .metadata
+            .update(network_id, member_id, |metadata| {
+                Ok((
+                    metadata.schedule_override.take(),
+                    metadata.expired_at.is_some(),
+                ))
+            })
+            .await?;
+        if previous.is_none() {
+            return Err(AppError::NotFound(format!(
+                "Member {} has no schedule override",
+                member_id
+            )));
+        }
+        if expired {
+            return Ok(());
+        }
+
+        let member = self.fetch_member(network_id, member_id).await?;
+        let now = Utc::now();
+        let config = self.config.get_config();
+        let current = config
+            .access_schedules
+            .iter()
+            .filter(|schedule| schedule.network_id == network_id)
+            .filter_map(|schedule| ParsedSchedule::parse(schedule).ok())
+            .filter(|parsed| parsed.covers(&member))
+            .filter_map(|parsed| {
+                parsed
+                    .last_transition(now)
+                    .map(|transition| (parsed.schedule.name.clone(), transition))
+            })
+            .max_by_key(|(_, (at, _))| *at);
+
+        if let Some((name, (_, authorized))) = current {
+            self.set_authorized(
+                &format!("schedule:{}", name),
+                network_id,
+                &member,
+                authorized,
+            )
+            .await?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::AppState;
+    use crate::test_support::TestEnv;
+
+    const NETWORK: &str = "abcdef0123000001";
+    /// Midnight on January 1st every year
+    const NEW_YEAR: &str = "0 0 0 1 1 *";
+    /// A single transition long before any test runs
+    const Y2K: &str = "0 0 0 1 1 * 2000";
+
+    fn schedule(authorize: &str, deauthorize: &str) -> AccessSchedule {
+        AccessSchedule {
+            name: "office".to_string(),
+            network_id: NETWORK.to_string(),
+            members: vec!["1111111111".to_string()],
+            tags: vec![[1, 2]],
+            authorize: authorize.to_string(),
+            deauthorize: deauthorize.to_string(),
+            timezone: chrono_tz::Tz::UTC,
+        }
+    }
+
+    fn member(value: Value) -> Map<String, Value> {
+        value.as_object().cloned().unwrap()
+    }
+
+    async fn setup(env: &TestEnv, schedules: Vec<AccessSchedule>) -> AppState {
+        env.config
+            .update(|config| {
+                config.access_schedules = schedules;
+                Ok(())
+            })
+            .await
+            .unwrap();
+        env.controller.add_network(NETWORK, json!({}));
+        env.controller.add_member(NETWORK, "1111111111", json!({}));
+        env.controller
+            .add_member(NETWORK, "2222222222", json!({ "tags": [[1, 2]] }));
+        env.controller.add_member(NETWORK, "3333333333", json!({}));
+        env.app_state()
+    }
+
+    fn authorized(env: &TestEnv, member_id: &str) -> bool {
+        env.controller.member(NETWORK, member_id).unwrap()["authorized"] == true
+    }
+
+    #[test]
+    fn parses_five_and_six_field_expressions() {
+        let schedule = parse_cron("30 9 * * MON-FRI").unwrap();
+        let next = schedule.upcoming(Utc).next().unwrap();
+        assert_eq!(next.format("%S:%M:%H").to_string(), "00:30:09");
+
+        assert!(parse_cron("*/5 * * * * *").is_ok());
+        assert!(matches!(
+            parse_cron("every morning"),
+            Err(AppError::ConfigError(_))
+        ));
+    }
+
+    #[test]
+    fn numbers_standard_weekdays_from_sunday() {
+        // Saturday
+        let start: DateTime<Utc> = "2024-03-16T12:00:00Z".parse().unwrap();
+        let days = |expression: &str| -> Vec<String> {
+            parse_cron(expression)
+                .unwrap()
+                .after(&start)
+                .take(7)
+                .map(|at| at.format("%a").to_string())
+                .collect()
+        };
+
+        assert_eq!(
+            days("0 9 * * 1-5"),
+            ["Mon", "Tue", "Wed", "Thu", "Fri", "Mon", "Tue"]
+        );
+        assert_eq!(days("0 9 * * 1-5"), days("0 9 * * MON-FRI"));
+        assert_eq!(days("0 9 * * 0")[..2], ["Sun", "Sun"]);
+        assert_eq!(days("0 9 * * 7")[..2], ["Sun", "Sun"]);
+        assert_eq!(days("0 9 * * 5-7")[..3], ["Sun", "Fri", "Sat"]);
+        assert_eq!(days("0 9 * * */3")[..3], ["Sun", "Wed", "Sat"]);
+        assert_eq!(days("0 9 * * 2/2")[..3], ["Tue", "Thu", "Sat"]);
+        assert_eq!(days("0 9 * * 6,SUN")[..3], ["Sun", "Sat", "Sun"]);
+
+        for invalid in ["0 9 * * 8", "0 9 * * 5-1", "0 9 * * */0", "0 9 * * MON-5"] {
+            assert!(
+                matches!(parse_cron(invalid), Err(AppError::ConfigError(_))),
+                "{}",
+                invalid
+            );
+        }
+    }
+
+    #[test]
+    fn finds_the_latest_transition() {
+        let now: DateTime<Utc> = "2024-03-15T12:00:00Z".parse().unwrap();
+
+        let schedule = schedule("0 0 9 * * *", "0 0 17 * * *");
+        let parsed = ParsedSchedule::parse(&schedule).unwrap();
+        let (at, authorized) = parsed.last_transition(now).unwrap();
+        assert_eq!(at, "2024-03-15T09:00:00Z".parse::<DateTime<Utc>>().unwrap());
+        assert!(authorized);
+
+        let evening: DateTime<Utc> = "2024-03-15T18:00:00Z".parse().unwrap();
+        let (at, authorized) = parsed.last_transition(evening).unwrap();
+        assert_eq!(at, "2024-03-15T17:00:00Z".parse::<DateTime<Utc>>().unwrap());
+        assert!(!authorized);
+
+        let upcoming = parsed.upcoming(now, 3);
+        let states: Vec<_> = upcoming.iter().map(|t| t.authorized).collect();
+        assert_eq!(states, [false, true, false]);
+        assert_eq!(
+            upcoming[0].at,
+            "2024-03-15T17:00:00Z".parse::<DateTime<Utc>>().unwrap()
+        );
+    }
+
+    #[test]
+    fn uses_the_schedule_timezone() {
+        let mut schedule = schedule("0 0 9 * * *", "0 0 17 * * *");
+        schedule.timezone = chrono_tz::Asia::Shanghai;
+        let parsed = ParsedSchedule::parse(&schedule).unwrap();
+
+        let now: DateTime<Utc> = "2024-03-15T02:00:00Z".parse().unwrap();
+        let (at, authorized) = parsed.last_transition(now).unwrap();
+        assert_eq!(at, "2024-03-15T01:00:00Z".parse::<DateTime<Utc>>().unwrap());
+        assert!(authorized);
+    }
+
+    #[test]
+    fn covers_listed_members_and_tags() {
+        let schedule = schedule(NEW_YEAR, Y2K);
+        let parsed = ParsedSchedule::parse(&schedule).unwrap();
+
+        assert!(parsed.covers(&member(json!({ "id": "1111111111" }))));
+        assert!(parsed.covers(&member(json!({ "id": "1111111111".to_uppercase() }))));
+        assert!(parsed.covers(&member(json!({ "id": "2222222222", "tags": [[1, 2]] }))));
+        assert!(!parsed.covers(&member(json!({ "id": "2222222222", "tags": [[1, 3]] }))));
+    }
+
+    #[tokio::test]
+    async fn applies_the_current_state_on_startup() {
+        let env = TestEnv::new().await;
+        let app_state = setup(&env, vec![schedule(NEW_YEAR, Y2K)]).await;
+
+        app_state.access_schedules.check().await;
+
+        assert!(authorized(&env, "1111111111"));
+        assert!(authorized(&env, "2222222222"));
+        assert!(!authorized(&env, "3333333333"));
+        let entries = app_state.audit.recent(10, |_| true).await;
+        assert_eq!(entries.len(), 2);
+        assert!(entries.iter().all(|entry| entry.actor == "schedule:office"));
+
+        // A transition that was applied is not applied again
+        env.zerotier()
+            .update_member(NETWORK, "1111111111", &json!({ "authorized": false }))
+            .await
+            .unwrap();
+        app_state.access_schedules.check().await;
+        assert!(!authorized(&env, "1111111111"));
+    }
+
+    #[tokio::test]
+    async fn retries_transitions_that_failed_for_some_members() {
+        let env = TestEnv::new().await;
+        let app_state = setup(&env, vec![schedule(NEW_YEAR, Y2K)]).await;
+        let failure = format!("POST /controller/network/{}/member/1111111111", NETWORK);
+        env.controller
+            .state
+            .lock()
+            .unwrap()
+            .failures
+            .insert(failure.clone());
+
+        // The failing member does not stop the others
+        app_state.access_schedules.check().await;
+        assert!(!authorized(&env, "1111111111"));
+        assert!(authorized(&env, "2222222222"));
+
+        env.controller
+            .state
+            .lock()
+            .unwrap()
+            .failures
+            .remove(&failure);
+        app_state.access_schedules.check().await;
+        assert!(authorized(&env, "1111111111"));
+    }
+
+    #[tokio::test]
+    async fn reports_member_failures() {
+        let env = TestEnv::new().await;
+        let schedules = vec![schedule(NEW_YEAR, Y2K)];
+        let app_state = setup(&env, schedules.clone()).await;
+        env.controller
+            .state
+            .lock()
+            .unwrap()
+            .failures
+            .insert(format!(
+                "POST /controller/network/{}/member/2222222222",
+                NETWORK
+            ));
+
+        let parsed = ParsedSchedule::parse(&schedules[0]).unwrap();
+        let failures = app_state
+            .access_schedules
+            .apply_schedule(&parsed, true)
+            .await
+            .unwrap();
+        let failed: Vec<_> = failures.iter().map(|(id, _)| id.as_str()).collect();
+        assert_eq!(failed, ["2222222222"]);
+        assert!(authorized(&env, "1111111111"));
+    }
+
+    #[tokio::test]
+    async fn leaves_expired_members_deauthorized() {
+        let env = TestEnv::new().await;
+        let app_state = setup(&env, vec![schedule(NEW_YEAR, Y2K)]).await;
+        app_state
+            .metadata
+            .update(NETWORK, "1111111111", |metadata| {
+                metadata.expired_at = Some(Utc::now());
+                Ok(())
+            })
+            .await
+            .unwrap();
+
+        app_state.access_schedules.check().await;
+        assert!(!authorized(&env, "1111111111"));
+        assert!(authorized(&env, "2222222222"));
+
+        // Ending an override does not bring it back either
+        let schedules = &app_state.access_schedules;
+        let until = Utc::now() + chrono::TimeDelta::hours(1);
+        schedules
+            .set_override("admin", NETWORK, "1111111111", true, Some(until))
+            .await
+            .unwrap();
+        schedules
+            .set_override("admin", NETWORK, "1111111111", false, Some(until))
+            .await
+            .unwrap();
+        schedules
+            .clear_override(NETWORK, "1111111111")
+            .await
+            .unwrap();
+        assert!(!authorized(&env, "1111111111"));
+    }
+
+    #[tokio::test]
+    async fn pauses_while_read_only() {
+        let env = TestEnv::new().await;
+        let app_state = setup(&env, vec![schedule(NEW_YEAR, Y2K)]).await;
+        let set_read_only = |read_only| {
+            env.config.update(move |config| {
+                config.mode.read_only = read_only;
+                Ok(())
+            })
+        };
+
+        set_read_only(true).await.unwrap();
+        app_state.access_schedules.check().await;
+        assert!(!authorized(&env, "1111111111"));
+        assert!(!authorized(&env, "2222222222"));
+
+        // The transition is applied once the freeze ends
+        set_read_only(false).await.unwrap();
+        app_state.access_schedules.check().await;
+        assert!(authorized(&env, "1111111111"));
+        assert!(authorized(&env, "2222222222"));
+    }
+
+    #[tokio::test]
+    async fn overrides_take_precedence() {
+        let env = TestEnv::new().await;
+        let app_state = setup(&env, vec![schedule(NEW_YEAR, Y2K)]).await;
+        let schedules = &app_state.access_schedules;
+
+        let until = Utc::now() + chrono::TimeDelta::hours(1);
+        let schedule_override = schedules
+            .set_override("admin", NETWORK, "1111111111", false, Some(until))
+            .await
+            .unwrap();
+        assert_eq!(schedule_override.set_by, "admin");
+
+        schedules.check().await;
+        assert!(!authorized(&env, "1111111111"));
+        assert!(authorized(&env, "2222222222"));
+
+        // Clearing the override applies what the schedule calls for
+        schedules
+            .clear_override(NETWORK, "1111111111")
+            .await
+            .unwrap();
+        assert!(authorized(&env, "1111111111"));
+        assert!(matches!(
+            schedules.clear_override(NETWORK, "1111111111").await,
+            Err(AppError::NotFound(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn overrides_default_to_the_next_transition() {
+        let env = TestEnv::new().await;
+        let app_state = setup(&env, vec![schedule(NEW_YEAR, Y2K)]).await;
+        let schedules = &app_state.access_schedules;
+
+        let schedule_override = schedules
+            .set_override("admin", NETWORK, "2222222222", true, None)
+            .await
+            .unwrap();
+        assert_eq!(schedule_override.until, schedules.upcoming(None, 1)[0].at);
+
+        // No schedule covers this member, so the end must be given
+        let error = schedules
+            .set_override("admin", NETWORK, "3333333333", true, None)
+            .await
+            .unwrap_err();
+        assert!(matches!(error, AppError::ValidationError(_)));
+    }
+}
@@ -0,0 +1,227 @@
+use crate::error::Result;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::VecDeque;
+use std::path::PathBuf;
+use std::sync::Arc;
+use tokio::io::AsyncWriteExt;
+use tokio::sync::RwLock;
+
+/// Number of recent entries kept in memory for the API
+const RECENT_ENTRIES: usize = 1000;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuditEntry {
+    pub timestamp: DateTime<Utc>,
+    /// Who performed the action, e.g. `admin`, `api_key` or `policy:<name>`
+    pub actor: String,
+    /// Dotted action name, e.g. `member.authorize`
+    pub action: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub network_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub member_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Value::is_null")]
+    pub details: Value,
+}
+
+impl AuditEntry {
+    pub fn new(actor: impl Into<String>, action: impl Into<String>) -> Self {
+        Self {
+            timestamp: Utc::now(),
+            actor: actor.into(),
+            action: action.into(),
+            network_id: None,
+            member_id: None,
+            details: Value::Null,
+        }
+    }
+
+    pub fn network(mut self, network_id: impl Into<String>) -> Self {
+        self.network_id = Some(network_id.into());
+        self
+    }
+
+    pub fn member(mut self, network_id: impl Into<String>, member_id: impl Into<String>) -> Self {
+        self.network_id = Some(network_id.into());
+        self.member_id = Some(member_id.into());
+        self
+    }
+
+    pub fn details(mut self, details: Value) -> Self {
+        self.details = details;
+        self
+    }
+}
+
+/// Append-only audit log stored as JSON lines next to the config file
+#[derive(Clone)]
+pub struct AuditService {
+    path: Arc<PathBuf>,
+    recent: Arc<RwLock<VecDeque<AuditEntry>>>,
+}
+
+impl AuditService {
+    pub fn new(path: PathBuf) -> Result<Self> {
+        // Load the tail of an existing log so history survives restarts
+        let mut recent = VecDeque::with_capacity(RECENT_ENTRIES);
+        match std::fs::read_to_string(&path) {
+            Ok(content) => {
+                for line in content.lines() {
+                    if let Ok(entry) = serde_json::from_str(line) {
+                        if recent.len() == RECENT_ENTRIES {
+                            recent.pop_front();
+                        }
+                        recent.push_back(entry);
+                    }
+                }
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => return Err(e.into()),
+        }
+
+        Ok(Self {
+            path: Arc::new(path),
+            recent: Arc::new(RwLock::new(recent)),
+        })
+    }
+
+    /// Record an entry, failures to write are logged but not propagated
+    pub async fn record(&self, entry: AuditEntry) {
+        tracing::info!(
+            "Audit: {} {} {}{}",
+            entry.actor,
+            entry.action,
+            entry.network_id.as_deref().unwrap_or("-"),
+            entry
+                .member_id
+                .as_deref()
+                .map(|id| format!("/{}", id))
+                .unwrap_or_default()
+        );
+
+        let mut recent = self.recent.write().await;
+
+        match serde_json::to_string(&entry) {
+            Ok(mut line) => {
+                line.push('\n');
+                let result = async {
+                    let mut file = tokio::fs::OpenOptions::new()
+                        .create(true)
+                        .append(true)
+                        .open(self.path.as_ref())
+                        .await?;
+                    file.write_all(line.as_bytes()).await?;
+                    // Tokio finishes writes in the background, flush before the file is dropped
+                    file.flush().await
+                }
+                .await;
+
+                if let Err(e) = result {
+                    tracing::error!("Failed to write audit log: {}", e);
+                }
+            }
+            Err(e) => tracing::error!("Failed to serialize audit entry: {}", e),
+        }
+
+        if recent.len() == RECENT_ENTRIES {
+            recent.pop_front();
+        }
+        recent.push_back(entry);
+    }
+
+    /// Recent entries, newest first
+    pub async fn recent<F>(&self, limit: usize, filter: F) -> Vec<AuditEntry>
+    where
+        F: Fn(&AuditEntry) -> bool,
+    {
+        self.recent
+            .read()
+            .await
+            .iter()
+            .rev()
+            .filter(|entry| filter(entry))
+            .take(limit)
+            .cloned()
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn log_path() -> PathBuf {
+        std::env::temp_dir().join(format!("ztvrui-audit-{}.jsonl", uuid::Uuid::new_v4()))
+    }
+
+    #[tokio::test]
+    async fn records_and_reloads_entries() {
+        let path = log_path();
+        let audit = AuditService::new(path.clone()).unwrap();
+        audit
+            .record(AuditEntry::new("admin", "network.create").network("abcdef0123000001"))
+            .await;
+        audit
+            .record(
+                AuditEntry::new("policy:lab", "member.authorize")
+                    .member("abcdef0123000001", "1111111111")
+                    .details(json!({ "policy": "lab" })),
+            )
+            .await;
+
+        let content = std::fs::read_to_string(&path).unwrap();
+        assert_eq!(content.lines().count(), 2);
+
+        // A new service picks up the existing log, newest first
+        let reloaded = AuditService::new(path.clone()).unwrap();
+        let entries = reloaded.recent(10, |_| true).await;
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].action, "member.authorize");
+        assert_eq!(entries[0].member_id.as_deref(), Some("1111111111"));
+        assert_eq!(entries[0].details["policy"], "lab");
+        assert_eq!(entries[1].action, "network.create");
+        assert_eq!(entries[1].member_id, None);
+
+        std::fs::remove_file(&path).ok();
+    }
+
+    #[tokio::test]
+    async fn filters_and_limits_recent_entries() {
+        let path = log_path();
+        let audit = AuditService::new(path.clone()).unwrap();
+        for i in 0..5 {
+            audit
+                .record(AuditEntry::new("admin", format!("member.update.{}", i)))
+                .await;
+        }
+        audit
+            .record(AuditEntry::new("api_key", "network.delete"))
+            .await;
+
+        let entries = audit.recent(2, |entry| entry.actor == "admin").await;
+        let actions: Vec<_> = entries.iter().map(|entry| entry.action.as_str()).collect();
+        assert_eq!(actions, ["member.update.4", "member.update.3"]);
+
+        std::fs::remove_file(&path).ok();
+    }
+
+    #[tokio::test]
+    async fn skips_unreadable_lines() {
+        let path = log_path();
+        std::fs::write(
+            &path,
+            "not json\n{\"timestamp\":\"2024-01-01T00:00:00Z\",\"actor\":\"admin\",\"action\":\"login\"}\n",
+        )
+        .unwrap();
+
+        let audit = AuditService::new(path.clone()).unwrap();
+        let entries = audit.recent(10, |_| true).await;
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].action, "login");
+
+        std::fs::remove_file(&path).ok();
+    }
+}
@@ -12,6 +12,9 @@ pub struct Claims {
     pub username: String, // Username for convenience
 }
 
+/// Cookie mirroring the session token so page loads can be identified
+pub const SESSION_COOKIE: &str = "ztvrui_session";
+
 #[derive(Clone)]
 pub struct AuthService {
     encoding_key: Arc<EncodingKey>,
@@ -46,6 +49,24 @@ impl AuthService {
         Ok((token, exp.timestamp()))
     }
 
+    /// Build a `Set-Cookie` value carrying the session token
+    pub fn session_cookie(&self, token: &str) -> String {
+        format!(
+            "{}={}; Path=/; Max-Age={}; HttpOnly; SameSite=Strict",
+            SESSION_COOKIE,
+            token,
+            self.token_duration.num_seconds()
+        )
+    }
+
+    /// Build a `Set-Cookie` value removing the session cookie
+    pub fn clear_session_cookie(&self) -> String {
+        format!(
+            "{}=; Path=/; Max-Age=0; HttpOnly; SameSite=Strict",
+            SESSION_COOKIE
+        )
+    }
+
     pub fn validate_token(&self, token: &str) -> Result<Claims> {
         let validation = Validation::new(Algorithm::HS256);
 
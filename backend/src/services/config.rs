@@ -2,13 +2,15 @@ use crate::error::{AppError, Result};
 use crate::models::AppConfig;
 use arc_swap::ArcSwap;
 use bcrypt::{hash, verify, DEFAULT_COST};
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
-use tokio::fs;
+use tokio::{fs, sync::Mutex};
 
 #[derive(Clone)]
 pub struct ConfigService {
     config: Arc<ArcSwap<AppConfig>>,
     config_path: String,
+    write_lock: Arc<Mutex<()>>,
 }
 
 impl ConfigService {
@@ -22,6 +24,7 @@ impl ConfigService {
         Ok(Self {
             config: Arc::new(ArcSwap::new(Arc::new(config))),
             config_path,
+            write_lock: Arc::new(Mutex::new(())),
         })
     }
 
@@ -45,21 +48,48 @@ impl ConfigService {
             .any(|configured_key| configured_key == api_key)
     }
 
-    pub async fn update_user_info(&self, username: &str, password: &str) -> Result<()> {
+    /// Apply a change to the configuration, persist it and make it visible
+    pub async fn update<F>(&self, apply: F) -> Result<Arc<AppConfig>>
+    where
+        F: FnOnce(&mut AppConfig) -> Result<()>,
+    {
+        // Serialize writers so concurrent updates are not lost
+        let _guard = self.write_lock.lock().await;
         let mut config = (*self.get_config()).clone();
 
-        config.info.username = username.to_string();
-        config.info.password = hash(password, DEFAULT_COST).map_err(|e| {
-            AppError::InternalServerError(format!("Failed to hash password: {}", e))
-        })?;
+        apply(&mut config)?;
 
         let config_json = serde_json::to_string_pretty(&config)?;
         fs::write(&self.config_path, config_json).await?;
 
-        self.config.store(Arc::new(config));
+        let config = Arc::new(config);
+        self.config.store(config.clone());
+        Ok(config)
+    }
+
+    pub async fn update_user_info(&self, username: &str, password: &str) -> Result<()> {
+        let password = hash(password, DEFAULT_COST).map_err(|e| {
+            AppError::InternalServerError(format!("Failed to hash password: {}", e))
+        })?;
+
+        self.update(|config| {
+            config.info.username = username.to_string();
+            config.info.password = password;
+            Ok(())
+        })
+        .await?;
+
         Ok(())
     }
 
+    /// Path of a backend data file stored next to the config file
+    pub fn data_path(&self, file_name: &str) -> PathBuf {
+        Path::new(&self.config_path)
+            .parent()
+            .unwrap_or_else(|| Path::new("."))
+            .join(file_name)
+    }
+
     pub fn get_listen_address(&self) -> String {
         self.get_config().listen.clone()
     }
@@ -0,0 +1,869 @@
+use crate::models::{DnsConfig, MemberMetadata};
+use crate::services::monitor::ControllerSnapshot;
+use crate::services::{ConfigService, ControllerMonitor, MetadataService};
+use crate::utils::dns_label;
+use arc_swap::ArcSwap;
+use chrono::Utc;
+use serde_json::{Map, Value};
+use std::collections::{BTreeSet, HashMap};
+use std::net::IpAddr;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream, UdpSocket};
+
+const TYPE_A: u16 = 1;
+const TYPE_SOA: u16 = 6;
+const TYPE_PTR: u16 = 12;
+const TYPE_AAAA: u16 = 28;
+const TYPE_ANY: u16 = 255;
+const CLASS_IN: u16 = 1;
+const CLASS_ANY: u16 = 255;
+
+const RCODE_NO_ERROR: u16 = 0;
+const RCODE_FORMAT_ERROR: u16 = 1;
+const RCODE_NX_DOMAIN: u16 = 3;
+const RCODE_NOT_IMPLEMENTED: u16 = 4;
+const RCODE_REFUSED: u16 = 5;
+
+const FLAG_RESPONSE: u16 = 0x8000;
+const FLAG_AUTHORITATIVE: u16 = 0x0400;
+const FLAG_TRUNCATED: u16 = 0x0200;
+const FLAG_RECURSION_DESIRED: u16 = 0x0100;
+
+/// Largest UDP response without EDNS
+const MAX_UDP_SIZE: usize = 512;
+/// How long an idle TCP connection is kept open
+const TCP_IDLE_TIMEOUT: Duration = Duration::from_secs(10);
+
+fn normalize_domain(domain: &str) -> Option<String> {
+    let domain = domain.trim().trim_end_matches('.').to_lowercase();
+    (!domain.is_empty()).then_some(domain)
+}
+
+/// Name of the PTR record for an address, e.g. `4.3.2.1.in-addr.arpa`
+fn reverse_name(ip: IpAddr) -> String {
+    match ip {
+        IpAddr::V4(ipv4) => {
+            let [a, b, c, d] = ipv4.octets();
+            format!("{}.{}.{}.{}.in-addr.arpa", d, c, b, a)
+        }
+        IpAddr::V6(ipv6) => {
+            let mut name = String::with_capacity(72);
+            for byte in ipv6.octets().iter().rev() {
+                name.push_str(&format!("{:x}.{:x}.", byte & 0x0f, byte >> 4));
+            }
+            name.push_str("ip6.arpa");
+            name
+        }
+    }
+}
+
+/// The zone a network's members are served under: the network's own `dns.domain`,
+/// otherwise `<network>.<domain>` from the configuration
+fn network_zone(
+    network_id: &str,
+    network: &Map<String, Value>,
+    config: &DnsConfig,
+) -> Option<String> {
+    let own = network
+        .get("dns")
+        .and_then(|dns| dns.get("domain"))
+        .and_then(Value::as_str)
+        .and_then(normalize_domain);
+    if own.is_some() {
+        return own;
+    }
+
+    let parent = config.domain.as_deref().and_then(normalize_domain)?;
+    let name = network
+        .get("name")
+        .and_then(Value::as_str)
+        .and_then(dns_label)
+        .unwrap_or_else(|| network_id.to_lowercase());
+    Some(format!("{}.{}", name, parent))
+}
+
+/// Records of every served zone
+#[derive(Debug, Default)]
+struct DnsZones {
+    zones: BTreeSet<String>,
+    addresses: HashMap<String, Vec<IpAddr>>,
+    pointers: HashMap<String, String>,
+    serial: u32,
+    ttl: u32,
+}
+
+impl DnsZones {
+    fn build(
+        snapshot: &ControllerSnapshot,
+        metadata: &HashMap<String, HashMap<String, MemberMetadata>>,
+        config: &DnsConfig,
+    ) -> Self {
+        let mut zones = DnsZones {
+            serial: Utc::now().timestamp() as u32,
+            ttl: config.ttl,
+            ..Default::default()
+        };
+
+        for (network_id, network) in &snapshot.networks {
+            let Some(zone) = network_zone(network_id, &network.config, config) else {
+                continue;
+            };
+            let network_metadata = metadata.get(network_id);
+
+            for (member_id, member) in &network.members {
+                if !member.authorized() {
+                    continue;
+                }
+                let ips = member.addresses();
+
+                let hostname = network_metadata
+                    .and_then(|members| members.get(member_id))
+                    .and_then(|metadata| metadata.info.hostname())
+                    .and_then(dns_label)
+                    .or_else(|| dns_label(member.name()));
+                let mut names = vec![format!("zt-{}.{}", member_id.to_lowercase(), zone)];
+                if let Some(hostname) = hostname {
+                    names.insert(0, format!("{}.{}", hostname, zone));
+                }
+
+                for ip in &ips {
+                    zones
+                        .pointers
+                        .entry(reverse_name(*ip))
+                        .or_insert_with(|| names[0].clone());
+                }
+                for name in names {
+                    zones.addresses.entry(name).or_default().extend(&ips);
+                }
+            }
+
+            zones.zones.insert(zone);
+        }
+
+        zones
+    }
+
+    /// The served zone a name falls in, the most specific one if zones are nested
+    fn zone_of(&self, name: &str) -> Option<&str> {
+        self.zones
+            .iter()
+            .filter(|zone| {
+                name == zone.as_str()
+                    || name
+                        .strip_suffix(zone.as_str())
+                        .is_some_and(|rest| rest.ends_with('.'))
+            })
+            .max_by_key(|zone| zone.len())
+            .map(String::as_str)
+    }
+}
+
+fn write_name(buffer: &mut Vec<u8>, name: &str) {
+    for label in name.split('.').filter(|label| !label.is_empty()) {
+        buffer.push(label.len() as u8);
+        buffer.extend_from_slice(label.as_bytes());
+    }
+    buffer.push(0);
+}
+
+/// Read a possibly compressed name, returning it and the offset after it
+fn read_name(message: &[u8], mut offset: usize) -> Option<(String, usize)> {
+    let mut labels = Vec::new();
+    let mut end = None;
+    // Bound the number of compression pointers followed
+    for _ in 0..64 {
+        let length = *message.get(offset)? as usize;
+        match length {
+            0 => {
+                let name = labels.join(".").to_lowercase();
+                return Some((name, end.unwrap_or(offset + 1)));
+            }
+            _ if length & 0xc0 == 0xc0 => {
+                let pointer = ((length & 0x3f) << 8) | *message.get(offset + 1)? as usize;
+                end.get_or_insert(offset + 2);
+                offset = pointer;
+            }
+            _ if length <= 63 => {
+                let label = message.get(offset + 1..offset + 1 + length)?;
+                labels.push(String::from_utf8_lossy(label).into_owned());
+                offset += 1 + length;
+            }
+            _ => return None,
+        }
+    }
+    None
+}
+
+struct Record {
+    name: String,
+    record_type: u16,
+    data: Vec<u8>,
+}
+
+impl Record {
+    fn address(name: &str, ip: IpAddr) -> Self {
+        let (record_type, data) = match ip {
+            IpAddr::V4(ipv4) => (TYPE_A, ipv4.octets().to_vec()),
+            IpAddr::V6(ipv6) => (TYPE_AAAA, ipv6.octets().to_vec()),
+        };
+        Self {
+            name: name.to_string(),
+            record_type,
+            data,
+        }
+    }
+
+    fn pointer(name: &str, target: &str) -> Self {
+        let mut data = Vec::new();
+        write_name(&mut data, target);
+        Self {
+            name: name.to_string(),
+            record_type: TYPE_PTR,
+            data,
+        }
+    }
+
+    fn soa(zone: &str, zones: &DnsZones) -> Self {
+        let mut data = Vec::new();
+        write_name(&mut data, zone);
+        write_name(&mut data, &format!("hostmaster.{}", zone));
+        for value in [zones.serial, 3600, 600, 86400, zones.ttl] {
+            data.extend_from_slice(&value.to_be_bytes());
+        }
+        Self {
+            name: zone.to_string(),
+            record_type: TYPE_SOA,
+            data,
+        }
+    }
+
+    fn write(&self, buffer: &mut Vec<u8>, ttl: u32) {
+        write_name(buffer, &self.name);
+        buffer.extend_from_slice(&self.record_type.to_be_bytes());
+        buffer.extend_from_slice(&CLASS_IN.to_be_bytes());
+        buffer.extend_from_slice(&ttl.to_be_bytes());
+        buffer.extend_from_slice(&(self.data.len() as u16).to_be_bytes());
+        buffer.extend_from_slice(&self.data);
+    }
+}
+
+/// A parsed query and the answer to it
+struct Response<'a> {
+    id: [u8; 2],
+    flags: u16,
+    /// The question section as received, if it could be parsed
+    question: Option<&'a [u8]>,
+    answers: Vec<Record>,
+    authority: Vec<Record>,
+}
+
+impl Response<'_> {
+    fn encode(&self, ttl: u32, max_size: usize) -> Vec<u8> {
+        let mut message = self.encode_records(self.flags, true, ttl);
+        if message.len() > max_size {
+            // Let the client retry over TCP
+            message = self.encode_records(self.flags | FLAG_TRUNCATED, false, ttl);
+        }
+        message
+    }
+
+    fn encode_records(&self, flags: u16, records: bool, ttl: u32) -> Vec<u8> {
+        let (answers, authority) = if records {
+            (self.answers.as_slice(), self.authority.as_slice())
+        } else {
+            (&[][..], &[][..])
+        };
+
+        let mut message = Vec::with_capacity(MAX_UDP_SIZE);
+        message.extend_from_slice(&self.id);
+        message.extend_from_slice(&flags.to_be_bytes());
+        for count in [
+            usize::from(self.question.is_some()),
+            answers.len(),
+            authority.len(),
+            0,
+        ] {
+            message.extend_from_slice(&(count as u16).to_be_bytes());
+        }
+        if let Some(question) = self.question {
+            message.extend_from_slice(question);
+        }
+        for record in answers.iter().chain(authority) {
+            record.write(&mut message, ttl);
+        }
+        message
+    }
+}
+
+/// Authoritative DNS server for the members of every network with a zone
+#[derive(Clone)]
+pub struct DnsService {
+    config: ConfigService,
+    monitor: ControllerMonitor,
+    metadata: MetadataService,
+    zones: Arc<ArcSwap<DnsZones>>,
+}
+
+impl DnsService {
+    pub fn new(
+        config: ConfigService,
+        monitor: ControllerMonitor,
+        metadata: MetadataService,
+    ) -> Self {
+        Self {
+            config,
+            monitor,
+            metadata,
+            zones: Arc::new(ArcSwap::from_pointee(DnsZones::default())),
+        }
+    }
+
+    /// Start answering on the configured address, rebuilding the zones from every
+    /// controller snapshot
+    pub fn spawn(&self) {
+        let Some(listen) = self.config.get_config().dns.listen.clone() else {
+            return;
+        };
+        if self.config.get_config().monitor.poll_interval_secs == 0 {
+            tracing::warn!("DNS server enabled but the controller monitor is disabled, no members will be served");
+        }
+
+        let service = self.clone();
+        let mut receiver = self.monitor.subscribe();
+        tokio::spawn(async move {
+            while receiver.changed().await.is_ok() {
+                let snapshot = receiver.borrow_and_update().clone();
+                service.refresh(&snapshot).await;
+            }
+        });
+
+        let (service, udp_listen) = (self.clone(), listen.clone());
+        tokio::spawn(async move {
+            match UdpSocket::bind(&udp_listen).await {
+                Ok(socket) => service.serve_udp(socket).await,
+                Err(e) => tracing::error!("Failed to bind DNS server to UDP {}: {}", udp_listen, e),
+            }
+        });
+
+        let service = self.clone();
+        tokio::spawn(async move {
+            match TcpListener::bind(&listen).await {
+                Ok(listener) => {
+                    tracing::info!("DNS server listening on {} (UDP and TCP)", listen);
+                    service.serve_tcp(listener).await
+                }
+                Err(e) => tracing::error!("Failed to bind DNS server to TCP {}: {}", listen, e),
+            }
+        });
+    }
+
+    async fn refresh(&self, snapshot: &ControllerSnapshot) {
+        let mut metadata: HashMap<String, HashMap<String, MemberMetadata>> = HashMap::new();
+        for (network_id, member_id, member) in self.metadata.all().await {
+            metadata
+                .entry(network_id)
+                .or_default()
+                .insert(member_id, member);
+        }
+
+        let zones = DnsZones::build(snapshot, &metadata, &self.config.get_config().dns);
+        self.zones.store(Arc::new(zones));
+    }
+
+    async fn serve_udp(&self, socket: UdpSocket) {
+        let mut buffer = [0u8; 4096];
+        loop {
+            let (length, peer) = match socket.recv_from(&mut buffer).await {
+                Ok(received) => received,
+                Err(e) => {
+                    tracing::debug!("Failed to receive DNS query: {}", e);
+                    continue;
+                }
+            };
+            if let Some(response) = self.answer(&buffer[..length], MAX_UDP_SIZE) {
+                if let Err(e) = socket.send_to(&response, peer).await {
+                    tracing::debug!("Failed to send DNS response to {}: {}", peer, e);
+                }
+            }
+        }
+    }
+
+    async fn serve_tcp(&self, listener: TcpListener) {
+        loop {
+            let stream = match listener.accept().await {
+                Ok((stream, _)) => stream,
+                Err(e) => {
+                    tracing::debug!("Failed to accept DNS connection: {}", e);
+                    continue;
+                }
+            };
+
+            let service = self.clone();
+            tokio::spawn(async move {
+                if let Err(e) = service.serve_connection(stream).await {
+                    tracing::debug!("DNS connection closed: {}", e);
+                }
+            });
+        }
+    }
+
+    /// Answer length-prefixed queries until the client closes or goes idle
+    async fn serve_connection(&self, mut stream: TcpStream) -> std::io::Result<()> {
+        loop {
+            let length = match tokio::time::timeout(TCP_IDLE_TIMEOUT, stream.read_u16()).await {
+                Ok(Ok(length)) => length as usize,
+                Ok(Err(e)) if e.kind() == std::io::ErrorKind::UnexpectedEof => return Ok(()),
+                Ok(Err(e)) => return Err(e),
+                Err(_) => return Ok(()),
+            };
+
+            let mut query = vec![0u8; length];
+            tokio::time::timeout(TCP_IDLE_TIMEOUT, stream.read_exact(&mut query))
+                .await
+                .map_err(|_| std::io::ErrorKind::TimedOut)??;
+
+            if let Some(response) = self.answer(&query, u16::MAX as usize) {
+                stream.write_u16(response.len() as u16).await?;
+                stream.write_all(&response).await?;
+            }
+        }
+    }
+
+    /// Build the response to a query, `None` for messages that get no reply
+    fn answer(&self, query: &[u8], max_size: usize) -> Option<Vec<u8>> {
+        if query.len() < 12 {
+            return None;
+        }
+        let flags = u16::from_be_bytes([query[2], query[3]]);
+        if flags & FLAG_RESPONSE != 0 {
+            return None;
+        }
+
+        let zones = self.zones.load();
+        let mut response = Response {
+            id: [query[0], query[1]],
+            flags: FLAG_RESPONSE | (flags & 0x7800) | (flags & FLAG_RECURSION_DESIRED),
+            question: None,
+            answers: Vec::new(),
+            authority: Vec::new(),
+        };
+
+        let opcode = (flags >> 11) & 0x0f;
+        let question_count = u16::from_be_bytes([query[4], query[5]]);
+        let question = read_name(query, 12).and_then(|(name, end)| {
+            let fields = query.get(end..end + 4)?;
+            let record_type = u16::from_be_bytes([fields[0], fields[1]]);
+            let class = u16::from_be_bytes([fields[2], fields[3]]);
+            Some((name, record_type, class, &query[12..end + 4]))
+        });
+
+        let rcode = match question {
+            _ if opcode != 0 => RCODE_NOT_IMPLEMENTED,
+            Some((name, record_type, class, raw)) if question_count == 1 => {
+                response.question = Some(raw);
+                if class == CLASS_IN || class == CLASS_ANY {
+                    Self::resolve(&zones, &name, record_type, &mut response)
+                } else {
+                    RCODE_REFUSED
+                }
+            }
+            _ => RCODE_FORMAT_ERROR,
+        };
+        response.flags |= rcode;
+
+        Some(response.encode(zones.ttl, max_size))
+    }
+
+    fn resolve(zones: &DnsZones, name: &str, record_type: u16, response: &mut Response) -> u16 {
+        let wants = |wanted: u16| record_type == wanted || record_type == TYPE_ANY;
+
+        if let Some(target) = zones.pointers.get(name) {
+            response.flags |= FLAG_AUTHORITATIVE;
+            if wants(TYPE_PTR) {
+                response.answers.push(Record::pointer(name, target));
+            }
+            return RCODE_NO_ERROR;
+        }
+
+        let Some(zone) = zones.zone_of(name) else {
+            return RCODE_REFUSED;
+        };
+        response.flags |= FLAG_AUTHORITATIVE;
+
+        if name == zone && wants(TYPE_SOA) {
+            response.answers.push(Record::soa(zone, zones));
+        }
+        match zones.addresses.get(name) {
+            Some(ips) => {
+                response.answers.extend(
+                    ips.iter()
+                        .map(|ip| Record::address(name, *ip))
+                        .filter(|record| wants(record.record_type)),
+                );
+            }
+            None if name != zone => {
+                response.authority.push(Record::soa(zone, zones));
+                return RCODE_NX_DOMAIN;
+            }
+            None => {}
+        }
+
+        // Tell resolvers how long to cache the missing record type
+        if response.answers.is_empty() {
+            response.authority.push(Record::soa(zone, zones));
+        }
+        RCODE_NO_ERROR
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TestEnv;
+    use serde_json::json;
+
+    const LAB: &str = "abcdef0123000001";
+    const OFFICE: &str = "abcdef0123000002";
+
+    struct Answer {
+        flags: u16,
+        answers: Vec<(String, u16, Vec<u8>)>,
+        authority: usize,
+    }
+
+    impl Answer {
+        fn rcode(&self) -> u16 {
+            self.flags & 0x000f
+        }
+    }
+
+    fn query(name: &str, record_type: u16) -> Vec<u8> {
+        let mut query = vec![0x12, 0x34];
+        query.extend_from_slice(&FLAG_RECURSION_DESIRED.to_be_bytes());
+        query.extend_from_slice(&[0, 1, 0, 0, 0, 0, 0, 0]);
+        write_name(&mut query, name);
+        query.extend_from_slice(&record_type.to_be_bytes());
+        query.extend_from_slice(&CLASS_IN.to_be_bytes());
+        query
+    }
+
+    fn parse(message: &[u8]) -> Answer {
+        assert_eq!(message[..2], [0x12, 0x34]);
+        let count = |offset: usize| u16::from_be_bytes([message[offset], message[offset + 1]]);
+        let (questions, answer_count, authority) = (count(4), count(6), count(8));
+
+        let mut offset = 12;
+        for _ in 0..questions {
+            offset = read_name(message, offset).unwrap().1 + 4;
+        }
+        let mut answers = Vec::new();
+        for _ in 0..answer_count {
+            let (name, end) = read_name(message, offset).unwrap();
+            let record_type = count(end);
+            let length = count(end + 8) as usize;
+            answers.push((
+                name,
+                record_type,
+                message[end + 10..end + 10 + length].to_vec(),
+            ));
+            offset = end + 10 + length;
+        }
+
+        Answer {
+            flags: count(2),
+            answers,
+            authority: authority as usize,
+        }
+    }
+
+    fn ips(answer: &Answer) -> Vec<IpAddr> {
+        answer
+            .answers
+            .iter()
+            .map(|(_, _, data)| match data.len() {
+                4 => IpAddr::from(<[u8; 4]>::try_from(data.as_slice()).unwrap()),
+                _ => IpAddr::from(<[u8; 16]>::try_from(data.as_slice()).unwrap()),
+            })
+            .collect()
+    }
+
+    /// A DNS service serving a lab network under `zt.example` and an office network
+    /// with its own domain
+    async fn serving(env: &TestEnv) -> DnsService {
+        env.controller.add_network(
+            LAB,
+            json!({ "name": "Lab Net", "v6AssignMode": { "rfc4193": true } }),
+        );
+        env.controller.add_member(
+            LAB,
+            "1111111111",
+            json!({ "name": "Build Box", "authorized": true, "ipAssignments": ["10.0.0.1"] }),
+        );
+        env.controller.add_member(
+            LAB,
+            "2222222222",
+            json!({ "name": "intruder", "authorized": false, "ipAssignments": ["10.0.0.2"] }),
+        );
+        env.controller.add_network(
+            OFFICE,
+            json!({ "name": "office", "dns": { "domain": "Office.Example.", "servers": [] } }),
+        );
+        env.controller.add_member(
+            OFFICE,
+            "3333333333",
+            json!({ "name": "hp", "authorized": true, "ipAssignments": ["10.1.0.3"] }),
+        );
+
+        let app_state = env.app_state();
+        app_state
+            .metadata
+            .update(OFFICE, "3333333333", |metadata| {
+                metadata
+                    .info
+                    .labels
+                    .insert("hostname".to_string(), "Printer".to_string());
+                Ok(())
+            })
+            .await
+            .unwrap();
+        app_state.monitor.poll().await.unwrap();
+        app_state.dns.refresh(&app_state.monitor.snapshot()).await;
+        app_state.dns
+    }
+
+    async fn dns_env() -> TestEnv {
+        TestEnv::with_config(|config| {
+            config.dns.domain = Some("zt.example".to_string());
+            config.dns.ttl = 30;
+        })
+        .await
+    }
+
+    fn ask(dns: &DnsService, name: &str, record_type: u16) -> Answer {
+        parse(&dns.answer(&query(name, record_type), MAX_UDP_SIZE).unwrap())
+    }
+
+    #[test]
+    fn names_zones_and_reverse_records() {
+        let config = DnsConfig {
+            domain: Some(" ZT.example. ".to_string()),
+            ..Default::default()
+        };
+        let network = |value: Value| value.as_object().unwrap().clone();
+        assert_eq!(
+            network_zone(LAB, &network(json!({ "name": "Lab Net" })), &config).as_deref(),
+            Some("lab-net.zt.example")
+        );
+        assert_eq!(
+            network_zone("ABCDEF0123000001", &network(json!({ "name": "" })), &config).as_deref(),
+            Some("abcdef0123000001.zt.example")
+        );
+        let own = network(json!({ "dns": { "domain": "Corp.Example." } }));
+        assert_eq!(
+            network_zone(LAB, &own, &DnsConfig::default()).as_deref(),
+            Some("corp.example")
+        );
+        assert_eq!(
+            network_zone(LAB, &network(json!({})), &DnsConfig::default()),
+            None
+        );
+
+        assert_eq!(
+            reverse_name("10.1.2.3".parse().unwrap()),
+            "3.2.1.10.in-addr.arpa"
+        );
+        assert_eq!(
+            reverse_name("fd00::1".parse().unwrap()),
+            "1.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.d.f.ip6.arpa"
+        );
+    }
+
+    #[test]
+    fn reads_compressed_names() {
+        let mut message = vec![0u8; 12];
+        write_name(&mut message, "Host.Example");
+        // "www" followed by a pointer to the name at offset 12
+        message.extend_from_slice(&[3, b'w', b'w', b'w', 0xc0, 12]);
+        assert_eq!(
+            read_name(&message, 12),
+            Some(("host.example".to_string(), 26))
+        );
+        assert_eq!(
+            read_name(&message, 26),
+            Some(("www.host.example".to_string(), 32))
+        );
+
+        // Pointer loops and truncated labels are rejected
+        assert_eq!(read_name(&[0xc0, 0], 0), None);
+        assert_eq!(read_name(&[5, b'a'], 0), None);
+        assert_eq!(read_name(&[0x40], 0), None);
+    }
+
+    #[tokio::test]
+    async fn answers_address_queries() {
+        let env = dns_env().await;
+        let dns = serving(&env).await;
+        let rfc4193: IpAddr = "fdab:cdef:123:0:199:9311:1111:1111".parse().unwrap();
+
+        let answer = ask(&dns, "Build-Box.lab-net.zt.example", TYPE_A);
+        assert_eq!(answer.rcode(), RCODE_NO_ERROR);
+        assert_ne!(answer.flags & FLAG_AUTHORITATIVE, 0);
+        assert_ne!(answer.flags & FLAG_RECURSION_DESIRED, 0);
+        assert_eq!(answer.answers[0].0, "build-box.lab-net.zt.example");
+        assert_eq!(ips(&answer), ["10.0.0.1".parse::<IpAddr>().unwrap()]);
+
+        let answer = ask(&dns, "build-box.lab-net.zt.example", TYPE_AAAA);
+        assert_eq!(ips(&answer), [rfc4193]);
+        let answer = ask(&dns, "zt-1111111111.lab-net.zt.example", TYPE_ANY);
+        assert_eq!(
+            ips(&answer),
+            ["10.0.0.1".parse::<IpAddr>().unwrap(), rfc4193]
+        );
+
+        // Metadata hostnames win over member names, networks can bring their own domain
+        let answer = ask(&dns, "printer.office.example", TYPE_A);
+        assert_eq!(ips(&answer), ["10.1.0.3".parse::<IpAddr>().unwrap()]);
+        assert_eq!(
+            ask(&dns, "hp.office.example", TYPE_A).rcode(),
+            RCODE_NX_DOMAIN
+        );
+    }
+
+    #[tokio::test]
+    async fn answers_reverse_queries() {
+        let env = dns_env().await;
+        let dns = serving(&env).await;
+
+        let answer = ask(&dns, "1.0.0.10.in-addr.arpa", TYPE_PTR);
+        assert_eq!(answer.rcode(), RCODE_NO_ERROR);
+        let mut target = Vec::new();
+        write_name(&mut target, "build-box.lab-net.zt.example");
+        assert_eq!(
+            answer.answers,
+            [("1.0.0.10.in-addr.arpa".to_string(), TYPE_PTR, target)]
+        );
+
+        let v6 = reverse_name("fdab:cdef:123:0:199:9311:1111:1111".parse().unwrap());
+        assert_eq!(ask(&dns, &v6, TYPE_PTR).answers.len(), 1);
+        assert!(ask(&dns, &v6, TYPE_A).answers.is_empty());
+
+        // Unauthorized members are not served
+        assert_eq!(
+            ask(&dns, "2.0.0.10.in-addr.arpa", TYPE_PTR).rcode(),
+            RCODE_REFUSED
+        );
+    }
+
+    #[tokio::test]
+    async fn answers_misses_with_the_zone_soa() {
+        let env = dns_env().await;
+        let dns = serving(&env).await;
+
+        let missing = ask(&dns, "intruder.lab-net.zt.example", TYPE_A);
+        assert_eq!(missing.rcode(), RCODE_NX_DOMAIN);
+        assert!(missing.answers.is_empty());
+        assert_eq!(missing.authority, 1);
+
+        // The name exists without records of the type
+        let no_data = ask(&dns, "printer.office.example", TYPE_AAAA);
+        assert_eq!(no_data.rcode(), RCODE_NO_ERROR);
+        assert!(no_data.answers.is_empty());
+        assert_eq!(no_data.authority, 1);
+
+        let apex = ask(&dns, "lab-net.zt.example", TYPE_SOA);
+        assert_eq!(apex.rcode(), RCODE_NO_ERROR);
+        assert_eq!(apex.answers[0].1, TYPE_SOA);
+        let soa = &apex.answers[0].2;
+        assert_eq!(soa[soa.len() - 4..], 30u32.to_be_bytes());
+
+        // Names outside the served zones are refused, the parent domain included
+        let outside = ask(&dns, "example.com", TYPE_A);
+        assert_eq!(outside.rcode(), RCODE_REFUSED);
+        assert_eq!(outside.flags & FLAG_AUTHORITATIVE, 0);
+        assert_eq!(ask(&dns, "zt.example", TYPE_A).rcode(), RCODE_REFUSED);
+    }
+
+    #[tokio::test]
+    async fn rejects_malformed_queries() {
+        let env = dns_env().await;
+        let dns = serving(&env).await;
+        let rcode = |query: &[u8]| parse(&dns.answer(query, MAX_UDP_SIZE).unwrap()).rcode();
+
+        assert!(dns.answer(&[0x12, 0x34, 0, 0], MAX_UDP_SIZE).is_none());
+        let mut response = query("printer.office.example", TYPE_A);
+        response[2] |= 0x80;
+        assert!(dns.answer(&response, MAX_UDP_SIZE).is_none());
+
+        let mut notify = query("printer.office.example", TYPE_SOA);
+        notify[2] |= 4 << 3;
+        assert_eq!(rcode(&notify), RCODE_NOT_IMPLEMENTED);
+
+        let mut two_questions = query("printer.office.example", TYPE_A);
+        two_questions[5] = 2;
+        assert_eq!(rcode(&two_questions), RCODE_FORMAT_ERROR);
+        let truncated = query("printer.office.example", TYPE_A);
+        assert_eq!(rcode(&truncated[..truncated.len() - 2]), RCODE_FORMAT_ERROR);
+
+        let mut chaos = query("printer.office.example", TYPE_A);
+        let length = chaos.len();
+        chaos[length - 1] = 3;
+        assert_eq!(rcode(&chaos), RCODE_REFUSED);
+    }
+
+    #[tokio::test]
+    async fn truncates_large_udp_responses() {
+        let env = dns_env().await;
+        let assignments: Vec<String> = (1..=40).map(|n| format!("10.9.0.{}", n)).collect();
+        env.controller.add_network(LAB, json!({ "name": "big" }));
+        env.controller.add_member(
+            LAB,
+            "1111111111",
+            json!({ "name": "many", "authorized": true, "ipAssignments": assignments }),
+        );
+        let app_state = env.app_state();
+        app_state.monitor.poll().await.unwrap();
+        app_state.dns.refresh(&app_state.monitor.snapshot()).await;
+
+        let query = query("many.big.zt.example", TYPE_A);
+        let udp = parse(&app_state.dns.answer(&query, MAX_UDP_SIZE).unwrap());
+        assert_ne!(udp.flags & FLAG_TRUNCATED, 0);
+        assert!(udp.answers.is_empty());
+
+        let tcp = parse(&app_state.dns.answer(&query, u16::MAX as usize).unwrap());
+        assert_eq!(tcp.flags & FLAG_TRUNCATED, 0);
+        assert_eq!(tcp.answers.len(), 40);
+    }
+
+    #[tokio::test]
+    async fn serves_udp_and_tcp() {
+        let env = dns_env().await;
+        let dns = serving(&env).await;
+        let query = query("printer.office.example", TYPE_A);
+
+        let socket = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let address = socket.local_addr().unwrap();
+        let server = dns.clone();
+        tokio::spawn(async move { server.serve_udp(socket).await });
+        let client = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        client.send_to(&query, address).await.unwrap();
+        let mut buffer = [0u8; MAX_UDP_SIZE];
+        let length = client.recv(&mut buffer).await.unwrap();
+        assert_eq!(ips(&parse(&buffer[..length])).len(), 1);
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        tokio::spawn(async move { dns.serve_tcp(listener).await });
+        let mut stream = TcpStream::connect(address).await.unwrap();
+        // Several queries on one connection
+        for _ in 0..2 {
+            stream.write_u16(query.len() as u16).await.unwrap();
+            stream.write_all(&query).await.unwrap();
+            let length = stream.read_u16().await.unwrap() as usize;
+            let mut response = vec![0u8; length];
+            stream.read_exact(&mut response).await.unwrap();
+            assert_eq!(ips(&parse(&response)).len(), 1);
+        }
+    }
+}
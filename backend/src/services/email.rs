@@ -0,0 +1,494 @@
+use crate::models::{EmailTemplate, SmtpConfig, SmtpSecurity};
+use crate::services::events::{Event, EventKind};
+use crate::services::{ConfigService, EventService};
+use lettre::{
+    message::{header::ContentType, Mailbox},
+    transport::smtp::authentication::Credentials,
+    AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor,
+};
+use serde_json::Value;
+use std::sync::Arc;
+use tokio::sync::broadcast::error::RecvError;
+
+/// Events mailed when the SMTP config does not list any
+const DEFAULT_EVENTS: &[&str] = &[
+    "member_joined",
+    "member_expiring",
+    "drift_detected",
+    "ip_banned",
+    "login_from_new_ip",
+    "password_changed",
+];
+
+fn builtin_template(event_type: &str) -> EmailTemplate {
+    let (subject, body) = match event_type {
+        "member_joined" => (
+            "[ZTVRUI] {{member_id}} is waiting for approval on {{network_id}}",
+            "Node {{member_id}} asked to join network {{network_id}} at {{timestamp}}.\n\n\
+             Authorize or delete the member in ZTVRUI.",
+        ),
+        "member_expiring" => (
+            "[ZTVRUI] Access for {{member_id}} on {{network_id}} expires soon",
+            "Member {{member_id}} ({{name}}) on network {{network_id}} loses access at {{expires_at}}.\n\n\
+             Extend the expiry in ZTVRUI if it still needs access.",
+        ),
+        "drift_detected" => (
+            "[ZTVRUI] Controller drifted from the GitOps manifest",
+            "{{changes}} changes are needed to bring the controller back in line with the manifest:\n\n\
+             {{summary}}\n\n\
+             Review them with the plan endpoint or `backend plan`, then apply or update the manifest.",
+        ),
+        "ip_banned" => (
+            "[ZTVRUI] IP {{ip}} has been banned",
+            "{{ip}} was banned after {{failures}} failed login attempts.\n\n\
+             The ban expires at {{banned_until}}.",
+        ),
+        "login_from_new_ip" => (
+            "[ZTVRUI] New login for {{username}} from {{ip}}",
+            "{{username}} logged in from {{ip}} at {{timestamp}}, \
+             an address not seen for this account before.\n\n\
+             If this was not you, change the password immediately.",
+        ),
+        "password_changed" => (
+            "[ZTVRUI] Admin credentials changed",
+            "The admin username and password were changed at {{timestamp}}. \
+             The account is now named {{username}}.\n\n\
+             If you did not make this change, restore the config file from a backup.",
+        ),
+        _ => ("[ZTVRUI] {{type}}", "{{event}}"),
+    };
+
+    EmailTemplate {
+        subject: subject.to_string(),
+        body: body.to_string(),
+    }
+}
+
+/// Replace `{{field}}` placeholders with the event's fields.
+/// `{{event}}` expands to the whole event as pretty-printed JSON.
+fn render(template: &str, event: &Value) -> String {
+    let mut output = template.replace(
+        "{{event}}",
+        &serde_json::to_string_pretty(event).unwrap_or_default(),
+    );
+
+    if let Some(fields) = event.as_object() {
+        for (key, value) in fields {
+            let value = match value {
+                Value::String(text) => text.clone(),
+                // Lists of lines such as a drift summary read best one per line
+                Value::Array(items) if items.iter().all(Value::is_string) => items
+                    .iter()
+                    .filter_map(Value::as_str)
+                    .collect::<Vec<_>>()
+                    .join("\n"),
+                other => other.to_string(),
+            };
+            output = output.replace(&format!("{{{{{}}}}}", key), &value);
+        }
+    }
+
+    output
+}
+
+/// The subject and body mailed for an event, `None` when it is not mailed
+fn compose(smtp: &SmtpConfig, event: &Event) -> Option<(String, String)> {
+    let event_type = event.kind.name();
+    let subscribed = if smtp.events.is_empty() {
+        DEFAULT_EVENTS.contains(&event_type)
+    } else {
+        smtp.events.iter().any(|name| name == event_type)
+    };
+
+    // Members that join already authorized need no approval
+    let needs_attention = !matches!(
+        event.kind,
+        EventKind::MemberJoined {
+            authorized: true,
+            ..
+        }
+    );
+
+    if !subscribed || !needs_attention {
+        return None;
+    }
+
+    let template = smtp
+        .templates
+        .get(event_type)
+        .cloned()
+        .unwrap_or_else(|| builtin_template(event_type));
+    let fields = serde_json::to_value(event).unwrap_or_default();
+
+    Some((
+        render(&template.subject, &fields),
+        render(&template.body, &fields),
+    ))
+}
+
+/// Sends email notifications for selected events through SMTP
+#[derive(Clone)]
+pub struct EmailService {
+    config: ConfigService,
+    events: EventService,
+}
+
+impl EmailService {
+    pub fn new(config: ConfigService, events: EventService) -> Self {
+        Self { config, events }
+    }
+
+    /// Start sending notifications in the background
+    pub fn spawn(&self) {
+        let service = self.clone();
+        let mut receiver = self.events.subscribe();
+
+        tokio::spawn(async move {
+            loop {
+                match receiver.recv().await {
+                    Ok(event) => {
+                        let service = service.clone();
+                        tokio::spawn(async move { service.notify(event).await });
+                    }
+                    Err(RecvError::Lagged(skipped)) => {
+                        tracing::warn!("Email notifications lagged, skipped {} events", skipped);
+                    }
+                    Err(RecvError::Closed) => break,
+                }
+            }
+        });
+    }
+
+    async fn notify(&self, event: Arc<Event>) {
+        let config = self.config.get_config();
+        let Some(smtp) = config.smtp.as_ref() else {
+            return;
+        };
+        let Some((subject, body)) = compose(smtp, &event) else {
+            return;
+        };
+
+        match Self::send(smtp, &subject, body).await {
+            Ok(()) => tracing::info!("Sent email notification for event {}", event.id),
+            Err(e) => tracing::error!(
+                "Failed to send email notification for event {}: {}",
+                event.id,
+                e
+            ),
+        }
+    }
+
+    async fn send(
+        smtp: &SmtpConfig,
+        subject: &str,
+        body: String,
+    ) -> std::result::Result<(), String> {
+        let from: Mailbox = smtp
+            .from
+            .parse()
+            .map_err(|e| format!("Invalid from address: {}", e))?;
+
+        let mut builder = Message::builder()
+            .from(from)
+            .subject(subject)
+            .header(ContentType::TEXT_PLAIN);
+        for to in &smtp.to {
+            let to: Mailbox = to
+                .parse()
+                .map_err(|e| format!("Invalid recipient {}: {}", to, e))?;
+            builder = builder.to(to);
+        }
+        let message = builder.body(body).map_err(|e| e.to_string())?;
+
+        let mut transport = match smtp.security {
+            SmtpSecurity::Starttls => {
+                AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(&smtp.host)
+                    .map_err(|e| e.to_string())?
+            }
+            SmtpSecurity::Tls => AsyncSmtpTransport::<Tokio1Executor>::relay(&smtp.host)
+                .map_err(|e| e.to_string())?,
+            SmtpSecurity::None => {
+                AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(&smtp.host)
+            }
+        };
+
+        if let Some(port) = smtp.port {
+            transport = transport.port(port);
+        }
+        if let (Some(username), Some(password)) = (&smtp.username, &smtp.password) {
+            transport = transport.credentials(Credentials::new(username.clone(), password.clone()));
+        }
+
+        transport
+            .build()
+            .send(message)
+            .await
+            .map(|_| ())
+            .map_err(|e| e.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{TimeZone, Utc};
+    use serde_json::json;
+    use std::collections::HashMap;
+    use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+    use tokio::net::TcpListener;
+    use tokio::task::JoinHandle;
+
+    fn smtp() -> SmtpConfig {
+        SmtpConfig {
+            host: "smtp.example.com".to_string(),
+            port: None,
+            security: SmtpSecurity::Starttls,
+            username: None,
+            password: None,
+            from: "ztvrui@example.com".to_string(),
+            to: vec!["admin@example.com".to_string()],
+            events: Vec::new(),
+            templates: HashMap::new(),
+        }
+    }
+
+    fn event(kind: EventKind) -> Event {
+        Event {
+            id: 1,
+            timestamp: Utc.with_ymd_and_hms(2026, 1, 2, 3, 4, 5).unwrap(),
+            kind,
+        }
+    }
+
+    fn joined(authorized: bool) -> Event {
+        event(EventKind::MemberJoined {
+            network_id: "abcdef0123000001".to_string(),
+            member_id: "1111111111".to_string(),
+            name: "alpha".to_string(),
+            authorized,
+        })
+    }
+
+    #[test]
+    fn renders_placeholders() {
+        let fields = json!({ "ip": "192.0.2.1", "failures": 5, "nested": { "a": 1 } });
+
+        assert_eq!(
+            render("{{ip}} failed {{failures}} times {{missing}}", &fields),
+            "192.0.2.1 failed 5 times {{missing}}"
+        );
+        assert_eq!(render("{{nested}}", &fields), r#"{"a":1}"#);
+        assert!(render("{{event}}", &fields).contains("\"ip\": \"192.0.2.1\""));
+
+        let lines = json!({ "lines": ["a", "b"], "numbers": [1, 2] });
+        assert_eq!(render("{{lines}}", &lines), "a\nb");
+        assert_eq!(render("{{numbers}}", &lines), "[1,2]");
+    }
+
+    #[test]
+    fn mails_drift_one_change_per_line() {
+        let drift = event(EventKind::DriftDetected {
+            changes: 2,
+            summary: vec![
+                "create network office".to_string(),
+                "update member 1111111111 on lab (abcdef0123000001): name".to_string(),
+            ],
+        });
+
+        let (subject, body) = compose(&smtp(), &drift).unwrap();
+        assert_eq!(
+            subject,
+            "[ZTVRUI] Controller drifted from the GitOps manifest"
+        );
+        assert_eq!(
+            body,
+            "2 changes are needed to bring the controller back in line with the manifest:\n\n\
+             create network office\n\
+             update member 1111111111 on lab (abcdef0123000001): name\n\n\
+             Review them with the plan endpoint or `backend plan`, then apply or update the manifest."
+        );
+    }
+
+    #[test]
+    fn mails_join_requests_and_security_events() {
+        let smtp = smtp();
+
+        let (subject, body) = compose(&smtp, &joined(false)).unwrap();
+        assert_eq!(
+            subject,
+            "[ZTVRUI] 1111111111 is waiting for approval on abcdef0123000001"
+        );
+        assert_eq!(
+            body,
+            "Node 1111111111 asked to join network abcdef0123000001 at \
+             2026-01-02T03:04:05Z.\n\nAuthorize or delete the member in ZTVRUI."
+        );
+
+        // Members that are already authorized need no approval
+        assert!(compose(&smtp, &joined(true)).is_none());
+        // Routine events are not mailed by default
+        let online = event(EventKind::MemberOnline {
+            network_id: "abcdef0123000001".to_string(),
+            member_id: "1111111111".to_string(),
+            name: "alpha".to_string(),
+        });
+        assert!(compose(&smtp, &online).is_none());
+    }
+
+    #[test]
+    fn builtin_templates_fill_every_placeholder() {
+        let smtp = smtp();
+        let events = [
+            joined(false),
+            event(EventKind::IpBanned {
+                ip: "192.0.2.1".parse().unwrap(),
+                failures: 5,
+                banned_until: Utc.with_ymd_and_hms(2026, 1, 3, 3, 4, 5).unwrap(),
+            }),
+            event(EventKind::LoginFromNewIp {
+                username: "admin".to_string(),
+                ip: "192.0.2.1".parse().unwrap(),
+            }),
+            event(EventKind::PasswordChanged {
+                username: "root".to_string(),
+            }),
+        ];
+
+        for event in events {
+            let (subject, body) = compose(&smtp, &event).unwrap();
+            for text in [&subject, &body] {
+                assert!(!text.contains("{{"), "{}: {}", event.kind.name(), text);
+                assert!(!text.contains("  "), "{}: {}", event.kind.name(), text);
+            }
+        }
+    }
+
+    #[test]
+    fn honours_configured_events_and_templates() {
+        let mut smtp = smtp();
+        smtp.events = vec!["member_online".to_string()];
+        smtp.templates.insert(
+            "member_online".to_string(),
+            EmailTemplate {
+                subject: "{{name}} is up".to_string(),
+                body: "{{type}} on {{network_id}}".to_string(),
+            },
+        );
+
+        let online = event(EventKind::MemberOnline {
+            network_id: "abcdef0123000001".to_string(),
+            member_id: "1111111111".to_string(),
+            name: "alpha".to_string(),
+        });
+        assert_eq!(
+            compose(&smtp, &online),
+            Some((
+                "alpha is up".to_string(),
+                "member_online on abcdef0123000001".to_string()
+            ))
+        );
+        assert!(compose(&smtp, &joined(false)).is_none());
+    }
+
+    /// A local SMTP server that accepts one session and returns the lines the
+    /// client sent, message content included
+    async fn smtp_sink() -> (u16, JoinHandle<Vec<String>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+
+        let sink = tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            let (reader, mut writer) = stream.into_split();
+            let mut lines = BufReader::new(reader).lines();
+            let mut received = Vec::new();
+            let mut in_data = false;
+
+            writer.write_all(b"220 sink ESMTP\r\n").await.unwrap();
+            while let Ok(Some(line)) = lines.next_line().await {
+                let command = line.to_ascii_uppercase();
+                let reply: &[u8] = if in_data {
+                    in_data = line != ".";
+                    if in_data {
+                        received.push(line);
+                        continue;
+                    }
+                    b"250 queued\r\n"
+                } else if command.starts_with("EHLO") {
+                    b"250-sink\r\n250 AUTH PLAIN\r\n"
+                } else if command.starts_with("AUTH") {
+                    b"235 authenticated\r\n"
+                } else if command == "DATA" {
+                    in_data = true;
+                    b"354 go ahead\r\n"
+                } else if command == "QUIT" {
+                    b"221 bye\r\n"
+                } else {
+                    b"250 ok\r\n"
+                };
+                received.push(line);
+                if writer.write_all(reply).await.is_err() {
+                    break;
+                }
+            }
+            received
+        });
+
+        (port, sink)
+    }
+
+    #[tokio::test]
+    async fn sends_through_an_smtp_relay() {
+        let (port, sink) = smtp_sink().await;
+        let mut smtp = smtp();
+        smtp.host = "127.0.0.1".to_string();
+        smtp.port = Some(port);
+        smtp.security = SmtpSecurity::None;
+        smtp.username = Some("alice".to_string());
+        smtp.password = Some("s3cret".to_string());
+        smtp.to.push("Ops <ops@example.com>".to_string());
+
+        let (subject, body) = compose(&smtp, &joined(false)).unwrap();
+        EmailService::send(&smtp, &subject, body.clone())
+            .await
+            .unwrap();
+        let session = sink.await.unwrap();
+
+        assert!(session[0].starts_with("EHLO "));
+        assert_eq!(
+            session[1..6],
+            [
+                "AUTH PLAIN AGFsaWNlAHMzY3JldA==",
+                "MAIL FROM:<ztvrui@example.com>",
+                "RCPT TO:<admin@example.com>",
+                "RCPT TO:<ops@example.com>",
+                "DATA",
+            ]
+        );
+        assert_eq!(session[session.len() - 2..], [".", "QUIT"]);
+
+        let content = &session[6..session.len() - 2];
+        let blank = content.iter().position(String::is_empty).unwrap();
+        let headers = &content[..blank];
+        assert!(headers.contains(&format!("Subject: {}", subject)));
+        assert!(headers.contains(&"From: ztvrui@example.com".to_string()));
+        assert!(headers.contains(&"To: admin@example.com, Ops <ops@example.com>".to_string()));
+        // Undo quoted-printable soft line breaks
+        let sent = content[blank + 1..].join("\n").replace("=\n", "");
+        assert_eq!(sent, body);
+    }
+
+    #[tokio::test]
+    async fn requires_starttls_unless_disabled() {
+        let (port, sink) = smtp_sink().await;
+        let mut smtp = smtp();
+        smtp.host = "127.0.0.1".to_string();
+        smtp.port = Some(port);
+
+        // The sink does not offer STARTTLS, so nothing is sent in the clear
+        assert!(EmailService::send(&smtp, "subject", "body".to_string())
+            .await
+            .is_err());
+        let session = sink.await.unwrap();
+        assert!(!session.iter().any(|line| line.starts_with("MAIL")));
+    }
+}
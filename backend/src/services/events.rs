@@ -0,0 +1,177 @@
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::net::IpAddr;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use tokio::sync::broadcast;
+
+/// Number of events a slow subscriber may fall behind before missing some
+const EVENT_BUFFER: usize = 1024;
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum EventKind {
+    NetworkCreated {
+        network_id: String,
+        name: String,
+    },
+    NetworkChanged {
+        network_id: String,
+        name: String,
+        revision: u64,
+    },
+    NetworkDeleted {
+        network_id: String,
+    },
+    MemberJoined {
+        network_id: String,
+        member_id: String,
+        name: String,
+        authorized: bool,
+    },
+    MemberAuthorized {
+        network_id: String,
+        member_id: String,
+        name: String,
+    },
+    MemberDeauthorized {
+        network_id: String,
+        member_id: String,
+        name: String,
+    },
+    MemberOnline {
+        network_id: String,
+        member_id: String,
+        name: String,
+    },
+    MemberOffline {
+        network_id: String,
+        member_id: String,
+        name: String,
+    },
+    MemberDeleted {
+        network_id: String,
+        member_id: String,
+    },
+    MemberExpiring {
+        network_id: String,
+        member_id: String,
+        name: String,
+        expires_at: DateTime<Utc>,
+    },
+    MemberExpired {
+        network_id: String,
+        member_id: String,
+        name: String,
+        deleted: bool,
+    },
+    DriftDetected {
+        changes: usize,
+        summary: Vec<String>,
+    },
+    IpBanned {
+        ip: IpAddr,
+        failures: u32,
+        banned_until: DateTime<Utc>,
+    },
+    LoginFromNewIp {
+        username: String,
+        ip: IpAddr,
+    },
+    PasswordChanged {
+        username: String,
+    },
+}
+
+impl EventKind {
+    /// The network the event refers to
+    pub fn network_id(&self) -> Option<&str> {
+        match self {
+            EventKind::NetworkCreated { network_id, .. }
+            | EventKind::NetworkChanged { network_id, .. }
+            | EventKind::NetworkDeleted { network_id }
+            | EventKind::MemberJoined { network_id, .. }
+            | EventKind::MemberAuthorized { network_id, .. }
+            | EventKind::MemberDeauthorized { network_id, .. }
+            | EventKind::MemberOnline { network_id, .. }
+            | EventKind::MemberOffline { network_id, .. }
+            | EventKind::MemberDeleted { network_id, .. }
+            | EventKind::MemberExpiring { network_id, .. }
+            | EventKind::MemberExpired { network_id, .. } => Some(network_id),
+            EventKind::DriftDetected { .. }
+            | EventKind::IpBanned { .. }
+            | EventKind::LoginFromNewIp { .. }
+            | EventKind::PasswordChanged { .. } => None,
+        }
+    }
+
+    /// The event type as used in SSE event names and subscription filters
+    pub fn name(&self) -> &'static str {
+        match self {
+            EventKind::NetworkCreated { .. } => "network_created",
+            EventKind::NetworkChanged { .. } => "network_changed",
+            EventKind::NetworkDeleted { .. } => "network_deleted",
+            EventKind::MemberJoined { .. } => "member_joined",
+            EventKind::MemberAuthorized { .. } => "member_authorized",
+            EventKind::MemberDeauthorized { .. } => "member_deauthorized",
+            EventKind::MemberOnline { .. } => "member_online",
+            EventKind::MemberOffline { .. } => "member_offline",
+            EventKind::MemberDeleted { .. } => "member_deleted",
+            EventKind::MemberExpiring { .. } => "member_expiring",
+            EventKind::MemberExpired { .. } => "member_expired",
+            EventKind::DriftDetected { .. } => "drift_detected",
+            EventKind::IpBanned { .. } => "ip_banned",
+            EventKind::LoginFromNewIp { .. } => "login_from_new_ip",
+            EventKind::PasswordChanged { .. } => "password_changed",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Event {
+    pub id: u64,
+    pub timestamp: DateTime<Utc>,
+    #[serde(flatten)]
+    pub kind: EventKind,
+}
+
+/// In-process event bus fanning events out to every subscriber
+#[derive(Clone)]
+pub struct EventService {
+    sender: broadcast::Sender<Arc<Event>>,
+    next_id: Arc<AtomicU64>,
+}
+
+impl Default for EventService {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl EventService {
+    pub fn new() -> Self {
+        let (sender, _) = broadcast::channel(EVENT_BUFFER);
+
+        Self {
+            sender,
+            next_id: Arc::new(AtomicU64::new(1)),
+        }
+    }
+
+    pub fn publish(&self, kind: EventKind) {
+        let event = Event {
+            id: self.next_id.fetch_add(1, Ordering::Relaxed),
+            timestamp: Utc::now(),
+            kind,
+        };
+
+        tracing::debug!("Publishing event {}: {}", event.id, event.kind.name());
+
+        // Sending only fails when nobody is subscribed, which is fine
+        let _ = self.sender.send(Arc::new(event));
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<Arc<Event>> {
+        self.sender.subscribe()
+    }
+}
@@ -0,0 +1,408 @@
+use crate::error::{AppError, Result};
+use crate::models::MemberExpiry;
+use crate::services::audit::{AuditEntry, AuditService};
+use crate::services::events::{EventKind, EventService};
+use crate::services::{ConfigService, ControllerMonitor, MetadataService, ZeroTierService};
+use chrono::{TimeDelta, Utc};
+use serde_json::json;
+
+/// Deauthorizes or deletes members once their `expires_at` has passed
+#[derive(Clone)]
+pub struct ExpiryService {
+    config: ConfigService,
+    metadata: MetadataService,
+    zerotier: ZeroTierService,
+    monitor: ControllerMonitor,
+    events: EventService,
+    audit: AuditService,
+}
+
+impl ExpiryService {
+    pub fn new(
+        config: ConfigService,
+        metadata: MetadataService,
+        zerotier: ZeroTierService,
+        monitor: ControllerMonitor,
+        events: EventService,
+        audit: AuditService,
+    ) -> Self {
+        Self {
+            config,
+            metadata,
+            zerotier,
+            monitor,
+            events,
+            audit,
+        }
+    }
+
+    /// Start checking for expired members in the background, an interval of 0 disables expiry
+    pub fn spawn(&self, interval_secs: u64) {
+        if interval_secs == 0 {
+            tracing::info!("Member expiry disabled");
+            return;
+        }
+
+        let service = self.clone();
+
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(std::time::Duration::from_secs(interval_secs));
+            interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+
+            loop {
+                interval.tick().await;
+                service.check().await;
+            }
+        });
+    }
+
+    async fn check(&self) {
+        let now = Utc::now();
+        let config = self.config.get_config();
+        // A warning period too long to represent warns about every member
+        let warning = TimeDelta::try_seconds(config.expiry.warning_secs).unwrap_or(TimeDelta::MAX);
+
+        for (network_id, member_id, metadata) in self.metadata.all().await {
+            let Some(expiry) = metadata.expiry else {
+                continue;
+            };
+
+            if now >= expiry.expires_at {
+                // The controller is frozen, expire the member on a later check
+                if config.mode.read_only {
+                    continue;
+                }
+                if let Err(e) = self.expire(&network_id, &member_id, &expiry).await {
+                    // Try again on the next check
+                    tracing::warn!(
+                        "Failed to expire member {} on network {}: {}",
+                        member_id,
+                        network_id,
+                        e
+                    );
+                }
+            } else if !expiry.warned && expiry.expires_at - now <= warning {
+                self.warn(&network_id, &member_id, &expiry).await;
+            }
+        }
+    }
+
+    fn member_name(&self, network_id: &str, member_id: &str) -> String {
+        self.monitor
+            .snapshot()
+            .networks
+            .get(network_id)
+            .and_then(|network| network.members.get(member_id))
+            .map(|member| member.name().to_string())
+            .unwrap_or_default()
+    }
+
+    async fn warn(&self, network_id: &str, member_id: &str, expiry: &MemberExpiry) {
+        let marked = self
+            .metadata
+            .update(network_id, member_id, |metadata| {
+                // Skip if the expiry was changed since it was read
+                if let Some(stored) = metadata.expiry.as_mut() {
+                    if stored.expires_at == expiry.expires_at {
+                        stored.warned = true;
+                    }
+                }
+                Ok(())
+            })
+            .await;
+        if let Err(e) = marked {
+            tracing::error!("Failed to store expiry warning for {}: {}", member_id, e);
+            return;
+        }
+
+        self.events.publish(EventKind::MemberExpiring {
+            network_id: network_id.to_string(),
+            member_id: member_id.to_string(),
+            name: self.member_name(network_id, member_id),
+            expires_at: expiry.expires_at,
+        });
+    }
+
+    async fn expire(&self, network_id: &str, member_id: &str, expiry: &MemberExpiry) -> Result<()> {
+        let result = if expiry.delete {
+            self.zerotier.delete_member(network_id, member_id).await
+        } else {
+            self.zerotier
+                .update_member(network_id, member_id, &json!({ "authorized": false }))
+                .await
+                .map(|_| ())
+        };
+
+        // A member that is already gone needs nothing more
+        match result {
+            Ok(()) | Err(AppError::NotFound(_)) => {}
+            Err(e) => return Err(e),
+        }
+
+        if expiry.delete {
+            self.metadata.remove_member(network_id, member_id).await?;
+        } else {
+            self.metadata
+                .update(network_id, member_id, |metadata| {
+                    if metadata
+                        .expiry
+                        .as_ref()
+                        .is_some_and(|stored| stored.expires_at == expiry.expires_at)
+                    {
+                        metadata.expiry = None;
+                        metadata.expired_at = Some(Utc::now());
+                    }
+                    Ok(())
+                })
+                .await?;
+        }
+
+        let name = self.member_name(network_id, member_id);
+        self.audit
+            .record(
+                AuditEntry::new(
+                    "expiry",
+                    if expiry.delete {
+                        "member.delete"
+                    } else {
+                        "member.deauthorize"
+                    },
+                )
+                .member(network_id, member_id)
+                .details(json!({ "name": name, "expires_at": expiry.expires_at })),
+            )
+            .await;
+
+        self.events.publish(EventKind::MemberExpired {
+            network_id: network_id.to_string(),
+            member_id: member_id.to_string(),
+            name,
+            deleted: expiry.delete,
+        });
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::AppState;
+    use crate::test_support::TestEnv;
+    use chrono::DateTime;
+
+    const NETWORK: &str = "abcdef0123000001";
+
+    async fn set_expiry(
+        app_state: &AppState,
+        member_id: &str,
+        expires_at: DateTime<Utc>,
+        delete: bool,
+    ) {
+        app_state
+            .metadata
+            .update(NETWORK, member_id, |metadata| {
+                metadata.expiry = Some(MemberExpiry {
+                    expires_at,
+                    delete,
+                    warned: false,
+                });
+                Ok(())
+            })
+            .await
+            .unwrap();
+    }
+
+    async fn setup(env: &TestEnv) -> AppState {
+        env.controller.add_network(NETWORK, json!({}));
+        for member_id in ["1111111111", "2222222222", "3333333333"] {
+            env.controller
+                .add_member(NETWORK, member_id, json!({ "authorized": true }));
+        }
+        let app_state = env.app_state();
+        app_state.monitor.poll().await.unwrap();
+        app_state
+    }
+
+    #[tokio::test]
+    async fn deauthorizes_and_deletes_expired_members() {
+        let env = TestEnv::new().await;
+        let app_state = setup(&env).await;
+        let past = Utc::now() - TimeDelta::seconds(1);
+        set_expiry(&app_state, "1111111111", past, false).await;
+        set_expiry(&app_state, "2222222222", past, true).await;
+        set_expiry(
+            &app_state,
+            "3333333333",
+            Utc::now() + TimeDelta::days(30),
+            false,
+        )
+        .await;
+        let mut receiver = app_state.events.subscribe();
+
+        app_state.expiry.check().await;
+
+        let member = env.controller.member(NETWORK, "1111111111").unwrap();
+        assert_eq!(member["authorized"], false);
+        assert!(env.controller.member(NETWORK, "2222222222").is_none());
+        let member = env.controller.member(NETWORK, "3333333333").unwrap();
+        assert_eq!(member["authorized"], true);
+
+        // Only the member that has not expired keeps its expiry, the deauthorized
+        // one is marked as expired
+        let metadata = app_state.metadata.network(NETWORK).await;
+        assert!(metadata["1111111111"].expiry.is_none());
+        assert!(metadata["1111111111"].expired_at.is_some());
+        assert!(!metadata.contains_key("2222222222"));
+        assert!(metadata["3333333333"].expiry.is_some());
+
+        let mut expired = Vec::new();
+        while let Ok(event) = receiver.try_recv() {
+            if let EventKind::MemberExpired {
+                member_id, deleted, ..
+            } = &event.kind
+            {
+                expired.push((member_id.clone(), *deleted));
+            }
+        }
+        expired.sort();
+        assert_eq!(
+            expired,
+            [
+                ("1111111111".to_string(), false),
+                ("2222222222".to_string(), true)
+            ]
+        );
+
+        let mut actions: Vec<_> = app_state
+            .audit
+            .recent(10, |_| true)
+            .await
+            .into_iter()
+            .map(|entry| (entry.actor, entry.action))
+            .collect();
+        actions.sort();
+        assert_eq!(
+            actions,
+            [
+                ("expiry".to_string(), "member.deauthorize".to_string()),
+                ("expiry".to_string(), "member.delete".to_string())
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn waits_while_read_only() {
+        let env = TestEnv::new().await;
+        let app_state = setup(&env).await;
+        set_expiry(
+            &app_state,
+            "1111111111",
+            Utc::now() - TimeDelta::seconds(1),
+            true,
+        )
+        .await;
+        let set_read_only = |read_only| {
+            env.config.update(move |config| {
+                config.mode.read_only = read_only;
+                Ok(())
+            })
+        };
+
+        set_read_only(true).await.unwrap();
+        app_state.expiry.check().await;
+        assert!(env.controller.member(NETWORK, "1111111111").is_some());
+        let metadata = app_state.metadata.network(NETWORK).await;
+        assert!(metadata["1111111111"].expiry.is_some());
+
+        set_read_only(false).await.unwrap();
+        app_state.expiry.check().await;
+        assert!(env.controller.member(NETWORK, "1111111111").is_none());
+    }
+
+    #[tokio::test]
+    async fn warns_once_before_expiry() {
+        let env = TestEnv::new().await;
+        let app_state = setup(&env).await;
+        // The default warning period is a day
+        set_expiry(
+            &app_state,
+            "1111111111",
+            Utc::now() + TimeDelta::hours(1),
+            false,
+        )
+        .await;
+        set_expiry(
+            &app_state,
+            "2222222222",
+            Utc::now() + TimeDelta::days(2),
+            false,
+        )
+        .await;
+        let mut receiver = app_state.events.subscribe();
+
+        app_state.expiry.check().await;
+        app_state.expiry.check().await;
+
+        let mut warned = Vec::new();
+        while let Ok(event) = receiver.try_recv() {
+            if let EventKind::MemberExpiring { member_id, .. } = &event.kind {
+                warned.push(member_id.clone());
+            }
+        }
+        assert_eq!(warned, ["1111111111"]);
+
+        let metadata = app_state.metadata.network(NETWORK).await;
+        assert!(metadata["1111111111"].expiry.as_ref().unwrap().warned);
+        assert!(!metadata["2222222222"].expiry.as_ref().unwrap().warned);
+        let member = env.controller.member(NETWORK, "1111111111").unwrap();
+        assert_eq!(member["authorized"], true);
+    }
+
+    #[tokio::test]
+    async fn huge_warning_periods_warn_without_overflowing() {
+        let env = TestEnv::with_config(|config| config.expiry.warning_secs = i64::MAX).await;
+        let app_state = setup(&env).await;
+        set_expiry(
+            &app_state,
+            "1111111111",
+            Utc::now() + TimeDelta::days(3650),
+            false,
+        )
+        .await;
+
+        app_state.expiry.check().await;
+
+        let metadata = app_state.metadata.network(NETWORK).await;
+        assert!(metadata["1111111111"].expiry.as_ref().unwrap().warned);
+    }
+
+    #[tokio::test]
+    async fn keeps_the_expiry_when_the_controller_fails() {
+        let env = TestEnv::new().await;
+        let app_state = setup(&env).await;
+        set_expiry(
+            &app_state,
+            "1111111111",
+            Utc::now() - TimeDelta::seconds(1),
+            false,
+        )
+        .await;
+        env.controller
+            .state
+            .lock()
+            .unwrap()
+            .failures
+            .insert(format!(
+                "POST /controller/network/{}/member/1111111111",
+                NETWORK
+            ));
+
+        app_state.expiry.check().await;
+
+        // Retried on the next check
+        let metadata = app_state.metadata.network(NETWORK).await;
+        assert!(metadata["1111111111"].expiry.is_some());
+        assert!(app_state.audit.recent(10, |_| true).await.is_empty());
+    }
+}
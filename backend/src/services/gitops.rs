@@ -0,0 +1,976 @@
+use crate::error::{AppError, FieldError, Result};
+use crate::models::{
+    AppliedChange, ApplyResponse, ChangeAction, ChangeTarget, FieldChange, Manifest, Plan,
+    PlannedChange,
+};
+use crate::services::audit::{AuditEntry, AuditService};
+use crate::services::events::{EventKind, EventService};
+use crate::services::store::JsonStore;
+use crate::services::{ConfigService, MetadataService, ZeroTierService};
+use crate::utils::is_node_id;
+use crate::validation;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+/// Networks and members created or adopted by a manifest.
+/// Members are deleted when they disappear from the manifest, networks only
+/// if the manifest created them.
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct Ownership {
+    networks: BTreeMap<String, BTreeSet<String>>,
+    /// Networks the manifest created, adopted ones are released instead of deleted
+    #[serde(default)]
+    created: BTreeSet<String>,
+}
+
+/// Whether the controller's value already satisfies the desired one.
+/// Objects only need to match the keys the manifest sets, so defaults the
+/// controller fills in (e.g. `via: null` on routes) are not reported as drift.
+fn satisfies(desired: &Value, current: &Value) -> bool {
+    match (desired, current) {
+        (Value::Object(desired), Value::Object(current)) => desired
+            .iter()
+            .all(|(key, value)| satisfies(value, current.get(key).unwrap_or(&Value::Null))),
+        (Value::Array(desired), Value::Array(current)) => {
+            desired.len() == current.len()
+                && desired
+                    .iter()
+                    .zip(current)
+                    .all(|(desired, current)| satisfies(desired, current))
+        }
+        (Value::Number(desired), Value::Number(current)) => desired.as_f64() == current.as_f64(),
+        _ => desired == current,
+    }
+}
+
+/// The desired fields the controller does not satisfy yet
+fn diff(
+    desired: &Map<String, Value>,
+    current: &Map<String, Value>,
+) -> BTreeMap<String, FieldChange> {
+    desired
+        .iter()
+        .filter_map(|(key, value)| {
+            let from = current.get(key);
+            if satisfies(value, from.unwrap_or(&Value::Null)) {
+                return None;
+            }
+            Some((
+                key.clone(),
+                FieldChange {
+                    from: from.cloned(),
+                    to: value.clone(),
+                },
+            ))
+        })
+        .collect()
+}
+
+/// Every desired field, for objects that do not exist yet
+fn create_fields(desired: &Map<String, Value>) -> BTreeMap<String, FieldChange> {
+    desired
+        .iter()
+        .map(|(key, value)| {
+            (
+                key.clone(),
+                FieldChange {
+                    from: None,
+                    to: value.clone(),
+                },
+            )
+        })
+        .collect()
+}
+
+fn update_body(change: &PlannedChange) -> Value {
+    Value::Object(
+        change
+            .fields
+            .iter()
+            .map(|(key, field)| (key.clone(), field.to.clone()))
+            .collect(),
+    )
+}
+
+fn error_message(error: AppError) -> String {
+    match error {
+        AppError::ValidationError(fields) => fields
+            .iter()
+            .map(|field| format!("{}: {}", field.field, field.message))
+            .collect::<Vec<_>>()
+            .join("; "),
+        e => e.to_string(),
+    }
+}
+
+fn validate_manifest(manifest: &Manifest) -> Vec<FieldError> {
+    let mut errors = Vec::new();
+    let mut network_ids = HashSet::new();
+    let mut network_names = HashSet::new();
+
+    let check_fields = |prefix: &str, config: &Map<String, Value>, errors: &mut Vec<FieldError>| {
+        for key in config.keys() {
+            if validation::CONTROLLER_FIELDS.contains(&key.as_str()) {
+                errors.push(FieldError::new(
+                    format!("{}.{}", prefix, key),
+                    "is set by the controller",
+                ));
+            }
+        }
+    };
+
+    for (index, network) in manifest.networks.iter().enumerate() {
+        let prefix = format!("networks[{}]", index);
+
+        if network.name.trim().is_empty() {
+            errors.push(FieldError::new(
+                format!("{}.name", prefix),
+                "must not be empty",
+            ));
+        } else if !network_names.insert(network.name.as_str()) {
+            errors.push(FieldError::new(
+                format!("{}.name", prefix),
+                "is used by another network in the manifest",
+            ));
+        }
+
+        if let Some(id) = &network.id {
+            if id.len() != 16 || !id.chars().all(|c| c.is_ascii_hexdigit()) {
+                errors.push(FieldError::new(
+                    format!("{}.id", prefix),
+                    "must be a 16 hex digit network ID",
+                ));
+            } else if !network_ids.insert(id.to_lowercase()) {
+                errors.push(FieldError::new(
+                    format!("{}.id", prefix),
+                    "is listed more than once",
+                ));
+            }
+        }
+
+        check_fields(&prefix, &network.config, &mut errors);
+        errors.extend(
+            validation::validate_network(&network.config)
+                .into_iter()
+                .map(|error| FieldError::new(format!("{}.{}", prefix, error.field), error.message)),
+        );
+
+        let mut member_ids = HashSet::new();
+        for (member_index, member) in network.members.iter().enumerate() {
+            let prefix = format!("{}.members[{}]", prefix, member_index);
+
+            if !is_node_id(&member.id) {
+                errors.push(FieldError::new(
+                    format!("{}.id", prefix),
+                    "must be a 10 hex digit node ID",
+                ));
+            } else if !member_ids.insert(member.id.to_lowercase()) {
+                errors.push(FieldError::new(
+                    format!("{}.id", prefix),
+                    "is listed more than once",
+                ));
+            }
+
+            check_fields(&prefix, &member.config, &mut errors);
+        }
+    }
+
+    errors
+}
+
+/// Converges the controller to a declarative manifest of networks and members
+#[derive(Clone)]
+pub struct GitOpsService {
+    config: ConfigService,
+    ownership: JsonStore<Ownership>,
+    zerotier: ZeroTierService,
+    metadata: MetadataService,
+    events: EventService,
+    audit: AuditService,
+    apply_lock: Arc<tokio::sync::Mutex<()>>,
+    last_drift: Arc<Mutex<Vec<String>>>,
+}
+
+impl GitOpsService {
+    pub fn new(
+        config: ConfigService,
+        zerotier: ZeroTierService,
+        metadata: MetadataService,
+        events: EventService,
+        audit: AuditService,
+    ) -> Result<Self> {
+        Ok(Self {
+            ownership: JsonStore::open(config.data_path("gitops.json"))?,
+            config,
+            zerotier,
+            metadata,
+            events,
+            audit,
+            apply_lock: Arc::new(tokio::sync::Mutex::new(())),
+            last_drift: Arc::new(Mutex::new(Vec::new())),
+        })
+    }
+
+    /// Parse and validate a YAML or JSON manifest
+    pub fn parse_manifest(content: &str) -> Result<Manifest> {
+        let manifest: Manifest = serde_yaml::from_str(content).map_err(|e| {
+            AppError::ValidationError(vec![FieldError::new("manifest", e.to_string())])
+        })?;
+
+        let errors = validate_manifest(&manifest);
+        if errors.is_empty() {
+            Ok(manifest)
+        } else {
+            Err(AppError::ValidationError(errors))
+        }
+    }
+
+    pub async fn load_manifest(path: &Path) -> Result<Manifest> {
+        let content = tokio::fs::read_to_string(path).await.map_err(|e| {
+            AppError::ConfigError(format!("Failed to read {}: {}", path.display(), e))
+        })?;
+        Self::parse_manifest(&content)
+    }
+
+    /// Load the manifest configured as `gitops.manifest`, relative to the config file
+    pub async fn configured_manifest(&self) -> Result<Manifest> {
+        let Some(path) = self.config.get_config().gitops.manifest.clone() else {
+            return Err(AppError::ValidationError(vec![FieldError::new(
+                "manifest",
+                "no manifest given and gitops.manifest is not configured",
+            )]));
+        };
+
+        Self::load_manifest(&self.config.data_path(&path.to_string_lossy())).await
+    }
+
+    /// Start checking the controller against the configured manifest in the background
+    pub fn spawn(&self) {
+        let interval_secs = self.config.get_config().gitops.drift_check_interval_secs;
+        if interval_secs == 0 {
+            tracing::info!("GitOps drift detection disabled");
+            return;
+        }
+
+        let service = self.clone();
+
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(std::time::Duration::from_secs(interval_secs));
+            interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+
+            loop {
+                interval.tick().await;
+                service.check_drift().await;
+            }
+        });
+    }
+
+    /// Publish a `drift_detected` event when the pending changes differ from the last check
+    async fn check_drift(&self) {
+        let plan = match self.configured_manifest().await {
+            Ok(manifest) => self.plan(&manifest).await,
+            Err(e) => Err(e),
+        };
+        let plan = match plan {
+            Ok(plan) => plan,
+            Err(e) => {
+                tracing::warn!("GitOps drift check failed: {}", error_message(e));
+                return;
+            }
+        };
+
+        let summary: Vec<String> = plan.changes.iter().map(PlannedChange::describe).collect();
+        {
+            let mut last_drift = self.last_drift.lock().unwrap();
+            if *last_drift == summary {
+                return;
+            }
+            *last_drift = summary.clone();
+        }
+
+        if summary.is_empty() {
+            tracing::info!("Controller matches the GitOps manifest again");
+            return;
+        }
+
+        tracing::warn!(
+            "Controller drifted from the GitOps manifest, {} changes pending",
+            summary.len()
+        );
+        self.events.publish(EventKind::DriftDetected {
+            changes: summary.len(),
+            summary,
+        });
+    }
+
+    /// The changes needed to converge the controller to the manifest
+    pub async fn plan(&self, manifest: &Manifest) -> Result<Plan> {
+        Ok(self.reconcile(manifest).await?.0)
+    }
+
+    /// Diff the manifest against the controller.
+    /// Also returns the ID each manifest network resolved to, if it exists.
+    async fn reconcile(&self, manifest: &Manifest) -> Result<(Plan, Vec<Option<String>>)> {
+        let networks = self.zerotier.list_networks().await?;
+        let (owned, owned_created) = {
+            let ownership = self.ownership.read().await;
+            (ownership.networks.clone(), ownership.created.clone())
+        };
+
+        let network_id = |network: &Map<String, Value>| {
+            network
+                .get("id")
+                .and_then(Value::as_str)
+                .unwrap_or_default()
+                .to_string()
+        };
+        let by_id: HashMap<String, &Map<String, Value>> = networks
+            .iter()
+            .map(|network| (network_id(network), network))
+            .collect();
+
+        let mut plan = Plan::default();
+        let mut network_changes = Vec::new();
+        let mut member_changes = Vec::new();
+        let mut member_deletes = Vec::new();
+        let mut resolved = Vec::with_capacity(manifest.networks.len());
+
+        for (index, desired) in manifest.networks.iter().enumerate() {
+            let current = match &desired.id {
+                Some(id) => by_id.get(&id.to_lowercase()).copied(),
+                None => {
+                    let mut matches = networks.iter().filter(|network| {
+                        network.get("name").and_then(Value::as_str) == Some(&desired.name)
+                    });
+                    let found = matches.next();
+                    if matches.next().is_some() {
+                        return Err(AppError::ValidationError(vec![FieldError::new(
+                            format!("networks[{}].name", index),
+                            "matches several networks, set the network id",
+                        )]));
+                    }
+                    found
+                }
+            };
+
+            let mut config = desired.config.clone();
+            config.insert("name".to_string(), Value::String(desired.name.clone()));
+
+            let Some(current) = current else {
+                let network_id = desired.id.as_ref().map(|id| id.to_lowercase());
+                network_changes.push(PlannedChange {
+                    action: ChangeAction::Create,
+                    target: ChangeTarget::Network,
+                    network_id: network_id.clone(),
+                    network_name: desired.name.clone(),
+                    member_id: None,
+                    fields: create_fields(&config),
+                });
+                member_changes.extend(desired.members.iter().map(|member| PlannedChange {
+                    action: ChangeAction::Create,
+                    target: ChangeTarget::Member,
+                    network_id: network_id.clone(),
+                    network_name: desired.name.clone(),
+                    member_id: Some(member.id.to_lowercase()),
+                    fields: create_fields(&member.config),
+                }));
+                resolved.push(None);
+                continue;
+            };
+
+            let id = network_id(current);
+            let fields = diff(&config, current);
+            if !fields.is_empty() {
+                network_changes.push(PlannedChange {
+                    action: ChangeAction::Update,
+                    target: ChangeTarget::Network,
+                    network_id: Some(id.clone()),
+                    network_name: desired.name.clone(),
+                    member_id: None,
+                    fields,
+                });
+            }
+
+            let mut members: HashMap<String, Map<String, Value>> = self
+                .zerotier
+                .list_members(&id)
+                .await?
+                .into_iter()
+                .filter_map(|member| {
+                    let member_id = member.get("id")?.as_str()?.to_string();
+                    Some((member_id, member))
+                })
+                .collect();
+
+            for member in &desired.members {
+                let member_id = member.id.to_lowercase();
+                let change = match members.remove(&member_id) {
+                    None => Some((ChangeAction::Create, create_fields(&member.config))),
+                    Some(current) => {
+                        let fields = diff(&member.config, &current);
+                        (!fields.is_empty()).then_some((ChangeAction::Update, fields))
+                    }
+                };
+
+                if let Some((action, fields)) = change {
+                    member_changes.push(PlannedChange {
+                        action,
+                        target: ChangeTarget::Member,
+                        network_id: Some(id.clone()),
+                        network_name: desired.name.clone(),
+                        member_id: Some(member_id),
+                        fields,
+                    });
+                }
+            }
+
+            // Members left over are deleted only if the manifest owns them
+            let managed = owned.get(&id);
+            let mut leftover: Vec<String> = members.into_keys().collect();
+            leftover.sort();
+            for member_id in leftover {
+                if managed.is_some_and(|managed| managed.contains(&member_id)) {
+                    member_deletes.push(PlannedChange {
+                        action: ChangeAction::Delete,
+                        target: ChangeTarget::Member,
+                        network_id: Some(id.clone()),
+                        network_name: desired.name.clone(),
+                        member_id: Some(member_id),
+                        fields: BTreeMap::new(),
+                    });
+                } else {
+                    plan.unmanaged += 1;
+                }
+            }
+
+            resolved.push(Some(id));
+        }
+
+        let mut network_deletes = Vec::new();
+        for id in owned
+            .keys()
+            .filter(|id| !resolved.iter().flatten().any(|resolved| resolved == *id))
+        {
+            let Some(network) = by_id.get(id) else {
+                continue;
+            };
+            if !owned_created.contains(id) {
+                plan.released.push(id.clone());
+                continue;
+            }
+            network_deletes.push(PlannedChange {
+                action: ChangeAction::Delete,
+                target: ChangeTarget::Network,
+                network_id: Some(network_id(network)),
+                network_name: network
+                    .get("name")
+                    .and_then(Value::as_str)
+                    .unwrap_or_default()
+                    .to_string(),
+                member_id: None,
+                fields: BTreeMap::new(),
+            });
+        }
+
+        plan.changes = network_changes
+            .into_iter()
+            .chain(member_changes)
+            .chain(member_deletes)
+            .chain(network_deletes)
+            .collect();
+        let count = |action| {
+            plan.changes
+                .iter()
+                .filter(|change| change.action == action)
+                .count()
+        };
+        plan.create = count(ChangeAction::Create);
+        plan.update = count(ChangeAction::Update);
+        plan.delete = count(ChangeAction::Delete);
+
+        Ok((plan, resolved))
+    }
+
+    /// Perform the planned changes and record what the manifest now owns.
+    /// A failed change does not stop the remaining ones.
+    pub async fn apply(&self, actor: &str, manifest: &Manifest) -> Result<ApplyResponse> {
+        let _guard = self.apply_lock.lock().await;
+
+        let (plan, resolved) = self.reconcile(manifest).await?;
+        let mut created = HashMap::new();
+        let mut changes = Vec::with_capacity(plan.changes.len());
+        for change in plan.changes {
+            let error = self
+                .apply_change(actor, &change, &mut created)
+                .await
+                .err()
+                .map(error_message);
+            changes.push(AppliedChange { change, error });
+        }
+
+        self.record_ownership(manifest, &resolved, &created, &changes)
+            .await?;
+
+        let failed = changes
+            .iter()
+            .filter(|change| change.error.is_some())
+            .count();
+        Ok(ApplyResponse {
+            succeeded: changes.len() - failed,
+            failed,
+            changes,
+        })
+    }
+
+    async fn apply_change(
+        &self,
+        actor: &str,
+        change: &PlannedChange,
+        created: &mut HashMap<String, String>,
+    ) -> Result<()> {
+        let network_id = match &change.network_id {
+            Some(network_id) => network_id.clone(),
+            None if change.target == ChangeTarget::Network => String::new(),
+            None => created.get(&change.network_name).cloned().ok_or_else(|| {
+                AppError::NotFound(format!("network {} was not created", change.network_name))
+            })?,
+        };
+        let body = update_body(change);
+
+        let entry = match (change.target, change.action) {
+            (ChangeTarget::Network, ChangeAction::Create) => {
+                let network = if network_id.is_empty() {
+                    self.zerotier.create_network(&body).await?
+                } else {
+                    self.zerotier
+                        .post_json(&format!("/controller/network/{}", network_id), &body)
+                        .await?
+                };
+                let network_id = network
+                    .get("id")
+                    .and_then(Value::as_str)
+                    .ok_or_else(|| {
+                        AppError::ZeroTierError("created network has no id".to_string())
+                    })?
+                    .to_string();
+                created.insert(change.network_name.clone(), network_id.clone());
+                AuditEntry::new(actor, "network.create").network(network_id)
+            }
+            (ChangeTarget::Network, ChangeAction::Update) => {
+                self.zerotier
+                    .post_json(&format!("/controller/network/{}", network_id), &body)
+                    .await?;
+                AuditEntry::new(actor, "network.update").network(&network_id)
+            }
+            (ChangeTarget::Network, ChangeAction::Delete) => {
+                match self.zerotier.delete_network(&network_id).await {
+                    Ok(()) | Err(AppError::NotFound(_)) => {}
+                    Err(e) => return Err(e),
+                }
+                self.metadata.remove_network(&network_id).await?;
+                AuditEntry::new(actor, "network.delete").network(&network_id)
+            }
+            (ChangeTarget::Member, action) => {
+                let member_id = change.member_id.as_deref().unwrap_or_default();
+                if action == ChangeAction::Delete {
+                    match self.zerotier.delete_member(&network_id, member_id).await {
+                        Ok(()) | Err(AppError::NotFound(_)) => {}
+                        Err(e) => return Err(e),
+                    }
+                    self.metadata.remove_member(&network_id, member_id).await?;
+                } else {
+                    // Posting to a member that does not exist yet creates it
+                    self.zerotier
+                        .update_member(&network_id, member_id, &body)
+                        .await?;
+                }
+
+                let name = match action {
+                    ChangeAction::Create => "member.create",
+                    ChangeAction::Update => "member.update",
+                    ChangeAction::Delete => "member.delete",
+                };
+                AuditEntry::new(actor, name).member(&network_id, member_id)
+            }
+        };
+
+        self.audit
+            .record(entry.details(json!({ "gitops": true, "update": body })))
+            .await;
+
+        Ok(())
+    }
+
+    /// The manifest owns every network and member it lists, plus anything
+    /// it failed to delete so that the delete is retried on the next apply
+    async fn record_ownership(
+        &self,
+        manifest: &Manifest,
+        resolved: &[Option<String>],
+        created: &HashMap<String, String>,
+        changes: &[AppliedChange],
+    ) -> Result<()> {
+        self.ownership
+            .update(|ownership| {
+                let mut networks: BTreeMap<String, BTreeSet<String>> = BTreeMap::new();
+
+                for (network, network_id) in manifest.networks.iter().zip(resolved) {
+                    let Some(network_id) = network_id.as_ref().or(created.get(&network.name))
+                    else {
+                        continue;
+                    };
+                    networks.entry(network_id.clone()).or_default().extend(
+                        network
+                            .members
+                            .iter()
+                            .map(|member| member.id.to_lowercase()),
+                    );
+                }
+
+                for applied in changes {
+                    let change = &applied.change;
+                    if applied.error.is_none() || change.action != ChangeAction::Delete {
+                        continue;
+                    }
+                    let Some(network_id) = &change.network_id else {
+                        continue;
+                    };
+                    match &change.member_id {
+                        Some(member_id) => {
+                            networks
+                                .entry(network_id.clone())
+                                .or_default()
+                                .insert(member_id.clone());
+                        }
+                        None => {
+                            let members = ownership
+                                .networks
+                                .get(network_id)
+                                .cloned()
+                                .unwrap_or_default();
+                            networks.insert(network_id.clone(), members);
+                        }
+                    }
+                }
+
+                // Networks created before stay created while the manifest owns them
+                ownership.created = ownership
+                    .created
+                    .iter()
+                    .chain(created.values())
+                    .filter(|network_id| networks.contains_key(*network_id))
+                    .cloned()
+                    .collect();
+                ownership.networks = networks;
+                Ok(())
+            })
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::ChangeAction::{Create, Delete, Update};
+    use crate::test_support::TestEnv;
+
+    /// A network that exists before any manifest is applied
+    const ADOPTED: &str = "abcdef0123aaaaaa";
+
+    fn manifest(yaml: &str) -> Manifest {
+        GitOpsService::parse_manifest(yaml).unwrap()
+    }
+
+    fn actions(plan: &Plan) -> Vec<(ChangeAction, String)> {
+        plan.changes
+            .iter()
+            .map(|change| (change.action, change.describe()))
+            .collect()
+    }
+
+    fn invalid_fields(yaml: &str) -> Vec<String> {
+        match GitOpsService::parse_manifest(yaml) {
+            Err(AppError::ValidationError(fields)) => {
+                fields.into_iter().map(|field| field.field).collect()
+            }
+            other => panic!("expected a validation error, got {:?}", other.map(|_| ())),
+        }
+    }
+
+    #[test]
+    fn compares_only_the_fields_the_manifest_sets() {
+        let desired = json!({ "routes": [{ "target": "10.0.0.0/24" }], "mtu": 2800 });
+        let current = json!({ "routes": [{ "target": "10.0.0.0/24", "via": null }], "mtu": 2800.0, "name": "x" });
+        assert!(satisfies(&desired, &current));
+
+        let longer =
+            json!({ "routes": [{ "target": "10.0.0.0/24" }, { "target": "10.1.0.0/24" }] });
+        assert!(!satisfies(&longer, &current));
+
+        let desired = json!({ "private": true, "mtu": 2800 });
+        let changes = diff(
+            desired.as_object().unwrap(),
+            json!({ "private": false, "mtu": 2800 })
+                .as_object()
+                .unwrap(),
+        );
+        assert_eq!(changes.keys().collect::<Vec<_>>(), ["private"]);
+        assert_eq!(changes["private"].from, Some(json!(false)));
+    }
+
+    #[test]
+    fn validates_manifests() {
+        assert_eq!(
+            invalid_fields(
+                "networks:\n\
+                 - name: lab\n  id: xyz\n  revision: 3\n\
+                 - name: lab\n  members:\n  - id: nope\n  - id: 1111111111\n  - id: 1111111111\n"
+            ),
+            [
+                "networks[0].id",
+                "networks[0].revision",
+                "networks[1].name",
+                "networks[1].members[0].id",
+                "networks[1].members[2].id",
+            ]
+        );
+        assert_eq!(
+            invalid_fields("networks: [{ name: ' ' }]"),
+            ["networks[0].name"]
+        );
+        assert_eq!(invalid_fields("networks: {"), ["manifest"]);
+        // JSON is YAML too
+        assert_eq!(
+            GitOpsService::parse_manifest(r#"{"networks": [{"name": "lab"}]}"#)
+                .unwrap()
+                .networks[0]
+                .name,
+            "lab"
+        );
+    }
+
+    #[tokio::test]
+    async fn creates_networks_and_members() {
+        let env = TestEnv::new().await;
+        let gitops = env.app_state().gitops;
+        let manifest = manifest(
+            "networks:\n\
+             - name: office\n  private: true\n  members:\n  - id: 1111111111\n    authorized: true\n",
+        );
+
+        let plan = gitops.plan(&manifest).await.unwrap();
+        assert_eq!(
+            actions(&plan),
+            [
+                (Create, "create network office".to_string()),
+                (Create, "create member 1111111111 on office".to_string()),
+            ]
+        );
+
+        let applied = gitops.apply("admin", &manifest).await.unwrap();
+        assert_eq!((applied.succeeded, applied.failed), (2, 0));
+        let network_id = "abcdef0123000001";
+        let state = env.controller.state.lock().unwrap().networks[network_id].clone();
+        assert_eq!(state["name"], "office");
+        assert_eq!(state["private"], true);
+        assert_eq!(
+            env.controller.member(network_id, "1111111111").unwrap()["authorized"],
+            true
+        );
+
+        // Applying again changes nothing
+        assert!(gitops.plan(&manifest).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn updates_drifted_fields_and_leaves_unmanaged_members() {
+        let env = TestEnv::new().await;
+        env.controller
+            .add_network(ADOPTED, json!({ "name": "lab", "private": false }));
+        env.controller
+            .add_member(ADOPTED, "1111111111", json!({ "name": "old" }));
+        env.controller.add_member(ADOPTED, "2222222222", json!({}));
+        let gitops = env.app_state().gitops;
+
+        let manifest = manifest(
+            "networks:\n\
+             - name: lab\n  private: true\n  members:\n  - id: 1111111111\n    name: laptop\n",
+        );
+        let plan = gitops.plan(&manifest).await.unwrap();
+        assert_eq!(
+            actions(&plan),
+            [
+                (Update, format!("update network lab ({}): private", ADOPTED)),
+                (
+                    Update,
+                    format!("update member 1111111111 on lab ({}): name", ADOPTED)
+                ),
+            ]
+        );
+        assert_eq!(plan.unmanaged, 1);
+
+        gitops.apply("admin", &manifest).await.unwrap();
+        assert_eq!(
+            env.controller.member(ADOPTED, "1111111111").unwrap()["name"],
+            "laptop"
+        );
+        assert!(env.controller.member(ADOPTED, "2222222222").is_some());
+    }
+
+    #[tokio::test]
+    async fn deletes_members_removed_from_the_manifest() {
+        let env = TestEnv::new().await;
+        env.controller
+            .add_network(ADOPTED, json!({ "name": "lab" }));
+        let gitops = env.app_state().gitops;
+
+        let with_members = manifest(
+            "networks:\n- name: lab\n  members:\n  - id: 1111111111\n  - id: 2222222222\n",
+        );
+        gitops.apply("admin", &with_members).await.unwrap();
+
+        let without = manifest("networks:\n- name: lab\n  members:\n  - id: 1111111111\n");
+        let plan = gitops.plan(&without).await.unwrap();
+        assert_eq!(
+            actions(&plan),
+            [(
+                Delete,
+                format!("delete member 2222222222 on lab ({})", ADOPTED)
+            )]
+        );
+        gitops.apply("admin", &without).await.unwrap();
+        assert!(env.controller.member(ADOPTED, "2222222222").is_none());
+        assert!(env.controller.member(ADOPTED, "1111111111").is_some());
+    }
+
+    #[tokio::test]
+    async fn releases_adopted_networks_instead_of_deleting_them() {
+        let env = TestEnv::new().await;
+        env.controller
+            .add_network(ADOPTED, json!({ "name": "lab" }));
+        let gitops = env.app_state().gitops;
+
+        gitops
+            .apply("admin", &manifest("networks:\n- name: lab\n"))
+            .await
+            .unwrap();
+
+        let empty = manifest("networks: []");
+        let plan = gitops.plan(&empty).await.unwrap();
+        assert!(plan.is_empty());
+        assert_eq!(plan.released, [ADOPTED]);
+
+        gitops.apply("admin", &empty).await.unwrap();
+        assert!(env
+            .controller
+            .state
+            .lock()
+            .unwrap()
+            .networks
+            .contains_key(ADOPTED));
+        // Once released the network is no longer managed at all
+        assert!(gitops.plan(&empty).await.unwrap().released.is_empty());
+    }
+
+    #[tokio::test]
+    async fn deletes_networks_it_created() {
+        let env = TestEnv::new().await;
+        env.controller
+            .add_network(ADOPTED, json!({ "name": "lab" }));
+        let gitops = env.app_state().gitops;
+
+        gitops
+            .apply("admin", &manifest("networks:\n- name: lab\n- name: temp\n"))
+            .await
+            .unwrap();
+
+        let empty = manifest("networks: []");
+        let plan = gitops.plan(&empty).await.unwrap();
+        assert_eq!(
+            actions(&plan),
+            [(Delete, "delete network temp (abcdef0123000001)".to_string())]
+        );
+        assert_eq!(plan.released, [ADOPTED]);
+
+        // A failed delete is retried on the next apply
+        let failure = "DELETE /controller/network/abcdef0123000001".to_string();
+        env.controller
+            .state
+            .lock()
+            .unwrap()
+            .failures
+            .insert(failure.clone());
+        let applied = gitops.apply("admin", &empty).await.unwrap();
+        assert_eq!(applied.failed, 1);
+
+        env.controller
+            .state
+            .lock()
+            .unwrap()
+            .failures
+            .remove(&failure);
+        let applied = gitops.apply("admin", &empty).await.unwrap();
+        assert_eq!(applied.succeeded, 1);
+        let networks = env.controller.state.lock().unwrap().networks.clone();
+        assert!(!networks.contains_key("abcdef0123000001"));
+        assert!(networks.contains_key(ADOPTED));
+    }
+
+    #[tokio::test]
+    async fn refuses_ambiguous_names() {
+        let env = TestEnv::new().await;
+        env.controller
+            .add_network(ADOPTED, json!({ "name": "lab" }));
+        env.controller
+            .add_network("abcdef0123bbbbbb", json!({ "name": "lab" }));
+        let gitops = env.app_state().gitops;
+
+        let error = gitops
+            .plan(&manifest("networks:\n- name: lab\n"))
+            .await
+            .unwrap_err();
+        assert!(matches!(error, AppError::ValidationError(_)));
+
+        let plan = gitops
+            .plan(&manifest(&format!(
+                "networks:\n- name: lab\n  id: {}\n",
+                ADOPTED
+            )))
+            .await
+            .unwrap();
+        assert!(plan.is_empty());
+    }
+
+    #[tokio::test]
+    async fn publishes_drift_once_per_change() {
+        let env = TestEnv::with_config(|config| {
+            config.gitops.manifest = Some("manifest.yaml".into());
+        })
+        .await;
+        std::fs::write(env.dir.join("manifest.yaml"), "networks:\n- name: lab\n").unwrap();
+        let app_state = env.app_state();
+        let mut receiver = app_state.events.subscribe();
+
+        app_state.gitops.check_drift().await;
+        app_state.gitops.check_drift().await;
+        env.controller
+            .add_network(ADOPTED, json!({ "name": "lab" }));
+        app_state.gitops.check_drift().await;
+
+        let Ok(event) = receiver.try_recv() else {
+            panic!("expected a drift event");
+        };
+        match &event.kind {
+            EventKind::DriftDetected { changes, summary } => {
+                assert_eq!(*changes, 1);
+                assert_eq!(summary, &["create network lab"]);
+            }
+            other => panic!("unexpected event {:?}", other),
+        }
+        // Back in line with the manifest, nothing more to report
+        assert!(receiver.try_recv().is_err());
+    }
+}
@@ -0,0 +1,720 @@
+use crate::error::{AppError, FieldError, Result};
+use crate::models::{
+    InventoryFormat, InventoryQuery, IpFamily, MemberMetadata, NameSource, NetworkMember,
+};
+use crate::services::{MetadataService, ZeroTierService};
+use crate::utils::{dns_label, glob_match};
+use serde_json::{json, Map, Value};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt::Write;
+use std::net::{IpAddr, SocketAddr};
+
+const DEFAULT_PROMETHEUS_PORT: u16 = 9100;
+
+/// An authorized member selected for an inventory
+struct Host {
+    /// Host name, qualified with the requested domain
+    name: String,
+    member_id: String,
+    member_name: String,
+    addresses: Vec<IpAddr>,
+    tags: Vec<[u32; 2]>,
+    labels: BTreeMap<String, String>,
+}
+
+impl Host {
+    /// The address tools should connect to, IPv4 preferred
+    fn address(&self) -> IpAddr {
+        self.addresses
+            .iter()
+            .copied()
+            .find(IpAddr::is_ipv4)
+            .unwrap_or(self.addresses[0])
+    }
+}
+
+/// Split a `<key>` or `<key>:<value>` filter
+fn split_filter(filter: &str) -> (&str, Option<&str>) {
+    match filter.split_once(':') {
+        Some((key, value)) => (key.trim(), Some(value.trim())),
+        None => (filter.trim(), None),
+    }
+}
+
+fn tag_filter(filter: &str) -> Result<(u32, Option<u32>)> {
+    let (id, value) = split_filter(filter);
+    let parsed = id.parse().ok().zip(value.map(str::parse).transpose().ok());
+    parsed.ok_or_else(|| {
+        AppError::ValidationError(vec![FieldError::new("tag", "must be <id> or <id>:<value>")])
+    })
+}
+
+/// A name usable as an Ansible group or Prometheus label, e.g. `web-servers` as `web_servers`
+fn identifier(name: &str) -> String {
+    name.chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect()
+}
+
+/// Text for a comment line, with control characters that could start a new line replaced
+fn comment_text(text: &str) -> String {
+    text.chars()
+        .map(|c| if c.is_control() { ' ' } else { c })
+        .collect()
+}
+
+/// A host variable for an INI inventory. Ansible splits host lines like a shell,
+/// so values with whitespace, quotes, comments or backslashes are double-quoted.
+fn ini_value(value: &Value) -> String {
+    // Strings without their JSON quotes, numbers as they are
+    let value = comment_text(
+        &value
+            .as_str()
+            .map_or_else(|| value.to_string(), str::to_string),
+    );
+    if !value.is_empty()
+        && !value
+            .chars()
+            .any(|c| c.is_whitespace() || matches!(c, '"' | '\'' | '#' | '\\'))
+    {
+        return value;
+    }
+    format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+/// The network's members that are authorized, have an address and pass the filters
+fn select_hosts(
+    mut members: Vec<NetworkMember>,
+    metadata: &HashMap<String, MemberMetadata>,
+    query: &InventoryQuery,
+) -> Result<Vec<Host>> {
+    let tag = query.tag.as_deref().map(tag_filter).transpose()?;
+    let label = query.label.as_deref().map(split_filter);
+    let domain = query
+        .domain
+        .as_deref()
+        .map(|domain| domain.trim().trim_matches('.'))
+        .filter(|domain| !domain.is_empty());
+
+    members.sort_by(|a, b| a.id().cmp(b.id()));
+
+    let mut hosts = Vec::new();
+    let mut names = HashSet::new();
+    for member in members {
+        if !member.authorized() || query.online.is_some_and(|online| member.online != online) {
+            continue;
+        }
+
+        let tags: Vec<[u32; 2]> = member
+            .config
+            .get("tags")
+            .cloned()
+            .and_then(|tags| serde_json::from_value(tags).ok())
+            .unwrap_or_default();
+        if let Some((id, value)) = tag {
+            if !tags
+                .iter()
+                .any(|[tag_id, tag_value]| *tag_id == id && value.is_none_or(|v| v == *tag_value))
+            {
+                continue;
+            }
+        }
+
+        let info = metadata
+            .get(member.id())
+            .map(|metadata| metadata.info.clone())
+            .unwrap_or_default();
+        if let Some((key, value)) = label {
+            if info
+                .labels
+                .get(key)
+                .is_none_or(|label| value.is_some_and(|v| v != label))
+            {
+                continue;
+            }
+        }
+
+        let addresses: Vec<IpAddr> = member
+            .addresses()
+            .into_iter()
+            .filter(|ip| match query.family {
+                Some(IpFamily::Ipv4) => ip.is_ipv4(),
+                Some(IpFamily::Ipv6) => ip.is_ipv6(),
+                None => true,
+            })
+            .collect();
+        if addresses.is_empty() {
+            continue;
+        }
+
+        let name = match query.name_source {
+            NameSource::Hostname => info
+                .hostname()
+                .and_then(dns_label)
+                .or_else(|| dns_label(member.name())),
+            NameSource::Name => dns_label(member.name()),
+            NameSource::Id => None,
+        }
+        .unwrap_or_else(|| member.id().to_string());
+        // Keep names unique, later duplicates get the member ID appended
+        let name = if names.contains(&name) {
+            format!("{}-{}", name, member.id())
+        } else {
+            name
+        };
+        if query
+            .name
+            .as_deref()
+            .is_some_and(|pattern| !glob_match(pattern, &name))
+        {
+            continue;
+        }
+        names.insert(name.clone());
+
+        hosts.push(Host {
+            name: match domain {
+                Some(domain) => format!("{}.{}", name, domain),
+                None => name,
+            },
+            member_id: member.id().to_string(),
+            member_name: member.name().to_string(),
+            addresses,
+            tags,
+            labels: info.labels,
+        });
+    }
+
+    Ok(hosts)
+}
+
+fn hosts_file(hosts: &[Host], network_id: &str, network_name: &str) -> String {
+    let mut output = format!(
+        "# ZeroTier network {} ({})\n",
+        comment_text(network_name),
+        network_id
+    );
+    for host in hosts {
+        // Also resolve the short name when the names are qualified
+        let short = host.name.split_once('.').map(|(short, _)| short);
+        for ip in &host.addresses {
+            match short {
+                Some(short) => writeln!(output, "{}\t{} {}", ip, host.name, short),
+                None => writeln!(output, "{}\t{}", ip, host.name),
+            }
+            .ok();
+        }
+    }
+    output
+}
+
+/// Ansible groups: one for the network with every host, and one per tag value
+fn ansible_groups<'a>(
+    hosts: &'a [Host],
+    network_id: &str,
+    network_name: &str,
+) -> (String, BTreeMap<String, Vec<&'a Host>>) {
+    let network_group = dns_label(network_name)
+        .map(|name| identifier(&name))
+        .unwrap_or_else(|| format!("zerotier_{}", network_id));
+
+    let mut groups: BTreeMap<String, Vec<&Host>> = BTreeMap::new();
+    for host in hosts {
+        for [id, value] in &host.tags {
+            groups
+                .entry(format!("tag_{}_{}", id, value))
+                .or_default()
+                .push(host);
+        }
+    }
+    (network_group, groups)
+}
+
+fn ansible_host_vars(host: &Host, query: &InventoryQuery) -> Map<String, Value> {
+    let mut vars = Map::new();
+    vars.insert("ansible_host".to_string(), json!(host.address()));
+    if let Some(user) = &query.user {
+        vars.insert("ansible_user".to_string(), json!(user));
+    }
+    if let Some(port) = query.port {
+        vars.insert("ansible_port".to_string(), json!(port));
+    }
+    vars.insert("zerotier_member_id".to_string(), json!(host.member_id));
+    vars
+}
+
+fn ansible_yaml(
+    hosts: &[Host],
+    network_id: &str,
+    network_name: &str,
+    query: &InventoryQuery,
+) -> Result<String> {
+    let (network_group, groups) = ansible_groups(hosts, network_id, network_name);
+
+    let mut children = Map::new();
+    let network_hosts: Map<String, Value> = hosts
+        .iter()
+        .map(|host| {
+            let mut vars = ansible_host_vars(host, query);
+            vars.insert("zerotier_addresses".to_string(), json!(host.addresses));
+            (host.name.clone(), Value::Object(vars))
+        })
+        .collect();
+    children.insert(network_group, json!({ "hosts": network_hosts }));
+    for (group, members) in groups {
+        let members: Map<String, Value> = members
+            .iter()
+            .map(|host| (host.name.clone(), json!({})))
+            .collect();
+        children.insert(group, json!({ "hosts": members }));
+    }
+
+    serde_yaml::to_string(&json!({ "all": { "children": children } }))
+        .map_err(|e| AppError::InternalServerError(format!("Failed to write YAML: {}", e)))
+}
+
+fn ansible_ini(
+    hosts: &[Host],
+    network_id: &str,
+    network_name: &str,
+    query: &InventoryQuery,
+) -> String {
+    let (network_group, groups) = ansible_groups(hosts, network_id, network_name);
+
+    let mut output = format!("[{}]\n", network_group);
+    for host in hosts {
+        let vars: Vec<String> = ansible_host_vars(host, query)
+            .into_iter()
+            .map(|(key, value)| format!("{}={}", key, ini_value(&value)))
+            .collect();
+        writeln!(output, "{} {}", host.name, vars.join(" ")).ok();
+    }
+    for (group, members) in groups {
+        writeln!(output, "\n[{}]", group).ok();
+        for host in members {
+            writeln!(output, "{}", host.name).ok();
+        }
+    }
+    output
+}
+
+/// Prometheus `http_sd` target groups, one per member
+fn prometheus_targets(
+    hosts: &[Host],
+    network_id: &str,
+    network_name: &str,
+    query: &InventoryQuery,
+) -> Result<String> {
+    let port = query.port.unwrap_or(DEFAULT_PROMETHEUS_PORT);
+    let groups: Vec<Value> = hosts
+        .iter()
+        .map(|host| {
+            let mut labels = BTreeMap::from([
+                (
+                    "__meta_zerotier_network_id".to_string(),
+                    network_id.to_string(),
+                ),
+                (
+                    "__meta_zerotier_network_name".to_string(),
+                    network_name.to_string(),
+                ),
+                (
+                    "__meta_zerotier_member_id".to_string(),
+                    host.member_id.clone(),
+                ),
+                (
+                    "__meta_zerotier_member_name".to_string(),
+                    host.member_name.clone(),
+                ),
+                ("__meta_zerotier_host".to_string(), host.name.clone()),
+            ]);
+            for [id, value] in &host.tags {
+                labels.insert(format!("__meta_zerotier_tag_{}", id), value.to_string());
+            }
+            for (key, value) in &host.labels {
+                labels.insert(
+                    format!("__meta_zerotier_label_{}", identifier(key)),
+                    value.clone(),
+                );
+            }
+
+            json!({
+                "targets": [SocketAddr::new(host.address(), port).to_string()],
+                "labels": labels,
+            })
+        })
+        .collect();
+
+    Ok(serde_json::to_string_pretty(&groups)?)
+}
+
+fn ssh_config(
+    hosts: &[Host],
+    network_id: &str,
+    network_name: &str,
+    query: &InventoryQuery,
+) -> String {
+    let mut output = format!(
+        "# ZeroTier network {} ({})\n",
+        comment_text(network_name),
+        network_id
+    );
+    for host in hosts {
+        let aliases = match host.name.split_once('.') {
+            Some((short, _)) => format!("{} {}", short, host.name),
+            None => host.name.clone(),
+        };
+        writeln!(output, "\nHost {}", aliases).ok();
+        writeln!(output, "    HostName {}", host.address()).ok();
+        if let Some(user) = &query.user {
+            writeln!(output, "    User {}", user).ok();
+        }
+        if let Some(port) = query.port {
+            writeln!(output, "    Port {}", port).ok();
+        }
+    }
+    output
+}
+
+/// Renders a network's members as inventories for other tools
+#[derive(Clone)]
+pub struct InventoryService {
+    zerotier: ZeroTierService,
+    metadata: MetadataService,
+}
+
+impl InventoryService {
+    pub fn new(zerotier: ZeroTierService, metadata: MetadataService) -> Self {
+        Self { zerotier, metadata }
+    }
+
+    /// Render the network's authorized members, returning the content type and body
+    pub async fn render(
+        &self,
+        network_id: &str,
+        format: InventoryFormat,
+        query: &InventoryQuery,
+    ) -> Result<(&'static str, String)> {
+        let endpoint = format!("/controller/network/{}", network_id);
+        let (network, members) = tokio::try_join!(
+            self.zerotier.get_json::<Map<String, Value>>(&endpoint),
+            self.zerotier.network_members(network_id)
+        )?;
+        let metadata = self.metadata.network(network_id).await;
+
+        let hosts = select_hosts(members, &metadata, query)?;
+        let network_name = network
+            .get("name")
+            .and_then(Value::as_str)
+            .unwrap_or_default();
+
+        Ok(match format {
+            InventoryFormat::Hosts => (
+                "text/plain; charset=utf-8",
+                hosts_file(&hosts, network_id, network_name),
+            ),
+            InventoryFormat::AnsibleYaml => (
+                "application/yaml",
+                ansible_yaml(&hosts, network_id, network_name, query)?,
+            ),
+            InventoryFormat::AnsibleIni => (
+                "text/plain; charset=utf-8",
+                ansible_ini(&hosts, network_id, network_name, query),
+            ),
+            InventoryFormat::Prometheus => (
+                "application/json",
+                prometheus_targets(&hosts, network_id, network_name, query)?,
+            ),
+            InventoryFormat::SshConfig => (
+                "text/plain; charset=utf-8",
+                ssh_config(&hosts, network_id, network_name, query),
+            ),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TestEnv;
+
+    const NETWORK: &str = "abcdef0123000001";
+
+    async fn inventory_env() -> TestEnv {
+        let env = TestEnv::new().await;
+        env.controller
+            .add_network(NETWORK, json!({ "name": "Web Servers" }));
+        env.controller.add_member(
+            NETWORK,
+            "1111111111",
+            json!({
+                "name": "Web 1",
+                "authorized": true,
+                "ipAssignments": ["10.0.0.1", "fd00::1"],
+                "tags": [[1, 10]],
+            }),
+        );
+        env.controller.add_member(
+            NETWORK,
+            "2222222222",
+            json!({
+                "name": "Web 1",
+                "authorized": true,
+                "ipAssignments": ["10.0.0.2"],
+                "tags": [[1, 20], [2, 1]],
+            }),
+        );
+        env.controller.add_member(
+            NETWORK,
+            "3333333333",
+            json!({ "name": "guest", "authorized": false, "ipAssignments": ["10.0.0.3"] }),
+        );
+        env.controller.add_member(
+            NETWORK,
+            "4444444444",
+            json!({ "name": "unassigned", "authorized": true }),
+        );
+        env.controller.add_member(
+            NETWORK,
+            "5555555555",
+            json!({ "authorized": true, "ipAssignments": ["fd00::5"] }),
+        );
+        env.controller.add_peer("1111111111", 10, true);
+
+        env.app_state()
+            .metadata
+            .update(NETWORK, "1111111111", |metadata| {
+                let labels = &mut metadata.info.labels;
+                labels.insert("hostname".to_string(), "web1".to_string());
+                labels.insert("role".to_string(), "web".to_string());
+                Ok(())
+            })
+            .await
+            .unwrap();
+        env
+    }
+
+    async fn render(env: &TestEnv, format: InventoryFormat, query: Value) -> String {
+        let query: InventoryQuery = serde_json::from_value(query).unwrap();
+        env.app_state()
+            .inventory
+            .render(NETWORK, format, &query)
+            .await
+            .unwrap()
+            .1
+    }
+
+    /// Host names in the hosts file, one per member
+    async fn names(env: &TestEnv, query: Value) -> Vec<String> {
+        let hosts = render(env, InventoryFormat::Hosts, query).await;
+        let mut names: Vec<String> = hosts
+            .lines()
+            .skip(1)
+            .map(|line| line.split('\t').nth(1).unwrap().to_string())
+            .collect();
+        names.dedup();
+        names
+    }
+
+    #[tokio::test]
+    async fn writes_hosts_files() {
+        let env = inventory_env().await;
+        assert_eq!(
+            render(&env, InventoryFormat::Hosts, json!({})).await,
+            "# ZeroTier network Web Servers (abcdef0123000001)\n\
+             10.0.0.1\tweb1\n\
+             fd00::1\tweb1\n\
+             10.0.0.2\tweb-1\n\
+             fd00::5\t5555555555\n"
+        );
+        assert_eq!(
+            render(
+                &env,
+                InventoryFormat::Hosts,
+                json!({ "domain": ".zt.example.", "family": "ipv4" })
+            )
+            .await,
+            "# ZeroTier network Web Servers (abcdef0123000001)\n\
+             10.0.0.1\tweb1.zt.example web1\n\
+             10.0.0.2\tweb-1.zt.example web-1\n"
+        );
+    }
+
+    #[tokio::test]
+    async fn keeps_network_names_on_the_comment_line() {
+        let env = TestEnv::new().await;
+        env.controller.add_network(
+            NETWORK,
+            json!({ "name": "lab\n10.6.6.6 bank.example.com\r\nHost *" }),
+        );
+        env.controller.add_member(
+            NETWORK,
+            "1111111111",
+            json!({ "name": "web", "authorized": true, "ipAssignments": ["10.0.0.1"] }),
+        );
+
+        let header =
+            "# ZeroTier network lab 10.6.6.6 bank.example.com  Host * (abcdef0123000001)\n";
+        assert_eq!(
+            render(&env, InventoryFormat::Hosts, json!({})).await,
+            format!("{}10.0.0.1\tweb\n", header)
+        );
+        assert_eq!(
+            render(&env, InventoryFormat::SshConfig, json!({})).await,
+            format!("{}\nHost web\n    HostName 10.0.0.1\n", header)
+        );
+    }
+
+    #[test]
+    fn quotes_ini_values() {
+        assert_eq!(ini_value(&json!("deploy")), "deploy");
+        assert_eq!(ini_value(&json!(2222)), "2222");
+        assert_eq!(ini_value(&json!("deploy ops")), "\"deploy ops\"");
+        assert_eq!(ini_value(&json!("a\"b\\c")), "\"a\\\"b\\\\c\"");
+        assert_eq!(ini_value(&json!("#admin")), "\"#admin\"");
+        assert_eq!(ini_value(&json!("it's")), "\"it's\"");
+        assert_eq!(ini_value(&json!("a\nb")), "\"a b\"");
+        assert_eq!(ini_value(&json!("")), "\"\"");
+    }
+
+    #[tokio::test]
+    async fn names_hosts_from_the_chosen_source() {
+        let env = inventory_env().await;
+        // Duplicate names get the member ID appended
+        assert_eq!(
+            names(&env, json!({ "name_source": "name" })).await,
+            ["web-1", "web-1-2222222222", "5555555555"]
+        );
+        assert_eq!(
+            names(&env, json!({ "name_source": "id" })).await,
+            ["1111111111", "2222222222", "5555555555"]
+        );
+    }
+
+    #[tokio::test]
+    async fn filters_hosts() {
+        let env = inventory_env().await;
+        assert_eq!(names(&env, json!({ "tag": "1" })).await, ["web1", "web-1"]);
+        assert_eq!(names(&env, json!({ "tag": " 1 : 20 " })).await, ["web-1"]);
+        assert_eq!(names(&env, json!({ "label": "role" })).await, ["web1"]);
+        assert_eq!(
+            names(&env, json!({ "label": "role:db" })).await,
+            Vec::<String>::new()
+        );
+        assert_eq!(
+            names(&env, json!({ "family": "ipv6" })).await,
+            ["web1", "5555555555"]
+        );
+        assert_eq!(names(&env, json!({ "online": true })).await, ["web1"]);
+        assert_eq!(
+            names(&env, json!({ "online": false })).await,
+            ["web-1", "5555555555"]
+        );
+        assert_eq!(names(&env, json!({ "name": "web?" })).await, ["web1"]);
+
+        for tag in ["x", "1:", "1:y"] {
+            let query: InventoryQuery = serde_json::from_value(json!({ "tag": tag })).unwrap();
+            let result = env
+                .app_state()
+                .inventory
+                .render(NETWORK, InventoryFormat::Hosts, &query)
+                .await;
+            assert!(
+                matches!(result, Err(AppError::ValidationError(_))),
+                "{}",
+                tag
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn writes_ansible_inventories() {
+        let env = inventory_env().await;
+        let query = json!({ "family": "ipv4", "user": "deploy", "port": 2222 });
+
+        let yaml = render(&env, InventoryFormat::AnsibleYaml, query.clone()).await;
+        let inventory: Value = serde_yaml::from_str(&yaml).unwrap();
+        assert_eq!(
+            inventory,
+            json!({ "all": { "children": {
+                "web_servers": { "hosts": {
+                    "web1": {
+                        "ansible_host": "10.0.0.1",
+                        "ansible_user": "deploy",
+                        "ansible_port": 2222,
+                        "zerotier_member_id": "1111111111",
+                        "zerotier_addresses": ["10.0.0.1"],
+                    },
+                    "web-1": {
+                        "ansible_host": "10.0.0.2",
+                        "ansible_user": "deploy",
+                        "ansible_port": 2222,
+                        "zerotier_member_id": "2222222222",
+                        "zerotier_addresses": ["10.0.0.2"],
+                    },
+                } },
+                "tag_1_10": { "hosts": { "web1": {} } },
+                "tag_1_20": { "hosts": { "web-1": {} } },
+                "tag_2_1": { "hosts": { "web-1": {} } },
+            } } })
+        );
+
+        assert_eq!(
+            render(&env, InventoryFormat::AnsibleIni, query).await,
+            "[web_servers]\n\
+             web1 ansible_host=10.0.0.1 ansible_port=2222 ansible_user=deploy zerotier_member_id=1111111111\n\
+             web-1 ansible_host=10.0.0.2 ansible_port=2222 ansible_user=deploy zerotier_member_id=2222222222\n\
+             \n[tag_1_10]\nweb1\n\
+             \n[tag_1_20]\nweb-1\n\
+             \n[tag_2_1]\nweb-1\n"
+        );
+    }
+
+    #[tokio::test]
+    async fn writes_prometheus_targets() {
+        let env = inventory_env().await;
+        let targets = render(&env, InventoryFormat::Prometheus, json!({ "tag": "1:10" })).await;
+        let targets: Value = serde_json::from_str(&targets).unwrap();
+        assert_eq!(
+            targets,
+            json!([{
+                "targets": ["10.0.0.1:9100"],
+                "labels": {
+                    "__meta_zerotier_network_id": NETWORK,
+                    "__meta_zerotier_network_name": "Web Servers",
+                    "__meta_zerotier_member_id": "1111111111",
+                    "__meta_zerotier_member_name": "Web 1",
+                    "__meta_zerotier_host": "web1",
+                    "__meta_zerotier_tag_1": "10",
+                    "__meta_zerotier_label_hostname": "web1",
+                    "__meta_zerotier_label_role": "web",
+                },
+            }])
+        );
+
+        // IPv6 targets are bracketed
+        let targets = render(
+            &env,
+            InventoryFormat::Prometheus,
+            json!({ "family": "ipv6", "port": 9273, "name": "5*" }),
+        )
+        .await;
+        let targets: Value = serde_json::from_str(&targets).unwrap();
+        assert_eq!(targets[0]["targets"], json!(["[fd00::5]:9273"]));
+    }
+
+    #[tokio::test]
+    async fn writes_ssh_configs() {
+        let env = inventory_env().await;
+        assert_eq!(
+            render(
+                &env,
+                InventoryFormat::SshConfig,
+                json!({ "domain": "zt.example", "user": "root", "port": 22, "tag": "1" })
+            )
+            .await,
+            "# ZeroTier network Web Servers (abcdef0123000001)\n\
+             \nHost web1 web1.zt.example\n    HostName 10.0.0.1\n    User root\n    Port 22\n\
+             \nHost web-1 web-1.zt.example\n    HostName 10.0.0.2\n    User root\n    Port 22\n"
+        );
+    }
+}
@@ -0,0 +1,492 @@
+use crate::error::{AppError, FieldError, Result};
+use crate::models::{CreateInvitationRequest, Invitation, InvitationInfo, Redemption};
+use crate::services::audit::{AuditEntry, AuditService};
+use crate::services::store::JsonStore;
+use crate::services::ZeroTierService;
+use crate::utils::is_node_id;
+use chrono::{TimeDelta, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+use std::net::IpAddr;
+use std::path::PathBuf;
+use uuid::Uuid;
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct InvitationStore {
+    invitations: Vec<Invitation>,
+}
+
+/// Manages join links and authorizes the nodes that redeem them
+#[derive(Clone)]
+pub struct InvitationService {
+    store: JsonStore<InvitationStore>,
+    zerotier: ZeroTierService,
+    audit: AuditService,
+}
+
+/// Generate an unguessable token from two random UUIDs (244 random bits)
+fn generate_token() -> String {
+    format!("{}{}", Uuid::new_v4().simple(), Uuid::new_v4().simple())
+}
+
+fn invalid_invitation() -> AppError {
+    AppError::NotFound("Invitation not found or no longer valid".to_string())
+}
+
+impl InvitationService {
+    pub fn new(path: PathBuf, zerotier: ZeroTierService, audit: AuditService) -> Result<Self> {
+        Ok(Self {
+            store: JsonStore::open(path)?,
+            zerotier,
+            audit,
+        })
+    }
+
+    pub async fn create(
+        &self,
+        actor: &str,
+        request: CreateInvitationRequest,
+    ) -> Result<Invitation> {
+        let now = Utc::now();
+        let expires_at = TimeDelta::try_seconds(request.expires_in)
+            .and_then(|lifetime| now.checked_add_signed(lifetime));
+
+        let mut errors = Vec::new();
+        if request.expires_in <= 0 {
+            errors.push(FieldError::new("expires_in", "must be greater than 0"));
+        } else if expires_at.is_none() {
+            errors.push(FieldError::new("expires_in", "is too large"));
+        }
+        if request.max_uses == 0 {
+            errors.push(FieldError::new("max_uses", "must be at least 1"));
+        }
+        let Some(expires_at) = expires_at.filter(|_| errors.is_empty()) else {
+            return Err(AppError::ValidationError(errors));
+        };
+
+        // Make sure the network exists before handing out links to it
+        self.zerotier
+            .get_json::<Value>(&format!("/controller/network/{}", request.network_id))
+            .await?;
+
+        let invitation = Invitation {
+            id: Uuid::new_v4().to_string(),
+            token: generate_token(),
+            network_id: request.network_id,
+            created_by: actor.to_string(),
+            created_at: now,
+            expires_at,
+            max_uses: request.max_uses,
+            uses: 0,
+            default_name: request.default_name.filter(|name| !name.is_empty()),
+            tags: request.tags,
+            revoked: false,
+            redemptions: Vec::new(),
+        };
+
+        self.store
+            .update(|store| {
+                store.invitations.push(invitation.clone());
+                Ok(())
+            })
+            .await?;
+
+        self.audit
+            .record(
+                AuditEntry::new(actor, "invitation.create")
+                    .network(&invitation.network_id)
+                    .details(json!({
+                        "invitation_id": invitation.id,
+                        "expires_at": invitation.expires_at,
+                        "max_uses": invitation.max_uses,
+                        "default_name": invitation.default_name,
+                        "tags": invitation.tags,
+                    })),
+            )
+            .await;
+
+        Ok(invitation)
+    }
+
+    pub async fn list(&self, network_id: Option<&str>) -> Vec<Invitation> {
+        self.store
+            .read()
+            .await
+            .invitations
+            .iter()
+            .filter(|invitation| network_id.is_none_or(|id| invitation.network_id == id))
+            .cloned()
+            .collect()
+    }
+
+    /// Revoke an invitation, it stays listed for reference
+    pub async fn revoke(&self, actor: &str, id: &str) -> Result<Invitation> {
+        let invitation = self
+            .store
+            .update(|store| {
+                let invitation = store
+                    .invitations
+                    .iter_mut()
+                    .find(|invitation| invitation.id == id)
+                    .ok_or_else(|| AppError::NotFound(format!("Invitation {} not found", id)))?;
+                invitation.revoked = true;
+                Ok(invitation.clone())
+            })
+            .await?;
+
+        self.audit
+            .record(
+                AuditEntry::new(actor, "invitation.revoke")
+                    .network(&invitation.network_id)
+                    .details(json!({ "invitation_id": invitation.id })),
+            )
+            .await;
+
+        Ok(invitation)
+    }
+
+    /// Public details for the join page
+    pub async fn info(&self, token: &str) -> Result<InvitationInfo> {
+        let invitation = {
+            let store = self.store.read().await;
+            store
+                .invitations
+                .iter()
+                .find(|invitation| invitation.token == token && invitation.is_usable(Utc::now()))
+                .cloned()
+                .ok_or_else(invalid_invitation)?
+        };
+
+        let network: Map<String, Value> = self
+            .zerotier
+            .get_json(&format!("/controller/network/{}", invitation.network_id))
+            .await
+            .map_err(|_| invalid_invitation())?;
+
+        Ok(InvitationInfo {
+            network_name: network
+                .get("name")
+                .and_then(Value::as_str)
+                .unwrap_or("")
+                .to_string(),
+            network_id: invitation.network_id,
+            expires_at: invitation.expires_at,
+        })
+    }
+
+    /// Authorize `node_id` on the invitation's network.
+    /// Unknown, expired and used up tokens all return `NotFound`.
+    pub async fn redeem(&self, token: &str, node_id: &str, ip: IpAddr) -> Result<Invitation> {
+        if !is_node_id(node_id) {
+            return Err(AppError::ValidationError(vec![FieldError::new(
+                "node_id",
+                "must be 10 hexadecimal characters",
+            )]));
+        }
+        let node_id = node_id.to_ascii_lowercase();
+
+        // Claim a use up front so concurrent redemptions cannot exceed max_uses
+        let claimed = self
+            .store
+            .update(|store| {
+                let invitation = store
+                    .invitations
+                    .iter_mut()
+                    .find(|invitation| {
+                        invitation.token == token && invitation.is_usable(Utc::now())
+                    })
+                    .ok_or_else(invalid_invitation)?;
+                invitation.uses += 1;
+                invitation.redemptions.push(Redemption {
+                    member_id: node_id.clone(),
+                    ip,
+                    redeemed_at: Utc::now(),
+                });
+                Ok(invitation.clone())
+            })
+            .await;
+
+        let invitation = match claimed {
+            Ok(invitation) => invitation,
+            Err(e) => {
+                self.audit
+                    .record(
+                        AuditEntry::new("anonymous", "invitation.redeem_failed").details(
+                            json!({ "ip": ip, "member_id": node_id, "error": e.to_string() }),
+                        ),
+                    )
+                    .await;
+                return Err(e);
+            }
+        };
+
+        let mut update = Map::new();
+        update.insert("authorized".to_string(), Value::Bool(true));
+        if let Some(name) = &invitation.default_name {
+            update.insert("name".to_string(), Value::String(name.clone()));
+        }
+        if !invitation.tags.is_empty() {
+            update.insert("tags".to_string(), json!(invitation.tags));
+        }
+
+        let result = self
+            .zerotier
+            .update_member(&invitation.network_id, &node_id, &Value::Object(update))
+            .await;
+
+        let actor = format!("invitation:{}", invitation.id);
+        if let Err(e) = result {
+            // Give the use back, the node was not authorized
+            let released = self
+                .store
+                .update(|store| {
+                    if let Some(invitation) = store
+                        .invitations
+                        .iter_mut()
+                        .find(|stored| stored.id == invitation.id)
+                    {
+                        invitation.uses = invitation.uses.saturating_sub(1);
+                        if let Some(index) = invitation
+                            .redemptions
+                            .iter()
+                            .rposition(|redemption| redemption.member_id == node_id)
+                        {
+                            invitation.redemptions.remove(index);
+                        }
+                    }
+                    Ok(())
+                })
+                .await;
+            if let Err(e) = released {
+                tracing::error!("Failed to release invitation {}: {}", invitation.id, e);
+            }
+
+            self.audit
+                .record(
+                    AuditEntry::new(actor, "invitation.redeem_failed")
+                        .member(&invitation.network_id, &node_id)
+                        .details(json!({ "ip": ip, "error": e.to_string() })),
+                )
+                .await;
+            return Err(e);
+        }
+
+        self.audit
+            .record(
+                AuditEntry::new(actor, "member.authorize")
+                    .member(&invitation.network_id, &node_id)
+                    .details(json!({
+                        "ip": ip,
+                        "name": invitation.default_name,
+                        "tags": invitation.tags,
+                        "uses": invitation.uses,
+                        "max_uses": invitation.max_uses,
+                    })),
+            )
+            .await;
+
+        Ok(invitation)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TestEnv;
+
+    const NETWORK: &str = "abcdef0123000001";
+
+    fn request(expires_in: i64, max_uses: u32) -> CreateInvitationRequest {
+        CreateInvitationRequest {
+            network_id: NETWORK.to_string(),
+            expires_in,
+            max_uses,
+            default_name: Some("guest".to_string()),
+            tags: vec![[1, 2]],
+        }
+    }
+
+    fn ip() -> IpAddr {
+        "192.0.2.10".parse().unwrap()
+    }
+
+    fn invalid_fields(error: AppError) -> Vec<String> {
+        match error {
+            AppError::ValidationError(fields) => {
+                fields.into_iter().map(|field| field.field).collect()
+            }
+            e => panic!("expected a validation error, got {}", e),
+        }
+    }
+
+    #[tokio::test]
+    async fn rejects_invalid_lifetimes() {
+        let env = TestEnv::new().await;
+        env.controller.add_network(NETWORK, json!({}));
+        let invitations = env.app_state().invitations;
+
+        for expires_in in [0, -1, i64::MAX, i64::MIN] {
+            let error = invitations
+                .create("admin", request(expires_in, 1))
+                .await
+                .unwrap_err();
+            assert_eq!(invalid_fields(error), ["expires_in"], "{}", expires_in);
+        }
+
+        let error = invitations
+            .create("admin", request(i64::MAX, 0))
+            .await
+            .unwrap_err();
+        assert_eq!(invalid_fields(error), ["expires_in", "max_uses"]);
+        assert!(invitations.list(None).await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn requires_an_existing_network() {
+        let env = TestEnv::new().await;
+        let invitations = env.app_state().invitations;
+
+        assert!(invitations.create("admin", request(60, 1)).await.is_err());
+        assert!(invitations.list(None).await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn redeeming_authorizes_the_node() {
+        let env = TestEnv::new().await;
+        env.controller
+            .add_network(NETWORK, json!({ "name": "lab" }));
+        let app_state = env.app_state();
+        let invitations = &app_state.invitations;
+
+        let invitation = invitations.create("admin", request(3600, 1)).await.unwrap();
+        assert_eq!(invitation.token.len(), 64);
+        let lifetime = invitation.expires_at - invitation.created_at;
+        assert_eq!(lifetime, TimeDelta::seconds(3600));
+
+        let info = invitations.info(&invitation.token).await.unwrap();
+        assert_eq!(info.network_name, "lab");
+
+        // Node IDs are normalized to lowercase
+        let redeemed = invitations
+            .redeem(&invitation.token, "ABCDEF9876", ip())
+            .await
+            .unwrap();
+        assert_eq!(redeemed.uses, 1);
+        assert_eq!(redeemed.redemptions[0].member_id, "abcdef9876");
+
+        let member = env.controller.member(NETWORK, "abcdef9876").unwrap();
+        assert_eq!(member["authorized"], true);
+        assert_eq!(member["name"], "guest");
+        assert_eq!(member["tags"], json!([[1, 2]]));
+
+        let actions: Vec<_> = app_state
+            .audit
+            .recent(10, |_| true)
+            .await
+            .into_iter()
+            .map(|entry| entry.action)
+            .collect();
+        assert_eq!(actions, ["member.authorize", "invitation.create"]);
+    }
+
+    #[tokio::test]
+    async fn tokens_stop_working_once_used_up() {
+        let env = TestEnv::new().await;
+        env.controller.add_network(NETWORK, json!({}));
+        let invitations = env.app_state().invitations;
+
+        let invitation = invitations.create("admin", request(3600, 1)).await.unwrap();
+        invitations
+            .redeem(&invitation.token, "1111111111", ip())
+            .await
+            .unwrap();
+
+        let error = invitations
+            .redeem(&invitation.token, "2222222222", ip())
+            .await
+            .unwrap_err();
+        assert!(matches!(error, AppError::NotFound(_)));
+        assert!(invitations.info(&invitation.token).await.is_err());
+        assert!(env.controller.member(NETWORK, "2222222222").is_none());
+    }
+
+    #[tokio::test]
+    async fn expired_and_revoked_tokens_are_rejected() {
+        let env = TestEnv::new().await;
+        env.controller.add_network(NETWORK, json!({}));
+        let invitations = env.app_state().invitations;
+
+        let expired = invitations.create("admin", request(3600, 5)).await.unwrap();
+        invitations
+            .store
+            .update(|store| {
+                store.invitations[0].expires_at = Utc::now() - TimeDelta::seconds(1);
+                Ok(())
+            })
+            .await
+            .unwrap();
+        let error = invitations
+            .redeem(&expired.token, "1111111111", ip())
+            .await
+            .unwrap_err();
+        assert!(matches!(error, AppError::NotFound(_)));
+
+        let revoked = invitations.create("admin", request(3600, 5)).await.unwrap();
+        invitations.revoke("admin", &revoked.id).await.unwrap();
+        let error = invitations
+            .redeem(&revoked.token, "1111111111", ip())
+            .await
+            .unwrap_err();
+        assert!(matches!(error, AppError::NotFound(_)));
+
+        let error = invitations.revoke("admin", "missing").await.unwrap_err();
+        assert!(matches!(error, AppError::NotFound(_)));
+    }
+
+    #[tokio::test]
+    async fn rejects_malformed_node_ids() {
+        let env = TestEnv::new().await;
+        env.controller.add_network(NETWORK, json!({}));
+        let invitations = env.app_state().invitations;
+
+        let invitation = invitations.create("admin", request(3600, 1)).await.unwrap();
+        let error = invitations
+            .redeem(&invitation.token, "not-a-node", ip())
+            .await
+            .unwrap_err();
+        assert_eq!(invalid_fields(error), ["node_id"]);
+        assert_eq!(invitations.list(None).await[0].uses, 0);
+    }
+
+    #[tokio::test]
+    async fn failed_authorization_gives_the_use_back() {
+        let env = TestEnv::new().await;
+        env.controller.add_network(NETWORK, json!({}));
+        env.controller
+            .state
+            .lock()
+            .unwrap()
+            .failures
+            .insert(format!(
+                "POST /controller/network/{}/member/1111111111",
+                NETWORK
+            ));
+        let invitations = env.app_state().invitations;
+
+        let invitation = invitations.create("admin", request(3600, 1)).await.unwrap();
+        assert!(invitations
+            .redeem(&invitation.token, "1111111111", ip())
+            .await
+            .is_err());
+
+        let stored = &invitations.list(Some(NETWORK)).await[0];
+        assert_eq!(stored.uses, 0);
+        assert!(stored.redemptions.is_empty());
+
+        // The token still works for another node
+        invitations
+            .redeem(&invitation.token, "2222222222", ip())
+            .await
+            .unwrap();
+    }
+}
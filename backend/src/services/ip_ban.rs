@@ -1,3 +1,4 @@
+use crate::services::events::{EventKind, EventService};
 use chrono::{DateTime, Duration, Utc};
 use ipnet::Ipv6Net;
 use std::collections::HashMap;
@@ -15,18 +16,14 @@ struct FailureRecord {
 #[derive(Clone)]
 pub struct IpBanService {
     records: Arc<RwLock<HashMap<IpAddr, FailureRecord>>>,
-}
-
-impl Default for IpBanService {
-    fn default() -> Self {
-        Self::new()
-    }
+    events: EventService,
 }
 
 impl IpBanService {
-    pub fn new() -> Self {
+    pub fn new(events: EventService) -> Self {
         Self {
             records: Arc::new(RwLock::new(HashMap::new())),
+            events,
         }
     }
 
@@ -98,12 +95,22 @@ impl IpBanService {
 
             // If you fail 5 times, you will be banned for 1 day.
             if record.count >= 5 {
-                record.banned_until = Some(now + Duration::days(1));
+                let banned_until = now + Duration::days(1);
+                record.banned_until = Some(banned_until);
                 tracing::warn!(
                     "IP {} has been banned for 1 day due to {} failed login attempts",
                     ip,
                     record.count
                 );
+
+                // Only announce the ban when it is issued, not on later failures
+                if record.count == 5 {
+                    self.events.publish(EventKind::IpBanned {
+                        ip: *ip,
+                        failures: record.count,
+                        banned_until,
+                    });
+                }
             }
         }
     }
@@ -134,3 +141,38 @@ impl IpBanService {
         None
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn announces_a_ban_once() {
+        let events = EventService::new();
+        let mut receiver = events.subscribe();
+        let ip_ban = IpBanService::new(events);
+        let ip: IpAddr = "2001:db8:1:2::1".parse().unwrap();
+
+        for _ in 0..4 {
+            ip_ban.record_failure(&ip).await;
+        }
+        assert!(!ip_ban.is_banned(&ip).await);
+        assert!(receiver.try_recv().is_err());
+
+        ip_ban.record_failure(&ip).await;
+        ip_ban.record_failure(&ip).await;
+        // IPv6 clients are banned by /48
+        assert!(
+            ip_ban
+                .is_banned(&"2001:db8:1:ffff::1".parse().unwrap())
+                .await
+        );
+
+        let event = receiver.try_recv().unwrap();
+        assert!(matches!(
+            event.kind,
+            EventKind::IpBanned { failures: 5, ip: banned, .. } if banned == ip
+        ));
+        assert!(receiver.try_recv().is_err());
+    }
+}
@@ -0,0 +1,992 @@
+use crate::error::{AppError, FieldError, Result};
+use crate::models::{
+    AllocateIpRequest, IpReservation, IpamConflict, IpamOverview, IpamUsage, IpamUsageKind,
+    NetworkMember, ReserveIpRequest,
+};
+use crate::services::audit::{AuditEntry, AuditService};
+use crate::services::store::JsonStore;
+use crate::services::{ConfigService, ZeroTierService};
+use crate::utils::{ip_offset, ip_to_u128, is_node_id};
+use crate::validation;
+use chrono::Utc;
+use ipnet::IpNet;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+use std::collections::HashSet;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::path::PathBuf;
+use std::sync::Arc;
+use tokio::sync::{Mutex, MutexGuard};
+
+/// Most addresses returned by one next free IP query
+const MAX_NEXT_IPS: usize = 256;
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct IpamStore {
+    reservations: Vec<IpReservation>,
+}
+
+/// An inclusive address range in numeric form
+#[derive(Debug, Clone, Copy)]
+struct Range {
+    v6: bool,
+    start: u128,
+    end: u128,
+}
+
+impl Range {
+    fn subnet(subnet: &IpNet) -> Self {
+        Self {
+            v6: matches!(subnet, IpNet::V6(_)),
+            start: ip_to_u128(subnet.network()),
+            end: ip_to_u128(subnet.broadcast()),
+        }
+    }
+
+    fn pool(start: IpAddr, end: IpAddr) -> Self {
+        Self {
+            v6: start.is_ipv6(),
+            start: ip_to_u128(start),
+            end: ip_to_u128(end),
+        }
+    }
+
+    /// The range's host addresses, without the network and broadcast
+    /// addresses when the subnet is large enough to have others
+    fn hosts(subnet: &IpNet) -> Self {
+        let mut range = Self::subnet(subnet);
+        if range.end - range.start >= 2 {
+            range.start += 1;
+            range.end -= 1;
+        }
+        range
+    }
+
+    fn overlaps(&self, other: &Range) -> bool {
+        self.v6 == other.v6 && self.start <= other.end && other.start <= self.end
+    }
+
+    fn address(&self, value: u128) -> Option<IpAddr> {
+        let base = if self.v6 {
+            IpAddr::V6(Ipv6Addr::UNSPECIFIED)
+        } else {
+            IpAddr::V4(Ipv4Addr::UNSPECIFIED)
+        };
+        ip_offset(base, value)
+    }
+}
+
+struct Usage {
+    usage: IpamUsage,
+    range: Range,
+}
+
+fn network_name(network: &Map<String, Value>) -> String {
+    network
+        .get("name")
+        .and_then(Value::as_str)
+        .unwrap_or_default()
+        .to_string()
+}
+
+/// The routes and assignment pools of every network
+fn usages(networks: &[Map<String, Value>]) -> Vec<Usage> {
+    let mut usages = Vec::new();
+
+    for network in networks {
+        let network_id = network
+            .get("id")
+            .and_then(Value::as_str)
+            .unwrap_or_default();
+        let usage = |kind, range: String| IpamUsage {
+            kind,
+            network_id: network_id.to_string(),
+            network_name: network_name(network),
+            range,
+        };
+
+        for subnet in validation::routed_subnets(network) {
+            let subnet = subnet.trunc();
+            usages.push(Usage {
+                usage: usage(IpamUsageKind::Route, subnet.to_string()),
+                range: Range::subnet(&subnet),
+            });
+        }
+        for (start, end) in validation::assignment_pools(network) {
+            usages.push(Usage {
+                usage: usage(IpamUsageKind::Pool, format!("{}-{}", start, end)),
+                range: Range::pool(start, end),
+            });
+        }
+    }
+
+    usages
+}
+
+/// Routes overlapping routes, and pools overlapping pools, of other networks
+fn conflicts(usages: &[Usage]) -> Vec<IpamConflict> {
+    let mut conflicts = Vec::new();
+
+    for (n, first) in usages.iter().enumerate() {
+        for second in &usages[n + 1..] {
+            if first.usage.kind == second.usage.kind
+                && first.usage.network_id != second.usage.network_id
+                && first.range.overlaps(&second.range)
+            {
+                conflicts.push(IpamConflict {
+                    first: first.usage.clone(),
+                    second: second.usage.clone(),
+                });
+            }
+        }
+    }
+
+    conflicts
+}
+
+/// Check that `/prefix_len` blocks fit in `supernet`
+fn check_prefix_len(supernet: &IpNet, prefix_len: u8, field: &str) -> Result<()> {
+    if prefix_len < supernet.prefix_len() || prefix_len > supernet.max_prefix_len() {
+        return Err(AppError::ValidationError(vec![FieldError::new(
+            field,
+            format!(
+                "must be between {} and {} for supernet {}",
+                supernet.prefix_len(),
+                supernet.max_prefix_len(),
+                supernet
+            ),
+        )]));
+    }
+    Ok(())
+}
+
+/// The first aligned `/prefix_len` block of `supernet` that overlaps none of `used`
+fn next_free_subnet(supernet: &IpNet, prefix_len: u8, used: &[Usage]) -> Option<IpNet> {
+    let supernet_range = Range::subnet(supernet);
+    let host_bits = u32::from(supernet.max_prefix_len().checked_sub(prefix_len)?);
+    // Offset of the last address in a block, blocks are aligned to this + 1
+    let block_mask = u128::MAX.checked_shr(128 - host_bits).unwrap_or(0);
+
+    let used: Vec<Range> = used
+        .iter()
+        .map(|usage| usage.range)
+        .filter(|range| range.overlaps(&supernet_range))
+        .collect();
+
+    let mut start = supernet_range.start;
+    loop {
+        let end = start.checked_add(block_mask)?;
+        if end > supernet_range.end {
+            return None;
+        }
+
+        let block = Range {
+            v6: supernet_range.v6,
+            start,
+            end,
+        };
+        match used
+            .iter()
+            .filter(|range| range.overlaps(&block))
+            .map(|range| range.end)
+            .max()
+        {
+            None => return IpNet::new(block.address(start)?, prefix_len).ok(),
+            // Continue at the next block boundary after the overlapping range
+            Some(used_end) => {
+                start = used_end.checked_add(1)?.checked_add(block_mask)? & !block_mask
+            }
+        }
+    }
+}
+
+/// Up to `count` free addresses of a network, taken from its assignment
+/// pools, or from its routes if it has none
+fn free_addresses(
+    network: &Map<String, Value>,
+    taken: &HashSet<IpAddr>,
+    count: usize,
+) -> Vec<IpAddr> {
+    let pools = validation::assignment_pools(network);
+    let ranges: Vec<Range> = if pools.is_empty() {
+        validation::routed_subnets(network)
+            .iter()
+            .map(|subnet| Range::hosts(&subnet.trunc()))
+            .collect()
+    } else {
+        pools
+            .into_iter()
+            .map(|(start, end)| Range::pool(start, end))
+            .collect()
+    };
+
+    let mut free = Vec::new();
+    for range in ranges {
+        let mut value = range.start;
+        while free.len() < count && value <= range.end {
+            if let Some(ip) = range.address(value).filter(|ip| !taken.contains(ip)) {
+                free.push(ip);
+            }
+            match value.checked_add(1) {
+                Some(next) => value = next,
+                None => break,
+            }
+        }
+    }
+
+    free
+}
+
+/// Allocates subnets from the configured supernet and addresses within networks
+#[derive(Clone)]
+pub struct IpamService {
+    config: ConfigService,
+    store: JsonStore<IpamStore>,
+    zerotier: ZeroTierService,
+    audit: AuditService,
+    allocation: Arc<Mutex<()>>,
+}
+
+impl IpamService {
+    pub fn new(
+        config: ConfigService,
+        path: PathBuf,
+        zerotier: ZeroTierService,
+        audit: AuditService,
+    ) -> Result<Self> {
+        Ok(Self {
+            config,
+            store: JsonStore::open(path)?,
+            zerotier,
+            audit,
+            allocation: Arc::new(Mutex::new(())),
+        })
+    }
+
+    /// Hold while allocating so concurrent requests do not pick the same subnet or address
+    pub async fn lock(&self) -> MutexGuard<'_, ()> {
+        self.allocation.lock().await
+    }
+
+    pub async fn overview(&self) -> Result<IpamOverview> {
+        let config = self.config.get_config().ipam.clone();
+        if let Some(supernet) = &config.supernet {
+            check_prefix_len(
+                &supernet.trunc(),
+                config.subnet_prefix_len,
+                "ipam.subnet_prefix_len",
+            )?;
+        }
+        let networks = self.zerotier.list_networks().await?;
+        let usages = usages(&networks);
+
+        let allocated = match &config.supernet {
+            Some(supernet) => {
+                let supernet = Range::subnet(&supernet.trunc());
+                usages
+                    .iter()
+                    .filter(|usage| usage.range.overlaps(&supernet))
+                    .map(|usage| usage.usage.clone())
+                    .collect()
+            }
+            None => Vec::new(),
+        };
+        let next_subnet = config.supernet.and_then(|supernet| {
+            next_free_subnet(&supernet.trunc(), config.subnet_prefix_len, &usages)
+        });
+
+        Ok(IpamOverview {
+            supernet: config.supernet,
+            subnet_prefix_len: config.subnet_prefix_len,
+            allocated,
+            next_subnet,
+            conflicts: conflicts(&usages),
+        })
+    }
+
+    /// The next subnet of the supernet that no network routes or pools overlap
+    pub async fn next_subnet(&self, prefix_len: Option<u8>) -> Result<IpNet> {
+        let config = self.config.get_config().ipam.clone();
+        let Some(supernet) = config.supernet.map(|supernet| supernet.trunc()) else {
+            return Err(AppError::ValidationError(vec![FieldError::new(
+                "supernet",
+                "is not configured, set ipam.supernet",
+            )]));
+        };
+
+        let field = match prefix_len {
+            Some(_) => "prefix_len",
+            None => "ipam.subnet_prefix_len",
+        };
+        let prefix_len = prefix_len.unwrap_or(config.subnet_prefix_len);
+        check_prefix_len(&supernet, prefix_len, field)?;
+
+        let networks = self.zerotier.list_networks().await?;
+        next_free_subnet(&supernet, prefix_len, &usages(&networks)).ok_or_else(|| {
+            AppError::ValidationError(vec![FieldError::new(
+                "supernet",
+                format!("{} has no free /{} left", supernet, prefix_len),
+            )])
+        })
+    }
+
+    /// Give a network the next free subnet as a route with a matching assignment pool
+    pub async fn allocate_subnet(
+        &self,
+        actor: &str,
+        network_id: &str,
+        prefix_len: Option<u8>,
+    ) -> Result<Map<String, Value>> {
+        let _guard = self.lock().await;
+
+        let endpoint = format!("/controller/network/{}", network_id);
+        let network: Map<String, Value> = self.zerotier.get_json(&endpoint).await?;
+        let subnet = self.next_subnet(prefix_len).await?;
+        let hosts = Range::hosts(&subnet);
+
+        let mut routes = network
+            .get("routes")
+            .and_then(Value::as_array)
+            .cloned()
+            .unwrap_or_default();
+        routes.push(json!({ "target": subnet.to_string(), "via": null }));
+        let mut pools = network
+            .get("ipAssignmentPools")
+            .and_then(Value::as_array)
+            .cloned()
+            .unwrap_or_default();
+        pools.push(json!({
+            "ipRangeStart": hosts.address(hosts.start),
+            "ipRangeEnd": hosts.address(hosts.end),
+        }));
+
+        let network = self
+            .zerotier
+            .post_json(
+                &endpoint,
+                &json!({ "routes": routes, "ipAssignmentPools": pools }),
+            )
+            .await?;
+
+        self.audit
+            .record(
+                AuditEntry::new(actor, "ipam.allocate_subnet")
+                    .network(network_id)
+                    .details(json!({ "subnet": subnet })),
+            )
+            .await;
+
+        Ok(network)
+    }
+
+    pub async fn reservations(&self, network_id: &str) -> Vec<IpReservation> {
+        self.store
+            .read()
+            .await
+            .reservations
+            .iter()
+            .filter(|reservation| reservation.network_id == network_id)
+            .cloned()
+            .collect()
+    }
+
+    /// Addresses in use by members of the network, computed RFC4193 and 6PLANE
+    /// ones included, or reserved, except those assigned to or reserved for `member_id`
+    async fn taken(
+        &self,
+        network_id: &str,
+        member_id: Option<&str>,
+    ) -> Result<(Map<String, Value>, HashSet<IpAddr>)> {
+        let endpoint = format!("/controller/network/{}", network_id);
+        let (network, members) = tokio::try_join!(
+            self.zerotier.get_json::<Map<String, Value>>(&endpoint),
+            self.zerotier.list_members(network_id)
+        )?;
+
+        let mut taken: HashSet<IpAddr> = HashSet::new();
+        for member in members
+            .into_iter()
+            .filter(|member| member.get("id").and_then(Value::as_str) != member_id)
+        {
+            let mut member = NetworkMember::new(member, None);
+            member.compute_addresses(network_id, &network);
+            taken.extend(member.addresses());
+        }
+        taken.extend(
+            self.reservations(network_id)
+                .await
+                .into_iter()
+                .filter(|reservation| {
+                    member_id.is_none() || reservation.member_id.as_deref() != member_id
+                })
+                .map(|reservation| reservation.ip),
+        );
+
+        Ok((network, taken))
+    }
+
+    pub async fn next_ips(&self, network_id: &str, count: usize) -> Result<Vec<IpAddr>> {
+        let (network, taken) = self.taken(network_id, None).await?;
+        Ok(free_addresses(
+            &network,
+            &taken,
+            count.clamp(1, MAX_NEXT_IPS),
+        ))
+    }
+
+    /// Check that `ip` is routed by the network and not taken
+    fn check_address(
+        network: &Map<String, Value>,
+        taken: &HashSet<IpAddr>,
+        ip: IpAddr,
+    ) -> Result<()> {
+        let routed = validation::routed_subnets(network)
+            .iter()
+            .any(|subnet| subnet.contains(&ip));
+
+        let message = if !routed {
+            "is outside the network's routes"
+        } else if taken.contains(&ip) {
+            "is already assigned or reserved"
+        } else {
+            return Ok(());
+        };
+
+        Err(AppError::ValidationError(vec![FieldError::new(
+            "ip", message,
+        )]))
+    }
+
+    pub async fn reserve(
+        &self,
+        actor: &str,
+        network_id: &str,
+        request: ReserveIpRequest,
+    ) -> Result<IpReservation> {
+        if let Some(member_id) = &request.member_id {
+            if !is_node_id(member_id) {
+                return Err(AppError::ValidationError(vec![FieldError::new(
+                    "member_id",
+                    "must be a 10 hex digit node ID",
+                )]));
+            }
+        }
+
+        let _guard = self.lock().await;
+        let (network, mut taken) = self.taken(network_id, request.member_id.as_deref()).await?;
+        // A member's own reservations still block a second one for the same address
+        taken.extend(
+            self.reservations(network_id)
+                .await
+                .into_iter()
+                .map(|reservation| reservation.ip),
+        );
+
+        let ip = match request.ip {
+            Some(ip) => {
+                Self::check_address(&network, &taken, ip)?;
+                ip
+            }
+            None => free_addresses(&network, &taken, 1)
+                .into_iter()
+                .next()
+                .ok_or_else(|| {
+                    AppError::ValidationError(vec![FieldError::new(
+                        "ip",
+                        "no free address left in the network",
+                    )])
+                })?,
+        };
+
+        let reservation = IpReservation {
+            network_id: network_id.to_string(),
+            ip,
+            member_id: request.member_id,
+            note: request.note,
+            created_by: actor.to_string(),
+            created_at: Utc::now(),
+        };
+        self.store
+            .update(|store| {
+                store.reservations.push(reservation.clone());
+                Ok(())
+            })
+            .await?;
+
+        self.audit
+            .record(
+                AuditEntry::new(actor, "ipam.reserve")
+                    .network(network_id)
+                    .details(json!({
+                        "ip": reservation.ip,
+                        "member_id": reservation.member_id,
+                        "note": reservation.note,
+                    })),
+            )
+            .await;
+
+        Ok(reservation)
+    }
+
+    pub async fn release(&self, actor: &str, network_id: &str, ip: IpAddr) -> Result<()> {
+        self.store
+            .update(|store| {
+                let count = store.reservations.len();
+                store.reservations.retain(|reservation| {
+                    reservation.network_id != network_id || reservation.ip != ip
+                });
+                if store.reservations.len() == count {
+                    return Err(AppError::NotFound(format!("No reservation for {}", ip)));
+                }
+                Ok(())
+            })
+            .await?;
+
+        self.audit
+            .record(
+                AuditEntry::new(actor, "ipam.release")
+                    .network(network_id)
+                    .details(json!({ "ip": ip })),
+            )
+            .await;
+
+        Ok(())
+    }
+
+    /// Add an address to a member's `ipAssignments`: the requested one, the
+    /// member's reservation or the next free one
+    pub async fn allocate_ip(
+        &self,
+        actor: &str,
+        network_id: &str,
+        member_id: &str,
+        request: AllocateIpRequest,
+    ) -> Result<Map<String, Value>> {
+        let _guard = self.lock().await;
+
+        let member: Map<String, Value> = self
+            .zerotier
+            .get_json(&format!(
+                "/controller/network/{}/member/{}",
+                network_id, member_id
+            ))
+            .await?;
+        let mut assignments = member
+            .get("ipAssignments")
+            .and_then(Value::as_array)
+            .cloned()
+            .unwrap_or_default();
+        let assigned: HashSet<IpAddr> = assignments
+            .iter()
+            .filter_map(|ip| ip.as_str()?.parse().ok())
+            .collect();
+
+        let (network, taken) = self.taken(network_id, Some(member_id)).await?;
+        let reserved = self
+            .reservations(network_id)
+            .await
+            .into_iter()
+            .filter(|reservation| reservation.member_id.as_deref() == Some(member_id))
+            .map(|reservation| reservation.ip)
+            .find(|ip| !assigned.contains(ip));
+
+        let ip = match request.ip.or(reserved) {
+            Some(ip) => {
+                Self::check_address(&network, &taken, ip)?;
+                ip
+            }
+            None => free_addresses(&network, &taken, assigned.len() + 1)
+                .into_iter()
+                .find(|ip| !assigned.contains(ip))
+                .ok_or_else(|| {
+                    AppError::ValidationError(vec![FieldError::new(
+                        "ip",
+                        "no free address left in the network",
+                    )])
+                })?,
+        };
+
+        if assigned.contains(&ip) {
+            return Ok(member);
+        }
+        assignments.push(json!(ip));
+        let member = self
+            .zerotier
+            .update_member(
+                network_id,
+                member_id,
+                &json!({ "ipAssignments": assignments }),
+            )
+            .await?;
+
+        self.audit
+            .record(
+                AuditEntry::new(actor, "ipam.allocate_ip")
+                    .member(network_id, member_id)
+                    .details(json!({ "ip": ip })),
+            )
+            .await;
+
+        Ok(member)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TestEnv;
+
+    const NETWORK: &str = "abcdef0123000001";
+
+    fn subnet(text: &str) -> IpNet {
+        text.parse().unwrap()
+    }
+
+    fn ip(text: &str) -> IpAddr {
+        text.parse().unwrap()
+    }
+
+    fn routed(subnets: &[&str]) -> Vec<Usage> {
+        let routes: Vec<Value> = subnets
+            .iter()
+            .map(|subnet| json!({ "target": subnet, "via": null }))
+            .collect();
+        usages(&[json!({ "id": NETWORK, "routes": routes })
+            .as_object()
+            .unwrap()
+            .clone()])
+    }
+
+    async fn ipam_env(supernet: &str, prefix_len: u8) -> TestEnv {
+        let supernet = subnet(supernet);
+        TestEnv::with_config(move |config| {
+            config.ipam.supernet = Some(supernet);
+            config.ipam.subnet_prefix_len = prefix_len;
+        })
+        .await
+    }
+
+    fn invalid_field(result: Result<impl std::fmt::Debug>) -> (String, String) {
+        match result {
+            Err(AppError::ValidationError(mut fields)) => {
+                let field = fields.remove(0);
+                (field.field, field.message)
+            }
+            other => panic!("expected a validation error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn finds_the_next_aligned_free_subnet() {
+        let supernet = subnet("10.8.0.0/16");
+        assert_eq!(
+            next_free_subnet(&supernet, 24, &[]),
+            Some(subnet("10.8.0.0/24"))
+        );
+        assert_eq!(
+            next_free_subnet(&supernet, 24, &routed(&["10.8.0.0/24", "10.8.1.128/25"])),
+            Some(subnet("10.8.2.0/24"))
+        );
+        // A larger block skips to its next boundary
+        assert_eq!(
+            next_free_subnet(&supernet, 22, &routed(&["10.8.1.0/24"])),
+            Some(subnet("10.8.4.0/22"))
+        );
+        // Routes outside the supernet do not matter, a default route covers it all
+        assert_eq!(
+            next_free_subnet(&supernet, 24, &routed(&["10.9.0.0/24"])),
+            Some(subnet("10.8.0.0/24"))
+        );
+        assert_eq!(
+            next_free_subnet(&supernet, 24, &routed(&["0.0.0.0/0"])),
+            None
+        );
+        assert_eq!(
+            next_free_subnet(&supernet, 24, &routed(&["10.7.0.0/16"])),
+            Some(subnet("10.8.0.0/24"))
+        );
+        assert_eq!(
+            next_free_subnet(&supernet, 16, &routed(&["10.8.9.0/24"])),
+            None
+        );
+
+        let v6 = subnet("fd00::/48");
+        assert_eq!(
+            next_free_subnet(&v6, 64, &routed(&["fd00::/64", "10.8.0.0/16"])),
+            Some(subnet("fd00:0:0:1::/64"))
+        );
+        assert_eq!(
+            next_free_subnet(&subnet("fd00::/120"), 128, &routed(&["fd00::/121"])),
+            Some(subnet("fd00::80/128"))
+        );
+    }
+
+    #[test]
+    fn rejects_prefix_lengths_outside_the_supernet() {
+        let supernet = subnet("10.8.0.0/16");
+        assert_eq!(next_free_subnet(&supernet, 64, &[]), None);
+        assert_eq!(
+            invalid_field(check_prefix_len(&supernet, 64, "prefix_len")),
+            (
+                "prefix_len".to_string(),
+                "must be between 16 and 32 for supernet 10.8.0.0/16".to_string()
+            )
+        );
+        assert!(check_prefix_len(&supernet, 8, "prefix_len").is_err());
+        assert!(check_prefix_len(&supernet, 32, "prefix_len").is_ok());
+    }
+
+    #[test]
+    fn lists_free_addresses_from_pools_or_routes() {
+        let network = json!({
+            "routes": [{ "target": "10.0.0.0/29", "via": null }],
+        });
+        let network = network.as_object().unwrap();
+        let taken = HashSet::from([ip("10.0.0.2")]);
+        assert_eq!(
+            free_addresses(network, &taken, 10),
+            ["10.0.0.1", "10.0.0.3", "10.0.0.4", "10.0.0.5", "10.0.0.6"].map(ip)
+        );
+        assert_eq!(
+            free_addresses(network, &taken, 2),
+            ["10.0.0.1", "10.0.0.3"].map(ip)
+        );
+
+        let pooled = json!({
+            "routes": [{ "target": "10.0.0.0/24", "via": null }],
+            "ipAssignmentPools": [
+                { "ipRangeStart": "10.0.0.100", "ipRangeEnd": "10.0.0.101" },
+                { "ipRangeStart": "10.0.0.200", "ipRangeEnd": "10.0.0.200" }
+            ]
+        });
+        assert_eq!(
+            free_addresses(pooled.as_object().unwrap(), &taken, 10),
+            ["10.0.0.100", "10.0.0.101", "10.0.0.200"].map(ip)
+        );
+
+        let top =
+            json!({ "routes": [{ "target": "ffff:ffff:ffff:ffff:ffff:ffff:ffff:fffe/127" }] });
+        assert_eq!(
+            free_addresses(top.as_object().unwrap(), &HashSet::new(), 10).len(),
+            2
+        );
+    }
+
+    #[tokio::test]
+    async fn reports_invalid_configured_prefix_lengths() {
+        let env = ipam_env("10.8.0.0/16", 64).await;
+        let ipam = env.app_state().ipam;
+
+        let expected = (
+            "ipam.subnet_prefix_len".to_string(),
+            "must be between 16 and 32 for supernet 10.8.0.0/16".to_string(),
+        );
+        assert_eq!(invalid_field(ipam.overview().await), expected);
+        assert_eq!(invalid_field(ipam.next_subnet(None).await), expected);
+        assert_eq!(
+            invalid_field(ipam.next_subnet(Some(40)).await).0,
+            "prefix_len"
+        );
+        assert_eq!(
+            ipam.next_subnet(Some(24)).await.unwrap(),
+            subnet("10.8.0.0/24")
+        );
+    }
+
+    #[tokio::test]
+    async fn reports_usage_and_conflicts() {
+        let env = ipam_env("10.8.0.0/16", 24).await;
+        env.controller.add_network(
+            NETWORK,
+            json!({ "name": "lab", "routes": [{ "target": "10.8.0.0/24", "via": null }] }),
+        );
+        env.controller.add_network(
+            "abcdef0123000002",
+            json!({ "name": "office", "routes": [
+                { "target": "10.8.0.128/25", "via": null },
+                { "target": "192.168.0.0/24", "via": null }
+            ] }),
+        );
+        let ipam = env.app_state().ipam;
+
+        let overview = ipam.overview().await.unwrap();
+        assert_eq!(overview.allocated.len(), 2);
+        assert_eq!(overview.next_subnet, Some(subnet("10.8.1.0/24")));
+        assert_eq!(overview.conflicts.len(), 1);
+        assert_eq!(overview.conflicts[0].first.network_name, "lab");
+        assert_eq!(overview.conflicts[0].second.range, "10.8.0.128/25");
+
+        let unconfigured = TestEnv::new().await;
+        assert_eq!(
+            invalid_field(unconfigured.app_state().ipam.next_subnet(None).await).0,
+            "supernet"
+        );
+    }
+
+    #[tokio::test]
+    async fn allocates_subnets_with_a_matching_pool() {
+        let env = ipam_env("10.8.0.0/22", 24).await;
+        env.controller.add_network(
+            NETWORK,
+            json!({ "routes": [{ "target": "10.8.0.0/24", "via": null }] }),
+        );
+        let ipam = env.app_state().ipam;
+
+        let network = ipam.allocate_subnet("admin", NETWORK, None).await.unwrap();
+        assert_eq!(
+            network["routes"],
+            json!([
+                { "target": "10.8.0.0/24", "via": null },
+                { "target": "10.8.1.0/24", "via": null }
+            ])
+        );
+        assert_eq!(
+            network["ipAssignmentPools"],
+            json!([{ "ipRangeStart": "10.8.1.1", "ipRangeEnd": "10.8.1.254" }])
+        );
+
+        ipam.allocate_subnet("admin", NETWORK, Some(23))
+            .await
+            .unwrap();
+        assert_eq!(
+            invalid_field(ipam.allocate_subnet("admin", NETWORK, None).await),
+            (
+                "supernet".to_string(),
+                "10.8.0.0/22 has no free /24 left".to_string()
+            )
+        );
+    }
+
+    #[tokio::test]
+    async fn concurrent_subnet_allocations_do_not_overlap() {
+        let env = ipam_env("10.8.0.0/22", 24).await;
+        env.controller.add_network(NETWORK, json!({}));
+        env.controller.add_network("abcdef0123000002", json!({}));
+        let ipam = env.app_state().ipam;
+
+        let (first, second) = tokio::join!(
+            ipam.allocate_subnet("admin", NETWORK, None),
+            ipam.allocate_subnet("admin", "abcdef0123000002", None)
+        );
+        let mut targets = [first.unwrap(), second.unwrap()]
+            .map(|network| network["routes"][0]["target"].as_str().unwrap().to_string());
+        targets.sort();
+        assert_eq!(targets, ["10.8.0.0/24", "10.8.1.0/24"]);
+    }
+
+    #[tokio::test]
+    async fn reserves_and_allocates_addresses() {
+        let env = TestEnv::new().await;
+        env.controller.add_network(
+            NETWORK,
+            json!({ "routes": [{ "target": "10.0.0.0/29", "via": null }] }),
+        );
+        env.controller.add_member(
+            NETWORK,
+            "1111111111",
+            json!({ "ipAssignments": ["10.0.0.1"] }),
+        );
+        env.controller.add_member(NETWORK, "2222222222", json!({}));
+        let ipam = env.app_state().ipam;
+
+        let reserve = |ip: Option<&str>, member_id: Option<&str>| ReserveIpRequest {
+            ip: ip.map(|text| text.parse().unwrap()),
+            member_id: member_id.map(str::to_string),
+            note: String::new(),
+        };
+        let reserved = ipam
+            .reserve("admin", NETWORK, reserve(None, Some("2222222222")))
+            .await
+            .unwrap();
+        assert_eq!(reserved.ip, ip("10.0.0.2"));
+        ipam.reserve("admin", NETWORK, reserve(Some("10.0.0.3"), None))
+            .await
+            .unwrap();
+
+        assert_eq!(
+            invalid_field(
+                ipam.reserve("admin", NETWORK, reserve(Some("10.0.0.1"), None))
+                    .await
+            ),
+            (
+                "ip".to_string(),
+                "is already assigned or reserved".to_string()
+            )
+        );
+        assert_eq!(
+            invalid_field(
+                ipam.reserve("admin", NETWORK, reserve(Some("10.1.0.1"), None))
+                    .await
+            )
+            .1,
+            "is outside the network's routes"
+        );
+        assert_eq!(
+            invalid_field(
+                ipam.reserve("admin", NETWORK, reserve(None, Some("nope")))
+                    .await
+            )
+            .0,
+            "member_id"
+        );
+        assert_eq!(ipam.next_ips(NETWORK, 0).await.unwrap(), [ip("10.0.0.4")]);
+
+        // The member gets its reservation, later the next free address
+        let member = ipam
+            .allocate_ip(
+                "admin",
+                NETWORK,
+                "2222222222",
+                AllocateIpRequest { ip: None },
+            )
+            .await
+            .unwrap();
+        assert_eq!(member["ipAssignments"], json!(["10.0.0.2"]));
+        let member = ipam
+            .allocate_ip(
+                "admin",
+                NETWORK,
+                "2222222222",
+                AllocateIpRequest { ip: None },
+            )
+            .await
+            .unwrap();
+        assert_eq!(member["ipAssignments"], json!(["10.0.0.2", "10.0.0.4"]));
+
+        ipam.release("admin", NETWORK, ip("10.0.0.3"))
+            .await
+            .unwrap();
+        assert!(matches!(
+            ipam.release("admin", NETWORK, ip("10.0.0.3")).await,
+            Err(AppError::NotFound(_))
+        ));
+        assert_eq!(ipam.reservations(NETWORK).await.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn concurrent_address_allocations_do_not_collide() {
+        let env = TestEnv::new().await;
+        env.controller.add_network(
+            NETWORK,
+            json!({ "routes": [{ "target": "10.0.0.0/29", "via": null }] }),
+        );
+        let members = ["1111111111", "2222222222", "3333333333"];
+        for member_id in members {
+            env.controller.add_member(NETWORK, member_id, json!({}));
+        }
+        let ipam = env.app_state().ipam;
+
+        let allocate = |member_id| {
+            ipam.allocate_ip("admin", NETWORK, member_id, AllocateIpRequest { ip: None })
+        };
+        let (first, second, third) = tokio::join!(
+            allocate(members[0]),
+            allocate(members[1]),
+            allocate(members[2])
+        );
+        let mut assigned = [first.unwrap(), second.unwrap(), third.unwrap()]
+            .map(|member| member["ipAssignments"][0].as_str().unwrap().to_string());
+        assigned.sort();
+        assert_eq!(assigned, ["10.0.0.1", "10.0.0.2", "10.0.0.3"]);
+    }
+}
@@ -0,0 +1,73 @@
+use crate::error::Result;
+use crate::services::store::JsonStore;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::path::PathBuf;
+
+/// Number of addresses remembered per user
+const MAX_KNOWN_IPS: usize = 100;
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct LoginHistory {
+    /// Addresses each user has logged in from, most recent last
+    known_ips: HashMap<String, Vec<IpAddr>>,
+}
+
+/// Remembers where users logged in from to detect logins from new addresses
+#[derive(Clone)]
+pub struct LoginHistoryService {
+    store: JsonStore<LoginHistory>,
+}
+
+impl LoginHistoryService {
+    pub fn new(path: PathBuf) -> Result<Self> {
+        Ok(Self {
+            store: JsonStore::open(path)?,
+        })
+    }
+
+    /// Record a successful login and return whether the address is new for the user
+    pub async fn record(&self, username: &str, ip: IpAddr) -> Result<bool> {
+        self.store
+            .update(|history| {
+                let known_ips = history.known_ips.entry(username.to_string()).or_default();
+                let is_new = !known_ips.contains(&ip);
+
+                known_ips.retain(|known| *known != ip);
+                known_ips.push(ip);
+                if known_ips.len() > MAX_KNOWN_IPS {
+                    known_ips.remove(0);
+                }
+
+                Ok(is_new)
+            })
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn detects_new_addresses_per_user() {
+        let path =
+            std::env::temp_dir().join(format!("login-history-{}.json", uuid::Uuid::new_v4()));
+        let history = LoginHistoryService::new(path.clone()).unwrap();
+        let ip: IpAddr = "192.0.2.1".parse().unwrap();
+
+        assert!(history.record("admin", ip).await.unwrap());
+        assert!(!history.record("admin", ip).await.unwrap());
+        assert!(history.record("other", ip).await.unwrap());
+
+        // Known addresses survive a restart
+        let reopened = LoginHistoryService::new(path.clone()).unwrap();
+        assert!(!reopened.record("admin", ip).await.unwrap());
+        assert!(reopened
+            .record("admin", "192.0.2.2".parse().unwrap())
+            .await
+            .unwrap());
+        std::fs::remove_file(path).ok();
+    }
+}
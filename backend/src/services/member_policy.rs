@@ -0,0 +1,569 @@
+use crate::error::{AppError, Result};
+use crate::models::{MemberPolicy, NetworkMember, PolicyAction};
+use crate::services::audit::{AuditEntry, AuditService};
+use crate::services::events::EventKind;
+use crate::services::{
+    ConfigService, ControllerMonitor, EventService, MetadataService, ZeroTierService,
+};
+use crate::utils::glob_match;
+use serde_json::{json, Value};
+use tokio::sync::broadcast::error::RecvError;
+
+/// Applies the configured member policies to members waiting for approval
+#[derive(Clone)]
+pub struct MemberPolicyService {
+    config: ConfigService,
+    events: EventService,
+    zerotier: ZeroTierService,
+    monitor: ControllerMonitor,
+    metadata: MetadataService,
+    audit: AuditService,
+}
+
+impl MemberPolicyService {
+    pub fn new(
+        config: ConfigService,
+        events: EventService,
+        zerotier: ZeroTierService,
+        monitor: ControllerMonitor,
+        metadata: MetadataService,
+        audit: AuditService,
+    ) -> Self {
+        Self {
+            config,
+            events,
+            zerotier,
+            monitor,
+            metadata,
+            audit,
+        }
+    }
+
+    /// Start evaluating new members in the background
+    pub fn spawn(&self) {
+        let service = self.clone();
+        let mut receiver = self.events.subscribe();
+
+        tokio::spawn(async move {
+            loop {
+                match receiver.recv().await {
+                    Ok(event) => {
+                        if let EventKind::MemberJoined {
+                            network_id,
+                            member_id,
+                            authorized: false,
+                            ..
+                        } = &event.kind
+                        {
+                            service.evaluate(network_id, member_id).await;
+                        }
+                    }
+                    Err(RecvError::Lagged(skipped)) => {
+                        tracing::warn!("Member policies lagged, skipped {} events", skipped);
+                    }
+                    Err(RecvError::Closed) => break,
+                }
+            }
+        });
+    }
+
+    async fn evaluate(&self, network_id: &str, member_id: &str) {
+        let config = self.config.get_config();
+        if config.mode.read_only {
+            tracing::info!(
+                "Member policies are paused while read-only, {} on network {} is left pending",
+                member_id,
+                network_id
+            );
+            return;
+        }
+        let policies: Vec<&MemberPolicy> = config
+            .member_policies
+            .iter()
+            .filter(|policy| policy.network_id == network_id)
+            .collect();
+        if policies.is_empty() {
+            return;
+        }
+
+        let snapshot = self.monitor.snapshot();
+        let Some(network) = snapshot.networks.get(network_id) else {
+            return;
+        };
+        let Some(member) = network.members.get(member_id) else {
+            return;
+        };
+        let authorized_count = network
+            .members
+            .values()
+            .filter(|member| member.authorized())
+            .count();
+
+        let Some(policy) = policies
+            .into_iter()
+            .find(|policy| policy_matches(policy, member, authorized_count))
+        else {
+            tracing::debug!(
+                "No member policy matched {} on network {}",
+                member_id,
+                network_id
+            );
+            return;
+        };
+
+        let result = self.apply(policy, network_id, member_id).await;
+        let outcome = match &result {
+            Ok(()) => Value::String("applied".to_string()),
+            Err(e @ AppError::ValidationError(fields)) => {
+                json!({ "error": e.to_string(), "fields": fields })
+            }
+            Err(e) => json!({ "error": e.to_string() }),
+        };
+
+        self.audit
+            .record(
+                AuditEntry::new(
+                    format!("policy:{}", policy.name),
+                    policy_action_name(policy),
+                )
+                .member(network_id, member_id)
+                .details(json!({
+                    "policy": policy.name,
+                    "name": member.name(),
+                    "physical_addresses": member.physical_addresses(),
+                    "tags": policy.tags,
+                    "ip_assignments": policy.ip_assignments,
+                    "outcome": outcome,
+                })),
+            )
+            .await;
+    }
+
+    async fn apply(&self, policy: &MemberPolicy, network_id: &str, member_id: &str) -> Result<()> {
+        if policy.action == PolicyAction::Reject {
+            if policy.delete_rejected {
+                self.zerotier.delete_member(network_id, member_id).await?;
+                self.metadata.remove_member(network_id, member_id).await?;
+            }
+            return Ok(());
+        }
+
+        let mut update = serde_json::Map::new();
+        if policy.action == PolicyAction::Authorize {
+            update.insert("authorized".to_string(), Value::Bool(true));
+        }
+        if !policy.tags.is_empty() {
+            update.insert("tags".to_string(), json!(policy.tags));
+        }
+        if !policy.ip_assignments.is_empty() {
+            update.insert("ipAssignments".to_string(), json!(policy.ip_assignments));
+        }
+
+        if update.is_empty() {
+            return Ok(());
+        }
+        let update = Value::Object(update);
+        if !policy.ip_assignments.is_empty() {
+            // Checked like a member update from the API, so a fixed address
+            // is only ever handed to one member
+            self.zerotier
+                .validate_update(
+                    &format!("/controller/network/{}/member/{}", network_id, member_id),
+                    &serde_json::to_vec(&update)?,
+                )
+                .await?;
+        }
+        self.zerotier
+            .update_member(network_id, member_id, &update)
+            .await?;
+
+        Ok(())
+    }
+}
+
+fn policy_action_name(policy: &MemberPolicy) -> &'static str {
+    match policy.action {
+        PolicyAction::Authorize => "member.authorize",
+        PolicyAction::Reject if policy.delete_rejected => "member.delete",
+        PolicyAction::Reject => "member.reject",
+        PolicyAction::Assign => "member.assign",
+    }
+}
+
+fn policy_matches(policy: &MemberPolicy, member: &NetworkMember, authorized_count: usize) -> bool {
+    let conditions = &policy.conditions;
+
+    if !conditions.node_ids.is_empty()
+        && !conditions
+            .node_ids
+            .iter()
+            .any(|id| id.eq_ignore_ascii_case(member.id()))
+    {
+        return false;
+    }
+
+    if !conditions.physical_cidrs.is_empty() {
+        let addresses = member.physical_addresses();
+        let inside = conditions
+            .physical_cidrs
+            .iter()
+            .any(|cidr| addresses.iter().any(|address| cidr.contains(address)));
+        if !inside {
+            return false;
+        }
+    }
+
+    if let Some(pattern) = &conditions.name_pattern {
+        if !glob_match(pattern, member.name()) {
+            return false;
+        }
+    }
+
+    if let Some(max_members) = conditions.max_members {
+        if authorized_count >= max_members {
+            return false;
+        }
+    }
+
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::PolicyConditions;
+    use crate::state::AppState;
+    use crate::test_support::TestEnv;
+
+    const NETWORK: &str = "abcdef0123000001";
+
+    fn policy(action: PolicyAction, conditions: PolicyConditions) -> MemberPolicy {
+        MemberPolicy {
+            name: "lab".to_string(),
+            network_id: NETWORK.to_string(),
+            conditions,
+            action,
+            tags: Vec::new(),
+            ip_assignments: Vec::new(),
+            delete_rejected: false,
+        }
+    }
+
+    fn member(id: &str, name: &str) -> NetworkMember {
+        let config = json!({ "id": id, "name": name });
+        NetworkMember::new(config.as_object().cloned().unwrap(), None)
+    }
+
+    /// Start the services with `policies` and a polled controller snapshot
+    async fn setup(env: &TestEnv, policies: Vec<MemberPolicy>) -> AppState {
+        env.config
+            .update(|config| {
+                config.member_policies = policies;
+                Ok(())
+            })
+            .await
+            .unwrap();
+        let app_state = env.app_state();
+        app_state.monitor.poll().await.unwrap();
+        app_state
+    }
+
+    #[test]
+    fn empty_conditions_match_everyone() {
+        let policy = policy(PolicyAction::Authorize, PolicyConditions::default());
+        assert!(policy_matches(&policy, &member("1111111111", ""), 0));
+    }
+
+    #[test]
+    fn matches_node_ids_and_names() {
+        let by_id = policy(
+            PolicyAction::Authorize,
+            PolicyConditions {
+                node_ids: vec!["1111111111".to_string()],
+                ..Default::default()
+            },
+        );
+        assert!(policy_matches(&by_id, &member("1111111111", ""), 0));
+        assert!(!policy_matches(&by_id, &member("2222222222", ""), 0));
+
+        let by_name = policy(
+            PolicyAction::Authorize,
+            PolicyConditions {
+                name_pattern: Some("lab-*".to_string()),
+                ..Default::default()
+            },
+        );
+        assert!(policy_matches(&by_name, &member("1111111111", "lab-01"), 0));
+        assert!(!policy_matches(
+            &by_name,
+            &member("1111111111", "office"),
+            0
+        ));
+    }
+
+    #[test]
+    fn stops_matching_at_max_members() {
+        let policy = policy(
+            PolicyAction::Authorize,
+            PolicyConditions {
+                max_members: Some(2),
+                ..Default::default()
+            },
+        );
+        assert!(policy_matches(&policy, &member("1111111111", ""), 1));
+        assert!(!policy_matches(&policy, &member("1111111111", ""), 2));
+    }
+
+    #[test]
+    fn names_audit_actions() {
+        let mut reject = policy(PolicyAction::Reject, PolicyConditions::default());
+        assert_eq!(policy_action_name(&reject), "member.reject");
+        reject.delete_rejected = true;
+        assert_eq!(policy_action_name(&reject), "member.delete");
+        let assign = policy(PolicyAction::Assign, PolicyConditions::default());
+        assert_eq!(policy_action_name(&assign), "member.assign");
+    }
+
+    #[tokio::test]
+    async fn authorizes_matching_members_from_a_subnet() {
+        let env = TestEnv::new().await;
+        env.controller.add_network(
+            NETWORK,
+            json!({ "routes": [{ "target": "10.0.0.0/24", "via": null }] }),
+        );
+        env.controller.add_member(NETWORK, "1111111111", json!({}));
+        env.controller.add_member(NETWORK, "2222222222", json!({}));
+        // The fake controller reports peers on 192.0.2.1
+        env.controller.add_peer("1111111111", 10, true);
+
+        let mut authorize = policy(
+            PolicyAction::Authorize,
+            PolicyConditions {
+                physical_cidrs: vec!["192.0.2.0/24".parse().unwrap()],
+                ..Default::default()
+            },
+        );
+        authorize.tags = vec![[1, 100]];
+        authorize.ip_assignments = vec!["10.0.0.10".to_string()];
+        let app_state = setup(&env, vec![authorize]).await;
+
+        app_state
+            .member_policy
+            .evaluate(NETWORK, "1111111111")
+            .await;
+        app_state
+            .member_policy
+            .evaluate(NETWORK, "2222222222")
+            .await;
+
+        let member = env.controller.member(NETWORK, "1111111111").unwrap();
+        assert_eq!(member["authorized"], true);
+        assert_eq!(member["tags"], json!([[1, 100]]));
+        assert_eq!(member["ipAssignments"], json!(["10.0.0.10"]));
+        // No active path inside the subnet
+        let member = env.controller.member(NETWORK, "2222222222").unwrap();
+        assert_eq!(member["authorized"], false);
+
+        let entries = app_state.audit.recent(10, |_| true).await;
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].actor, "policy:lab");
+        assert_eq!(entries[0].action, "member.authorize");
+        assert_eq!(entries[0].member_id.as_deref(), Some("1111111111"));
+        assert_eq!(entries[0].details["outcome"], "applied");
+        assert_eq!(
+            entries[0].details["physical_addresses"],
+            json!(["192.0.2.1"])
+        );
+    }
+
+    #[tokio::test]
+    async fn hands_out_fixed_addresses_once() {
+        let env = TestEnv::new().await;
+        env.controller.add_network(
+            NETWORK,
+            json!({ "routes": [{ "target": "10.0.0.0/24", "via": null }] }),
+        );
+        env.controller.add_member(NETWORK, "1111111111", json!({}));
+        env.controller.add_member(NETWORK, "2222222222", json!({}));
+
+        let mut authorize = policy(PolicyAction::Authorize, PolicyConditions::default());
+        authorize.ip_assignments = vec!["10.0.0.10".to_string()];
+        let app_state = setup(&env, vec![authorize]).await;
+        for member_id in ["1111111111", "2222222222"] {
+            app_state.member_policy.evaluate(NETWORK, member_id).await;
+        }
+
+        let member = env.controller.member(NETWORK, "1111111111").unwrap();
+        assert_eq!(member["ipAssignments"], json!(["10.0.0.10"]));
+        // The second member is left pending
+        let member = env.controller.member(NETWORK, "2222222222").unwrap();
+        assert_eq!(member["authorized"], false);
+        assert!(member
+            .get("ipAssignments")
+            .is_none_or(|ips| ips == &json!([])));
+
+        let entries = app_state.audit.recent(10, |_| true).await;
+        let entry = entries
+            .iter()
+            .find(|entry| entry.member_id.as_deref() == Some("2222222222"))
+            .unwrap();
+        let fields = &entry.details["outcome"]["fields"];
+        assert_eq!(fields[0]["field"], "ipAssignments[0]");
+        assert_eq!(
+            fields[0]["message"],
+            "10.0.0.10 is already assigned to member 1111111111"
+        );
+    }
+
+    #[tokio::test]
+    async fn rejects_addresses_outside_the_routes() {
+        let env = TestEnv::new().await;
+        env.controller.add_network(NETWORK, json!({}));
+        env.controller.add_member(NETWORK, "1111111111", json!({}));
+
+        let mut assign = policy(PolicyAction::Assign, PolicyConditions::default());
+        assign.ip_assignments = vec!["10.0.0.10".to_string()];
+        let app_state = setup(&env, vec![assign]).await;
+        app_state
+            .member_policy
+            .evaluate(NETWORK, "1111111111")
+            .await;
+
+        let member = env.controller.member(NETWORK, "1111111111").unwrap();
+        assert!(member
+            .get("ipAssignments")
+            .is_none_or(|ips| ips == &json!([])));
+        let entries = app_state.audit.recent(10, |_| true).await;
+        assert_eq!(
+            entries[0].details["outcome"]["fields"][0]["field"],
+            "ipAssignments[0]"
+        );
+    }
+
+    #[tokio::test]
+    async fn does_nothing_while_read_only() {
+        let env = TestEnv::new().await;
+        env.controller.add_network(NETWORK, json!({}));
+        env.controller.add_member(NETWORK, "1111111111", json!({}));
+
+        let app_state = setup(
+            &env,
+            vec![policy(PolicyAction::Authorize, PolicyConditions::default())],
+        )
+        .await;
+        env.config
+            .update(|config| {
+                config.mode.read_only = true;
+                Ok(())
+            })
+            .await
+            .unwrap();
+        app_state
+            .member_policy
+            .evaluate(NETWORK, "1111111111")
+            .await;
+
+        let member = env.controller.member(NETWORK, "1111111111").unwrap();
+        assert_eq!(member["authorized"], false);
+        assert!(app_state.audit.recent(10, |_| true).await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn assigns_without_authorizing() {
+        let env = TestEnv::new().await;
+        env.controller.add_network(NETWORK, json!({}));
+        env.controller.add_member(NETWORK, "1111111111", json!({}));
+
+        let mut assign = policy(PolicyAction::Assign, PolicyConditions::default());
+        assign.tags = vec![[2, 1]];
+        let app_state = setup(&env, vec![assign]).await;
+        app_state
+            .member_policy
+            .evaluate(NETWORK, "1111111111")
+            .await;
+
+        let member = env.controller.member(NETWORK, "1111111111").unwrap();
+        assert_eq!(member["authorized"], false);
+        assert_eq!(member["tags"], json!([[2, 1]]));
+    }
+
+    #[tokio::test]
+    async fn first_matching_policy_wins() {
+        let env = TestEnv::new().await;
+        env.controller.add_network(NETWORK, json!({}));
+        env.controller.add_member(NETWORK, "1111111111", json!({}));
+
+        let mut reject = policy(PolicyAction::Reject, PolicyConditions::default());
+        reject.name = "deny".to_string();
+        reject.delete_rejected = true;
+        let authorize = policy(PolicyAction::Authorize, PolicyConditions::default());
+        let app_state = setup(&env, vec![reject, authorize]).await;
+        app_state
+            .metadata
+            .update(NETWORK, "1111111111", |metadata| {
+                metadata.info.owner = Some("previous owner".to_string());
+                Ok(())
+            })
+            .await
+            .unwrap();
+        app_state
+            .member_policy
+            .evaluate(NETWORK, "1111111111")
+            .await;
+
+        assert!(env.controller.member(NETWORK, "1111111111").is_none());
+        // Nothing is kept for a member that no longer exists
+        assert!(app_state.metadata.network(NETWORK).await.is_empty());
+        let entries = app_state.audit.recent(10, |_| true).await;
+        assert_eq!(entries[0].actor, "policy:deny");
+        assert_eq!(entries[0].action, "member.delete");
+    }
+
+    #[tokio::test]
+    async fn records_failures() {
+        let env = TestEnv::new().await;
+        env.controller.add_network(NETWORK, json!({}));
+        env.controller.add_member(NETWORK, "1111111111", json!({}));
+        env.controller
+            .state
+            .lock()
+            .unwrap()
+            .failures
+            .insert(format!(
+                "POST /controller/network/{}/member/1111111111",
+                NETWORK
+            ));
+
+        let app_state = setup(
+            &env,
+            vec![policy(PolicyAction::Authorize, PolicyConditions::default())],
+        )
+        .await;
+        app_state
+            .member_policy
+            .evaluate(NETWORK, "1111111111")
+            .await;
+
+        let entries = app_state.audit.recent(10, |_| true).await;
+        assert!(entries[0].details["outcome"]["error"].is_string());
+    }
+
+    #[tokio::test]
+    async fn ignores_other_networks() {
+        let env = TestEnv::new().await;
+        env.controller.add_network(NETWORK, json!({}));
+        env.controller.add_member(NETWORK, "1111111111", json!({}));
+
+        let mut other = policy(PolicyAction::Authorize, PolicyConditions::default());
+        other.network_id = "abcdef0123000002".to_string();
+        let app_state = setup(&env, vec![other]).await;
+        app_state
+            .member_policy
+            .evaluate(NETWORK, "1111111111")
+            .await;
+
+        let member = env.controller.member(NETWORK, "1111111111").unwrap();
+        assert_eq!(member["authorized"], false);
+        assert!(app_state.audit.recent(10, |_| true).await.is_empty());
+    }
+}
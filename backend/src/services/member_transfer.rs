@@ -0,0 +1,742 @@
+use crate::error::{AppError, FieldError, Result};
+use crate::models::{
+    ImportField, ImportRequest, ImportResponse, ImportRowResult, ImportStatus, MemberInfo,
+    MemberMetadata, MemberRecord,
+};
+use crate::services::audit::{AuditEntry, AuditService};
+use crate::services::{MetadataService, ZeroTierService};
+use crate::utils::is_node_id;
+use serde_json::{json, Map, Value};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::net::IpAddr;
+
+const CSV_HEADER: [&str; 15] = [
+    "id",
+    "name",
+    "authorized",
+    "ip_assignments",
+    "rfc4193",
+    "6plane",
+    "6plane_prefix",
+    "tags",
+    "online",
+    "last_seen",
+    "owner",
+    "asset_tag",
+    "location",
+    "notes",
+    "labels",
+];
+
+/// Split a list cell on `;`, `,` or whitespace
+fn split_list(cell: &str) -> impl Iterator<Item = &str> {
+    cell.split(|c: char| c == ';' || c == ',' || c.is_whitespace())
+        .filter(|item| !item.is_empty())
+}
+
+fn parse_bool(cell: &str) -> Option<bool> {
+    match cell.to_ascii_lowercase().as_str() {
+        "true" | "yes" | "y" | "1" => Some(true),
+        "false" | "no" | "n" | "0" => Some(false),
+        _ => None,
+    }
+}
+
+/// Parse `id=value` pairs
+fn parse_pairs(cell: &str) -> Option<Vec<(&str, &str)>> {
+    split_list(cell)
+        .map(|pair| pair.split_once('=').or_else(|| pair.split_once(':')))
+        .collect()
+}
+
+/// The values of one CSV row, `None` leaves the field unchanged
+#[derive(Debug, Default)]
+struct ImportRow {
+    line: u64,
+    id: Option<String>,
+    name: Option<String>,
+    authorized: Option<bool>,
+    ip_assignments: Option<Vec<IpAddr>>,
+    tags: Option<Vec<[u32; 2]>>,
+    owner: Option<String>,
+    asset_tag: Option<String>,
+    location: Option<String>,
+    notes: Option<String>,
+    labels: Option<BTreeMap<String, String>>,
+    errors: Vec<String>,
+}
+
+impl ImportRow {
+    fn parse(line: u64, columns: &[ImportField], record: &csv::StringRecord) -> Self {
+        let mut row = ImportRow {
+            line,
+            ..Default::default()
+        };
+
+        for (field, cell) in columns.iter().zip(record.iter()) {
+            if cell.is_empty() {
+                continue;
+            }
+
+            match field {
+                ImportField::Id => {
+                    if is_node_id(cell) {
+                        row.id = Some(cell.to_ascii_lowercase());
+                    } else {
+                        row.errors
+                            .push(format!("{} is not a valid member ID", cell));
+                    }
+                }
+                ImportField::Name => row.name = Some(cell.to_string()),
+                ImportField::Authorized => match parse_bool(cell) {
+                    Some(authorized) => row.authorized = Some(authorized),
+                    None => row
+                        .errors
+                        .push(format!("authorized must be true or false, got {}", cell)),
+                },
+                ImportField::IpAssignments => {
+                    match split_list(cell)
+                        .map(str::parse)
+                        .collect::<std::result::Result<_, _>>()
+                    {
+                        Ok(ips) => row.ip_assignments = Some(ips),
+                        Err(_) => row
+                            .errors
+                            .push(format!("ip_assignments contains an invalid IP: {}", cell)),
+                    }
+                }
+                ImportField::Tags => {
+                    let tags = parse_pairs(cell).and_then(|pairs| {
+                        pairs
+                            .into_iter()
+                            .map(|(id, value)| Some([id.parse().ok()?, value.parse().ok()?]))
+                            .collect::<Option<Vec<_>>>()
+                    });
+                    match tags {
+                        Some(tags) => row.tags = Some(tags),
+                        None => row
+                            .errors
+                            .push(format!("tags must be id=value pairs, got {}", cell)),
+                    }
+                }
+                ImportField::Owner => row.owner = Some(cell.to_string()),
+                ImportField::AssetTag => row.asset_tag = Some(cell.to_string()),
+                ImportField::Location => row.location = Some(cell.to_string()),
+                ImportField::Notes => row.notes = Some(cell.to_string()),
+                ImportField::Labels => match parse_pairs(cell) {
+                    Some(pairs) => {
+                        row.labels = Some(
+                            pairs
+                                .into_iter()
+                                .map(|(key, value)| (key.to_string(), value.to_string()))
+                                .collect(),
+                        )
+                    }
+                    None => row
+                        .errors
+                        .push(format!("labels must be key=value pairs, got {}", cell)),
+                },
+                ImportField::Ignore => {}
+            }
+        }
+
+        if row.id.is_none() && row.errors.is_empty() {
+            row.errors.push("id is missing".to_string());
+        }
+
+        row
+    }
+
+    /// The controller fields of the row that differ from `current`
+    fn member_update(&self, current: Option<&Map<String, Value>>) -> Map<String, Value> {
+        let mut update = Map::new();
+        let mut set = |key: &str, value: Value| {
+            if current.and_then(|member| member.get(key)) != Some(&value) {
+                update.insert(key.to_string(), value);
+            }
+        };
+
+        if let Some(name) = &self.name {
+            set("name", json!(name));
+        }
+        if let Some(authorized) = self.authorized {
+            set("authorized", json!(authorized));
+        }
+        if let Some(ips) = &self.ip_assignments {
+            set("ipAssignments", json!(ips));
+        }
+        if let Some(tags) = &self.tags {
+            set("tags", json!(tags));
+        }
+
+        update
+    }
+
+    /// The metadata after applying the row, if it differs from `current`
+    fn metadata_update(&self, current: &MemberInfo) -> Option<MemberInfo> {
+        let mut info = current.clone();
+        let fields = [
+            (&mut info.owner, &self.owner),
+            (&mut info.asset_tag, &self.asset_tag),
+            (&mut info.location, &self.location),
+            (&mut info.notes, &self.notes),
+        ];
+        for (target, value) in fields {
+            if value.is_some() {
+                target.clone_from(value);
+            }
+        }
+        if let Some(labels) = &self.labels {
+            info.labels.extend(labels.clone());
+        }
+
+        let changed = serde_json::to_value(&info).ok() != serde_json::to_value(current).ok();
+        changed.then_some(info)
+    }
+}
+
+/// Exports members for reporting and imports them from spreadsheets
+#[derive(Clone)]
+pub struct MemberTransferService {
+    zerotier: ZeroTierService,
+    metadata: MetadataService,
+    audit: AuditService,
+}
+
+impl MemberTransferService {
+    pub fn new(zerotier: ZeroTierService, metadata: MetadataService, audit: AuditService) -> Self {
+        Self {
+            zerotier,
+            metadata,
+            audit,
+        }
+    }
+
+    /// All members of a network with their metadata, sorted by ID
+    pub async fn export(&self, network_id: &str) -> Result<Vec<MemberRecord>> {
+        let members = self.zerotier.network_members(network_id).await?;
+        let metadata = self.metadata.network(network_id).await;
+
+        let mut records: Vec<MemberRecord> = members
+            .into_iter()
+            .map(|member| {
+                let info = metadata
+                    .get(member.id())
+                    .map(|metadata| metadata.info.clone())
+                    .unwrap_or_default();
+
+                MemberRecord {
+                    id: member.id().to_string(),
+                    name: member.name().to_string(),
+                    authorized: member.authorized(),
+                    ip_assignments: member
+                        .ip_assignments()
+                        .into_iter()
+                        .map(str::to_string)
+                        .collect(),
+                    rfc4193: member.computed_addresses.rfc4193,
+                    sixplane: member.computed_addresses.sixplane,
+                    sixplane_prefix: member.computed_addresses.sixplane_prefix,
+                    tags: member
+                        .config
+                        .get("tags")
+                        .cloned()
+                        .and_then(|tags| serde_json::from_value(tags).ok())
+                        .unwrap_or_default(),
+                    online: member.online,
+                    last_seen: member.last_seen(),
+                    owner: info.owner,
+                    asset_tag: info.asset_tag,
+                    location: info.location,
+                    notes: info.notes,
+                    labels: info.labels,
+                }
+            })
+            .collect();
+        records.sort_by(|a, b| a.id.cmp(&b.id));
+
+        Ok(records)
+    }
+
+    pub fn to_csv(records: &[MemberRecord]) -> Result<String> {
+        let mut writer = csv::Writer::from_writer(Vec::new());
+        let csv_error =
+            |e: csv::Error| AppError::InternalServerError(format!("Failed to write CSV: {}", e));
+
+        writer.write_record(CSV_HEADER).map_err(csv_error)?;
+        for record in records {
+            let pairs = |pairs: Vec<String>| pairs.join(";");
+            writer
+                .write_record([
+                    record.id.clone(),
+                    record.name.clone(),
+                    record.authorized.to_string(),
+                    record.ip_assignments.join(";"),
+                    record.rfc4193.map(|ip| ip.to_string()).unwrap_or_default(),
+                    record.sixplane.map(|ip| ip.to_string()).unwrap_or_default(),
+                    record
+                        .sixplane_prefix
+                        .map(|prefix| prefix.to_string())
+                        .unwrap_or_default(),
+                    pairs(
+                        record
+                            .tags
+                            .iter()
+                            .map(|[id, value]| format!("{}={}", id, value))
+                            .collect(),
+                    ),
+                    record.online.to_string(),
+                    record
+                        .last_seen
+                        .map(|last_seen| last_seen.to_rfc3339())
+                        .unwrap_or_default(),
+                    record.owner.clone().unwrap_or_default(),
+                    record.asset_tag.clone().unwrap_or_default(),
+                    record.location.clone().unwrap_or_default(),
+                    record.notes.clone().unwrap_or_default(),
+                    pairs(
+                        record
+                            .labels
+                            .iter()
+                            .map(|(key, value)| format!("{}={}", key, value))
+                            .collect(),
+                    ),
+                ])
+                .map_err(csv_error)?;
+        }
+
+        let bytes = writer
+            .into_inner()
+            .map_err(|e| AppError::InternalServerError(format!("Failed to write CSV: {}", e)))?;
+        String::from_utf8(bytes)
+            .map_err(|e| AppError::InternalServerError(format!("Failed to write CSV: {}", e)))
+    }
+
+    /// Create or update members from CSV rows. Rows are applied in order and
+    /// independently, a failed row does not stop the import.
+    pub async fn import(
+        &self,
+        actor: &str,
+        network_id: &str,
+        request: &ImportRequest,
+    ) -> Result<ImportResponse> {
+        let mut reader = csv::ReaderBuilder::new()
+            .trim(csv::Trim::All)
+            .from_reader(request.csv.as_bytes());
+
+        let headers = reader
+            .headers()
+            .map_err(|e| AppError::ValidationError(vec![FieldError::new("csv", e.to_string())]))?
+            .clone();
+        let columns: Vec<ImportField> = headers
+            .iter()
+            .map(|header| {
+                request
+                    .mapping
+                    .get(header)
+                    .copied()
+                    .or_else(|| ImportField::from_column(header))
+                    .unwrap_or(ImportField::Ignore)
+            })
+            .collect();
+
+        let mut errors = Vec::new();
+        if !columns.contains(&ImportField::Id) {
+            errors.push(FieldError::new("mapping", "no column is mapped to id"));
+        }
+        let mut seen = HashSet::new();
+        for field in columns
+            .iter()
+            .filter(|field| **field != ImportField::Ignore)
+        {
+            if !seen.insert(field) {
+                errors.push(FieldError::new(
+                    "mapping",
+                    format!("more than one column is mapped to {:?}", field),
+                ));
+            }
+        }
+        if !errors.is_empty() {
+            return Err(AppError::ValidationError(errors));
+        }
+
+        // Parse everything first so conflicts between rows are reported up front
+        let mut rows = Vec::new();
+        for record in reader.records() {
+            match record {
+                Ok(record) => {
+                    let line = record.position().map(|p| p.line()).unwrap_or_default();
+                    rows.push(ImportRow::parse(line, &columns, &record));
+                }
+                Err(e) => rows.push(ImportRow {
+                    line: e.position().map(|p| p.line()).unwrap_or_default(),
+                    errors: vec![e.to_string()],
+                    ..Default::default()
+                }),
+            }
+        }
+
+        let mut member_lines: HashMap<String, u64> = HashMap::new();
+        let mut ip_lines: HashMap<IpAddr, u64> = HashMap::new();
+        for row in &mut rows {
+            if let Some(id) = &row.id {
+                if let Some(line) = member_lines.insert(id.clone(), row.line) {
+                    row.errors
+                        .push(format!("member {} is also on line {}", id, line));
+                }
+            }
+            for ip in row.ip_assignments.iter().flatten() {
+                if let Some(line) = ip_lines.insert(*ip, row.line) {
+                    row.errors
+                        .push(format!("IP {} is also assigned on line {}", ip, line));
+                }
+            }
+        }
+
+        let metadata = self.metadata.network(network_id).await;
+        let mut results = Vec::with_capacity(rows.len());
+        for row in &rows {
+            let (status, errors) = if row.errors.is_empty() {
+                match self
+                    .import_row(actor, network_id, row, &metadata, request.dry_run)
+                    .await
+                {
+                    Ok(status) => (status, Vec::new()),
+                    Err(AppError::ValidationError(fields)) => (
+                        ImportStatus::Failed,
+                        fields
+                            .iter()
+                            .map(|field| format!("{}: {}", field.field, field.message))
+                            .collect(),
+                    ),
+                    Err(e) => (ImportStatus::Failed, vec![e.to_string()]),
+                }
+            } else {
+                (ImportStatus::Failed, row.errors.clone())
+            };
+
+            results.push(ImportRowResult {
+                line: row.line,
+                member_id: row.id.clone(),
+                status,
+                errors,
+            });
+        }
+
+        let count = |status| results.iter().filter(|row| row.status == status).count();
+        Ok(ImportResponse {
+            dry_run: request.dry_run,
+            total: results.len(),
+            created: count(ImportStatus::Created),
+            updated: count(ImportStatus::Updated),
+            unchanged: count(ImportStatus::Unchanged),
+            failed: count(ImportStatus::Failed),
+            rows: results,
+        })
+    }
+
+    async fn import_row(
+        &self,
+        actor: &str,
+        network_id: &str,
+        row: &ImportRow,
+        metadata: &HashMap<String, MemberMetadata>,
+        dry_run: bool,
+    ) -> Result<ImportStatus> {
+        let member_id = row.id.as_deref().unwrap_or_default();
+        let endpoint = format!("/controller/network/{}/member/{}", network_id, member_id);
+
+        let current = match self
+            .zerotier
+            .get_json::<Map<String, Value>>(&endpoint)
+            .await
+        {
+            Ok(member) => Some(member),
+            Err(AppError::NotFound(_)) => None,
+            Err(e) => return Err(e),
+        };
+        let update = row.member_update(current.as_ref());
+        let info = row.metadata_update(
+            &metadata
+                .get(member_id)
+                .map(|metadata| metadata.info.clone())
+                .unwrap_or_default(),
+        );
+
+        let status = match (&current, update.is_empty() && info.is_none()) {
+            (None, _) => ImportStatus::Created,
+            (Some(_), false) => ImportStatus::Updated,
+            (Some(_), true) => return Ok(ImportStatus::Unchanged),
+        };
+
+        let update = Value::Object(update);
+        if dry_run {
+            let body = serde_json::to_vec(&update)?;
+            self.zerotier.validate_update(&endpoint, &body).await?;
+            return Ok(status);
+        }
+
+        // Posting to a member that does not exist yet creates it
+        if current.is_none() || update.as_object().is_some_and(|update| !update.is_empty()) {
+            self.zerotier
+                .update_member(network_id, member_id, &update)
+                .await?;
+        }
+        if let Some(info) = &info {
+            self.metadata
+                .update(network_id, member_id, |metadata| {
+                    metadata.info = info.clone();
+                    Ok(())
+                })
+                .await?;
+        }
+
+        self.audit
+            .record(
+                AuditEntry::new(actor, "member.import")
+                    .member(network_id, member_id)
+                    .details(json!({
+                        "line": row.line,
+                        "created": current.is_none(),
+                        "update": update,
+                        "metadata": info,
+                    })),
+            )
+            .await;
+
+        Ok(status)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TestEnv;
+
+    const NETWORK: &str = "abcdef0123000001";
+
+    async fn setup(env: &TestEnv) -> MemberTransferService {
+        env.controller.add_network(
+            NETWORK,
+            json!({
+                "routes": [{ "target": "10.0.0.0/24", "via": null }],
+                "v6AssignMode": { "rfc4193": true },
+            }),
+        );
+        env.controller.add_member(
+            NETWORK,
+            "1111111111",
+            json!({
+                "name": "laptop",
+                "authorized": true,
+                "ipAssignments": ["10.0.0.11"],
+                "tags": [[1, 2]],
+            }),
+        );
+        let transfer = env.app_state().transfer;
+        transfer
+            .metadata
+            .update(NETWORK, "1111111111", |metadata| {
+                metadata.info.owner = Some("Alice".to_string());
+                metadata
+                    .info
+                    .labels
+                    .insert("team".to_string(), "design".to_string());
+                Ok(())
+            })
+            .await
+            .unwrap();
+        transfer
+    }
+
+    fn request(csv: &str) -> ImportRequest {
+        ImportRequest {
+            csv: csv.to_string(),
+            mapping: HashMap::new(),
+            dry_run: false,
+        }
+    }
+
+    fn statuses(response: &ImportResponse) -> Vec<(u64, ImportStatus)> {
+        response
+            .rows
+            .iter()
+            .map(|row| (row.line, row.status))
+            .collect()
+    }
+
+    #[test]
+    fn parses_cells() {
+        assert_eq!(
+            split_list("a; b,c  d").collect::<Vec<_>>(),
+            ["a", "b", "c", "d"]
+        );
+        assert_eq!(parse_bool("Yes"), Some(true));
+        assert_eq!(parse_bool("0"), Some(false));
+        assert_eq!(parse_bool("maybe"), None);
+        assert_eq!(parse_pairs("1=2;3:4"), Some(vec![("1", "2"), ("3", "4")]));
+        assert_eq!(parse_pairs("1=2;3"), None);
+    }
+
+    #[tokio::test]
+    async fn exports_members_with_metadata() {
+        let env = TestEnv::new().await;
+        let transfer = setup(&env).await;
+        env.controller.add_member(NETWORK, "2222222222", json!({}));
+
+        let records = transfer.export(NETWORK).await.unwrap();
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].id, "1111111111");
+        assert_eq!(records[0].tags, [[1, 2]]);
+        assert_eq!(records[0].owner.as_deref(), Some("Alice"));
+        assert_eq!(
+            records[0].rfc4193,
+            Some("fdab:cdef:123:0:199:9311:1111:1111".parse().unwrap())
+        );
+        assert_eq!(records[1].owner, None);
+
+        let csv = MemberTransferService::to_csv(&records).unwrap();
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines[0], CSV_HEADER.join(","));
+        assert_eq!(
+            lines[1],
+            "1111111111,laptop,true,10.0.0.11,fdab:cdef:123:0:199:9311:1111:1111,,,1=2,false,,Alice,,,,team=design"
+        );
+        assert!(lines[2].starts_with("2222222222,,false,"));
+    }
+
+    #[tokio::test]
+    async fn exported_csv_imports_unchanged() {
+        let env = TestEnv::new().await;
+        let transfer = setup(&env).await;
+
+        let records = transfer.export(NETWORK).await.unwrap();
+        let csv = MemberTransferService::to_csv(&records).unwrap();
+        let response = transfer
+            .import("admin", NETWORK, &request(&csv))
+            .await
+            .unwrap();
+        assert_eq!(statuses(&response), [(2, ImportStatus::Unchanged)]);
+        assert!(transfer.audit.recent(10, |_| true).await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn creates_and_updates_members() {
+        let env = TestEnv::new().await;
+        let transfer = setup(&env).await;
+
+        let csv = "id,name,authorized,ip_assignments,tags,location,labels\n\
+                   1111111111,laptop,yes,10.0.0.11,1=2,Berlin,floor=3\n\
+                   2222222222,printer,no,10.0.0.20;10.0.0.21,,,\n";
+        let response = transfer
+            .import("admin", NETWORK, &request(csv))
+            .await
+            .unwrap();
+        assert_eq!(
+            statuses(&response),
+            [(2, ImportStatus::Updated), (3, ImportStatus::Created)]
+        );
+        assert_eq!((response.created, response.updated), (1, 1));
+
+        let member = env.controller.member(NETWORK, "2222222222").unwrap();
+        assert_eq!(member["name"], "printer");
+        assert_eq!(member["ipAssignments"], json!(["10.0.0.20", "10.0.0.21"]));
+
+        // Metadata is merged, labels are added to the existing ones
+        let metadata = transfer.metadata.network(NETWORK).await;
+        let info = &metadata["1111111111"].info;
+        assert_eq!(info.owner.as_deref(), Some("Alice"));
+        assert_eq!(info.location.as_deref(), Some("Berlin"));
+        assert_eq!(info.labels.len(), 2);
+
+        let entries = transfer.audit.recent(10, |_| true).await;
+        assert_eq!(entries.len(), 2);
+        assert!(entries.iter().all(|entry| entry.action == "member.import"));
+    }
+
+    #[tokio::test]
+    async fn dry_runs_validate_without_writing() {
+        let env = TestEnv::new().await;
+        let transfer = setup(&env).await;
+
+        let mut request =
+            request("id,ip_assignments\n2222222222,10.0.0.20\n3333333333,192.168.1.1\n");
+        request.dry_run = true;
+        let response = transfer.import("admin", NETWORK, &request).await.unwrap();
+        assert_eq!(
+            statuses(&response),
+            [(2, ImportStatus::Created), (3, ImportStatus::Failed)]
+        );
+        // The controller would reject an IP outside the managed routes
+        assert!(response.rows[1].errors[0].starts_with("ipAssignments[0]"));
+        assert!(env.controller.member(NETWORK, "2222222222").is_none());
+    }
+
+    #[tokio::test]
+    async fn reports_invalid_rows() {
+        let env = TestEnv::new().await;
+        let transfer = setup(&env).await;
+
+        let csv = "id,authorized,ip_assignments,tags\n\
+                   bad,true,,\n\
+                   2222222222,maybe,10.0.0.x,1\n\
+                   3333333333,,10.0.0.30,\n\
+                   3333333333,,10.0.0.30,\n\
+                   ,true,,\n";
+        let response = transfer
+            .import("admin", NETWORK, &request(csv))
+            .await
+            .unwrap();
+        let errors: Vec<&Vec<String>> = response.rows.iter().map(|row| &row.errors).collect();
+        assert_eq!(errors[0], &["bad is not a valid member ID"]);
+        assert_eq!(errors[1].len(), 3);
+        assert!(errors[2].is_empty());
+        assert_eq!(
+            errors[3],
+            &[
+                "member 3333333333 is also on line 4",
+                "IP 10.0.0.30 is also assigned on line 4"
+            ]
+        );
+        assert_eq!(errors[4], &["id is missing"]);
+        assert_eq!(response.failed, 4);
+        assert_eq!(response.created, 1);
+    }
+
+    #[tokio::test]
+    async fn maps_custom_columns() {
+        let env = TestEnv::new().await;
+        let transfer = setup(&env).await;
+
+        let mut mapped = request("Node,Hostname,Comment\n2222222222,desk,ignored\n");
+        mapped.mapping.insert("Node".to_string(), ImportField::Id);
+        mapped
+            .mapping
+            .insert("Hostname".to_string(), ImportField::Name);
+        let response = transfer.import("admin", NETWORK, &mapped).await.unwrap();
+        assert_eq!(statuses(&response), [(2, ImportStatus::Created)]);
+        assert_eq!(
+            env.controller.member(NETWORK, "2222222222").unwrap()["name"],
+            "desk"
+        );
+
+        let error = transfer
+            .import("admin", NETWORK, &request("name\nx\n"))
+            .await
+            .unwrap_err();
+        assert!(matches!(error, AppError::ValidationError(_)));
+
+        let mut duplicate = request("id,Hostname\n2222222222,desk\n");
+        duplicate
+            .mapping
+            .insert("Hostname".to_string(), ImportField::Id);
+        let error = transfer
+            .import("admin", NETWORK, &duplicate)
+            .await
+            .unwrap_err();
+        let AppError::ValidationError(fields) = error else {
+            panic!("expected a validation error");
+        };
+        assert!(fields[0].message.contains("more than one column"));
+    }
+}
@@ -0,0 +1,99 @@
+use crate::error::Result;
+use crate::models::MemberMetadata;
+use crate::services::store::JsonStore;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct MetadataStore {
+    /// Metadata by network ID, then member ID
+    networks: HashMap<String, HashMap<String, MemberMetadata>>,
+}
+
+/// Backend-side member data keyed by network and member ID
+#[derive(Clone)]
+pub struct MetadataService {
+    store: JsonStore<MetadataStore>,
+}
+
+impl MetadataService {
+    pub fn new(path: PathBuf) -> Result<Self> {
+        Ok(Self {
+            store: JsonStore::open(path)?,
+        })
+    }
+
+    /// Metadata of every member of a network that has any
+    pub async fn network(&self, network_id: &str) -> HashMap<String, MemberMetadata> {
+        self.store
+            .read()
+            .await
+            .networks
+            .get(network_id)
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    /// Every stored entry as `(network_id, member_id, metadata)`
+    pub async fn all(&self) -> Vec<(String, String, MemberMetadata)> {
+        let store = self.store.read().await;
+        store
+            .networks
+            .iter()
+            .flat_map(|(network_id, members)| {
+                members.iter().map(|(member_id, metadata)| {
+                    (network_id.clone(), member_id.clone(), metadata.clone())
+                })
+            })
+            .collect()
+    }
+
+    /// Change a member's metadata, entries left empty are removed
+    pub async fn update<F, R>(&self, network_id: &str, member_id: &str, apply: F) -> Result<R>
+    where
+        F: FnOnce(&mut MemberMetadata) -> Result<R>,
+    {
+        self.store
+            .update(|store| {
+                let members = store.networks.entry(network_id.to_string()).or_default();
+                let metadata = members.entry(member_id.to_string()).or_default();
+                let result = apply(metadata)?;
+
+                if metadata.is_empty() {
+                    members.remove(member_id);
+                }
+                if members.is_empty() {
+                    store.networks.remove(network_id);
+                }
+
+                Ok(result)
+            })
+            .await
+    }
+
+    /// Drop everything stored for a member
+    pub async fn remove_member(&self, network_id: &str, member_id: &str) -> Result<()> {
+        self.store
+            .update(|store| {
+                if let Some(members) = store.networks.get_mut(network_id) {
+                    members.remove(member_id);
+                    if members.is_empty() {
+                        store.networks.remove(network_id);
+                    }
+                }
+                Ok(())
+            })
+            .await
+    }
+
+    /// Drop everything stored for a network's members
+    pub async fn remove_network(&self, network_id: &str) -> Result<()> {
+        self.store
+            .update(|store| {
+                store.networks.remove(network_id);
+                Ok(())
+            })
+            .await
+    }
+}
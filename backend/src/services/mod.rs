@@ -1,11 +1,50 @@
+pub mod access_schedule;
+pub mod audit;
 pub mod auth;
 pub mod config;
+pub mod dns;
+pub mod email;
+pub mod events;
+pub mod expiry;
+pub mod gitops;
+pub mod inventory;
+pub mod invitations;
 pub mod ip_ban;
+pub mod ipam;
+pub mod login_history;
+pub mod member_policy;
+pub mod member_transfer;
+pub mod metadata;
+pub mod monitor;
+pub mod network_templates;
+pub mod response_cache;
+pub mod search;
 pub mod static_files;
+pub mod store;
+pub mod webhooks;
 pub mod zerotier;
 
+pub use access_schedule::AccessScheduleService;
+pub use audit::AuditService;
 pub use auth::AuthService;
 pub use config::ConfigService;
+pub use dns::DnsService;
+pub use email::EmailService;
+pub use events::EventService;
+pub use expiry::ExpiryService;
+pub use gitops::GitOpsService;
+pub use inventory::InventoryService;
+pub use invitations::InvitationService;
 pub use ip_ban::IpBanService;
+pub use ipam::IpamService;
+pub use login_history::LoginHistoryService;
+pub use member_policy::MemberPolicyService;
+pub use member_transfer::MemberTransferService;
+pub use metadata::MetadataService;
+pub use monitor::ControllerMonitor;
+pub use network_templates::NetworkTemplateService;
+pub use response_cache::{ResponseCache, UpstreamResponse};
+pub use search::SearchService;
 pub use static_files::StaticFileService;
+pub use webhooks::WebhookService;
 pub use zerotier::ZeroTierService;
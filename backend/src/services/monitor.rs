@@ -0,0 +1,498 @@
+use crate::error::Result;
+use crate::models::NetworkMember;
+use crate::services::events::{EventKind, EventService};
+use crate::services::ZeroTierService;
+use chrono::{DateTime, TimeDelta, Utc};
+use futures::{stream, StreamExt, TryStreamExt};
+use serde_json::{Map, Value};
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{watch, Mutex};
+
+#[derive(Debug, Clone)]
+pub struct NetworkState {
+    pub config: Map<String, Value>,
+    pub members: HashMap<String, NetworkMember>,
+}
+
+impl NetworkState {
+    pub fn name(&self) -> &str {
+        self.config
+            .get("name")
+            .and_then(Value::as_str)
+            .unwrap_or("")
+    }
+
+    pub fn revision(&self) -> u64 {
+        self.config
+            .get("revision")
+            .and_then(Value::as_u64)
+            .unwrap_or(0)
+    }
+}
+
+/// The controller state as seen by the last successful poll
+#[derive(Debug, Clone, Default)]
+pub struct ControllerSnapshot {
+    pub networks: HashMap<String, NetworkState>,
+    pub updated_at: Option<DateTime<Utc>>,
+}
+
+impl ControllerSnapshot {
+    pub fn is_ready(&self) -> bool {
+        self.updated_at.is_some()
+    }
+}
+
+/// Polls the controller on a fixed interval, keeps the latest snapshot and
+/// publishes an event for every change between two polls.
+#[derive(Clone)]
+pub struct ControllerMonitor {
+    zerotier: ZeroTierService,
+    events: EventService,
+    snapshot: Arc<watch::Sender<Arc<ControllerSnapshot>>>,
+    /// Held while polling so concurrent polls cannot diff against the same
+    /// snapshot and publish the same events twice
+    poll_lock: Arc<Mutex<()>>,
+}
+
+impl ControllerMonitor {
+    pub fn new(zerotier: ZeroTierService, events: EventService) -> Self {
+        Self {
+            zerotier,
+            events,
+            snapshot: Arc::new(watch::Sender::new(Arc::new(ControllerSnapshot::default()))),
+            poll_lock: Arc::new(Mutex::new(())),
+        }
+    }
+
+    pub fn snapshot(&self) -> Arc<ControllerSnapshot> {
+        self.snapshot.borrow().clone()
+    }
+
+    /// Get notified whenever a poll stores a new snapshot
+    pub fn subscribe(&self) -> watch::Receiver<Arc<ControllerSnapshot>> {
+        self.snapshot.subscribe()
+    }
+
+    /// Start polling in the background, an interval of 0 disables the monitor
+    pub fn spawn(&self, interval_secs: u64) {
+        if interval_secs == 0 {
+            tracing::info!("Controller monitor disabled");
+            return;
+        }
+
+        let monitor = self.clone();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(Duration::from_secs(interval_secs));
+            interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+
+            loop {
+                interval.tick().await;
+                if let Err(e) = monitor.poll().await {
+                    tracing::warn!("Failed to poll controller: {}", e);
+                }
+            }
+        });
+    }
+
+    /// Poll unless the snapshot is younger than `max_age`, for callers that
+    /// need recent data while the background loop may not be running
+    pub async fn poll_if_older(&self, max_age: TimeDelta) -> Result<Arc<ControllerSnapshot>> {
+        let _guard = self.poll_lock.lock().await;
+        // Checked under the lock so a poll that just finished is reused
+        let fresh = self
+            .snapshot()
+            .updated_at
+            .is_some_and(|updated_at| Utc::now() - updated_at <= max_age);
+        if !fresh {
+            self.poll_locked().await?;
+        }
+        Ok(self.snapshot())
+    }
+
+    /// Fetch the current controller state and publish the differences
+    pub async fn poll(&self) -> Result<()> {
+        let _guard = self.poll_lock.lock().await;
+        self.poll_locked().await
+    }
+
+    async fn poll_locked(&self) -> Result<()> {
+        let previous = self.snapshot();
+
+        let network_ids: Vec<String> = self.zerotier.get_json("/controller/network").await?;
+        let peers = self.zerotier.list_peers().await?;
+
+        let mut networks = HashMap::new();
+        for network_id in network_ids {
+            let config: Map<String, Value> = self
+                .zerotier
+                .get_json(&format!("/controller/network/{}", network_id))
+                .await?;
+            let revisions: HashMap<String, u64> = self
+                .zerotier
+                .get_json(&format!("/controller/network/{}/member", network_id))
+                .await?;
+
+            let known = previous.networks.get(&network_id);
+            let peers = &peers;
+            let network = &config;
+            let members: Vec<NetworkMember> = stream::iter(revisions)
+                .map(|(member_id, revision)| {
+                    let network_id = &network_id;
+                    let cached = known
+                        .and_then(|network| network.members.get(&member_id))
+                        .filter(|member| member.revision() == revision)
+                        .map(|member| member.config.clone());
+
+                    async move {
+                        // Only refetch members whose revision changed
+                        let config = match cached {
+                            Some(config) => config,
+                            None => {
+                                self.zerotier
+                                    .get_json::<Map<String, Value>>(&format!(
+                                        "/controller/network/{}/member/{}",
+                                        network_id, member_id
+                                    ))
+                                    .await?
+                            }
+                        };
+                        let peer = config
+                            .get("address")
+                            .and_then(Value::as_str)
+                            .and_then(|address| peers.get(address))
+                            .cloned();
+
+                        let mut member = NetworkMember::new(config, peer);
+                        member.compute_addresses(network_id, network);
+                        Ok::<_, crate::error::AppError>(member)
+                    }
+                })
+                .buffer_unordered(self.zerotier.max_concurrency())
+                .try_collect()
+                .await?;
+
+            let members = members
+                .into_iter()
+                .map(|member| (member.id().to_string(), member))
+                .collect();
+            networks.insert(network_id, NetworkState { config, members });
+        }
+
+        let current = ControllerSnapshot {
+            networks,
+            updated_at: Some(Utc::now()),
+        };
+
+        // Publish after storing so subscribers see the state that caused the event
+        let current = Arc::new(current);
+        self.snapshot.send_replace(current.clone());
+
+        // The first poll only establishes the baseline
+        if previous.is_ready() {
+            for event in diff_snapshots(&previous, &current) {
+                self.events.publish(event);
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn diff_snapshots(previous: &ControllerSnapshot, current: &ControllerSnapshot) -> Vec<EventKind> {
+    let mut events = Vec::new();
+
+    for (network_id, network) in &current.networks {
+        let Some(old_network) = previous.networks.get(network_id) else {
+            events.push(EventKind::NetworkCreated {
+                network_id: network_id.clone(),
+                name: network.name().to_string(),
+            });
+            continue;
+        };
+
+        if network.revision() != old_network.revision() {
+            events.push(EventKind::NetworkChanged {
+                network_id: network_id.clone(),
+                name: network.name().to_string(),
+                revision: network.revision(),
+            });
+        }
+
+        for (member_id, member) in &network.members {
+            let network_id = network_id.clone();
+            let member_id = member_id.clone();
+            let name = member.name().to_string();
+
+            let Some(old_member) = old_network.members.get(&member_id) else {
+                events.push(EventKind::MemberJoined {
+                    network_id,
+                    member_id,
+                    name,
+                    authorized: member.authorized(),
+                });
+                continue;
+            };
+
+            if member.authorized() != old_member.authorized() {
+                events.push(if member.authorized() {
+                    EventKind::MemberAuthorized {
+                        network_id: network_id.clone(),
+                        member_id: member_id.clone(),
+                        name: name.clone(),
+                    }
+                } else {
+                    EventKind::MemberDeauthorized {
+                        network_id: network_id.clone(),
+                        member_id: member_id.clone(),
+                        name: name.clone(),
+                    }
+                });
+            }
+
+            if member.online != old_member.online {
+                events.push(if member.online {
+                    EventKind::MemberOnline {
+                        network_id,
+                        member_id,
+                        name,
+                    }
+                } else {
+                    EventKind::MemberOffline {
+                        network_id,
+                        member_id,
+                        name,
+                    }
+                });
+            }
+        }
+
+        for member_id in old_network.members.keys() {
+            if !network.members.contains_key(member_id) {
+                events.push(EventKind::MemberDeleted {
+                    network_id: network_id.clone(),
+                    member_id: member_id.clone(),
+                });
+            }
+        }
+    }
+
+    for network_id in previous.networks.keys() {
+        if !current.networks.contains_key(network_id) {
+            events.push(EventKind::NetworkDeleted {
+                network_id: network_id.clone(),
+            });
+        }
+    }
+
+    events
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TestEnv;
+    use serde_json::json;
+    use tokio::sync::broadcast::error::TryRecvError;
+
+    const NETWORK: &str = "abcdef0123000001";
+
+    fn drain(
+        receiver: &mut tokio::sync::broadcast::Receiver<Arc<crate::services::events::Event>>,
+    ) -> Vec<EventKind> {
+        let mut events = Vec::new();
+        loop {
+            match receiver.try_recv() {
+                Ok(event) => events.push(event.kind.clone()),
+                Err(TryRecvError::Empty) => return events,
+                Err(e) => panic!("{}", e),
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn publishes_changes_between_polls() {
+        let env = TestEnv::new().await;
+        env.controller
+            .add_network(NETWORK, json!({ "name": "lab" }));
+        env.controller
+            .add_member(NETWORK, "1111111111", json!({ "name": "alpha" }));
+        env.controller
+            .add_member(NETWORK, "2222222222", json!({ "name": "beta" }));
+
+        let events = EventService::new();
+        let mut receiver = events.subscribe();
+        let monitor = ControllerMonitor::new(env.zerotier(), events);
+
+        // The first poll only records the baseline
+        monitor.poll().await.unwrap();
+        assert!(monitor.snapshot().is_ready());
+        assert_eq!(monitor.snapshot().networks[NETWORK].members.len(), 2);
+        assert!(drain(&mut receiver).is_empty());
+
+        let zerotier = env.zerotier();
+        zerotier
+            .update_member(NETWORK, "1111111111", &json!({ "authorized": true }))
+            .await
+            .unwrap();
+        zerotier.delete_member(NETWORK, "2222222222").await.unwrap();
+        env.controller
+            .add_member(NETWORK, "3333333333", json!({ "name": "gamma" }));
+        env.controller.add_peer("1111111111", 10, true);
+        env.controller
+            .add_network("abcdef0123000002", json!({ "name": "new" }));
+
+        monitor.poll().await.unwrap();
+        let mut events = drain(&mut receiver);
+        events.sort_by_key(|event| format!("{:?}", event));
+        assert_eq!(
+            events,
+            [
+                EventKind::MemberAuthorized {
+                    network_id: NETWORK.to_string(),
+                    member_id: "1111111111".to_string(),
+                    name: "alpha".to_string(),
+                },
+                EventKind::MemberDeleted {
+                    network_id: NETWORK.to_string(),
+                    member_id: "2222222222".to_string(),
+                },
+                EventKind::MemberJoined {
+                    network_id: NETWORK.to_string(),
+                    member_id: "3333333333".to_string(),
+                    name: "gamma".to_string(),
+                    authorized: false,
+                },
+                EventKind::MemberOnline {
+                    network_id: NETWORK.to_string(),
+                    member_id: "1111111111".to_string(),
+                    name: "alpha".to_string(),
+                },
+                EventKind::NetworkCreated {
+                    network_id: "abcdef0123000002".to_string(),
+                    name: "new".to_string(),
+                },
+            ]
+        );
+
+        zerotier
+            .post_json(
+                &format!("/controller/network/{}", NETWORK),
+                &json!({ "name": "renamed" }),
+            )
+            .await
+            .unwrap();
+        env.controller
+            .state
+            .lock()
+            .unwrap()
+            .networks
+            .remove("abcdef0123000002");
+        monitor.poll().await.unwrap();
+        let mut events = drain(&mut receiver);
+        events.sort_by_key(|event| format!("{:?}", event));
+        assert_eq!(
+            events,
+            [
+                EventKind::NetworkChanged {
+                    network_id: NETWORK.to_string(),
+                    name: "renamed".to_string(),
+                    revision: 1,
+                },
+                EventKind::NetworkDeleted {
+                    network_id: "abcdef0123000002".to_string(),
+                },
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn only_refetches_changed_members() {
+        let env = TestEnv::new().await;
+        env.controller.add_network(NETWORK, json!({}));
+        env.controller.add_member(NETWORK, "1111111111", json!({}));
+        env.controller.add_member(NETWORK, "2222222222", json!({}));
+
+        let monitor = ControllerMonitor::new(env.zerotier(), EventService::new());
+        monitor.poll().await.unwrap();
+        env.zerotier()
+            .update_member(NETWORK, "2222222222", &json!({ "name": "beta" }))
+            .await
+            .unwrap();
+        monitor.poll().await.unwrap();
+
+        let member_reads = |member_id: &str| {
+            let request = format!("GET /controller/network/{}/member/{}", NETWORK, member_id);
+            env.controller
+                .requests()
+                .iter()
+                .filter(|r| **r == request)
+                .count()
+        };
+        assert_eq!(member_reads("1111111111"), 1);
+        // Once for the baseline and once after its revision changed
+        assert_eq!(member_reads("2222222222"), 2);
+        assert_eq!(
+            monitor.snapshot().networks[NETWORK].members["2222222222"].name(),
+            "beta"
+        );
+    }
+
+    #[tokio::test]
+    async fn concurrent_polls_publish_each_change_once() {
+        let env = TestEnv::new().await;
+        env.controller.add_network(NETWORK, json!({}));
+
+        let events = EventService::new();
+        let mut receiver = events.subscribe();
+        let monitor = ControllerMonitor::new(env.zerotier(), events);
+        monitor.poll().await.unwrap();
+
+        env.controller.add_member(NETWORK, "1111111111", json!({}));
+        let (first, second, third) = tokio::join!(
+            monitor.poll(),
+            monitor.poll(),
+            monitor.poll_if_older(TimeDelta::zero())
+        );
+        first.unwrap();
+        second.unwrap();
+        third.unwrap();
+
+        assert_eq!(
+            drain(&mut receiver),
+            [EventKind::MemberJoined {
+                network_id: NETWORK.to_string(),
+                member_id: "1111111111".to_string(),
+                name: String::new(),
+                authorized: false,
+            }]
+        );
+    }
+
+    #[tokio::test]
+    async fn reuses_fresh_snapshots() {
+        let env = TestEnv::new().await;
+        env.controller.add_network(NETWORK, json!({}));
+        let monitor = ControllerMonitor::new(env.zerotier(), EventService::new());
+        let network_lists = || {
+            env.controller
+                .requests()
+                .iter()
+                .filter(|r| *r == "GET /controller/network")
+                .count()
+        };
+
+        // An empty snapshot is never fresh
+        let snapshot = monitor.poll_if_older(TimeDelta::hours(1)).await.unwrap();
+        assert!(snapshot.networks.contains_key(NETWORK));
+        assert_eq!(network_lists(), 1);
+
+        monitor.poll_if_older(TimeDelta::hours(1)).await.unwrap();
+        assert_eq!(network_lists(), 1);
+
+        monitor.poll_if_older(TimeDelta::zero()).await.unwrap();
+        assert_eq!(network_lists(), 2);
+    }
+}
@@ -0,0 +1,649 @@
+use crate::error::{AppError, FieldError, Result};
+use crate::models::{
+    CreateFromTemplateRequest, CreateFromTemplateResponse, NetworkTemplate, SaveTemplateRequest,
+};
+use crate::services::audit::{AuditEntry, AuditService};
+use crate::services::store::JsonStore;
+use crate::services::{IpamService, ZeroTierService};
+use crate::utils::ip_offset;
+use crate::validation;
+use chrono::Utc;
+use ipnet::IpNet;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::PathBuf;
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct TemplateStore {
+    templates: BTreeMap<String, NetworkTemplate>,
+}
+
+/// Every `{{placeholder}}` expression in a string, trimmed
+fn placeholders(text: &str) -> impl Iterator<Item = &str> {
+    text.split("{{")
+        .skip(1)
+        .filter_map(|part| part.split_once("}}"))
+        .map(|(expression, _)| expression.trim())
+}
+
+fn collect_placeholders<'a>(value: &'a Value, found: &mut BTreeSet<&'a str>) {
+    match value {
+        Value::String(text) => found.extend(placeholders(text)),
+        Value::Array(items) => items
+            .iter()
+            .for_each(|item| collect_placeholders(item, found)),
+        Value::Object(fields) => fields
+            .values()
+            .for_each(|field| collect_placeholders(field, found)),
+        _ => {}
+    }
+}
+
+/// Attributes of a subnet variable, e.g. `{{subnet.first}}` or `{{subnet.host(1)}}`
+fn subnet_attribute(subnet: &IpNet, attribute: &str) -> std::result::Result<String, String> {
+    let host_bits = subnet.max_prefix_len() - subnet.prefix_len();
+    let last_offset = u128::MAX
+        .checked_shr(128 - u32::from(host_bits))
+        .unwrap_or(0);
+    // Skip the network and broadcast addresses when the subnet is large enough
+    let usable = last_offset >= 2;
+    let host = |offset: u128| ip_offset(subnet.network(), offset).map(|ip| ip.to_string());
+
+    let value = match attribute {
+        "network" => Some(subnet.network().to_string()),
+        "prefix" => Some(subnet.prefix_len().to_string()),
+        "netmask" => Some(subnet.netmask().to_string()),
+        "first" => host(if usable { 1 } else { 0 }),
+        "last" => host(if usable { last_offset - 1 } else { last_offset }),
+        _ => {
+            let Some(offset) = attribute
+                .strip_prefix("host(")
+                .and_then(|rest| rest.strip_suffix(')'))
+            else {
+                return Err(format!("has no attribute {}", attribute));
+            };
+            let offset: u128 = offset
+                .trim()
+                .parse()
+                .map_err(|_| format!("host({}) needs a number", offset))?;
+            if offset > last_offset {
+                return Err(format!("has no host({})", offset));
+            }
+            host(offset)
+        }
+    };
+
+    value.ok_or_else(|| format!("has no attribute {}", attribute))
+}
+
+/// Evaluate `name` or `name.attribute`, the latter for subnet variables
+fn evaluate(
+    expression: &str,
+    variables: &BTreeMap<String, String>,
+) -> std::result::Result<String, FieldError> {
+    let (name, attribute) = match expression.split_once('.') {
+        Some((name, attribute)) => (name.trim(), Some(attribute.trim())),
+        None => (expression, None),
+    };
+    let field = format!("variables.{}", name);
+
+    let value = variables
+        .get(name)
+        .ok_or_else(|| FieldError::new(&field, "is required by the template"))?;
+    let Some(attribute) = attribute else {
+        return Ok(value.clone());
+    };
+
+    let subnet: IpNet = value.trim().parse().map_err(|_| {
+        FieldError::new(
+            &field,
+            format!(
+                "must be a subnet such as 10.1.0.0/24 for {{{{{}}}}}",
+                expression
+            ),
+        )
+    })?;
+    subnet_attribute(&subnet.trunc(), attribute)
+        .map_err(|message| FieldError::new(&field, format!("{} {}", value, message)))
+}
+
+fn render_text(
+    text: &str,
+    variables: &BTreeMap<String, String>,
+    errors: &mut BTreeMap<String, String>,
+) -> String {
+    let mut output = String::with_capacity(text.len());
+    let mut rest = text;
+
+    while let Some(start) = rest.find("{{") {
+        let Some(length) = rest[start + 2..].find("}}") else {
+            break;
+        };
+        output.push_str(&rest[..start]);
+        match evaluate(rest[start + 2..start + 2 + length].trim(), variables) {
+            Ok(value) => output.push_str(&value),
+            Err(error) => {
+                errors.entry(error.field).or_insert(error.message);
+            }
+        }
+        rest = &rest[start + length + 4..];
+    }
+
+    output.push_str(rest);
+    output
+}
+
+fn render_value(
+    value: &Value,
+    variables: &BTreeMap<String, String>,
+    errors: &mut BTreeMap<String, String>,
+) -> Value {
+    match value {
+        Value::String(text) => Value::String(render_text(text, variables, errors)),
+        Value::Array(items) => Value::Array(
+            items
+                .iter()
+                .map(|item| render_value(item, variables, errors))
+                .collect(),
+        ),
+        Value::Object(fields) => Value::Object(
+            fields
+                .iter()
+                .map(|(key, field)| (key.clone(), render_value(field, variables, errors)))
+                .collect(),
+        ),
+        other => other.clone(),
+    }
+}
+
+/// Replace the placeholders in a template config with `variables`
+fn render(
+    config: &Map<String, Value>,
+    variables: &BTreeMap<String, String>,
+) -> Result<Map<String, Value>> {
+    let mut errors = BTreeMap::new();
+    let rendered = config
+        .iter()
+        .map(|(key, value)| (key.clone(), render_value(value, variables, &mut errors)))
+        .collect();
+
+    if errors.is_empty() {
+        Ok(rendered)
+    } else {
+        Err(AppError::ValidationError(
+            errors
+                .into_iter()
+                .map(|(field, message)| FieldError::new(field, message))
+                .collect(),
+        ))
+    }
+}
+
+/// Manages named network templates and creates networks from them
+#[derive(Clone)]
+pub struct NetworkTemplateService {
+    store: JsonStore<TemplateStore>,
+    zerotier: ZeroTierService,
+    ipam: IpamService,
+    audit: AuditService,
+}
+
+impl NetworkTemplateService {
+    pub fn new(
+        path: PathBuf,
+        zerotier: ZeroTierService,
+        ipam: IpamService,
+        audit: AuditService,
+    ) -> Result<Self> {
+        Ok(Self {
+            store: JsonStore::open(path)?,
+            zerotier,
+            ipam,
+            audit,
+        })
+    }
+
+    pub async fn list(&self) -> Vec<NetworkTemplate> {
+        self.store
+            .read()
+            .await
+            .templates
+            .values()
+            .cloned()
+            .collect()
+    }
+
+    pub async fn get(&self, name: &str) -> Result<NetworkTemplate> {
+        self.store
+            .read()
+            .await
+            .templates
+            .get(name)
+            .cloned()
+            .ok_or_else(|| AppError::NotFound(format!("Template {} not found", name)))
+    }
+
+    /// Create or replace a template
+    pub async fn save(
+        &self,
+        actor: &str,
+        name: &str,
+        request: SaveTemplateRequest,
+    ) -> Result<NetworkTemplate> {
+        let mut errors = Vec::new();
+        if name.is_empty()
+            || name.len() > 64
+            || !name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+        {
+            errors.push(FieldError::new(
+                "name",
+                "must be 1-64 letters, digits, '-', '_' or '.'",
+            ));
+        }
+        for key in request.config.keys() {
+            if validation::CONTROLLER_FIELDS.contains(&key.as_str()) {
+                errors.push(FieldError::new(
+                    format!("config.{}", key),
+                    "is set by the controller",
+                ));
+            }
+        }
+
+        let mut expressions = BTreeSet::new();
+        for value in request.config.values() {
+            collect_placeholders(value, &mut expressions);
+        }
+        let variables: BTreeSet<String> = expressions
+            .iter()
+            .map(|expression| match expression.split_once('.') {
+                Some((name, _)) => name.trim().to_string(),
+                None => expression.to_string(),
+            })
+            .collect();
+        if variables.contains("") {
+            errors.push(FieldError::new(
+                "config",
+                "contains a placeholder without a variable name",
+            ));
+        }
+        if !errors.is_empty() {
+            return Err(AppError::ValidationError(errors));
+        }
+
+        let template = NetworkTemplate {
+            name: name.to_string(),
+            description: request.description,
+            defaults: request.defaults,
+            variables,
+            config: request.config,
+            updated_by: actor.to_string(),
+            updated_at: Utc::now(),
+        };
+
+        self.store
+            .update(|store| {
+                store.templates.insert(name.to_string(), template.clone());
+                Ok(())
+            })
+            .await?;
+
+        self.audit
+            .record(AuditEntry::new(actor, "template.save").details(json!({ "template": name })))
+            .await;
+
+        Ok(template)
+    }
+
+    pub async fn delete(&self, actor: &str, name: &str) -> Result<()> {
+        self.store
+            .update(|store| {
+                store
+                    .templates
+                    .remove(name)
+                    .map(|_| ())
+                    .ok_or_else(|| AppError::NotFound(format!("Template {} not found", name)))
+            })
+            .await?;
+
+        self.audit
+            .record(AuditEntry::new(actor, "template.delete").details(json!({ "template": name })))
+            .await;
+
+        Ok(())
+    }
+
+    /// Render a template with the request's variables and create the network
+    pub async fn create_network(
+        &self,
+        actor: &str,
+        request: &CreateFromTemplateRequest,
+    ) -> Result<CreateFromTemplateResponse> {
+        let template = self.get(&request.template).await?;
+
+        let mut variables = template.defaults.clone();
+        variables.extend(request.variables.clone());
+
+        // Keep the subnet from being handed out again until the network exists
+        let _guard = match &request.allocate_subnet {
+            Some(name) => {
+                let guard = self.ipam.lock().await;
+                let subnet = self.ipam.next_subnet(None).await?;
+                variables.insert(name.clone(), subnet.to_string());
+                Some(guard)
+            }
+            None => None,
+        };
+        let config = render(&template.config, &variables)?;
+
+        let errors = validation::validate_network(&config);
+        if !errors.is_empty() {
+            return Err(AppError::ValidationError(errors));
+        }
+
+        if request.dry_run {
+            return Ok(CreateFromTemplateResponse {
+                dry_run: true,
+                network_id: None,
+                config,
+            });
+        }
+
+        let network = self.zerotier.create_network(&Value::Object(config)).await?;
+        let network_id = network
+            .get("id")
+            .and_then(Value::as_str)
+            .ok_or_else(|| AppError::ZeroTierError("created network has no id".to_string()))?
+            .to_string();
+
+        self.audit
+            .record(
+                AuditEntry::new(actor, "network.create")
+                    .network(&network_id)
+                    .details(json!({
+                        "template": template.name,
+                        "variables": variables,
+                    })),
+            )
+            .await;
+
+        Ok(CreateFromTemplateResponse {
+            dry_run: false,
+            network_id: Some(network_id),
+            config: network,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TestEnv;
+
+    fn variables(pairs: &[(&str, &str)]) -> BTreeMap<String, String> {
+        pairs
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect()
+    }
+
+    fn site_template() -> SaveTemplateRequest {
+        serde_json::from_value(json!({
+            "description": "Branch office",
+            "defaults": { "mtu_label": "standard" },
+            "config": {
+                "name": "site-{{ site }}",
+                "mtu": 2800,
+                "routes": [{ "target": "{{subnet}}", "via": null }],
+                "ipAssignmentPools": [
+                    { "ipRangeStart": "{{subnet.host(10)}}", "ipRangeEnd": "{{subnet.last}}" }
+                ],
+                "dns": { "domain": "{{site}}.example.com", "servers": ["{{subnet.first}}"] }
+            }
+        }))
+        .unwrap()
+    }
+
+    fn invalid_fields(result: Result<impl std::fmt::Debug>) -> Vec<(String, String)> {
+        match result {
+            Err(AppError::ValidationError(fields)) => fields
+                .into_iter()
+                .map(|field| (field.field, field.message))
+                .collect(),
+            other => panic!("expected a validation error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn renders_subnet_attributes() {
+        let subnet: IpNet = "10.1.2.0/24".parse().unwrap();
+        let attribute = |name| subnet_attribute(&subnet, name);
+        assert_eq!(attribute("network").unwrap(), "10.1.2.0");
+        assert_eq!(attribute("prefix").unwrap(), "24");
+        assert_eq!(attribute("netmask").unwrap(), "255.255.255.0");
+        assert_eq!(attribute("first").unwrap(), "10.1.2.1");
+        assert_eq!(attribute("last").unwrap(), "10.1.2.254");
+        assert_eq!(attribute("host(0)").unwrap(), "10.1.2.0");
+        assert_eq!(attribute("host( 255 )").unwrap(), "10.1.2.255");
+        assert_eq!(attribute("host(256)").unwrap_err(), "has no host(256)");
+        assert_eq!(attribute("host(x)").unwrap_err(), "host(x) needs a number");
+        assert_eq!(attribute("size").unwrap_err(), "has no attribute size");
+
+        // Point to point and single host subnets have no network or broadcast address
+        let pair: IpNet = "10.0.0.4/31".parse().unwrap();
+        assert_eq!(subnet_attribute(&pair, "first").unwrap(), "10.0.0.4");
+        assert_eq!(subnet_attribute(&pair, "last").unwrap(), "10.0.0.5");
+        let host: IpNet = "fd00::1/128".parse().unwrap();
+        assert_eq!(subnet_attribute(&host, "last").unwrap(), "fd00::1");
+
+        let v6: IpNet = "fd00:1::/64".parse().unwrap();
+        assert_eq!(
+            subnet_attribute(&v6, "last").unwrap(),
+            "fd00:1::ffff:ffff:ffff:fffe"
+        );
+    }
+
+    #[test]
+    fn renders_placeholders_in_nested_values() {
+        let config = json!({
+            "name": "{{site}}-{{ site }}",
+            "mtu": 2800,
+            "routes": [{ "target": "{{subnet}}", "via": "{{subnet.first}}" }],
+            "unclosed": "{{site"
+        });
+        let rendered = render(
+            config.as_object().unwrap(),
+            &variables(&[("site", "ber"), ("subnet", "10.1.2.7/24")]),
+        )
+        .unwrap();
+        assert_eq!(
+            Value::Object(rendered),
+            json!({
+                "name": "ber-ber",
+                "mtu": 2800,
+                "routes": [{ "target": "10.1.2.7/24", "via": "10.1.2.1" }],
+                "unclosed": "{{site"
+            })
+        );
+    }
+
+    #[test]
+    fn reports_every_unrenderable_variable() {
+        let config = json!({
+            "name": "{{site}}",
+            "routes": [{ "target": "{{subnet.network}}/{{subnet.prefix}}" }],
+            "dns": { "servers": ["{{gateway.first}}", "{{subnet.host(300)}}"] }
+        });
+        let result = render(
+            config.as_object().unwrap(),
+            &variables(&[("subnet", "10.1.2.0/24"), ("gateway", "router")]),
+        );
+        assert_eq!(
+            invalid_fields(result),
+            [
+                (
+                    "variables.gateway".to_string(),
+                    "must be a subnet such as 10.1.0.0/24 for {{gateway.first}}".to_string()
+                ),
+                (
+                    "variables.site".to_string(),
+                    "is required by the template".to_string()
+                ),
+                (
+                    "variables.subnet".to_string(),
+                    "10.1.2.0/24 has no host(300)".to_string()
+                ),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn saves_templates_with_their_variables() {
+        let env = TestEnv::new().await;
+        let templates = env.app_state().templates;
+
+        let template = templates
+            .save("admin", "site", site_template())
+            .await
+            .unwrap();
+        assert_eq!(
+            template.variables.iter().collect::<Vec<_>>(),
+            ["site", "subnet"]
+        );
+        assert_eq!(template.updated_by, "admin");
+        assert_eq!(templates.list().await.len(), 1);
+
+        // Persisted for the next start
+        let reopened = env.app_state().templates;
+        assert_eq!(
+            reopened.get("site").await.unwrap().description,
+            "Branch office"
+        );
+
+        templates.delete("admin", "site").await.unwrap();
+        assert!(matches!(
+            templates.get("site").await,
+            Err(AppError::NotFound(_))
+        ));
+        assert!(matches!(
+            templates.delete("admin", "site").await,
+            Err(AppError::NotFound(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn rejects_invalid_templates() {
+        let env = TestEnv::new().await;
+        let templates = env.app_state().templates;
+        let request: SaveTemplateRequest = serde_json::from_value(json!({
+            "config": { "id": "abcdef0123000001", "name": "{{ }}" }
+        }))
+        .unwrap();
+
+        let fields: Vec<String> = invalid_fields(templates.save("admin", "a b", request).await)
+            .into_iter()
+            .map(|(field, _)| field)
+            .collect();
+        assert_eq!(fields, ["name", "config.id", "config"]);
+        assert!(templates.list().await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn previews_and_creates_networks() {
+        let env = TestEnv::new().await;
+        let templates = env.app_state().templates;
+        templates
+            .save("admin", "site", site_template())
+            .await
+            .unwrap();
+
+        let mut request = CreateFromTemplateRequest {
+            template: "site".to_string(),
+            variables: variables(&[("site", "ber"), ("subnet", "10.1.2.0/24")]),
+            allocate_subnet: None,
+            dry_run: true,
+        };
+        let preview = templates.create_network("admin", &request).await.unwrap();
+        assert_eq!(preview.network_id, None);
+        assert_eq!(preview.config["name"], "site-ber");
+        assert_eq!(
+            preview.config["ipAssignmentPools"],
+            json!([{ "ipRangeStart": "10.1.2.10", "ipRangeEnd": "10.1.2.254" }])
+        );
+        assert!(env.controller.state.lock().unwrap().networks.is_empty());
+
+        request.dry_run = false;
+        let created = templates.create_network("admin", &request).await.unwrap();
+        let network_id = created.network_id.unwrap();
+        let network = env.controller.state.lock().unwrap().networks[&network_id].clone();
+        assert_eq!(network["name"], "site-ber");
+        assert_eq!(network["dns"]["domain"], "ber.example.com");
+        assert_eq!(network["mtu"], 2800);
+    }
+
+    #[tokio::test]
+    async fn validates_the_rendered_config() {
+        let env = TestEnv::new().await;
+        let templates = env.app_state().templates;
+        templates
+            .save("admin", "site", site_template())
+            .await
+            .unwrap();
+
+        // The pool starts past a /29's last host
+        let request = CreateFromTemplateRequest {
+            template: "site".to_string(),
+            variables: variables(&[("site", "ber"), ("subnet", "10.1.2.0/29")]),
+            allocate_subnet: None,
+            dry_run: false,
+        };
+        let fields = invalid_fields(templates.create_network("admin", &request).await);
+        assert_eq!(fields[0].0, "variables.subnet");
+
+        let request = CreateFromTemplateRequest {
+            variables: variables(&[("site", "ber"), ("subnet", "10.1.2.0/24")]),
+            template: "missing".to_string(),
+            ..request
+        };
+        assert!(matches!(
+            templates.create_network("admin", &request).await,
+            Err(AppError::NotFound(_))
+        ));
+        assert!(env.controller.state.lock().unwrap().networks.is_empty());
+    }
+
+    #[tokio::test]
+    async fn allocates_distinct_subnets_to_concurrent_requests() {
+        let env = TestEnv::with_config(|config| {
+            config.ipam.supernet = Some("10.8.0.0/16".parse().unwrap());
+            config.ipam.subnet_prefix_len = 24;
+        })
+        .await;
+        let templates = env.app_state().templates;
+        templates
+            .save("admin", "site", site_template())
+            .await
+            .unwrap();
+
+        let request = |site: &str| CreateFromTemplateRequest {
+            template: "site".to_string(),
+            variables: variables(&[("site", site)]),
+            allocate_subnet: Some("subnet".to_string()),
+            dry_run: false,
+        };
+        let (first, second) = (request("ber"), request("muc"));
+        let (first, second) = tokio::join!(
+            templates.create_network("admin", &first),
+            templates.create_network("admin", &second)
+        );
+
+        let mut targets: Vec<Value> = [first.unwrap(), second.unwrap()]
+            .iter()
+            .map(|created| created.config["routes"][0]["target"].clone())
+            .collect();
+        targets.sort_by_key(|target| target.to_string());
+        assert_eq!(targets, [json!("10.8.0.0/24"), json!("10.8.1.0/24")]);
+    }
+}
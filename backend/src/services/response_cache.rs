@@ -0,0 +1,193 @@
+use axum::body::Bytes;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::RwLock;
+
+/// A fully buffered response from the ZeroTier service
+#[derive(Debug, Clone)]
+pub struct UpstreamResponse {
+    pub status: u16,
+    pub content_type: Option<String>,
+    pub body: Bytes,
+    pub etag: String,
+}
+
+impl UpstreamResponse {
+    pub fn new(status: u16, content_type: Option<String>, body: Bytes) -> Self {
+        // Strong validator derived from the exact response bytes
+        let etag = format!("\"{}\"", hex::encode(&Sha256::digest(&body)[..16]));
+
+        Self {
+            status,
+            content_type,
+            body,
+            etag,
+        }
+    }
+
+    pub fn is_success(&self) -> bool {
+        (200..300).contains(&self.status)
+    }
+
+    /// Check an `If-None-Match` header value against this response's ETag
+    pub fn matches_etag(&self, if_none_match: &str) -> bool {
+        if_none_match
+            .split(',')
+            .map(str::trim)
+            .any(|tag| tag == "*" || tag.strip_prefix("W/").unwrap_or(tag) == self.etag)
+    }
+}
+
+/// Short-lived in-memory cache of GET responses keyed by endpoint
+#[derive(Clone)]
+pub struct ResponseCache {
+    ttl: Duration,
+    entries: Arc<RwLock<HashMap<String, (Instant, UpstreamResponse)>>>,
+}
+
+impl ResponseCache {
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            entries: Arc::new(RwLock::new(HashMap::new())),
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        !self.ttl.is_zero()
+    }
+
+    pub async fn get(&self, endpoint: &str) -> Option<UpstreamResponse> {
+        let entries = self.entries.read().await;
+
+        entries
+            .get(endpoint)
+            .filter(|(expires_at, _)| Instant::now() < *expires_at)
+            .map(|(_, response)| response.clone())
+    }
+
+    pub async fn insert(&self, endpoint: &str, response: UpstreamResponse) {
+        if !self.is_enabled() {
+            return;
+        }
+
+        let now = Instant::now();
+        let mut entries = self.entries.write().await;
+
+        // Drop expired entries so the cache does not grow without bound
+        entries.retain(|_, (expires_at, _)| now < *expires_at);
+        entries.insert(endpoint.to_string(), (now + self.ttl, response));
+    }
+
+    /// Invalidate every cached response affected by a write to `endpoint`.
+    /// Writes below `/controller/network/{id}` drop that network, its members
+    /// and the network list; any other write clears the whole cache.
+    pub async fn invalidate(&self, endpoint: &str) {
+        let mut entries = self.entries.write().await;
+
+        let segments: Vec<&str> = endpoint.trim_matches('/').split('/').collect();
+        match segments.as_slice() {
+            ["controller", "network", network_id, ..] => {
+                let network = format!("/controller/network/{}", network_id);
+                let children = format!("{}/", network);
+                entries.retain(|key, _| {
+                    key != "/controller"
+                        && key != "/controller/network"
+                        && *key != network
+                        && !key.starts_with(&children)
+                });
+            }
+            _ => entries.clear(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn response(body: &'static str) -> UpstreamResponse {
+        UpstreamResponse::new(200, None, Bytes::from_static(body.as_bytes()))
+    }
+
+    #[test]
+    fn etag_depends_on_body() {
+        let a = response("{\"id\":\"a\"}");
+        assert_eq!(a.etag, response("{\"id\":\"a\"}").etag);
+        assert_ne!(a.etag, response("{\"id\":\"b\"}").etag);
+        assert!(a.etag.starts_with('"') && a.etag.ends_with('"'));
+    }
+
+    #[test]
+    fn matches_if_none_match_lists() {
+        let response = response("{}");
+        let etag = response.etag.clone();
+
+        assert!(response.matches_etag(&etag));
+        assert!(response.matches_etag(&format!("W/{}", etag)));
+        assert!(response.matches_etag(&format!("\"other\", {}", etag)));
+        assert!(response.matches_etag("*"));
+        assert!(!response.matches_etag("\"other\""));
+        assert!(!response.matches_etag(etag.trim_matches('"')));
+    }
+
+    #[tokio::test]
+    async fn entries_expire_after_ttl() {
+        let cache = ResponseCache::new(Duration::from_millis(50));
+        cache.insert("/status", response("{}")).await;
+        assert!(cache.get("/status").await.is_some());
+
+        tokio::time::sleep(Duration::from_millis(60)).await;
+        assert!(cache.get("/status").await.is_none());
+    }
+
+    #[tokio::test]
+    async fn zero_ttl_disables_caching() {
+        let cache = ResponseCache::new(Duration::ZERO);
+        assert!(!cache.is_enabled());
+        cache.insert("/status", response("{}")).await;
+        assert!(cache.get("/status").await.is_none());
+    }
+
+    #[tokio::test]
+    async fn network_writes_invalidate_only_that_network() {
+        let cache = ResponseCache::new(Duration::from_secs(60));
+        let endpoints = [
+            "/status",
+            "/controller/network",
+            "/controller/network/abcdef0123000001",
+            "/controller/network/abcdef0123000001/member",
+            "/controller/network/abcdef0123000001/member/1111111111",
+            "/controller/network/abcdef0123000002",
+            "/controller/network/abcdef0123000002/member",
+        ];
+        for endpoint in endpoints {
+            cache.insert(endpoint, response("{}")).await;
+        }
+
+        cache
+            .invalidate("/controller/network/abcdef0123000001/member/1111111111")
+            .await;
+
+        let mut cached = Vec::new();
+        for endpoint in endpoints {
+            if cache.get(endpoint).await.is_some() {
+                cached.push(endpoint);
+            }
+        }
+        assert_eq!(
+            cached,
+            [
+                "/status",
+                "/controller/network/abcdef0123000002",
+                "/controller/network/abcdef0123000002/member",
+            ]
+        );
+
+        // Writes outside the controller API clear everything
+        cache.invalidate("/peer").await;
+        assert!(cache.get("/status").await.is_none());
+    }
+}
@@ -0,0 +1,452 @@
+use crate::error::Result;
+use crate::models::{MemberMetadata, NetworkMember, SearchResult, SearchResultKind};
+use crate::services::monitor::ControllerSnapshot;
+use crate::services::{ControllerMonitor, MetadataService};
+use chrono::{TimeDelta, Utc};
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::sync::RwLock;
+
+// Weight of a match per field, multiplied by how well the term matched
+const WEIGHT_ID: u32 = 40;
+const WEIGHT_IP: u32 = 35;
+const WEIGHT_NAME: u32 = 30;
+const WEIGHT_PHYSICAL_IP: u32 = 25;
+const WEIGHT_METADATA: u32 = 15;
+const WEIGHT_DESCRIPTION: u32 = 10;
+
+/// Age after which the snapshot is refreshed before searching
+const STALE_AFTER: TimeDelta = TimeDelta::seconds(60);
+
+/// Searchable, lowercased fields of a member
+#[derive(Debug, Clone)]
+struct MemberDocument {
+    revision: u64,
+    member_id: String,
+    name: String,
+    name_lower: String,
+    description_lower: String,
+    ips: Vec<String>,
+    physical_ips: Vec<String>,
+    authorized: bool,
+    online: bool,
+}
+
+impl MemberDocument {
+    fn new(member: &NetworkMember) -> Self {
+        Self {
+            revision: member.revision(),
+            member_id: member.id().to_lowercase(),
+            name: member.name().to_string(),
+            name_lower: member.name().to_lowercase(),
+            description_lower: member.description().to_lowercase(),
+            ips: Self::ips(member),
+            physical_ips: Self::physical_ips(member),
+            authorized: member.authorized(),
+            online: member.online,
+        }
+    }
+
+    /// Assigned and computed IPs, the latter follow the network's `v6AssignMode`
+    fn ips(member: &NetworkMember) -> Vec<String> {
+        member.addresses().iter().map(|ip| ip.to_string()).collect()
+    }
+
+    fn physical_ips(member: &NetworkMember) -> Vec<String> {
+        member
+            .physical_addresses()
+            .iter()
+            .map(|ip| ip.to_string())
+            .collect()
+    }
+}
+
+#[derive(Debug, Clone)]
+struct NetworkDocument {
+    network_id: String,
+    name: String,
+    name_lower: String,
+    members: HashMap<String, MemberDocument>,
+}
+
+#[derive(Debug, Default)]
+struct SearchIndex {
+    networks: HashMap<String, NetworkDocument>,
+}
+
+/// How well `term` matches `value`: 3 exact, 2 prefix, 1 substring, 0 none
+fn match_strength(value: &str, term: &str) -> u32 {
+    if value == term {
+        3
+    } else if value.starts_with(term) {
+        2
+    } else if value.contains(term) {
+        1
+    } else {
+        0
+    }
+}
+
+/// Scores every term against the fields of a document. Each term counts
+/// with its best matching field and every term has to match somewhere.
+struct Scorer<'a> {
+    terms: &'a [String],
+    best: Vec<u32>,
+    matched: Vec<&'static str>,
+}
+
+impl<'a> Scorer<'a> {
+    fn new(terms: &'a [String]) -> Self {
+        Self {
+            terms,
+            best: vec![0; terms.len()],
+            matched: Vec::new(),
+        }
+    }
+
+    fn field(&mut self, field: &'static str, weight: u32, value: &str) {
+        if value.is_empty() {
+            return;
+        }
+
+        for (term, best) in self.terms.iter().zip(self.best.iter_mut()) {
+            let score = weight * match_strength(value, term);
+            if score > 0 {
+                *best = (*best).max(score);
+                if !self.matched.contains(&field) {
+                    self.matched.push(field);
+                }
+            }
+        }
+    }
+
+    fn finish(self) -> Option<(u32, Vec<&'static str>)> {
+        if self.best.contains(&0) {
+            return None;
+        }
+        Some((self.best.iter().sum(), self.matched))
+    }
+}
+
+/// Search over every network and member on the controller, indexed from
+/// the monitor's snapshots
+#[derive(Clone)]
+pub struct SearchService {
+    monitor: ControllerMonitor,
+    metadata: MetadataService,
+    index: Arc<RwLock<SearchIndex>>,
+}
+
+impl SearchService {
+    pub fn new(monitor: ControllerMonitor, metadata: MetadataService) -> Self {
+        Self {
+            monitor,
+            metadata,
+            index: Arc::new(RwLock::new(SearchIndex::default())),
+        }
+    }
+
+    /// Refresh the index in the background whenever the monitor polls
+    pub fn spawn(&self) {
+        let service = self.clone();
+        let mut receiver = self.monitor.subscribe();
+
+        tokio::spawn(async move {
+            while receiver.changed().await.is_ok() {
+                let snapshot = receiver.borrow_and_update().clone();
+                service.refresh(&snapshot).await;
+            }
+        });
+    }
+
+    /// Update the index from a snapshot, reusing the documents of members
+    /// whose revision did not change
+    async fn refresh(&self, snapshot: &ControllerSnapshot) {
+        let mut index = self.index.write().await;
+        let mut previous = std::mem::take(&mut index.networks);
+
+        for (network_id, network) in &snapshot.networks {
+            let mut known = previous
+                .remove(network_id)
+                .map(|document| document.members)
+                .unwrap_or_default();
+
+            let members = network
+                .members
+                .iter()
+                .map(|(member_id, member)| {
+                    let document = match known.remove(member_id) {
+                        Some(mut document) if document.revision == member.revision() => {
+                            // Peer data and network settings change without a new revision
+                            document.ips = MemberDocument::ips(member);
+                            document.physical_ips = MemberDocument::physical_ips(member);
+                            document.online = member.online;
+                            document
+                        }
+                        _ => MemberDocument::new(member),
+                    };
+                    (member_id.clone(), document)
+                })
+                .collect();
+
+            index.networks.insert(
+                network_id.clone(),
+                NetworkDocument {
+                    network_id: network_id.clone(),
+                    name: network.name().to_string(),
+                    name_lower: network.name().to_lowercase(),
+                    members,
+                },
+            );
+        }
+    }
+
+    pub async fn search(&self, query: &str, limit: usize) -> Result<Vec<SearchResult>> {
+        let terms: Vec<String> = query.split_whitespace().map(str::to_lowercase).collect();
+        if terms.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        // Without a working background monitor the index is refreshed on demand
+        let stale = self
+            .monitor
+            .snapshot()
+            .updated_at
+            .is_none_or(|updated_at| Utc::now() - updated_at > STALE_AFTER);
+        if stale {
+            let snapshot = self.monitor.poll_if_older(STALE_AFTER).await?;
+            self.refresh(&snapshot).await;
+        }
+
+        let metadata: HashMap<(String, String), MemberMetadata> = self
+            .metadata
+            .all()
+            .await
+            .into_iter()
+            .map(|(network_id, member_id, metadata)| ((network_id, member_id), metadata))
+            .collect();
+
+        let index = self.index.read().await;
+        let mut results = Vec::new();
+
+        for network in index.networks.values() {
+            let mut scorer = Scorer::new(&terms);
+            scorer.field("network_id", WEIGHT_ID, &network.network_id);
+            scorer.field("name", WEIGHT_NAME, &network.name_lower);
+            if let Some((score, matched)) = scorer.finish() {
+                results.push(SearchResult {
+                    kind: SearchResultKind::Network,
+                    network_id: network.network_id.clone(),
+                    network_name: network.name.clone(),
+                    member_id: None,
+                    name: network.name.clone(),
+                    authorized: None,
+                    online: None,
+                    score,
+                    matched,
+                });
+            }
+
+            for member in network.members.values() {
+                let mut scorer = Scorer::new(&terms);
+                scorer.field("member_id", WEIGHT_ID, &member.member_id);
+                scorer.field("name", WEIGHT_NAME, &member.name_lower);
+                scorer.field("description", WEIGHT_DESCRIPTION, &member.description_lower);
+                for ip in &member.ips {
+                    scorer.field("ip", WEIGHT_IP, ip);
+                }
+                for ip in &member.physical_ips {
+                    scorer.field("physical_ip", WEIGHT_PHYSICAL_IP, ip);
+                }
+
+                let key = (network.network_id.clone(), member.member_id.clone());
+                if let Some(info) = metadata.get(&key).map(|metadata| &metadata.info) {
+                    let values = [&info.owner, &info.asset_tag, &info.location, &info.notes];
+                    for value in values.into_iter().flatten() {
+                        scorer.field("metadata", WEIGHT_METADATA, &value.to_lowercase());
+                    }
+                    for (key, value) in &info.labels {
+                        scorer.field("metadata", WEIGHT_METADATA, &key.to_lowercase());
+                        scorer.field("metadata", WEIGHT_METADATA, &value.to_lowercase());
+                    }
+                }
+
+                if let Some((score, matched)) = scorer.finish() {
+                    results.push(SearchResult {
+                        kind: SearchResultKind::Member,
+                        network_id: network.network_id.clone(),
+                        network_name: network.name.clone(),
+                        member_id: Some(member.member_id.clone()),
+                        name: member.name.clone(),
+                        authorized: Some(member.authorized),
+                        online: Some(member.online),
+                        score,
+                        matched,
+                    });
+                }
+            }
+        }
+
+        results.sort_by(|a, b| {
+            b.score
+                .cmp(&a.score)
+                .then_with(|| a.network_id.cmp(&b.network_id))
+                .then_with(|| a.member_id.cmp(&b.member_id))
+        });
+        results.truncate(limit);
+
+        Ok(results)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::EventService;
+    use crate::test_support::TestEnv;
+    use serde_json::json;
+
+    const NETWORK: &str = "abcdef0123000001";
+
+    async fn setup(env: &TestEnv) -> SearchService {
+        env.controller
+            .add_network(NETWORK, json!({ "name": "Office" }));
+        env.controller.add_member(
+            NETWORK,
+            "1111111111",
+            json!({ "name": "laptop", "ipAssignments": ["10.0.0.11"], "authorized": true }),
+        );
+        env.controller.add_member(
+            NETWORK,
+            "2222222222",
+            json!({ "name": "office-printer", "description": "second floor", "ipAssignments": ["10.0.0.110"] }),
+        );
+        env.controller.add_peer("2222222222", 5, true);
+
+        let monitor = ControllerMonitor::new(env.zerotier(), EventService::new());
+        let metadata = MetadataService::new(env.dir.join("metadata.json")).unwrap();
+        metadata
+            .update(NETWORK, "1111111111", |metadata| {
+                metadata.info.owner = Some("Alice".to_string());
+                metadata
+                    .info
+                    .labels
+                    .insert("team".to_string(), "design".to_string());
+                Ok(())
+            })
+            .await
+            .unwrap();
+        SearchService::new(monitor, metadata)
+    }
+
+    fn ids(results: &[SearchResult]) -> Vec<String> {
+        results
+            .iter()
+            .map(|result| {
+                result
+                    .member_id
+                    .clone()
+                    .unwrap_or_else(|| result.network_id.clone())
+            })
+            .collect()
+    }
+
+    #[test]
+    fn ranks_exact_above_prefix_above_substring() {
+        assert_eq!(match_strength("laptop", "laptop"), 3);
+        assert_eq!(match_strength("laptop", "lap"), 2);
+        assert_eq!(match_strength("laptop", "top"), 1);
+        assert_eq!(match_strength("laptop", "desk"), 0);
+    }
+
+    #[test]
+    fn every_term_must_match() {
+        let terms = vec!["lap".to_string(), "alice".to_string()];
+        let mut scorer = Scorer::new(&terms);
+        scorer.field("name", WEIGHT_NAME, "laptop");
+        assert!(scorer.finish().is_none());
+
+        let mut scorer = Scorer::new(&terms);
+        scorer.field("name", WEIGHT_NAME, "laptop");
+        scorer.field("metadata", WEIGHT_METADATA, "alice");
+        scorer.field("description", WEIGHT_DESCRIPTION, "alice's laptop");
+        let (score, matched) = scorer.finish().unwrap();
+        // Each term counts once with its best field
+        assert_eq!(score, WEIGHT_NAME * 2 + WEIGHT_METADATA * 3);
+        assert_eq!(matched, ["name", "metadata", "description"]);
+    }
+
+    #[tokio::test]
+    async fn finds_networks_and_members_by_any_field() {
+        let env = TestEnv::new().await;
+        let search = setup(&env).await;
+
+        let results = search.search("office", 10).await.unwrap();
+        assert_eq!(ids(&results), [NETWORK, "2222222222"]);
+        assert_eq!(results[0].kind, SearchResultKind::Network);
+        assert_eq!(results[1].network_name, "Office");
+
+        let results = search.search("10.0.0.11", 10).await.unwrap();
+        // The exact address ranks above the one it is a prefix of
+        assert_eq!(ids(&results), ["1111111111", "2222222222"]);
+        assert!(results[0].score > results[1].score);
+        assert_eq!(results[0].matched, ["ip"]);
+
+        let results = search.search("192.0.2.1", 10).await.unwrap();
+        assert_eq!(ids(&results), ["2222222222"]);
+        assert_eq!(results[0].matched, ["physical_ip"]);
+        assert_eq!(results[0].online, Some(true));
+
+        let results = search.search("ALICE design", 10).await.unwrap();
+        assert_eq!(ids(&results), ["1111111111"]);
+        assert_eq!(results[0].authorized, Some(true));
+
+        let results = search.search("floor", 10).await.unwrap();
+        assert_eq!(ids(&results), ["2222222222"]);
+
+        assert!(search
+            .search("laptop printer", 10)
+            .await
+            .unwrap()
+            .is_empty());
+        assert!(search.search("   ", 10).await.unwrap().is_empty());
+        assert_eq!(search.search("1", 1).await.unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn refreshes_changed_members() {
+        let env = TestEnv::new().await;
+        let search = setup(&env).await;
+        assert!(search.search("desktop", 10).await.unwrap().is_empty());
+
+        env.zerotier()
+            .update_member(NETWORK, "1111111111", &json!({ "name": "desktop" }))
+            .await
+            .unwrap();
+        search.monitor.poll().await.unwrap();
+        search.refresh(&search.monitor.snapshot()).await;
+
+        let results = search.search("desktop", 10).await.unwrap();
+        assert_eq!(ids(&results), ["1111111111"]);
+        assert_eq!(results[0].name, "desktop");
+    }
+
+    #[tokio::test]
+    async fn concurrent_searches_poll_once() {
+        let env = TestEnv::new().await;
+        let search = setup(&env).await;
+
+        let (first, second) =
+            tokio::join!(search.search("laptop", 10), search.search("laptop", 10));
+        assert_eq!(ids(&first.unwrap()), ["1111111111"]);
+        assert_eq!(ids(&second.unwrap()), ["1111111111"]);
+
+        let network_lists = env
+            .controller
+            .requests()
+            .iter()
+            .filter(|r| *r == "GET /controller/network")
+            .count();
+        assert_eq!(network_lists, 1);
+    }
+}
@@ -1,3 +1,4 @@
+use crate::utils::escape_html;
 use axum::{
     http::{header, StatusCode},
     response::{IntoResponse, Response},
@@ -5,10 +6,44 @@ use axum::{
 use include_dir::{include_dir, Dir};
 
 static FRONTEND: Dir = include_dir!("./dist");
+static MAINTENANCE_PAGE: &str = include_str!("maintenance.html");
+static JOIN_PAGE: &str = include_str!("join.html");
 
 pub struct StaticFileService;
 
 impl StaticFileService {
+    /// Check whether the path refers to a bundled asset rather than an SPA route
+    pub fn is_asset(path: &str) -> bool {
+        let file_path = path.trim_start_matches('/');
+        !file_path.is_empty() && file_path != "index.html" && FRONTEND.get_file(file_path).is_some()
+    }
+
+    pub fn serve_maintenance_page(message: &str) -> Response {
+        let page = MAINTENANCE_PAGE.replace("{{message}}", &escape_html(message));
+
+        (
+            StatusCode::SERVICE_UNAVAILABLE,
+            [
+                (header::CONTENT_TYPE, "text/html"),
+                (header::CACHE_CONTROL, "no-store"),
+            ],
+            page,
+        )
+            .into_response()
+    }
+
+    pub fn serve_join_page() -> Response {
+        (
+            StatusCode::OK,
+            [
+                (header::CONTENT_TYPE, "text/html"),
+                (header::CACHE_CONTROL, "no-store"),
+            ],
+            JOIN_PAGE,
+        )
+            .into_response()
+    }
+
     pub fn serve_file(path: &str) -> Response {
         let file_path = if path == "/" || path.is_empty() {
             "index.html"
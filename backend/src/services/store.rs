@@ -0,0 +1,71 @@
+use crate::error::{AppError, Result};
+use serde::{de::DeserializeOwned, Serialize};
+use std::path::PathBuf;
+use std::sync::Arc;
+use tokio::fs;
+use tokio::sync::{RwLock, RwLockReadGuard};
+
+/// Backend-owned data persisted as a JSON file next to the config file.
+/// Updates are written to a temporary file and renamed into place.
+pub struct JsonStore<T> {
+    path: Arc<PathBuf>,
+    data: Arc<RwLock<T>>,
+}
+
+impl<T> Clone for JsonStore<T> {
+    fn clone(&self) -> Self {
+        Self {
+            path: self.path.clone(),
+            data: self.data.clone(),
+        }
+    }
+}
+
+impl<T> JsonStore<T>
+where
+    T: Serialize + DeserializeOwned + Default + Send + Sync,
+{
+    /// Load the store from `path`, starting empty if the file does not exist
+    pub fn open(path: PathBuf) -> Result<Self> {
+        let data = match std::fs::read_to_string(&path) {
+            Ok(content) => serde_json::from_str(&content).map_err(|e| {
+                AppError::ConfigError(format!("Failed to parse {}: {}", path.display(), e))
+            })?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => T::default(),
+            Err(e) => {
+                return Err(AppError::ConfigError(format!(
+                    "Failed to read {}: {}",
+                    path.display(),
+                    e
+                )))
+            }
+        };
+
+        Ok(Self {
+            path: Arc::new(path),
+            data: Arc::new(RwLock::new(data)),
+        })
+    }
+
+    pub async fn read(&self) -> RwLockReadGuard<'_, T> {
+        self.data.read().await
+    }
+
+    /// Apply a change and persist it, returning the closure's result.
+    /// Nothing is persisted when the closure fails, so it should validate
+    /// its input before mutating the data.
+    pub async fn update<F, R>(&self, apply: F) -> Result<R>
+    where
+        F: FnOnce(&mut T) -> Result<R>,
+    {
+        let mut data = self.data.write().await;
+        let result = apply(&mut data)?;
+
+        let content = serde_json::to_string_pretty(&*data)?;
+        let temp_path = self.path.with_extension("json.tmp");
+        fs::write(&temp_path, content).await?;
+        fs::rename(&temp_path, self.path.as_ref()).await?;
+
+        Ok(result)
+    }
+}
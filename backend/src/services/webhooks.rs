@@ -0,0 +1,349 @@
+use crate::models::WebhookConfig;
+use crate::services::events::Event;
+use crate::services::{ConfigService, EventService};
+use chrono::Utc;
+use hmac::{Hmac, Mac};
+use reqwest::Client;
+use serde_json::json;
+use sha2::Sha256;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::AsyncWriteExt;
+use tokio::sync::broadcast::error::RecvError;
+
+const DEAD_LETTER_FILE: &str = "webhooks.dead-letter.jsonl";
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+const INITIAL_BACKOFF: Duration = Duration::from_secs(1);
+
+/// Delivers controller events to the configured webhook targets
+#[derive(Clone)]
+pub struct WebhookService {
+    client: Client,
+    config: ConfigService,
+    events: EventService,
+    dead_letter_path: Arc<PathBuf>,
+}
+
+impl WebhookService {
+    pub fn new(config: ConfigService, events: EventService) -> Self {
+        Self {
+            client: Client::builder()
+                .timeout(REQUEST_TIMEOUT)
+                .build()
+                .unwrap_or_default(),
+            dead_letter_path: Arc::new(config.data_path(DEAD_LETTER_FILE)),
+            config,
+            events,
+        }
+    }
+
+    /// Compute the `X-Ztvrui-Signature` header value for a payload
+    fn sign(secret: &str, payload: &[u8]) -> String {
+        let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes())
+            .expect("HMAC accepts keys of any length");
+        mac.update(payload);
+        format!("sha256={}", hex::encode(mac.finalize().into_bytes()))
+    }
+
+    /// Start delivering events in the background
+    pub fn spawn(&self) {
+        let service = self.clone();
+        let mut receiver = self.events.subscribe();
+
+        tokio::spawn(async move {
+            loop {
+                match receiver.recv().await {
+                    Ok(event) => service.dispatch(event),
+                    Err(RecvError::Lagged(skipped)) => {
+                        tracing::warn!("Webhook delivery lagged, skipped {} events", skipped);
+                    }
+                    Err(RecvError::Closed) => break,
+                }
+            }
+        });
+    }
+
+    fn dispatch(&self, event: Arc<Event>) {
+        let config = self.config.get_config();
+
+        for webhook in config
+            .webhooks
+            .iter()
+            .filter(|webhook| matches(webhook, &event))
+        {
+            let service = self.clone();
+            let webhook = webhook.clone();
+            let event = event.clone();
+
+            // Deliver each target independently so a slow one does not block others
+            tokio::spawn(async move { service.deliver(&webhook, &event).await });
+        }
+    }
+
+    async fn deliver(&self, webhook: &WebhookConfig, event: &Event) {
+        let payload = match serde_json::to_vec(event) {
+            Ok(payload) => payload,
+            Err(e) => {
+                tracing::error!("Failed to serialize event {}: {}", event.id, e);
+                return;
+            }
+        };
+
+        let max_attempts = webhook.max_attempts.max(1);
+        let mut backoff = INITIAL_BACKOFF;
+        let mut last_error = String::new();
+
+        for attempt in 1..=max_attempts {
+            match self.send(webhook, event, &payload).await {
+                Ok(()) => {
+                    tracing::debug!("Delivered event {} to {}", event.id, webhook.url);
+                    return;
+                }
+                Err(e) => {
+                    tracing::warn!(
+                        "Webhook delivery of event {} to {} failed (attempt {}/{}): {}",
+                        event.id,
+                        webhook.url,
+                        attempt,
+                        max_attempts,
+                        e
+                    );
+                    last_error = e;
+                }
+            }
+
+            if attempt < max_attempts {
+                tokio::time::sleep(backoff).await;
+                backoff *= 2;
+            }
+        }
+
+        self.dead_letter(webhook, event, max_attempts, &last_error)
+            .await;
+    }
+
+    async fn send(
+        &self,
+        webhook: &WebhookConfig,
+        event: &Event,
+        payload: &[u8],
+    ) -> std::result::Result<(), String> {
+        let mut request = self
+            .client
+            .post(&webhook.url)
+            .header("Content-Type", "application/json")
+            .header("X-Ztvrui-Event", event.kind.name())
+            .header("X-Ztvrui-Delivery", event.id.to_string())
+            .body(payload.to_vec());
+
+        if let Some(secret) = webhook.secret.as_deref().filter(|s| !s.is_empty()) {
+            request = request.header("X-Ztvrui-Signature", Self::sign(secret, payload));
+        }
+
+        let response = request.send().await.map_err(|e| e.to_string())?;
+        if response.status().is_success() {
+            Ok(())
+        } else {
+            Err(format!("target responded with {}", response.status()))
+        }
+    }
+
+    /// Record an undeliverable event so it can be inspected or replayed
+    async fn dead_letter(
+        &self,
+        webhook: &WebhookConfig,
+        event: &Event,
+        attempts: u32,
+        error: &str,
+    ) {
+        tracing::error!(
+            "Giving up on delivering event {} to {}, writing it to {}",
+            event.id,
+            webhook.url,
+            self.dead_letter_path.display()
+        );
+
+        let mut line = json!({
+            "failed_at": Utc::now(),
+            "url": webhook.url,
+            "attempts": attempts,
+            "error": error,
+            "event": event,
+        })
+        .to_string();
+        line.push('\n');
+
+        let result = async {
+            let mut file = tokio::fs::OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(self.dead_letter_path.as_ref())
+                .await?;
+            file.write_all(line.as_bytes()).await?;
+            // Tokio finishes writes in the background, flush before the file is dropped
+            file.flush().await
+        }
+        .await;
+
+        if let Err(e) = result {
+            tracing::error!("Failed to write webhook dead-letter log: {}", e);
+        }
+    }
+}
+
+fn matches(webhook: &WebhookConfig, event: &Event) -> bool {
+    let event_allowed =
+        webhook.events.is_empty() || webhook.events.iter().any(|name| name == event.kind.name());
+    let network_allowed = webhook.networks.is_empty()
+        || event
+            .kind
+            .network_id()
+            .is_some_and(|id| webhook.networks.iter().any(|network| network == id));
+
+    event_allowed && network_allowed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::events::EventKind;
+    use crate::test_support::TestEnv;
+    use axum::{body::Bytes, http::HeaderMap, http::StatusCode, routing::post, Router};
+    use std::sync::Mutex;
+    use tokio::net::TcpListener;
+
+    type Received = Arc<Mutex<Vec<(HeaderMap, Bytes)>>>;
+
+    /// A webhook target answering with the given statuses, then 200
+    async fn target(statuses: Vec<StatusCode>) -> (String, Received) {
+        let received: Received = Arc::default();
+        let statuses = Arc::new(Mutex::new(statuses));
+
+        let state = received.clone();
+        let app = Router::new().route(
+            "/hook",
+            post(move |headers: HeaderMap, body: Bytes| async move {
+                state.lock().unwrap().push((headers, body));
+                let mut statuses = statuses.lock().unwrap();
+                if statuses.is_empty() {
+                    StatusCode::OK
+                } else {
+                    statuses.remove(0)
+                }
+            }),
+        );
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/hook", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, app).await });
+        (url, received)
+    }
+
+    fn webhook(url: &str) -> WebhookConfig {
+        WebhookConfig {
+            url: url.to_string(),
+            secret: Some("key".to_string()),
+            events: Vec::new(),
+            networks: Vec::new(),
+            max_attempts: 2,
+        }
+    }
+
+    fn event(kind: EventKind) -> Event {
+        Event {
+            id: 7,
+            timestamp: Utc::now(),
+            kind,
+        }
+    }
+
+    fn member_deleted(network_id: &str) -> Event {
+        event(EventKind::MemberDeleted {
+            network_id: network_id.to_string(),
+            member_id: "1111111111".to_string(),
+        })
+    }
+
+    #[test]
+    fn signs_payloads_with_hmac_sha256() {
+        assert_eq!(
+            WebhookService::sign("key", b"The quick brown fox jumps over the lazy dog"),
+            "sha256=f7bc83f430538424b13298e6aa6fb143ef4d59a14946175997479dbc2d1a3cd8"
+        );
+    }
+
+    #[test]
+    fn filters_by_event_type_and_network() {
+        let mut webhook = webhook("http://127.0.0.1/hook");
+        assert!(matches(&webhook, &member_deleted("abcdef0123000001")));
+
+        webhook.events = vec!["member_joined".to_string()];
+        assert!(!matches(&webhook, &member_deleted("abcdef0123000001")));
+        webhook.events = vec!["member_deleted".to_string()];
+        assert!(matches(&webhook, &member_deleted("abcdef0123000001")));
+
+        webhook.networks = vec!["abcdef0123000002".to_string()];
+        assert!(!matches(&webhook, &member_deleted("abcdef0123000001")));
+        assert!(matches(&webhook, &member_deleted("abcdef0123000002")));
+
+        // Events without a network never match a network filter
+        webhook.events.clear();
+        let banned = event(EventKind::PasswordChanged {
+            username: "admin".to_string(),
+        });
+        assert!(!matches(&webhook, &banned));
+    }
+
+    #[tokio::test]
+    async fn retries_and_signs_deliveries() {
+        let env = TestEnv::new().await;
+        let service = WebhookService::new(env.config.clone(), EventService::new());
+        let (url, received) = target(vec![StatusCode::SERVICE_UNAVAILABLE]).await;
+
+        let event = member_deleted("abcdef0123000001");
+        service.deliver(&webhook(&url), &event).await;
+
+        let received = received.lock().unwrap();
+        assert_eq!(received.len(), 2);
+        let (headers, body) = &received[1];
+        assert_eq!(headers["X-Ztvrui-Event"], "member_deleted");
+        assert_eq!(headers["X-Ztvrui-Delivery"], "7");
+        assert_eq!(
+            headers["X-Ztvrui-Signature"],
+            WebhookService::sign("key", body)
+        );
+        let payload: serde_json::Value = serde_json::from_slice(body).unwrap();
+        assert_eq!(payload["type"], "member_deleted");
+        assert_eq!(payload["member_id"], "1111111111");
+        assert!(!env.dir.join(DEAD_LETTER_FILE).exists());
+    }
+
+    #[tokio::test]
+    async fn dead_letters_undeliverable_events() {
+        let env = TestEnv::new().await;
+        let service = WebhookService::new(env.config.clone(), EventService::new());
+        let (url, received) = target(vec![
+            StatusCode::INTERNAL_SERVER_ERROR,
+            StatusCode::BAD_GATEWAY,
+        ])
+        .await;
+
+        let mut webhook = webhook(&url);
+        webhook.secret = None;
+        service
+            .deliver(&webhook, &member_deleted("abcdef0123000001"))
+            .await;
+
+        assert_eq!(received.lock().unwrap().len(), 2);
+        assert!(!received.lock().unwrap()[0]
+            .0
+            .contains_key("X-Ztvrui-Signature"));
+
+        let log = std::fs::read_to_string(env.dir.join(DEAD_LETTER_FILE)).unwrap();
+        let entry: serde_json::Value = serde_json::from_str(log.trim()).unwrap();
+        assert_eq!(entry["url"], url);
+        assert_eq!(entry["attempts"], 2);
+        assert_eq!(entry["error"], "target responded with 502 Bad Gateway");
+        assert_eq!(entry["event"]["id"], 7);
+    }
+}
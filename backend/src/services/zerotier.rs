@@ -1,23 +1,37 @@
-use crate::error::{AppError, Result};
-use crate::models::ZeroTierConfig;
+use crate::error::{AppError, FieldError, Result};
+use crate::models::{NetworkMember, PeerSummary, ZeroTierConfig};
+use crate::services::{ResponseCache, UpstreamResponse};
+use crate::validation;
 use axum::body::Bytes;
-use reqwest::{Client, Method, Response};
+use futures::{stream, StreamExt, TryStreamExt};
+use reqwest::{Client, Method, Response, StatusCode};
+use serde::de::DeserializeOwned;
+use serde_json::{Map, Value};
+use std::collections::HashMap;
+use std::net::IpAddr;
 use std::sync::Arc;
+use std::time::Duration;
 
 #[derive(Clone)]
 pub struct ZeroTierService {
     client: Client,
     config: Arc<ZeroTierConfig>,
+    cache: ResponseCache,
 }
 
 impl ZeroTierService {
     pub fn new(config: ZeroTierConfig) -> Self {
         Self {
             client: Client::new(),
+            cache: ResponseCache::new(Duration::from_secs(config.cache_ttl_secs)),
             config: Arc::new(config),
         }
     }
 
+    pub fn max_concurrency(&self) -> usize {
+        self.config.max_concurrency.max(1)
+    }
+
     pub async fn forward_request(
         &self,
         endpoint: &str,
@@ -39,4 +53,382 @@ impl ZeroTierService {
             .await
             .map_err(|e| AppError::ZeroTierError(format!("Failed to forward request: {}", e)))
     }
+
+    /// Forward a request and buffer the response.
+    /// GET responses are served from the cache while fresh; successful writes
+    /// invalidate cached responses for the affected network and members.
+    pub async fn request(
+        &self,
+        endpoint: &str,
+        method: Method,
+        body: Bytes,
+    ) -> Result<UpstreamResponse> {
+        let is_get = method == Method::GET;
+        if is_get {
+            if let Some(cached) = self.cache.get(endpoint).await {
+                tracing::debug!("Serving {} from cache", endpoint);
+                return Ok(cached);
+            }
+        }
+
+        let response = self.forward_request(endpoint, method, body).await?;
+        let status = response.status().as_u16();
+        let content_type = response
+            .headers()
+            .get(reqwest::header::CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .map(str::to_string);
+        let body = response
+            .bytes()
+            .await
+            .map_err(|e| AppError::ZeroTierError(format!("Failed to read response: {}", e)))?;
+
+        let response = UpstreamResponse::new(status, content_type, body);
+        if response.is_success() {
+            if is_get {
+                self.cache.insert(endpoint, response.clone()).await;
+            } else {
+                self.cache.invalidate(endpoint).await;
+            }
+        }
+
+        Ok(response)
+    }
+
+    /// GET an endpoint and deserialize the JSON response
+    pub async fn get_json<T: DeserializeOwned>(&self, endpoint: &str) -> Result<T> {
+        let response = self.request(endpoint, Method::GET, Bytes::new()).await?;
+
+        match StatusCode::from_u16(response.status) {
+            Ok(status) if status.is_success() => {
+                serde_json::from_slice(&response.body).map_err(|e| {
+                    AppError::ZeroTierError(format!("Invalid response from {}: {}", endpoint, e))
+                })
+            }
+            Ok(StatusCode::NOT_FOUND) => Err(AppError::NotFound(format!("{} not found", endpoint))),
+            _ => Err(AppError::ZeroTierError(format!(
+                "{} returned {}",
+                endpoint, response.status
+            ))),
+        }
+    }
+
+    /// Send a validated JSON update to the controller and return the result
+    pub async fn post_json(&self, endpoint: &str, update: &Value) -> Result<Map<String, Value>> {
+        let body = Bytes::from(serde_json::to_vec(update)?);
+        self.validate_update(endpoint, &body).await?;
+
+        let response = self.request(endpoint, Method::POST, body).await?;
+        if !response.is_success() {
+            return Err(AppError::ZeroTierError(format!(
+                "{} returned {}",
+                endpoint, response.status
+            )));
+        }
+
+        serde_json::from_slice(&response.body).map_err(|e| {
+            AppError::ZeroTierError(format!("Invalid response from {}: {}", endpoint, e))
+        })
+    }
+
+    pub async fn update_member(
+        &self,
+        network_id: &str,
+        member_id: &str,
+        update: &Value,
+    ) -> Result<Map<String, Value>> {
+        self.post_json(
+            &format!("/controller/network/{}/member/{}", network_id, member_id),
+            update,
+        )
+        .await
+    }
+
+    pub async fn delete_member(&self, network_id: &str, member_id: &str) -> Result<()> {
+        self.delete(&format!(
+            "/controller/network/{}/member/{}",
+            network_id, member_id
+        ))
+        .await
+    }
+
+    /// DELETE an endpoint, mapping a 404 to `NotFound`
+    async fn delete(&self, endpoint: &str) -> Result<()> {
+        let response = self.request(endpoint, Method::DELETE, Bytes::new()).await?;
+
+        match StatusCode::from_u16(response.status) {
+            Ok(status) if status.is_success() => Ok(()),
+            Ok(StatusCode::NOT_FOUND) => Err(AppError::NotFound(format!("{} not found", endpoint))),
+            _ => Err(AppError::ZeroTierError(format!(
+                "{} returned {}",
+                endpoint, response.status
+            ))),
+        }
+    }
+
+    /// The controller's node ID, the first 10 hex digits of every network it owns
+    pub async fn controller_id(&self) -> Result<String> {
+        let status: Map<String, Value> = self.get_json("/status").await?;
+
+        status
+            .get("address")
+            .and_then(Value::as_str)
+            .map(str::to_string)
+            .ok_or_else(|| AppError::ZeroTierError("/status did not report an address".into()))
+    }
+
+    /// Create a network with a controller-assigned ID and return its configuration
+    pub async fn create_network(&self, config: &Value) -> Result<Map<String, Value>> {
+        let controller_id = self.controller_id().await?;
+        self.post_json(
+            &format!("/controller/network/{}______", controller_id),
+            config,
+        )
+        .await
+    }
+
+    pub async fn delete_network(&self, network_id: &str) -> Result<()> {
+        self.delete(&format!("/controller/network/{}", network_id))
+            .await
+    }
+
+    /// Fetch the configuration of every network on the controller
+    pub async fn list_networks(&self) -> Result<Vec<Map<String, Value>>> {
+        let ids: Vec<String> = self.get_json("/controller/network").await?;
+
+        stream::iter(ids)
+            .map(|network_id| async move {
+                self.get_json::<Map<String, Value>>(&format!("/controller/network/{}", network_id))
+                    .await
+            })
+            .buffered(self.max_concurrency())
+            .try_collect()
+            .await
+    }
+
+    /// Fetch the full configuration of every member of a network.
+    /// Requests are issued concurrently, bounded by `max_concurrency`.
+    pub async fn list_members(&self, network_id: &str) -> Result<Vec<Map<String, Value>>> {
+        let ids: HashMap<String, Value> = self
+            .get_json(&format!("/controller/network/{}/member", network_id))
+            .await?;
+
+        stream::iter(ids.into_keys())
+            .map(|member_id| async move {
+                self.get_json::<Map<String, Value>>(&format!(
+                    "/controller/network/{}/member/{}",
+                    network_id, member_id
+                ))
+                .await
+            })
+            .buffer_unordered(self.max_concurrency())
+            .try_collect()
+            .await
+    }
+
+    /// Fetch all peers known to the local node, keyed by ZeroTier address
+    pub async fn list_peers(&self) -> Result<HashMap<String, PeerSummary>> {
+        let peers: Vec<Value> = self.get_json("/peer").await?;
+
+        Ok(peers
+            .into_iter()
+            .filter_map(|peer| {
+                let address = peer.get("address")?.as_str()?.to_string();
+                let summary = PeerSummary {
+                    latency: peer.get("latency").and_then(Value::as_i64).unwrap_or(-1),
+                    role: peer
+                        .get("role")
+                        .and_then(Value::as_str)
+                        .unwrap_or_default()
+                        .to_string(),
+                    version: peer
+                        .get("version")
+                        .and_then(Value::as_str)
+                        .unwrap_or_default()
+                        .to_string(),
+                    paths: peer
+                        .get("paths")
+                        .and_then(Value::as_array)
+                        .cloned()
+                        .unwrap_or_default(),
+                };
+                Some((address, summary))
+            })
+            .collect())
+    }
+
+    /// Fetch all members of a network merged with their live peer data
+    pub async fn network_members(&self, network_id: &str) -> Result<Vec<NetworkMember>> {
+        let endpoint = format!("/controller/network/{}", network_id);
+        let (network, members, mut peers) = tokio::try_join!(
+            self.get_json::<Map<String, Value>>(&endpoint),
+            self.list_members(network_id),
+            self.list_peers()
+        )?;
+
+        Ok(members
+            .into_iter()
+            .map(|config| {
+                let peer = config
+                    .get("address")
+                    .and_then(Value::as_str)
+                    .and_then(|address| peers.remove(address));
+                let mut member = NetworkMember::new(config, peer);
+                member.compute_addresses(network_id, &network);
+                member
+            })
+            .collect())
+    }
+
+    /// Validate a network or member update before it is sent to the controller.
+    /// Requests to any other endpoint are not inspected.
+    pub async fn validate_update(&self, endpoint: &str, body: &[u8]) -> Result<()> {
+        let segments: Vec<&str> = endpoint.trim_matches('/').split('/').collect();
+        let target = match segments.as_slice() {
+            ["controller", "network", network_id] => (network_id, None),
+            ["controller", "network", network_id, "member", member_id] => {
+                (network_id, Some(member_id))
+            }
+            _ => return Ok(()),
+        };
+
+        if body.iter().all(u8::is_ascii_whitespace) {
+            return Ok(());
+        }
+
+        let update: Map<String, Value> = serde_json::from_slice(body).map_err(|_| {
+            AppError::ValidationError(vec![FieldError::new("body", "must be a JSON object")])
+        })?;
+
+        let errors = match target {
+            (_, None) => validation::validate_network(&update),
+            (network_id, Some(member_id)) => {
+                if !update.contains_key("ipAssignments") {
+                    return Ok(());
+                }
+
+                let network_endpoint = format!("/controller/network/{}", network_id);
+                let (network, members) = tokio::try_join!(
+                    self.get_json::<Map<String, Value>>(&network_endpoint),
+                    self.list_members(network_id)
+                )?;
+
+                let assigned: HashMap<IpAddr, String> = members
+                    .iter()
+                    .filter_map(|member| {
+                        let id = member.get("id")?.as_str()?;
+                        let ips = member.get("ipAssignments")?.as_array()?;
+                        Some((id, ips))
+                    })
+                    .filter(|(id, _)| id != member_id)
+                    .flat_map(|(id, ips)| {
+                        ips.iter()
+                            .filter_map(|ip| ip.as_str()?.parse().ok())
+                            .map(move |ip| (ip, id.to_string()))
+                    })
+                    .collect();
+
+                validation::validate_member(
+                    &update,
+                    &validation::routed_subnets(&network),
+                    &assigned,
+                )
+            }
+        };
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(AppError::ValidationError(errors))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TestEnv;
+    use serde_json::json;
+
+    const NETWORK: &str = "abcdef0123000001";
+
+    async fn env() -> TestEnv {
+        let env = TestEnv::new().await;
+        env.controller.add_network(
+            NETWORK,
+            json!({ "routes": [{ "target": "10.0.0.0/24", "via": null }] }),
+        );
+        env.controller.add_member(
+            NETWORK,
+            "1111111111",
+            json!({ "ipAssignments": ["10.0.0.1"] }),
+        );
+        env.controller.add_member(NETWORK, "2222222222", json!({}));
+        env
+    }
+
+    fn rejected_fields(result: Result<Map<String, Value>>) -> Vec<String> {
+        match result {
+            Err(AppError::ValidationError(errors)) => {
+                errors.into_iter().map(|error| error.field).collect()
+            }
+            other => panic!("expected a validation error, got {:?}", other.map(|_| ())),
+        }
+    }
+
+    #[tokio::test]
+    async fn rejects_addresses_of_other_members() {
+        let env = env().await;
+        let zerotier = env.zerotier();
+
+        let result = zerotier
+            .update_member(
+                NETWORK,
+                "2222222222",
+                &json!({ "ipAssignments": ["10.0.0.2", "10.0.0.1"] }),
+            )
+            .await;
+        assert_eq!(rejected_fields(result), ["ipAssignments[1]"]);
+        assert_eq!(
+            env.controller.member(NETWORK, "2222222222").unwrap()["ipAssignments"],
+            json!([])
+        );
+
+        // A member may keep its own addresses
+        let member = zerotier
+            .update_member(
+                NETWORK,
+                "1111111111",
+                &json!({ "ipAssignments": ["10.0.0.1", "10.0.0.3"] }),
+            )
+            .await
+            .unwrap();
+        assert_eq!(member["ipAssignments"], json!(["10.0.0.1", "10.0.0.3"]));
+    }
+
+    #[tokio::test]
+    async fn rejects_invalid_network_updates() {
+        let env = env().await;
+        let endpoint = format!("/controller/network/{}", NETWORK);
+
+        let result = env
+            .zerotier()
+            .post_json(&endpoint, &json!({ "mtu": 100 }))
+            .await;
+        assert_eq!(rejected_fields(result), ["mtu"]);
+
+        let result = env.zerotier().validate_update(&endpoint, b"[1, 2]").await;
+        assert!(matches!(result, Err(AppError::ValidationError(_))));
+        // Other endpoints and empty bodies are passed through unchecked
+        assert!(env
+            .zerotier()
+            .validate_update("/status", b"[]")
+            .await
+            .is_ok());
+        assert!(env
+            .zerotier()
+            .validate_update(&endpoint, b" ")
+            .await
+            .is_ok());
+    }
 }
@@ -1,4 +1,10 @@
-use crate::services::{AuthService, ConfigService, IpBanService, ZeroTierService};
+use crate::error::Result;
+use crate::services::{
+    AccessScheduleService, AuditService, AuthService, ConfigService, ControllerMonitor, DnsService,
+    EmailService, EventService, ExpiryService, GitOpsService, InventoryService, InvitationService,
+    IpBanService, IpamService, LoginHistoryService, MemberPolicyService, MemberTransferService,
+    MetadataService, NetworkTemplateService, SearchService, WebhookService, ZeroTierService,
+};
 use axum::extract::FromRef;
 
 #[derive(Clone)]
@@ -7,20 +13,114 @@ pub struct AppState {
     pub auth: AuthService,
     pub zerotier: ZeroTierService,
     pub ip_ban: IpBanService,
+    pub events: EventService,
+    pub monitor: ControllerMonitor,
+    pub webhooks: WebhookService,
+    pub email: EmailService,
+    pub login_history: LoginHistoryService,
+    pub audit: AuditService,
+    pub member_policy: MemberPolicyService,
+    pub invitations: InvitationService,
+    pub metadata: MetadataService,
+    pub expiry: ExpiryService,
+    pub access_schedules: AccessScheduleService,
+    pub search: SearchService,
+    pub transfer: MemberTransferService,
+    pub inventory: InventoryService,
+    pub gitops: GitOpsService,
+    pub templates: NetworkTemplateService,
+    pub ipam: IpamService,
+    pub dns: DnsService,
 }
 
 impl AppState {
-    pub fn new(config: ConfigService) -> Self {
+    pub fn new(config: ConfigService) -> Result<Self> {
         let auth = AuthService::new(config.get_zerotier_config().auth_token);
         let zerotier = ZeroTierService::new(config.get_zerotier_config());
-        let ip_ban = IpBanService::new();
+        let events = EventService::new();
+        let ip_ban = IpBanService::new(events.clone());
+        let monitor = ControllerMonitor::new(zerotier.clone(), events.clone());
+        let webhooks = WebhookService::new(config.clone(), events.clone());
+        let email = EmailService::new(config.clone(), events.clone());
+        let login_history = LoginHistoryService::new(config.data_path("login_history.json"))?;
+        let audit = AuditService::new(config.data_path("audit.jsonl"))?;
+        let metadata = MetadataService::new(config.data_path("metadata.json"))?;
+        let member_policy = MemberPolicyService::new(
+            config.clone(),
+            events.clone(),
+            zerotier.clone(),
+            monitor.clone(),
+            metadata.clone(),
+            audit.clone(),
+        );
+        let invitations = InvitationService::new(
+            config.data_path("invitations.json"),
+            zerotier.clone(),
+            audit.clone(),
+        )?;
+        let expiry = ExpiryService::new(
+            config.clone(),
+            metadata.clone(),
+            zerotier.clone(),
+            monitor.clone(),
+            events.clone(),
+            audit.clone(),
+        );
+        let access_schedules = AccessScheduleService::new(
+            config.clone(),
+            metadata.clone(),
+            zerotier.clone(),
+            audit.clone(),
+        );
+        let search = SearchService::new(monitor.clone(), metadata.clone());
+        let dns = DnsService::new(config.clone(), monitor.clone(), metadata.clone());
+        let transfer =
+            MemberTransferService::new(zerotier.clone(), metadata.clone(), audit.clone());
+        let inventory = InventoryService::new(zerotier.clone(), metadata.clone());
+        let gitops = GitOpsService::new(
+            config.clone(),
+            zerotier.clone(),
+            metadata.clone(),
+            events.clone(),
+            audit.clone(),
+        )?;
+        let ipam = IpamService::new(
+            config.clone(),
+            config.data_path("ipam.json"),
+            zerotier.clone(),
+            audit.clone(),
+        )?;
+        let templates = NetworkTemplateService::new(
+            config.data_path("templates.json"),
+            zerotier.clone(),
+            ipam.clone(),
+            audit.clone(),
+        )?;
 
-        Self {
+        Ok(Self {
             config,
             auth,
             zerotier,
             ip_ban,
-        }
+            events,
+            monitor,
+            webhooks,
+            email,
+            login_history,
+            audit,
+            member_policy,
+            invitations,
+            metadata,
+            expiry,
+            access_schedules,
+            search,
+            transfer,
+            inventory,
+            gitops,
+            templates,
+            ipam,
+            dns,
+        })
     }
 }
 
@@ -48,3 +148,9 @@ impl FromRef<AppState> for IpBanService {
         app_state.ip_ban.clone()
     }
 }
+
+impl FromRef<AppState> for EventService {
+    fn from_ref(app_state: &AppState) -> EventService {
+        app_state.events.clone()
+    }
+}
@@ -0,0 +1,328 @@
+//! Helpers shared by the unit tests: an in-memory ZeroTier controller and a
+//! throwaway data directory with a config file pointing at it
+
+use crate::models::AppConfig;
+use crate::routes::app_routes;
+use crate::services::{ConfigService, ZeroTierService};
+use crate::state::AppState;
+use axum::{
+    body::{Body, Bytes},
+    extract::ConnectInfo,
+    http::{Method, Request, StatusCode, Uri},
+    response::{IntoResponse, Response},
+    Json, Router,
+};
+use serde_json::{json, Map, Value};
+use std::collections::{BTreeMap, HashSet};
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use tokio::net::TcpListener;
+use tower::ServiceExt;
+
+/// Node ID the fake controller reports in `/status`
+pub const CONTROLLER_ID: &str = "abcdef0123";
+
+#[derive(Default)]
+pub struct ControllerState {
+    pub networks: BTreeMap<String, Map<String, Value>>,
+    pub members: BTreeMap<String, BTreeMap<String, Map<String, Value>>>,
+    pub peers: Vec<Value>,
+    /// `<METHOD> <path>` of requests answered with a 500
+    pub failures: HashSet<String>,
+    /// `<METHOD> <path>` of every request received, in order
+    pub requests: Vec<String>,
+    next_network: u32,
+}
+
+/// A ZeroTier controller API backed by memory, listening on a local port
+#[derive(Clone)]
+pub struct FakeController {
+    pub address: String,
+    pub state: Arc<Mutex<ControllerState>>,
+}
+
+fn merge(target: &mut Map<String, Value>, update: Map<String, Value>) {
+    for (key, value) in update {
+        target.insert(key, value);
+    }
+}
+
+async fn handle(
+    controller: Arc<Mutex<ControllerState>>,
+    method: Method,
+    uri: Uri,
+    body: Bytes,
+) -> Response {
+    let mut state = controller.lock().unwrap();
+    let request = format!("{} {}", method, uri.path());
+    state.requests.push(request.clone());
+    if state.failures.contains(&request) {
+        return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+    }
+
+    let update: Map<String, Value> = serde_json::from_slice(&body).unwrap_or_default();
+    let segments: Vec<&str> = uri.path().trim_matches('/').split('/').collect();
+    let not_found = || StatusCode::NOT_FOUND.into_response();
+
+    match (method, segments.as_slice()) {
+        (Method::GET, ["status"]) => {
+            Json(json!({ "address": CONTROLLER_ID, "online": true })).into_response()
+        }
+        (Method::GET, ["peer"]) => Json(state.peers.clone()).into_response(),
+        (Method::GET, ["controller", "network"]) => {
+            Json(state.networks.keys().cloned().collect::<Vec<_>>()).into_response()
+        }
+        (Method::GET, ["controller", "network", network_id]) => {
+            match state.networks.get(*network_id) {
+                Some(network) => Json(network.clone()).into_response(),
+                None => not_found(),
+            }
+        }
+        (Method::POST, ["controller", "network", network_id]) => {
+            let network_id = match network_id.strip_suffix("______") {
+                Some(controller_id) => {
+                    state.next_network += 1;
+                    format!("{}{:06x}", controller_id, state.next_network)
+                }
+                None => network_id.to_string(),
+            };
+            let network = state.networks.entry(network_id.clone()).or_insert_with(|| {
+                let mut network = Map::new();
+                network.insert("id".to_string(), json!(network_id));
+                network.insert("nwid".to_string(), json!(network_id));
+                network
+            });
+            merge(network, update);
+            bump_revision(network);
+            let network = network.clone();
+            state.members.entry(network_id).or_default();
+            Json(network).into_response()
+        }
+        (Method::DELETE, ["controller", "network", network_id]) => {
+            state.members.remove(*network_id);
+            match state.networks.remove(*network_id) {
+                Some(network) => Json(network).into_response(),
+                None => not_found(),
+            }
+        }
+        (Method::GET, ["controller", "network", network_id, "member"]) => {
+            match state.members.get(*network_id) {
+                Some(members) => {
+                    let revisions: Map<String, Value> = members
+                        .iter()
+                        .map(|(id, member)| (id.clone(), member["revision"].clone()))
+                        .collect();
+                    Json(revisions).into_response()
+                }
+                None => not_found(),
+            }
+        }
+        (Method::GET, ["controller", "network", network_id, "member", member_id]) => {
+            match state
+                .members
+                .get(*network_id)
+                .and_then(|members| members.get(*member_id))
+            {
+                Some(member) => Json(member.clone()).into_response(),
+                None => not_found(),
+            }
+        }
+        (Method::POST, ["controller", "network", network_id, "member", member_id]) => {
+            let Some(members) = state.members.get_mut(*network_id) else {
+                return not_found();
+            };
+            let member = members
+                .entry(member_id.to_string())
+                .or_insert_with(|| new_member(network_id, member_id));
+            merge(member, update);
+            bump_revision(member);
+            Json(member.clone()).into_response()
+        }
+        (Method::DELETE, ["controller", "network", network_id, "member", member_id]) => match state
+            .members
+            .get_mut(*network_id)
+            .and_then(|members| members.remove(*member_id))
+        {
+            Some(member) => Json(member).into_response(),
+            None => not_found(),
+        },
+        _ => not_found(),
+    }
+}
+
+fn bump_revision(object: &mut Map<String, Value>) {
+    let revision = object.get("revision").and_then(Value::as_u64).unwrap_or(0);
+    object.insert("revision".to_string(), json!(revision + 1));
+}
+
+fn new_member(network_id: &str, member_id: &str) -> Map<String, Value> {
+    let member = json!({
+        "id": member_id,
+        "address": member_id,
+        "nwid": network_id,
+        "name": "",
+        "authorized": false,
+        "ipAssignments": [],
+        "tags": [],
+        "capabilities": [],
+        "creationTime": 0,
+        "lastAuthorizedTime": 0,
+        "revision": 1,
+    });
+    member.as_object().cloned().unwrap_or_default()
+}
+
+impl FakeController {
+    pub async fn start() -> Self {
+        let state = Arc::new(Mutex::new(ControllerState::default()));
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = format!("http://{}", listener.local_addr().unwrap());
+
+        let handler_state = state.clone();
+        let app = Router::new().fallback(move |method: Method, uri: Uri, body: Bytes| {
+            handle(handler_state.clone(), method, uri, body)
+        });
+        tokio::spawn(async move { axum::serve(listener, app).await });
+
+        Self { address, state }
+    }
+
+    /// Add a network, `config` is merged over the ID fields
+    pub fn add_network(&self, network_id: &str, config: Value) {
+        let mut network = Map::new();
+        network.insert("id".to_string(), json!(network_id));
+        network.insert("nwid".to_string(), json!(network_id));
+        merge(
+            &mut network,
+            config.as_object().cloned().unwrap_or_default(),
+        );
+
+        let mut state = self.state.lock().unwrap();
+        state.networks.insert(network_id.to_string(), network);
+        state.members.entry(network_id.to_string()).or_default();
+    }
+
+    /// Add a member, `config` is merged over the controller's defaults
+    pub fn add_member(&self, network_id: &str, member_id: &str, config: Value) {
+        let mut member = new_member(network_id, member_id);
+        merge(&mut member, config.as_object().cloned().unwrap_or_default());
+
+        let mut state = self.state.lock().unwrap();
+        state
+            .members
+            .entry(network_id.to_string())
+            .or_default()
+            .insert(member_id.to_string(), member);
+    }
+
+    pub fn member(&self, network_id: &str, member_id: &str) -> Option<Map<String, Value>> {
+        self.state
+            .lock()
+            .unwrap()
+            .members
+            .get(network_id)
+            .and_then(|members| members.get(member_id))
+            .cloned()
+    }
+
+    /// `<METHOD> <path>` of every request received so far
+    pub fn requests(&self) -> Vec<String> {
+        self.state.lock().unwrap().requests.clone()
+    }
+
+    /// Report a peer with a single path, `latency` of -1 means unknown
+    pub fn add_peer(&self, address: &str, latency: i64, active: bool) {
+        self.state.lock().unwrap().peers.push(json!({
+            "address": address,
+            "latency": latency,
+            "role": "LEAF",
+            "version": "1.14.2",
+            "paths": [{ "active": active, "expired": false, "address": "192.0.2.1/9993" }],
+        }));
+    }
+}
+
+/// A fake controller and a data directory with a config file pointing at it
+pub struct TestEnv {
+    pub dir: PathBuf,
+    pub controller: FakeController,
+    pub config: ConfigService,
+}
+
+impl TestEnv {
+    pub async fn new() -> Self {
+        Self::with_config(|_| {}).await
+    }
+
+    /// Start with the default config changed by `configure`
+    pub async fn with_config(configure: impl FnOnce(&mut AppConfig)) -> Self {
+        let controller = FakeController::start().await;
+        let dir = std::env::temp_dir().join(format!("ztvrui-test-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let mut config = AppConfig::default();
+        config.zerotier.address = controller.address.clone();
+        config.zerotier.auth_token = "token".to_string();
+        // Every read should reach the controller so tests see their own changes
+        config.zerotier.cache_ttl_secs = 0;
+        configure(&mut config);
+
+        let path = dir.join("config.json");
+        std::fs::write(&path, serde_json::to_string_pretty(&config).unwrap()).unwrap();
+        let config = ConfigService::new(path.to_string_lossy().into_owned()).unwrap();
+
+        Self {
+            dir,
+            controller,
+            config,
+        }
+    }
+
+    pub fn zerotier(&self) -> ZeroTierService {
+        ZeroTierService::new(self.config.get_zerotier_config())
+    }
+
+    pub fn app_state(&self) -> AppState {
+        AppState::new(self.config.clone()).unwrap()
+    }
+}
+
+impl Drop for TestEnv {
+    fn drop(&mut self) {
+        std::fs::remove_dir_all(&self.dir).ok();
+    }
+}
+
+/// Read a handler's response as JSON
+pub async fn json_body(response: impl IntoResponse) -> (StatusCode, Value) {
+    let response = response.into_response();
+    let status = response.status();
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    (status, serde_json::from_slice(&body).unwrap_or(Value::Null))
+}
+
+/// Send a request from a local client through the full application router
+pub async fn send(app_state: &AppState, mut request: Request<Body>) -> Response {
+    request
+        .extensions_mut()
+        .insert(ConnectInfo(SocketAddr::from(([127, 0, 0, 1], 40000))));
+    app_routes(app_state.clone())
+        .oneshot(request)
+        .await
+        .unwrap()
+}
+
+/// A session token for the admin user
+pub fn user_token(app_state: &AppState) -> String {
+    app_state.auth.create_token("admin").unwrap().0
+}
+
+pub async fn text_body(response: Response) -> String {
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    String::from_utf8_lossy(&body).into_owned()
+}
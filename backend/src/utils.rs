@@ -1,4 +1,4 @@
-use std::net::IpAddr;
+use std::net::{IpAddr, Ipv6Addr};
 
 /// Check if an IP address is a private/internal IP
 /// Returns true if the IP is not a public routable address
@@ -30,3 +30,220 @@ pub fn is_private_ip(ip: &IpAddr) -> bool {
         }
     }
 }
+
+/// Numeric value of an IP address, for range arithmetic
+pub fn ip_to_u128(ip: IpAddr) -> u128 {
+    match ip {
+        IpAddr::V4(ipv4) => u32::from(ipv4) as u128,
+        IpAddr::V6(ipv6) => u128::from(ipv6),
+    }
+}
+
+/// The address `offset` positions after `base`, if it stays within the address family
+pub fn ip_offset(base: IpAddr, offset: u128) -> Option<IpAddr> {
+    let value = ip_to_u128(base).checked_add(offset)?;
+    match base {
+        IpAddr::V4(_) => u32::try_from(value)
+            .ok()
+            .map(|value| IpAddr::V4(value.into())),
+        IpAddr::V6(_) => Some(IpAddr::V6(value.into())),
+    }
+}
+
+/// Check whether the text is a 10 hex digit ZeroTier node ID
+pub fn is_node_id(node_id: &str) -> bool {
+    node_id.len() == 10 && node_id.chars().all(|c| c.is_ascii_hexdigit())
+}
+
+/// The RFC4193 address ZeroTier assigns a node: `fd`, the network ID, `9993` and the node ID
+pub fn rfc4193_address(network_id: u64, node_id: u64) -> Ipv6Addr {
+    let mut octets = [0u8; 16];
+    octets[0] = 0xfd;
+    octets[1..9].copy_from_slice(&network_id.to_be_bytes());
+    octets[9] = 0x99;
+    octets[10] = 0x93;
+    octets[11..].copy_from_slice(&node_id.to_be_bytes()[3..]);
+    Ipv6Addr::from(octets)
+}
+
+/// The /80 6PLANE prefix of a node: `fc`, the halves of the network ID XORed and the node ID
+pub fn sixplane_prefix(network_id: u64, node_id: u64) -> Ipv6Addr {
+    let mut octets = [0u8; 16];
+    octets[0] = 0xfc;
+    octets[1..5].copy_from_slice(&((network_id >> 32) as u32 ^ network_id as u32).to_be_bytes());
+    octets[5..10].copy_from_slice(&node_id.to_be_bytes()[3..]);
+    Ipv6Addr::from(octets)
+}
+
+/// The address ZeroTier assigns a node within its 6PLANE prefix
+pub fn sixplane_address(network_id: u64, node_id: u64) -> Ipv6Addr {
+    Ipv6Addr::from(u128::from(sixplane_prefix(network_id, node_id)) | 1)
+}
+
+/// The MAC address ZeroTier derives for a node on a network
+pub fn zerotier_mac(network_id: u64, node_id: u64) -> u64 {
+    let first_octet = match (network_id as u8 & 0xfe) | 0x02 {
+        // Avoid the range used by some virtualization software
+        0x52 => 0x32,
+        octet => octet,
+    };
+
+    let mut mac = (u64::from(first_octet) << 40) | (node_id & 0xff_ffff_ffff);
+    for byte in 1..=5 {
+        mac ^= ((network_id >> (byte * 8)) & 0xff) << (40 - byte * 8);
+    }
+    mac
+}
+
+/// Turn a member or network name into a DNS label, e.g. `Alice's Laptop` into `alices-laptop`
+pub fn dns_label(name: &str) -> Option<String> {
+    let mut label = String::new();
+    for c in name.trim().to_lowercase().chars() {
+        if c.is_ascii_alphanumeric() {
+            label.push(c);
+        } else if matches!(c, ' ' | '-' | '_' | '.') && !label.ends_with('-') {
+            label.push('-');
+        }
+    }
+
+    let label = label.trim_matches('-');
+    let label = &label[..label.len().min(63)];
+    let label = label.trim_end_matches('-');
+    (!label.is_empty()).then(|| label.to_string())
+}
+
+/// Escape text for safe inclusion in HTML
+pub fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Match text against a glob pattern supporting `*` and `?`
+pub fn glob_match(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+
+    let (mut p, mut t) = (0, 0);
+    let mut backtrack: Option<(usize, usize)> = None;
+
+    while t < text.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                backtrack = Some((p, t));
+                p += 1;
+            }
+            Some(c) if *c == '?' || *c == text[t] => {
+                p += 1;
+                t += 1;
+            }
+            _ => match backtrack {
+                // Let the last `*` consume one more character
+                Some((star, matched)) => {
+                    p = star + 1;
+                    t = matched + 1;
+                    backtrack = Some((star, matched + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+
+    pattern[p..].iter().all(|c| *c == '*')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn escapes_html() {
+        assert_eq!(
+            escape_html(r#"<a href="x">Tom & 'Jerry'</a>"#),
+            "&lt;a href=&quot;x&quot;&gt;Tom &amp; &#39;Jerry&#39;&lt;/a&gt;"
+        );
+        assert_eq!(escape_html("plain"), "plain");
+    }
+
+    #[test]
+    fn computes_rfc4193_addresses() {
+        assert_eq!(
+            rfc4193_address(0xabcd_ef01_2300_0001, 0x11_1111_1111),
+            "fdab:cdef:123:0:199:9311:1111:1111"
+                .parse::<Ipv6Addr>()
+                .unwrap()
+        );
+        assert_eq!(
+            rfc4193_address(0x8056_c2e2_1c00_0001, 0x89_e92c_eee5),
+            "fd80:56c2:e21c:0:199:9389:e92c:eee5"
+                .parse::<Ipv6Addr>()
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn computes_sixplane_addresses() {
+        assert_eq!(
+            sixplane_prefix(0xabcd_ef01_2300_0001, 0x11_1111_1111),
+            "fc88:cdef:11:1111:1111::".parse::<Ipv6Addr>().unwrap()
+        );
+        assert_eq!(
+            sixplane_address(0xabcd_ef01_2300_0001, 0x11_1111_1111),
+            "fc88:cdef:11:1111:1111::1".parse::<Ipv6Addr>().unwrap()
+        );
+        assert_eq!(
+            sixplane_prefix(0x8056_c2e2_1c00_0001, 0x89_e92c_eee5),
+            "fc9c:56c2:e389:e92c:eee5::".parse::<Ipv6Addr>().unwrap()
+        );
+        assert_eq!(
+            sixplane_address(0x8056_c2e2_1c00_0001, 0x89_e92c_eee5),
+            "fc9c:56c2:e389:e92c:eee5::1".parse::<Ipv6Addr>().unwrap()
+        );
+    }
+
+    #[test]
+    fn computes_zerotier_macs() {
+        assert_eq!(
+            zerotier_mac(0x8056_c2e2_1c00_0001, 0x89_e92c_eee5),
+            0x0289_e930_0c27
+        );
+        assert_eq!(
+            zerotier_mac(0xabcd_ef01_2300_0001, 0x11_1111_1111),
+            0x0211_1132_10fe
+        );
+        // A first octet of 0x52 becomes 0x32
+        assert_eq!(
+            zerotier_mac(0x8056_c2e2_1c00_0050, 0x89_e92c_eee5),
+            0x3289_e930_0c27
+        );
+        assert_eq!(
+            zerotier_mac(0x8056_c2e2_1c00_0053, 0x89_e92c_eee5),
+            0x3289_e930_0c27
+        );
+        assert_eq!(
+            zerotier_mac(0x8056_c2e2_1c00_0054, 0x89_e92c_eee5),
+            0x5689_e930_0c27
+        );
+    }
+
+    #[test]
+    fn makes_dns_labels() {
+        assert_eq!(
+            dns_label("Alice's Laptop").as_deref(),
+            Some("alices-laptop")
+        );
+        assert_eq!(dns_label(" -build__box. ").as_deref(), Some("build-box"));
+        assert_eq!(dns_label("!!!"), None);
+        let long = format!("{}-b", "a".repeat(62));
+        assert_eq!(dns_label(&long), Some("a".repeat(62)));
+    }
+}
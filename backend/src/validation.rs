@@ -0,0 +1,364 @@
+use crate::error::FieldError;
+use crate::utils::ip_to_u128;
+use ipnet::IpNet;
+use serde_json::{Map, Value};
+use std::collections::HashMap;
+use std::net::IpAddr;
+
+/// MTU limits enforced by the ZeroTier controller
+pub const MIN_MTU: u64 = 1280;
+pub const MAX_MTU: u64 = 10000;
+
+/// Fields maintained by the controller that clients cannot set
+pub const CONTROLLER_FIELDS: &[&str] = &[
+    "id",
+    "nwid",
+    "address",
+    "objtype",
+    "revision",
+    "creationTime",
+    "lastAuthorizedTime",
+    "lastDeauthorizedTime",
+];
+
+fn parse_ip(value: &Value) -> Option<IpAddr> {
+    value.as_str()?.trim().parse().ok()
+}
+
+/// Parse the route targets of a network configuration, ignoring invalid entries
+pub fn routed_subnets(network: &Map<String, Value>) -> Vec<IpNet> {
+    network
+        .get("routes")
+        .and_then(Value::as_array)
+        .map(|routes| {
+            routes
+                .iter()
+                .filter_map(|route| route.get("target")?.as_str()?.parse().ok())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Parse the IP assignment pools of a network configuration, ignoring invalid entries
+pub fn assignment_pools(network: &Map<String, Value>) -> Vec<(IpAddr, IpAddr)> {
+    network
+        .get("ipAssignmentPools")
+        .and_then(Value::as_array)
+        .map(|pools| {
+            pools
+                .iter()
+                .filter_map(|pool| {
+                    let start = pool.get("ipRangeStart").and_then(parse_ip)?;
+                    let end = pool.get("ipRangeEnd").and_then(parse_ip)?;
+                    (start.is_ipv4() == end.is_ipv4() && ip_to_u128(start) <= ip_to_u128(end))
+                        .then_some((start, end))
+                })
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn validate_mtu(network: &Map<String, Value>, errors: &mut Vec<FieldError>) {
+    let Some(mtu) = network.get("mtu") else {
+        return;
+    };
+
+    match mtu.as_u64() {
+        Some(mtu) if (MIN_MTU..=MAX_MTU).contains(&mtu) => {}
+        _ => errors.push(FieldError::new(
+            "mtu",
+            format!("must be an integer between {} and {}", MIN_MTU, MAX_MTU),
+        )),
+    }
+}
+
+fn validate_routes(network: &Map<String, Value>, errors: &mut Vec<FieldError>) {
+    let Some(routes) = network.get("routes") else {
+        return;
+    };
+    let Some(routes) = routes.as_array() else {
+        errors.push(FieldError::new("routes", "must be an array"));
+        return;
+    };
+
+    for (i, route) in routes.iter().enumerate() {
+        let target = route
+            .get("target")
+            .and_then(Value::as_str)
+            .and_then(|target| target.trim().parse::<IpNet>().ok());
+        let Some(target) = target else {
+            errors.push(FieldError::new(
+                format!("routes[{}].target", i),
+                "must be a valid CIDR, e.g. 10.147.17.0/24",
+            ));
+            continue;
+        };
+
+        if target.trunc() != target {
+            errors.push(FieldError::new(
+                format!("routes[{}].target", i),
+                format!("has host bits set, did you mean {}?", target.trunc()),
+            ));
+        }
+
+        match route.get("via") {
+            None | Some(Value::Null) => {}
+            Some(via) => match parse_ip(via) {
+                Some(via) if via.is_ipv4() == target.addr().is_ipv4() => {}
+                Some(_) => errors.push(FieldError::new(
+                    format!("routes[{}].via", i),
+                    "must be in the same address family as the target",
+                )),
+                None => errors.push(FieldError::new(
+                    format!("routes[{}].via", i),
+                    "must be a valid IP address or null",
+                )),
+            },
+        }
+    }
+}
+
+fn validate_pools(network: &Map<String, Value>, errors: &mut Vec<FieldError>) {
+    let Some(pools) = network.get("ipAssignmentPools") else {
+        return;
+    };
+    let Some(pools) = pools.as_array() else {
+        errors.push(FieldError::new("ipAssignmentPools", "must be an array"));
+        return;
+    };
+
+    let mut ranges: Vec<(usize, IpAddr, IpAddr)> = Vec::new();
+    for (i, pool) in pools.iter().enumerate() {
+        let start = pool.get("ipRangeStart").and_then(parse_ip);
+        let end = pool.get("ipRangeEnd").and_then(parse_ip);
+
+        if start.is_none() {
+            errors.push(FieldError::new(
+                format!("ipAssignmentPools[{}].ipRangeStart", i),
+                "must be a valid IP address",
+            ));
+        }
+        if end.is_none() {
+            errors.push(FieldError::new(
+                format!("ipAssignmentPools[{}].ipRangeEnd", i),
+                "must be a valid IP address",
+            ));
+        }
+        let (Some(start), Some(end)) = (start, end) else {
+            continue;
+        };
+
+        if start.is_ipv4() != end.is_ipv4() {
+            errors.push(FieldError::new(
+                format!("ipAssignmentPools[{}]", i),
+                "start and end must be in the same address family",
+            ));
+        } else if ip_to_u128(start) > ip_to_u128(end) {
+            errors.push(FieldError::new(
+                format!("ipAssignmentPools[{}].ipRangeEnd", i),
+                format!("must not be lower than ipRangeStart {}", start),
+            ));
+        } else {
+            ranges.push((i, start, end));
+        }
+    }
+
+    for (n, (i, start, end)) in ranges.iter().enumerate() {
+        for (j, other_start, other_end) in &ranges[n + 1..] {
+            let same_family = start.is_ipv4() == other_start.is_ipv4();
+            if same_family
+                && ip_to_u128(*start) <= ip_to_u128(*other_end)
+                && ip_to_u128(*other_start) <= ip_to_u128(*end)
+            {
+                errors.push(FieldError::new(
+                    format!("ipAssignmentPools[{}]", j),
+                    format!("overlaps ipAssignmentPools[{}]", i),
+                ));
+            }
+        }
+    }
+}
+
+/// Validate a network configuration update.
+/// Only the fields present in the update are checked.
+pub fn validate_network(network: &Map<String, Value>) -> Vec<FieldError> {
+    let mut errors = Vec::new();
+
+    validate_mtu(network, &mut errors);
+    validate_routes(network, &mut errors);
+    validate_pools(network, &mut errors);
+
+    errors
+}
+
+/// Validate a member configuration update against its network.
+/// `assigned` maps IPs already used by other members to their member IDs.
+pub fn validate_member(
+    member: &Map<String, Value>,
+    routes: &[IpNet],
+    assigned: &HashMap<IpAddr, String>,
+) -> Vec<FieldError> {
+    let mut errors = Vec::new();
+
+    let Some(ip_assignments) = member.get("ipAssignments") else {
+        return errors;
+    };
+    let Some(ip_assignments) = ip_assignments.as_array() else {
+        errors.push(FieldError::new("ipAssignments", "must be an array"));
+        return errors;
+    };
+
+    let mut seen = Vec::new();
+    for (i, ip) in ip_assignments.iter().enumerate() {
+        let field = format!("ipAssignments[{}]", i);
+        let Some(ip) = parse_ip(ip) else {
+            errors.push(FieldError::new(field, "must be a valid IP address"));
+            continue;
+        };
+
+        if seen.contains(&ip) {
+            errors.push(FieldError::new(field, format!("{} is listed twice", ip)));
+            continue;
+        }
+        seen.push(ip);
+
+        if !routes.iter().any(|route| route.contains(&ip)) {
+            errors.push(FieldError::new(
+                field,
+                format!("{} is not inside any routed subnet of the network", ip),
+            ));
+        } else if let Some(owner) = assigned.get(&ip) {
+            errors.push(FieldError::new(
+                field,
+                format!("{} is already assigned to member {}", ip, owner),
+            ));
+        }
+    }
+
+    errors
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn object(value: Value) -> Map<String, Value> {
+        value.as_object().cloned().unwrap()
+    }
+
+    fn fields(errors: &[FieldError]) -> Vec<&str> {
+        errors.iter().map(|error| error.field.as_str()).collect()
+    }
+
+    #[test]
+    fn accepts_a_valid_network() {
+        let network = object(json!({
+            "mtu": 2800,
+            "routes": [
+                { "target": "10.0.0.0/24", "via": null },
+                { "target": "fd00::/64", "via": "fd00::1" },
+            ],
+            "ipAssignmentPools": [
+                { "ipRangeStart": "10.0.0.10", "ipRangeEnd": "10.0.0.100" },
+                { "ipRangeStart": "10.0.0.101", "ipRangeEnd": "10.0.0.200" },
+            ],
+        }));
+        assert!(validate_network(&network).is_empty());
+        // Fields that are not part of the update are not checked
+        assert!(validate_network(&Map::new()).is_empty());
+    }
+
+    #[test]
+    fn rejects_mtu_out_of_range() {
+        for mtu in [json!(1279), json!(10001), json!("2800"), json!(-1)] {
+            let errors = validate_network(&object(json!({ "mtu": mtu })));
+            assert_eq!(fields(&errors), ["mtu"]);
+        }
+        assert!(validate_network(&object(json!({ "mtu": MIN_MTU }))).is_empty());
+        assert!(validate_network(&object(json!({ "mtu": MAX_MTU }))).is_empty());
+    }
+
+    #[test]
+    fn rejects_invalid_routes() {
+        let errors = validate_network(&object(json!({
+            "routes": [
+                { "target": "10.0.0.0/33" },
+                { "target": "10.0.0.1/24" },
+                { "target": "10.0.1.0/24", "via": "fd00::1" },
+                { "target": "10.0.2.0/24", "via": "gateway" },
+            ],
+        })));
+        assert_eq!(
+            fields(&errors),
+            [
+                "routes[0].target",
+                "routes[1].target",
+                "routes[2].via",
+                "routes[3].via"
+            ]
+        );
+        assert!(errors[1].message.contains("10.0.0.0/24"));
+
+        let errors = validate_network(&object(json!({ "routes": {} })));
+        assert_eq!(fields(&errors), ["routes"]);
+    }
+
+    #[test]
+    fn rejects_invalid_and_overlapping_pools() {
+        let errors = validate_network(&object(json!({
+            "ipAssignmentPools": [
+                { "ipRangeStart": "10.0.0.10", "ipRangeEnd": "10.0.0.100" },
+                { "ipRangeStart": "10.0.0.50", "ipRangeEnd": "10.0.0.150" },
+                { "ipRangeStart": "10.0.1.9", "ipRangeEnd": "10.0.1.1" },
+                { "ipRangeStart": "10.0.2.1", "ipRangeEnd": "fd00::1" },
+                { "ipRangeStart": "nope" },
+                // Pools of different families never overlap
+                { "ipRangeStart": "::a00:a", "ipRangeEnd": "::a00:64" },
+            ],
+        })));
+        assert_eq!(
+            fields(&errors),
+            [
+                "ipAssignmentPools[2].ipRangeEnd",
+                "ipAssignmentPools[3]",
+                "ipAssignmentPools[4].ipRangeStart",
+                "ipAssignmentPools[4].ipRangeEnd",
+                "ipAssignmentPools[1]",
+            ]
+        );
+        assert_eq!(errors[4].message, "overlaps ipAssignmentPools[0]");
+    }
+
+    #[test]
+    fn validates_member_ip_assignments() {
+        let routes: Vec<IpNet> = vec!["10.0.0.0/24".parse().unwrap()];
+        let assigned = HashMap::from([("10.0.0.2".parse().unwrap(), "2222222222".to_string())]);
+
+        let member = object(json!({ "ipAssignments": ["10.0.0.1"] }));
+        assert!(validate_member(&member, &routes, &assigned).is_empty());
+
+        let member = object(json!({
+            "ipAssignments": ["10.0.0.1", "10.0.0.1", "10.0.1.1", "10.0.0.2", "bogus"],
+        }));
+        let errors = validate_member(&member, &routes, &assigned);
+        assert_eq!(
+            fields(&errors),
+            [
+                "ipAssignments[1]",
+                "ipAssignments[2]",
+                "ipAssignments[3]",
+                "ipAssignments[4]"
+            ]
+        );
+        assert!(errors[0].message.contains("listed twice"));
+        assert!(errors[1].message.contains("not inside any routed subnet"));
+        assert!(errors[2].message.contains("member 2222222222"));
+
+        let member = object(json!({ "ipAssignments": "10.0.0.1" }));
+        assert_eq!(
+            fields(&validate_member(&member, &routes, &assigned)),
+            ["ipAssignments"]
+        );
+    }
+}
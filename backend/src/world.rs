@@ -0,0 +1,767 @@
+//! ZeroTier worlds, the signed lists of root servers nodes load as their planet or
+//! orbit as moons, in the binary format of `World.hpp`
+
+use crate::error::FieldError;
+use crate::models::{ParsedWorld, WorldDefinition, WorldRoot, WorldType};
+use ed25519_dalek::{Signature, Signer, SigningKey, Verifier, VerifyingKey};
+use sha2::{Digest, Sha512};
+use std::fmt;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use x25519_dalek::{PublicKey, StaticSecret};
+
+pub const PUBLIC_KEY_LEN: usize = 64;
+pub const SECRET_KEY_LEN: usize = 64;
+pub const SIGNATURE_LEN: usize = 96;
+
+const TYPE_PLANET: u8 = 1;
+const TYPE_MOON: u8 = 127;
+const MAX_ROOTS: usize = 4;
+const MAX_STABLE_ENDPOINTS: usize = 32;
+/// Put before and after the serialized world when it is signed
+const SIGNING_PREFIX: u64 = 0x7f7f_7f7f_7f7f_7f7f;
+const SIGNING_TRAILER: u64 = 0xf7f7_f7f7_f7f7_f7f7;
+/// Identity key type of C25519 identities, the only one roots use
+const IDENTITY_C25519: u8 = 0;
+
+/// A malformed binary world
+#[derive(Debug, Clone)]
+pub struct WorldError(String);
+
+impl fmt::Display for WorldError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+type WorldResult<T> = std::result::Result<T, WorldError>;
+
+/// A C25519 key pair: an X25519 key for agreement followed by an Ed25519 key for
+/// signatures, as ZeroTier stores identity and world signing keys
+pub struct KeyPair {
+    pub public: [u8; PUBLIC_KEY_LEN],
+    secret: [u8; SECRET_KEY_LEN],
+}
+
+impl KeyPair {
+    pub fn generate() -> Result<Self, getrandom::Error> {
+        let mut secret = [0u8; SECRET_KEY_LEN];
+        getrandom::getrandom(&mut secret)?;
+        Ok(Self::from_secret(secret))
+    }
+
+    pub fn from_secret(secret: [u8; SECRET_KEY_LEN]) -> Self {
+        let (agreement, signing) = secret.split_at(32);
+        let agreement = StaticSecret::from(<[u8; 32]>::try_from(agreement).unwrap_or_default());
+        let signing = SigningKey::from_bytes(&signing.try_into().unwrap_or_default());
+
+        let mut public = [0u8; PUBLIC_KEY_LEN];
+        public[..32].copy_from_slice(PublicKey::from(&agreement).as_bytes());
+        public[32..].copy_from_slice(signing.verifying_key().as_bytes());
+        Self { public, secret }
+    }
+
+    pub fn secret(&self) -> &[u8; SECRET_KEY_LEN] {
+        &self.secret
+    }
+
+    /// Sign like `C25519::sign`: Ed25519 over the first half of the message's
+    /// SHA-512, followed by that half
+    pub fn sign(&self, message: &[u8]) -> [u8; SIGNATURE_LEN] {
+        let digest = Sha512::digest(message);
+        let signing = SigningKey::from_bytes(&self.secret[32..].try_into().unwrap_or_default());
+
+        let mut signature = [0u8; SIGNATURE_LEN];
+        signature[..64].copy_from_slice(&signing.sign(&digest[..32]).to_bytes());
+        signature[64..].copy_from_slice(&digest[..32]);
+        signature
+    }
+}
+
+/// Check a signature made by [`KeyPair::sign`]
+pub fn verify(
+    public: &[u8; PUBLIC_KEY_LEN],
+    message: &[u8],
+    signature: &[u8; SIGNATURE_LEN],
+) -> bool {
+    let digest = Sha512::digest(message);
+    if signature[64..] != digest[..32] {
+        return false;
+    }
+
+    let Ok(key) = VerifyingKey::from_bytes(&public[32..].try_into().unwrap_or_default()) else {
+        return false;
+    };
+    let signature = Signature::from_bytes(&signature[..64].try_into().unwrap_or([0; 64]));
+    key.verify(&digest[..32], &signature).is_ok()
+}
+
+/// A node's public identity
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Identity {
+    pub address: u64,
+    pub public_key: [u8; PUBLIC_KEY_LEN],
+}
+
+impl Identity {
+    /// Parse `<address>:0:<public key>`, a secret key after it is ignored
+    pub fn parse(identity: &str) -> Option<Self> {
+        let mut fields = identity.trim().split(':');
+        let address = fields.next().filter(|address| address.len() == 10)?;
+        let address = u64::from_str_radix(address, 16).ok()?;
+        if fields.next()? != "0" {
+            return None;
+        }
+        let public_key = hex::decode(fields.next()?).ok()?.try_into().ok()?;
+        Some(Self {
+            address,
+            public_key,
+        })
+    }
+}
+
+impl fmt::Display for Identity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:010x}:0:{}",
+            self.address,
+            hex::encode(self.public_key)
+        )
+    }
+}
+
+/// A root server and the addresses it can be reached at
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Root {
+    pub identity: Identity,
+    pub stable_endpoints: Vec<SocketAddr>,
+}
+
+/// Parse an endpoint in ZeroTier's `<ip>/<port>` form, `<ip>:<port>` is accepted too
+fn parse_endpoint(endpoint: &str) -> Option<SocketAddr> {
+    let endpoint = endpoint.trim();
+    match endpoint.rsplit_once('/') {
+        Some((ip, port)) => Some(SocketAddr::new(ip.parse().ok()?, port.parse().ok()?)),
+        None => endpoint.parse().ok(),
+    }
+}
+
+fn parse_key<const N: usize>(key: &str) -> Option<[u8; N]> {
+    hex::decode(key.trim()).ok()?.try_into().ok()
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct World {
+    pub world_type: WorldType,
+    pub id: u64,
+    /// Milliseconds since the epoch
+    pub timestamp: u64,
+    pub updates_must_be_signed_by: [u8; PUBLIC_KEY_LEN],
+    pub signature: [u8; SIGNATURE_LEN],
+    pub roots: Vec<Root>,
+}
+
+/// Reads big-endian fields, failing on truncated input
+struct Reader<'a> {
+    data: &'a [u8],
+    offset: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn bytes(&mut self, length: usize) -> WorldResult<&'a [u8]> {
+        let bytes = self
+            .data
+            .get(self.offset..self.offset + length)
+            .ok_or_else(|| WorldError(format!("truncated after {} bytes", self.data.len())))?;
+        self.offset += length;
+        Ok(bytes)
+    }
+
+    fn array<const N: usize>(&mut self) -> WorldResult<[u8; N]> {
+        Ok(self.bytes(N)?.try_into().unwrap_or([0; N]))
+    }
+
+    fn u8(&mut self) -> WorldResult<u8> {
+        Ok(self.array::<1>()?[0])
+    }
+
+    fn u16(&mut self) -> WorldResult<u16> {
+        Ok(u16::from_be_bytes(self.array()?))
+    }
+
+    fn u64(&mut self) -> WorldResult<u64> {
+        Ok(u64::from_be_bytes(self.array()?))
+    }
+
+    /// An `InetAddress`, `None` for the nil and unsupported address types
+    fn endpoint(&mut self) -> WorldResult<Option<SocketAddr>> {
+        let ip = match self.u8()? {
+            0x00 => return Ok(None),
+            // Ethernet and Bluetooth addresses, reserved for future use
+            0x01 | 0x02 => {
+                self.bytes(6)?;
+                return Ok(None);
+            }
+            0x03 => {
+                let length = self.u16()? as usize;
+                self.bytes(length)?;
+                return Ok(None);
+            }
+            0x04 => IpAddr::V4(Ipv4Addr::from(self.array::<4>()?)),
+            0x06 => IpAddr::V6(Ipv6Addr::from(self.array::<16>()?)),
+            other => return Err(WorldError(format!("unknown address type {}", other))),
+        };
+        Ok(Some(SocketAddr::new(ip, self.u16()?)))
+    }
+}
+
+impl World {
+    /// Read a world from its binary form, bytes after it are ignored
+    pub fn parse(data: &[u8]) -> WorldResult<Self> {
+        let mut reader = Reader { data, offset: 0 };
+
+        let world_type = match reader.u8()? {
+            TYPE_PLANET => WorldType::Planet,
+            TYPE_MOON => WorldType::Moon,
+            other => return Err(WorldError(format!("unknown world type {}", other))),
+        };
+        let id = reader.u64()?;
+        let timestamp = reader.u64()?;
+        let updates_must_be_signed_by = reader.array()?;
+        let signature = reader.array()?;
+
+        let root_count = reader.u8()? as usize;
+        if root_count > MAX_ROOTS {
+            return Err(WorldError(format!(
+                "{} roots, at most {} are allowed",
+                root_count, MAX_ROOTS
+            )));
+        }
+        let mut roots = Vec::with_capacity(root_count);
+        for _ in 0..root_count {
+            let address = reader
+                .bytes(5)?
+                .iter()
+                .fold(0u64, |address, byte| (address << 8) | u64::from(*byte));
+            let identity_type = reader.u8()?;
+            if identity_type != IDENTITY_C25519 {
+                return Err(WorldError(format!(
+                    "root {:010x} has unsupported identity type {}",
+                    address, identity_type
+                )));
+            }
+            let public_key = reader.array()?;
+            // Roots are stored without their secret, skip one if present
+            let secret_length = reader.u8()? as usize;
+            reader.bytes(secret_length)?;
+
+            let endpoint_count = reader.u8()? as usize;
+            if endpoint_count > MAX_STABLE_ENDPOINTS {
+                return Err(WorldError(format!(
+                    "root {:010x} has {} stable endpoints, at most {} are allowed",
+                    address, endpoint_count, MAX_STABLE_ENDPOINTS
+                )));
+            }
+            let mut stable_endpoints = Vec::with_capacity(endpoint_count);
+            for _ in 0..endpoint_count {
+                stable_endpoints.extend(reader.endpoint()?);
+            }
+
+            roots.push(Root {
+                identity: Identity {
+                    address,
+                    public_key,
+                },
+                stable_endpoints,
+            });
+        }
+
+        if world_type == WorldType::Moon {
+            // Dictionary reserved for future use
+            let length = reader.u16()? as usize;
+            reader.bytes(length)?;
+        }
+
+        Ok(Self {
+            world_type,
+            id,
+            timestamp,
+            updates_must_be_signed_by,
+            signature,
+            roots,
+        })
+    }
+
+    /// The binary form nodes load
+    pub fn serialize(&self) -> Vec<u8> {
+        self.write(false)
+    }
+
+    /// The world as signed: without the signature, between a prefix and a trailer
+    fn write(&self, for_signing: bool) -> Vec<u8> {
+        let mut data = Vec::new();
+        if for_signing {
+            data.extend_from_slice(&SIGNING_PREFIX.to_be_bytes());
+        }
+        data.push(match self.world_type {
+            WorldType::Planet => TYPE_PLANET,
+            WorldType::Moon => TYPE_MOON,
+        });
+        data.extend_from_slice(&self.id.to_be_bytes());
+        data.extend_from_slice(&self.timestamp.to_be_bytes());
+        data.extend_from_slice(&self.updates_must_be_signed_by);
+        if !for_signing {
+            data.extend_from_slice(&self.signature);
+        }
+
+        data.push(self.roots.len() as u8);
+        for root in &self.roots {
+            data.extend_from_slice(&root.identity.address.to_be_bytes()[3..]);
+            data.push(IDENTITY_C25519);
+            data.extend_from_slice(&root.identity.public_key);
+            data.push(0);
+
+            data.push(root.stable_endpoints.len() as u8);
+            for endpoint in &root.stable_endpoints {
+                match endpoint.ip() {
+                    IpAddr::V4(ip) => {
+                        data.push(0x04);
+                        data.extend_from_slice(&ip.octets());
+                    }
+                    IpAddr::V6(ip) => {
+                        data.push(0x06);
+                        data.extend_from_slice(&ip.octets());
+                    }
+                }
+                data.extend_from_slice(&endpoint.port().to_be_bytes());
+            }
+        }
+
+        if self.world_type == WorldType::Moon {
+            data.extend_from_slice(&0u16.to_be_bytes());
+        }
+        if for_signing {
+            data.extend_from_slice(&SIGNING_TRAILER.to_be_bytes());
+        }
+        data
+    }
+
+    pub fn sign(&mut self, key: &KeyPair) {
+        self.signature = key.sign(&self.write(true));
+    }
+
+    pub fn verify(&self, public: &[u8; PUBLIC_KEY_LEN]) -> bool {
+        verify(public, &self.write(true), &self.signature)
+    }
+
+    /// Build and sign a world from its JSON definition, `now` is the default timestamp
+    pub fn generate(definition: &WorldDefinition, now: u64) -> Result<Self, Vec<FieldError>> {
+        let mut errors = Vec::new();
+
+        let id = u64::from_str_radix(definition.id.trim(), 16);
+        if id.is_err() {
+            errors.push(FieldError::new("id", "must be a 64-bit hex number"));
+        }
+
+        let key = parse_key(&definition.signing_key_secret).map(KeyPair::from_secret);
+        match &key {
+            None => errors.push(FieldError::new(
+                "signingKey_SECRET",
+                "must be a 64-byte key in hex",
+            )),
+            Some(key) => {
+                if definition
+                    .signing_key
+                    .as_deref()
+                    .is_some_and(|public| parse_key(public) != Some(key.public))
+                {
+                    errors.push(FieldError::new(
+                        "signingKey",
+                        "does not match signingKey_SECRET",
+                    ));
+                }
+            }
+        }
+
+        let updates_must_be_signed_by = match &definition.updates_must_be_signed_by {
+            Some(public) => {
+                let public = parse_key(public);
+                if public.is_none() {
+                    errors.push(FieldError::new(
+                        "updatesMustBeSignedBy",
+                        "must be a 64-byte key in hex",
+                    ));
+                }
+                public
+            }
+            None => key.as_ref().map(|key| key.public),
+        };
+
+        if definition.roots.is_empty() || definition.roots.len() > MAX_ROOTS {
+            errors.push(FieldError::new(
+                "roots",
+                format!("must have 1 to {} roots", MAX_ROOTS),
+            ));
+        }
+        let mut roots = Vec::new();
+        for (i, root) in definition.roots.iter().enumerate() {
+            let identity = Identity::parse(&root.identity);
+            if identity.is_none() {
+                errors.push(FieldError::new(
+                    format!("roots[{}].identity", i),
+                    "must be a public identity, <address>:0:<public key>",
+                ));
+            }
+            if root.stable_endpoints.len() > MAX_STABLE_ENDPOINTS {
+                errors.push(FieldError::new(
+                    format!("roots[{}].stableEndpoints", i),
+                    format!("must have at most {} endpoints", MAX_STABLE_ENDPOINTS),
+                ));
+            }
+
+            let mut stable_endpoints = Vec::new();
+            for (j, endpoint) in root.stable_endpoints.iter().enumerate() {
+                match parse_endpoint(endpoint) {
+                    Some(endpoint) => stable_endpoints.push(endpoint),
+                    None => errors.push(FieldError::new(
+                        format!("roots[{}].stableEndpoints[{}]", i, j),
+                        "must be <ip>/<port>",
+                    )),
+                }
+            }
+
+            if let Some(identity) = identity {
+                roots.push(Root {
+                    identity,
+                    stable_endpoints,
+                });
+            }
+        }
+
+        if !errors.is_empty() {
+            return Err(errors);
+        }
+        // Every missing value was reported above
+        let (Ok(id), Some(key), Some(updates_must_be_signed_by)) =
+            (id, key, updates_must_be_signed_by)
+        else {
+            return Err(errors);
+        };
+
+        let mut world = Self {
+            world_type: definition.world_type,
+            id,
+            timestamp: definition.timestamp.unwrap_or(now),
+            updates_must_be_signed_by,
+            signature: [0; SIGNATURE_LEN],
+            roots,
+        };
+        world.sign(&key);
+        Ok(world)
+    }
+
+    /// The world's fields, keys and signature in hex
+    pub fn describe(&self, signed_by: Option<&[u8; PUBLIC_KEY_LEN]>) -> ParsedWorld {
+        ParsedWorld {
+            world_type: self.world_type,
+            id: format!("{:016x}", self.id),
+            timestamp: self.timestamp,
+            roots: self
+                .roots
+                .iter()
+                .map(|root| WorldRoot {
+                    identity: root.identity.to_string(),
+                    stable_endpoints: root
+                        .stable_endpoints
+                        .iter()
+                        .map(|endpoint| format!("{}/{}", endpoint.ip(), endpoint.port()))
+                        .collect(),
+                })
+                .collect(),
+            updates_must_be_signed_by: hex::encode(self.updates_must_be_signed_by),
+            signature: hex::encode(self.signature),
+            self_signed: self.verify(&self.updates_must_be_signed_by),
+            verified: signed_by.map(|public| self.verify(public)),
+        }
+    }
+
+    /// File name nodes look for: `planet`, or `<id>.moon` in `moons.d`
+    pub fn file_name(&self) -> String {
+        match self.world_type {
+            WorldType::Planet => "planet".to_string(),
+            WorldType::Moon => format!("{:016x}.moon", self.id),
+        }
+    }
+}
+
+/// Parse a public key given in hex
+pub fn parse_public_key(key: &str) -> Option<[u8; PUBLIC_KEY_LEN]> {
+    parse_key(key)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    /// RFC 7748 section 6.1 (Alice) followed by RFC 8032 section 7.1 test 1
+    const SECRET: &str = "77076d0a7318a57d3c16c17251b26645df4c2f87ebc0992ab177fba51db92c2a\
+                          9d61b19deffd5a60ba844af492ec2cc44449c5697b326919703bac031cae7f60";
+    const PUBLIC: &str = "8520f0098930a754748b7ddcb43ef75a0dbf3a0d26381af4eba4a98eaa9b4e6a\
+                          d75a980182b10ab7d54bfed3c964073a0ee172f3daa62325af021a68f707511a";
+
+    fn key() -> KeyPair {
+        KeyPair::from_secret(parse_key(SECRET).unwrap())
+    }
+
+    fn definition(value: serde_json::Value) -> WorldDefinition {
+        serde_json::from_value(value).unwrap()
+    }
+
+    fn world(world_type: &str) -> World {
+        let root = format!("89e92ceee5:0:{}", PUBLIC);
+        World::generate(
+            &definition(json!({
+                "worldType": world_type,
+                "id": "00000089e92ceee5",
+                "timestamp": 1_700_000_000_000u64,
+                "roots": [{
+                    "identity": root,
+                    "stableEndpoints": ["192.0.2.1/9993", "2001:db8::1/9993"],
+                }],
+                "signingKey_SECRET": SECRET,
+            })),
+            0,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn derives_the_public_key_from_the_secret() {
+        assert_eq!(hex::encode(key().public), PUBLIC);
+        assert_eq!(hex::encode(key().secret()), SECRET);
+    }
+
+    #[test]
+    fn signs_the_first_half_of_the_sha512() {
+        let key = key();
+        let signature = key.sign(b"world");
+        let digest = Sha512::digest(b"world");
+
+        assert_eq!(signature, key.sign(b"world"));
+        assert_eq!(signature[64..], digest[..32]);
+        let verifying = VerifyingKey::from_bytes(&key.public[32..].try_into().unwrap()).unwrap();
+        let ed25519 = Signature::from_bytes(&signature[..64].try_into().unwrap());
+        assert!(verifying.verify(&digest[..32], &ed25519).is_ok());
+
+        assert!(verify(&key.public, b"world", &signature));
+        assert!(!verify(&key.public, b"worle", &signature));
+        let other = KeyPair::from_secret([7; SECRET_KEY_LEN]);
+        assert!(!verify(&other.public, b"world", &signature));
+    }
+
+    #[test]
+    fn serializes_in_the_world_hpp_layout() {
+        let world = world("planet");
+        let public = parse_key::<PUBLIC_KEY_LEN>(PUBLIC).unwrap();
+
+        let mut body = Vec::new();
+        body.push(1);
+        body.extend_from_slice(&hex::decode("89e92ceee5").unwrap());
+        body.push(0);
+        body.extend_from_slice(&public);
+        body.push(0);
+        body.push(2);
+        body.extend_from_slice(&[0x04, 192, 0, 2, 1, 0x27, 0x09]);
+        body.push(0x06);
+        body.extend_from_slice(&hex::decode("20010db8000000000000000000000001").unwrap());
+        body.extend_from_slice(&[0x27, 0x09]);
+
+        let mut header = vec![1];
+        header.extend_from_slice(&hex::decode("00000089e92ceee5").unwrap());
+        header.extend_from_slice(&hex::decode("0000018bcfe56800").unwrap());
+        header.extend_from_slice(&public);
+
+        let mut signed = vec![0x7f; 8];
+        signed.extend_from_slice(&header);
+        signed.extend_from_slice(&body);
+        signed.extend_from_slice(&[0xf7; 8]);
+        assert_eq!(world.write(true), signed);
+        assert_eq!(world.signature, key().sign(&signed));
+
+        let mut serialized = header;
+        serialized.extend_from_slice(&world.signature);
+        serialized.extend_from_slice(&body);
+        assert_eq!(world.serialize(), serialized);
+    }
+
+    #[test]
+    fn moons_end_with_an_empty_dictionary() {
+        let planet = world("planet").serialize();
+        let moon = world("moon").serialize();
+
+        assert_eq!(moon[0], TYPE_MOON);
+        assert_eq!(moon.len(), planet.len() + 2);
+        assert_eq!(moon[moon.len() - 2..], [0, 0]);
+    }
+
+    #[test]
+    fn round_trips_and_verifies() {
+        for world_type in ["planet", "moon"] {
+            let world = world(world_type);
+            let parsed = World::parse(&world.serialize()).unwrap();
+
+            assert_eq!(parsed, world);
+            assert!(parsed.verify(&key().public));
+            let described = parsed.describe(Some(&[0; PUBLIC_KEY_LEN]));
+            assert!(described.self_signed);
+            assert_eq!(described.verified, Some(false));
+            assert_eq!(
+                described.roots[0].stable_endpoints,
+                ["192.0.2.1/9993", "2001:db8::1/9993"]
+            );
+        }
+    }
+
+    #[test]
+    fn tampering_breaks_the_signature() {
+        let mut data = world("planet").serialize();
+        // Last byte of the IPv6 endpoint's port
+        let last = data.len() - 1;
+        data[last] ^= 1;
+
+        let parsed = World::parse(&data).unwrap();
+        assert_eq!(parsed.roots[0].stable_endpoints[1].port(), 9992);
+        assert!(!parsed.verify(&key().public));
+    }
+
+    #[test]
+    fn names_the_file_nodes_load() {
+        assert_eq!(world("planet").file_name(), "planet");
+        assert_eq!(world("moon").file_name(), "00000089e92ceee5.moon");
+    }
+
+    #[test]
+    fn rejects_malformed_worlds() {
+        let data = world("moon").serialize();
+        for length in [0, 1, 17, data.len() - 1] {
+            let error = World::parse(&data[..length]).unwrap_err();
+            assert!(error.to_string().starts_with("truncated"), "{}", length);
+        }
+
+        let mut unknown = data.clone();
+        unknown[0] = 2;
+        assert_eq!(
+            World::parse(&unknown).unwrap_err().to_string(),
+            "unknown world type 2"
+        );
+
+        let roots = 1 + 8 + 8 + PUBLIC_KEY_LEN + SIGNATURE_LEN;
+        let mut too_many = data.clone();
+        too_many[roots] = 5;
+        assert_eq!(
+            World::parse(&too_many).unwrap_err().to_string(),
+            "5 roots, at most 4 are allowed"
+        );
+
+        let mut identity_type = data;
+        identity_type[roots + 1 + 5] = 1;
+        assert_eq!(
+            World::parse(&identity_type).unwrap_err().to_string(),
+            "root 89e92ceee5 has unsupported identity type 1"
+        );
+    }
+
+    #[test]
+    fn skips_root_secrets_and_nil_endpoints() {
+        let world = world("planet");
+        let mut data = world.serialize();
+        let secret = 1 + 8 + 8 + PUBLIC_KEY_LEN + SIGNATURE_LEN + 1 + 5 + 1 + PUBLIC_KEY_LEN;
+        data.splice(secret..=secret + 1, [3, 0xaa, 0xbb, 0xcc, 3, 0x00]);
+
+        let parsed = World::parse(&data).unwrap();
+        assert_eq!(parsed.roots[0].stable_endpoints.len(), 2);
+        assert_eq!(parsed.roots, world.roots);
+    }
+
+    #[test]
+    fn parses_identities_and_endpoints() {
+        let identity = format!("89e92ceee5:0:{}", PUBLIC);
+        let parsed = Identity::parse(&format!("{}:{}\n", identity, SECRET)).unwrap();
+        assert_eq!(parsed.address, 0x89_e92c_eee5);
+        assert_eq!(parsed.to_string(), identity);
+        assert!(Identity::parse(&identity.replacen(":0:", ":1:", 1)).is_none());
+        assert!(Identity::parse(&identity[1..]).is_none());
+        assert!(Identity::parse("89e92ceee5:0:abcd").is_none());
+
+        assert_eq!(
+            parse_endpoint("192.0.2.1/9993"),
+            Some("192.0.2.1:9993".parse().unwrap())
+        );
+        assert_eq!(
+            parse_endpoint("[2001:db8::1]:9993"),
+            Some("[2001:db8::1]:9993".parse().unwrap())
+        );
+        assert_eq!(parse_endpoint("192.0.2.1"), None);
+        assert_eq!(parse_endpoint("192.0.2.1/70000"), None);
+    }
+
+    #[test]
+    fn reports_every_invalid_field() {
+        let errors = World::generate(
+            &definition(json!({
+                "worldType": "planet",
+                "id": "not hex",
+                "roots": [{ "identity": "nope", "stableEndpoints": ["192.0.2.1"] }],
+                "updatesMustBeSignedBy": "abcd",
+                "signingKey": "00",
+                "signingKey_SECRET": SECRET,
+            })),
+            0,
+        )
+        .unwrap_err();
+        let fields: Vec<_> = errors.iter().map(|error| error.field.as_str()).collect();
+        assert_eq!(
+            fields,
+            [
+                "id",
+                "signingKey",
+                "updatesMustBeSignedBy",
+                "roots[0].identity",
+                "roots[0].stableEndpoints[0]",
+            ]
+        );
+
+        let errors = World::generate(
+            &definition(
+                json!({ "worldType": "moon", "id": "1", "roots": [], "signingKey_SECRET": "" }),
+            ),
+            0,
+        )
+        .unwrap_err();
+        let fields: Vec<_> = errors.iter().map(|error| error.field.as_str()).collect();
+        assert_eq!(fields, ["signingKey_SECRET", "roots"]);
+    }
+
+    #[test]
+    fn defaults_the_timestamp_and_update_key() {
+        let other = KeyPair::from_secret([7; SECRET_KEY_LEN]);
+        let world = World::generate(
+            &definition(json!({
+                "worldType": "planet",
+                "id": "1",
+                "roots": [{ "identity": format!("89e92ceee5:0:{}", PUBLIC) }],
+                "updatesMustBeSignedBy": hex::encode(other.public),
+                "signingKey": PUBLIC,
+                "signingKey_SECRET": SECRET,
+            })),
+            42,
+        )
+        .unwrap();
+
+        assert_eq!(world.timestamp, 42);
+        assert_eq!(world.updates_must_be_signed_by, other.public);
+        assert!(world.verify(&key().public));
+        assert!(!world.verify(&other.public));
+    }
+}
@@ -0,0 +1,11 @@
+import type { AuditEntry, AuditQuery } from '@/types/manage'
+import apiClient from '@/utils/axios'
+
+export async function listAuditEntries(query: AuditQuery): Promise<AuditEntry[]> {
+  // Leave out empty filters so they do not match nothing
+  const params = Object.fromEntries(
+    Object.entries(query).filter(([, value]) => value !== undefined && value !== ''),
+  )
+  const { data } = await apiClient.get<AuditEntry[]>('/api/audit', { params })
+  return data
+}
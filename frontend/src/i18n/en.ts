@@ -1,4 +1,13 @@
 const en = {
+  audit: {
+    action: 'Action',
+    actor: 'Actor',
+    default: 'Audit Log',
+    details: 'Details',
+    error: 'Failed to get audit log',
+    target: 'Target',
+    time: 'Time',
+  },
   auth: {
     error: {
       emptyFields: 'Username and password cannot be empty',
@@ -1,4 +1,13 @@
 const zh_cn = {
+  audit: {
+    action: '操作类型',
+    actor: '操作者',
+    default: '审计日志',
+    details: '详情',
+    error: '获取审计日志失败',
+    target: '对象',
+    time: '时间',
+  },
   auth: {
     error: {
       emptyFields: '用户名和密码不能为空',
@@ -15,6 +15,11 @@ const router = createRouter({
       name: 'networks',
       component: () => import('@/views/NetworksView.vue'),
     },
+    {
+      path: '/audit',
+      name: 'audit',
+      component: () => import('@/views/AuditView.vue'),
+    },
     {
       path: '/network/:networkId',
       name: 'networkDetail',
@@ -22,3 +22,20 @@ export interface RefreshResponse {
   expires_at: number
   message: string
 }
+
+export interface AuditEntry {
+  timestamp: string
+  actor: string
+  action: string
+  network_id?: string
+  member_id?: string
+  details?: unknown
+}
+
+export interface AuditQuery {
+  network_id?: string
+  member_id?: string
+  actor?: string
+  action?: string
+  limit?: number
+}
@@ -93,6 +93,23 @@ export interface ControllerNetworkMemberInfo {
   vMinor: number
   vProto: number
   vRev: number
+  // Added by the backend when the member has metadata or an expiry
+  metadata?: MemberMetadata
+  expiry?: MemberExpiry
+}
+
+export interface MemberMetadata {
+  owner?: string
+  asset_tag?: string
+  location?: string
+  notes?: string
+  labels?: Record<string, string>
+}
+
+export interface MemberExpiry {
+  expiresAt: string
+  remainingSecs: number
+  delete: boolean
 }
 
 export interface ControllerNetworkMemberSettings {